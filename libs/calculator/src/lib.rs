@@ -1,4 +1,49 @@
 #[no_mangle]
 pub fn add(left: usize, right: usize) -> usize {
     left + right
-}
\ No newline at end of file
+}
+
+#[export_name = "_ZN4math3addEii"]
+pub extern "C" fn math_add(left: i32, right: i32) -> i32 {
+    left + right
+}
+
+#[no_mangle]
+pub static ANSWER: usize = 42;
+
+#[no_mangle]
+pub fn answer_ptr(valid: bool) -> *const usize {
+    if valid {
+        &ANSWER
+    } else {
+        std::ptr::null()
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn c_name(kind: i32) -> *const std::ffi::c_char {
+    match kind {
+        0 => c"calculator".as_ptr(),
+        1 => c"calc\xffulator".as_ptr(),
+        _ => std::ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn abi_version() -> u32 {
+    1
+}
+
+#[no_mangle]
+pub extern "C" fn c_add(left: i32, right: i32) -> i32 {
+    left + right
+}
+
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub extern "C" fn c_sum16(
+    a1: i32, a2: i32, a3: i32, a4: i32, a5: i32, a6: i32, a7: i32, a8: i32,
+    a9: i32, a10: i32, a11: i32, a12: i32, a13: i32, a14: i32, a15: i32, a16: i32,
+) -> i32 {
+    a1 + a2 + a3 + a4 + a5 + a6 + a7 + a8 + a9 + a10 + a11 + a12 + a13 + a14 + a15 + a16
+}
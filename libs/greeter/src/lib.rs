@@ -0,0 +1,65 @@
+use shared_lib::{declare_plugin, export_signature, Plugin};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+#[derive(Default)]
+pub struct Greeter;
+
+impl Plugin for Greeter {
+    fn name(&self) -> &str {
+        "greeter"
+    }
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+}
+
+declare_plugin!(Greeter, Greeter::default);
+
+#[no_mangle]
+pub fn greeter_create() -> *mut dyn Plugin {
+    Box::into_raw(Box::new(Greeter))
+}
+
+#[no_mangle]
+pub fn greeter_create_null() -> *mut dyn Plugin {
+    std::ptr::null_mut::<Greeter>()
+}
+
+/// # Safety
+/// The plugin must have been created by `greeter_create`.
+#[no_mangle]
+pub unsafe fn greeter_destroy(plugin: *mut dyn Plugin) {
+    drop(Box::from_raw(plugin));
+}
+
+#[no_mangle]
+pub extern "C" fn greeting_len(times: u32) -> u32 {
+    "Hello!".len() as u32 * times
+}
+export_signature!(greeting_len: extern "C" fn(u32) -> u32);
+
+/// Host functions bound by the plugin registry.
+#[repr(C)]
+pub struct HostApi {
+    pub greeted: extern "C" fn(times: u32),
+}
+static HOST: AtomicPtr<HostApi> = AtomicPtr::new(std::ptr::null_mut());
+
+#[no_mangle]
+pub extern "C" fn plugin_bind_host(api: *const HostApi) {
+    HOST.store(api.cast_mut(), Ordering::Release);
+}
+
+/// Report the greeting to the host, returning `false` if no host API is bound.
+/// # Safety
+/// The bound host API must still be alive.
+#[no_mangle]
+pub unsafe extern "C" fn greet_host(times: u32) -> bool {
+    match HOST.load(Ordering::Acquire).as_ref() {
+        Some(host) => {
+            (host.greeted)(times);
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,15 @@
+fn main() {
+    // `sigsetjmp` returns twice and `__try` has no Rust equivalent, so guarded calls are set up by a C shim.
+    #[cfg(feature = "guarded")]
+    {
+        let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+        let target_env = std::env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+        if (target_os == "linux" && target_env == "gnu") || target_os == "macos" {
+            cc::Build::new().file("src/fault.c").compile("shared_lib_fault");
+        } else if target_os == "windows" && target_env == "msvc" {
+            cc::Build::new().file("src/fault_windows.c").compile("shared_lib_fault");
+        }
+        println!("cargo:rerun-if-changed=src/fault.c");
+        println!("cargo:rerun-if-changed=src/fault_windows.c");
+    }
+}
@@ -0,0 +1,246 @@
+//! Loading of libraries distributed in zip, tar and gzip compressed tar archives, e.g. a plugin bundled with its assets.
+
+use crate::{inflate, ExtractionGuard, LibPath, SharedLib, SharedLibError};
+use std::path::{Component, Path, PathBuf};
+
+/// Maximum total size of the extracted files of an archive, which protects against archives expanding to
+/// an arbitrary size, e.g. zip bombs.
+const MAX_EXTRACTED_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Regular file or link of an archive, at a path relative to the root of the archive.
+enum Entry {
+    File { path: PathBuf, contents: Vec<u8> },
+    Link { path: PathBuf, target: PathBuf },
+}
+
+/// Read the files of the archive with the given contents, detecting its format from its first bytes.
+fn read_entries(data: &[u8]) -> Result<Vec<Entry>, String> {
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        zip_entries(data)
+    } else if data.starts_with(&[0x1f, 0x8b]) {
+        tar_entries(&inflate::gunzip(data, MAX_EXTRACTED_SIZE)?)
+    } else if data.get(257..262) == Some(&b"ustar"[..]) {
+        tar_entries(data)
+    } else {
+        Err("Unknown archive format, expected a zip, tar or gzip compressed tar archive.".into())
+    }
+}
+
+/// Get `len` bytes of `data` at `pos`, failing if they are out of bounds instead of overflowing.
+fn bytes_at(data: &[u8], pos: usize, len: usize) -> Option<&[u8]> {
+    data.get(pos..pos.checked_add(len)?)
+}
+fn u16_at(data: &[u8], pos: usize) -> Result<u16, String> {
+    bytes_at(data, pos, 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or_else(|| "Truncated zip archive.".into())
+}
+fn u32_at(data: &[u8], pos: usize) -> Result<u32, String> {
+    bytes_at(data, pos, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| "Truncated zip archive.".into())
+}
+
+/// Read the files of a zip archive, listed by its central directory, checking their CRC-32.
+fn zip_entries(data: &[u8]) -> Result<Vec<Entry>, String> {
+    const END_SIGNATURE: &[u8] = b"PK\x05\x06";
+    const END_LEN: usize = 22;
+    let end = (0..=data.len().saturating_sub(END_LEN)).rev().find(|&pos| data[pos..].starts_with(END_SIGNATURE)).ok_or("Missing the end of the zip central directory.")?;
+    let entry_count = u16_at(data, end + 10)?;
+    let mut pos = u32_at(data, end + 16)? as usize;
+    if entry_count == u16::MAX || pos == u32::MAX as usize {
+        return Err("Zip64 archives are not supported.".into());
+    }
+    let mut entries = Vec::new();
+    let mut total_size = 0u64;
+    for _ in 0..entry_count {
+        if u32_at(data, pos)? != 0x0201_4b50 {
+            return Err("Invalid zip central directory entry.".into());
+        }
+        let (flags, method, crc) = (u16_at(data, pos + 8)?, u16_at(data, pos + 10)?, u32_at(data, pos + 16)?);
+        let (compressed_size, size) = (u32_at(data, pos + 20)? as usize, u32_at(data, pos + 24)? as usize);
+        let (name_len, extra_len, comment_len) = (u16_at(data, pos + 28)? as usize, u16_at(data, pos + 30)? as usize, u16_at(data, pos + 32)? as usize);
+        let local_header = u32_at(data, pos + 42)? as usize;
+        let name = bytes_at(data, pos + 46, name_len).ok_or("Truncated zip archive.")?;
+        let name = String::from_utf8_lossy(name).into_owned();
+        pos += 46 + name_len + extra_len + comment_len;
+        if name.ends_with('/') {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(format!("Entry '{}' is encrypted.", name));
+        }
+        total_size += size as u64;
+        if total_size > MAX_EXTRACTED_SIZE as u64 {
+            return Err(format!("The extracted files exceed {} bytes.", MAX_EXTRACTED_SIZE));
+        }
+        if u32_at(data, local_header)? != 0x0403_4b50 {
+            return Err(format!("Invalid local header of entry '{}'.", name));
+        }
+        let header_len = 30 + u16_at(data, local_header + 26)? as usize + u16_at(data, local_header + 28)? as usize;
+        let compressed = local_header.checked_add(header_len).and_then(|start| bytes_at(data, start, compressed_size)).ok_or("Truncated zip archive.")?;
+        let contents = match method {
+            0 => compressed.to_vec(),
+            // Decompressing stops at the declared size, so an entry cannot expand beyond it.
+            8 => inflate::inflate(compressed, size).map_err(|e| format!("Failed to decompress entry '{}'. {}", name, e))?,
+            _ => return Err(format!("Entry '{}' uses the unsupported compression method {}.", name, method)),
+        };
+        if contents.len() != size {
+            return Err(format!("Entry '{}' has {} bytes, expected {}.", name, contents.len(), size));
+        }
+        if inflate::crc32(&contents) != crc {
+            return Err(format!("The CRC-32 of entry '{}' does not match.", name));
+        }
+        entries.push(Entry::File { path: PathBuf::from(name), contents });
+    }
+    Ok(entries)
+}
+
+/// Read the regular files and symbolic links of a tar archive, checking the checksum of every header.
+fn tar_entries(data: &[u8]) -> Result<Vec<Entry>, String> {
+    const BLOCK: usize = 512;
+    let field = |header: &[u8], range: std::ops::Range<usize>| {
+        let field = &header[range];
+        String::from_utf8_lossy(&field[..field.iter().position(|&b| b == 0).unwrap_or(field.len())]).into_owned()
+    };
+    let mut entries = Vec::new();
+    let mut long_name = None;
+    let mut pos = 0;
+    while let Some(header) = data.get(pos..pos + BLOCK) {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        // The checksum is the sum of the header bytes, with the checksum field itself counted as spaces.
+        let checksum_field = field(header, 148..156);
+        let checksum = u32::from_str_radix(checksum_field.trim(), 8).map_err(|_| format!("Invalid checksum '{}' in tar header.", checksum_field))?;
+        let sum = header.iter().enumerate().map(|(i, &b)| if (148..156).contains(&i) { b' ' as u32 } else { b as u32 }).sum::<u32>();
+        if sum != checksum {
+            return Err(format!("Invalid checksum of the tar header at offset {}.", pos));
+        }
+        let size_field = field(header, 124..136);
+        let size = usize::from_str_radix(size_field.trim(), 8).map_err(|_| format!("Invalid size '{}' in tar header.", size_field))?;
+        let contents = bytes_at(data, pos + BLOCK, size).ok_or("Truncated tar archive.")?;
+        pos += BLOCK + size.div_ceil(BLOCK) * BLOCK;
+        let name = match long_name.take() {
+            Some(name) => name,
+            None => match field(header, 345..500) {
+                prefix if prefix.is_empty() => field(header, 0..100),
+                prefix => format!("{}/{}", prefix, field(header, 0..100)),
+            },
+        };
+        match header[156] {
+            b'0' | 0 => entries.push(Entry::File { path: PathBuf::from(name), contents: contents.to_vec() }),
+            b'2' => entries.push(Entry::Link { path: PathBuf::from(name), target: PathBuf::from(field(header, 157..257)) }),
+            // GNU long name of the next entry.
+            b'L' => long_name = Some(field(contents, 0..contents.len())),
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// Check that `path` stays inside the directory it is relative to, so an archive cannot write anywhere else.
+pub(crate) fn is_contained(path: &Path) -> bool {
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+            Component::ParentDir if depth > 0 => depth -= 1,
+            _ => return false,
+        }
+    }
+    depth > 0
+}
+
+/// Write the entries to `dir`, creating the links once every file is written, so no file is written through a link.
+fn extract(entries: Vec<Entry>, dir: &Path) -> std::io::Result<()> {
+    let invalid_path = |path: &Path| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Entry '{}' is outside of the archive.", path.display()));
+    let mut links = Vec::new();
+    for entry in entries {
+        match entry {
+            Entry::File { path, contents } => {
+                if !is_contained(&path) {
+                    return Err(invalid_path(&path));
+                }
+                let path = dir.join(path);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(path, contents)?;
+            }
+            Entry::Link { path, target } => links.push((path, target)),
+        }
+    }
+    for (path, target) in links {
+        let resolved = path.parent().unwrap_or(Path::new("")).join(&target);
+        if !is_contained(&path) || !is_contained(&resolved) {
+            return Err(invalid_path(&path));
+        }
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, dir.join(&path))?;
+        // Links need extra privileges on Windows, so the target is copied instead.
+        #[cfg(not(unix))]
+        std::fs::copy(dir.join(&resolved), dir.join(&path))?;
+    }
+    Ok(())
+}
+
+/// Find the file of the library named `lib_name` in `dir` or its subdirectories, preferring the ones closer to `dir`.
+fn find_lib(dir: &Path, lib_name: &str) -> Option<LibPath> {
+    let filename = LibPath::new_no_path(lib_name.to_owned()).filename().ok()?;
+    let mut dirs = vec![dir.to_path_buf()];
+    while !dirs.is_empty() {
+        if let Some(found) = dirs.iter().find(|dir| dir.join(&filename).is_file()) {
+            return Some(LibPath::new(found.clone(), lib_name.to_owned()));
+        }
+        let mut subdirs: Vec<PathBuf> = dirs
+            .iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flat_map(|entries| entries.filter_map(Result::ok))
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        subdirs.sort();
+        dirs = subdirs;
+    }
+    None
+}
+
+impl SharedLib {
+    /// Create a new shared library from a zip, tar or gzip compressed tar archive, e.g. a plugin bundled with its assets.
+    ///
+    /// The whole archive is extracted to a new directory in the temporary directory, which is removed once the library
+    /// is unloaded unless its [`SharedLib::extraction`] is persisted, so the library can use the other files of the archive and its dependencies in it are found next to it.
+    /// `lib_name` is the name of the library (without the platform specific extension and prefix), whose file is looked up
+    /// in the root of the archive first and then in its subdirectories.
+    ///
+    /// Zip archives can be stored or compressed with deflate. Entries outside of the extraction directory, e.g. with `..`
+    /// in their path, are rejected with [`SharedLibError::InvalidArchive`], as are entries decompressing to more than their declared size,
+    /// archives whose files add up to more than 1 GiB and corrupted archives, i.e. zip entries or gzip files whose CRC-32 does not match
+    /// and tar headers whose checksum does not match. If the archive does not contain the library,
+    /// [`SharedLibError::LibNotFound`] is returned.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib = SharedLib::from_archive("plugins/codec.zip", "codec").expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn from_archive(archive_path: impl AsRef<Path>, lib_name: &str) -> Result<SharedLib, SharedLibError> {
+        let archive_path = archive_path.as_ref();
+        let invalid_archive = |msg: String| SharedLibError::InvalidArchive { path: archive_path.to_string_lossy().to_string(), msg };
+        let data = std::fs::read(archive_path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => SharedLibError::NotFound { path: archive_path.to_path_buf() },
+            _ => SharedLibError::load_failure(archive_path, e),
+        })?;
+        let entries = read_entries(&data).map_err(invalid_archive)?;
+        let extraction = ExtractionGuard::create_dir(lib_name).map_err(|e| SharedLibError::load_failure(std::env::temp_dir().join(lib_name), e))?;
+        extract(entries, extraction.path()).map_err(|e| invalid_archive(format!("Failed to extract the archive. {}", e)))?;
+        let lib_path = find_lib(extraction.path(), lib_name)
+            .ok_or_else(|| SharedLibError::LibNotFound { lib_name: lib_name.to_owned(), searched: vec![archive_path.to_path_buf()] })?;
+        let mut lib = SharedLib::new(lib_path)?;
+        lib.extraction = Some(extraction);
+        Ok(lib)
+    }
+}
@@ -0,0 +1,70 @@
+//! Typed bindings to the functions of a library declared like an `extern` block, see [`shared_lib_extern!`](crate::shared_lib_extern).
+
+/// Declare a struct of typed [`SharedLibFn`](crate::SharedLibFn) fields for a whole library API, written like an `extern` block.
+///
+/// The struct borrows the library with the lifetime `'lib` and has a field for every function, named like it.
+/// Each function is looked up by its name, or by the name given with the `#[link_name = "name"]` attribute.
+/// The functions of an `extern` block are unsafe to call, so the fields have `unsafe extern "C" fn` types for `extern "C"`.
+///
+/// The generated `load` function returns [`SharedLibError::SymbolsNotFound`](crate::SharedLibError::SymbolsNotFound)
+/// listing every missing function if any of them is not exported.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+///
+/// shared_lib_extern! {
+///     /// Functions of the calculator library.
+///     pub struct CalculatorApi;
+///     extern "C" {
+///         fn c_add(left: i32, right: i32) -> i32;
+///         #[link_name = "calculator_version"]
+///         fn version() -> u32;
+///     }
+/// }
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+///     let api = CalculatorApi::load(&lib).expect("Failed to load calculator API");
+///     let result = api.c_add.run(1, 2);
+/// }
+/// ```
+#[macro_export]
+macro_rules! shared_lib_extern {
+    (@symbol $fn_name:ident) => {
+        stringify!($fn_name)
+    };
+    (@symbol $fn_name:ident $link_name:literal) => {
+        $link_name
+    };
+    (
+        $(#[$struct_attr:meta])*
+        $vis:vis struct $name:ident;
+        extern $abi:literal {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[link_name = $link_name:literal])?
+                fn $fn_name:ident($($arg:ident: $arg_ty:ty),* $(,)?) $(-> $ret:ty)?;
+            )*
+        }
+    ) => {
+        $(#[$struct_attr])*
+        $vis struct $name<'lib> {
+            $(
+                $(#[doc = $doc])*
+                pub $fn_name: $crate::SharedLibFn<'lib, unsafe extern $abi fn($($arg: $arg_ty),*) $(-> $ret)?>,
+            )*
+        }
+        impl<'lib> $name<'lib> {
+            /// Look up every function of the API in the shared library.
+            /// # Safety
+            /// This function is unsafe because the declared function types are not checked against the actual types of the functions.
+            pub unsafe fn load(lib: &'lib $crate::SharedLib) -> ::core::result::Result<Self, $crate::SharedLibError> {
+                lib.check_symbols(&[$($crate::shared_lib_extern!(@symbol $fn_name $($link_name)?)),*])?;
+                ::core::result::Result::Ok(Self {
+                    $($fn_name: lib.get_fn($crate::shared_lib_extern!(@symbol $fn_name $($link_name)?))?,)*
+                })
+            }
+        }
+    };
+}
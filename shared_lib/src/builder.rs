@@ -0,0 +1,197 @@
+//! Configurable loading of a library, see [`SharedLib::builder`].
+
+use crate::{ErrorContext, FilenameConvention, LibPath, LoadFlags, LoadReport, SharedLib, SharedLibError};
+use std::path::PathBuf;
+
+/// Structure representing the options a shared library is loaded with, created with [`SharedLib::builder`].
+///
+/// The library is looked up in the search directories in the order they were added, trying the filename conventions
+/// in order for every directory. Without search directories the dynamic loader searches for the library, and without
+/// filename conventions the platform convention is used.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib = SharedLib::builder("shared_library")
+///         .search_dir(PathBuf::from("plugins"))
+///         .search_dir(PathBuf::from("/usr/lib/app"))
+///         .flags(LoadFlags::new().with_now(true))
+///         .load()
+///         .expect("Failed to load shared library");
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SharedLibBuilder {
+    lib_name: String,
+    search_dirs: Vec<PathBuf>,
+    version: Option<String>,
+    conventions: Vec<FilenameConvention>,
+    flags: LoadFlags,
+    load_report: bool,
+    symbol_names: Vec<String>,
+    context: Option<String>,
+    #[cfg(feature = "verify")]
+    sha256: Option<String>,
+}
+impl SharedLibBuilder {
+    /// Create a new builder for the library with the given name (without the platform specific extension and prefix).
+    pub fn new(lib_name: impl Into<String>) -> SharedLibBuilder {
+        SharedLibBuilder {
+            lib_name: lib_name.into(),
+            search_dirs: Vec::new(),
+            version: None,
+            conventions: Vec::new(),
+            flags: LoadFlags::default(),
+            load_report: false,
+            symbol_names: Vec::new(),
+            context: None,
+            #[cfg(feature = "verify")]
+            sha256: None,
+        }
+    }
+    /// Add a directory to search for the library, after the ones added before.
+    pub fn search_dir(mut self, dir_path: PathBuf) -> SharedLibBuilder {
+        self.search_dirs.push(dir_path);
+        self
+    }
+    /// Add several directories to search for the library, after the ones added before.
+    pub fn search_dirs(mut self, dir_paths: impl IntoIterator<Item = PathBuf>) -> SharedLibBuilder {
+        self.search_dirs.extend(dir_paths);
+        self
+    }
+    /// Include the given version in the filename, see [`LibPath::with_version`].
+    pub fn version(mut self, version: impl Into<String>) -> SharedLibBuilder {
+        self.version = Some(version.into());
+        self
+    }
+    /// Add a filename convention to try, after the ones added before, see [`SharedLib::new_with_conventions`].
+    pub fn convention(mut self, convention: FilenameConvention) -> SharedLibBuilder {
+        self.conventions.push(convention);
+        self
+    }
+    /// Load the library with the given flags, see [`SharedLib::new_with_flags`].
+    pub fn flags(mut self, flags: LoadFlags) -> SharedLibBuilder {
+        self.flags = flags;
+        self
+    }
+    /// Return a [`SharedLibError::LoadReport`] if the library cannot be loaded, listing the searched directories,
+    /// every library path tried with its error and the dependencies of the library files found that cannot be located,
+    /// see [`LoadReport`].
+    ///
+    /// Errors of an invalid library name are returned as is. The dependencies are read from the library files,
+    /// which is only done once loading has failed.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let result = SharedLib::builder("shared_library").search_dir(PathBuf::from("plugins")).load_report(true).load();
+    ///     if let Err(SharedLibError::LoadReport(report)) = &result {
+    ///         for dependency in &report.missing_dependencies {
+    ///             eprintln!("Missing '{}' required by '{}'", dependency.name, dependency.required_by.display());
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn load_report(mut self, load_report: bool) -> SharedLibBuilder {
+        self.load_report = load_report;
+        self
+    }
+    /// Resolve the given symbols when the library is loaded, after the ones added before, see [`SharedLib::new_with_symbols`].
+    ///
+    /// A library file missing any of them counts as a failed attempt, so the next library path is tried.
+    pub fn require_symbols<S: Into<String>>(mut self, symbol_names: impl IntoIterator<Item = S>) -> SharedLibBuilder {
+        self.symbol_names.extend(symbol_names.into_iter().map(Into::into));
+        self
+    }
+    /// Attach the given context to the error of [`SharedLibBuilder::load`], e.g. the subsystem the library is loaded for,
+    /// see [`SharedLibError::context`].
+    pub fn context(mut self, context: impl Into<String>) -> SharedLibBuilder {
+        self.context = Some(context.into());
+        self
+    }
+    /// Verify the SHA-256 hash of the library file before loading it, see [`SharedLib::new_verified`].
+    ///
+    /// Requires a search directory, since a library found by the dynamic loader cannot be hashed before it is loaded.
+    #[cfg(feature = "verify")]
+    pub fn verify_sha256(mut self, expected_sha256: impl Into<String>) -> SharedLibBuilder {
+        self.sha256 = Some(expected_sha256.into());
+        self
+    }
+    /// Get the library paths that are tried, in order.
+    pub fn lib_paths(&self) -> Vec<LibPath> {
+        let dir_paths = match self.search_dirs.is_empty() {
+            true => vec![PathBuf::new()],
+            false => self.search_dirs.clone(),
+        };
+        let conventions = match self.conventions.is_empty() {
+            true => vec![FilenameConvention::platform()],
+            false => self.conventions.clone(),
+        };
+        let mut lib_paths = Vec::with_capacity(dir_paths.len() * conventions.len());
+        for dir_path in dir_paths {
+            for convention in &conventions {
+                let mut lib_path = LibPath::new(dir_path.clone(), self.lib_name.clone()).with_convention(convention.clone());
+                lib_path.version = self.version.clone();
+                lib_paths.push(lib_path);
+            }
+        }
+        lib_paths
+    }
+    /// Load the library from the first of the [`SharedLibBuilder::lib_paths`] it can be loaded from.
+    ///
+    /// If there is a single library path, its error is returned as is. Otherwise the paths the library cannot be loaded
+    /// from for any reason are skipped, and [`SharedLibError::NotInSearchPath`] is returned with the error of every attempt
+    /// if none of them works. The error is wrapped into [`SharedLibError::Context`] if [`SharedLibBuilder::context`] is set.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn load(mut self) -> Result<SharedLib, SharedLibError> {
+        match self.context.take() {
+            Some(context) => self.load_paths().context(context),
+            None => self.load_paths(),
+        }
+    }
+    unsafe fn load_paths(self) -> Result<SharedLib, SharedLibError> {
+        let mut attempts = Vec::new();
+        for lib_path in self.lib_paths() {
+            match self.load_path(lib_path.clone()) {
+                Ok(lib) => return Ok(lib),
+                // The library name is invalid, which does not depend on the directory.
+                Err(e) if e.is_path_error() => return Err(e),
+                Err(e) => attempts.push((lib_path, e)),
+            }
+        }
+        let error = match &attempts[..] {
+            [(_, e)] => e.clone(),
+            _ => SharedLibError::NotInSearchPath { lib_name: self.lib_name.clone(), attempts: attempts.iter().map(|(_, e)| e.clone()).collect() },
+        };
+        match self.load_report {
+            true => Err(SharedLibError::LoadReport(Box::new(LoadReport::new(self.lib_name, &self.search_dirs, attempts, error)))),
+            false => Err(error),
+        }
+    }
+    unsafe fn load_path(&self, lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
+        let mut lib = self.open_path(lib_path)?;
+        lib.prefetch(&self.symbol_names)?;
+        Ok(lib)
+    }
+    unsafe fn open_path(&self, lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
+        #[cfg(feature = "verify")]
+        if let Some(expected_sha256) = &self.sha256 {
+            if lib_path.dir().as_os_str().is_empty() {
+                let msg = String::from("Verifying the library requires a search directory.");
+                return Err(SharedLibError::load_failure(lib_path.error_path(), msg));
+            }
+            return SharedLib::open_checked(lib_path, self.flags, |path, file| crate::verify::check_sha256(path, file, expected_sha256));
+        }
+        SharedLib::open(lib_path, self.flags)
+    }
+}
+
+impl SharedLib {
+    /// Create a builder to load the library with the given name with several options at once, see [`SharedLibBuilder`].
+    pub fn builder(lib_name: impl Into<String>) -> SharedLibBuilder {
+        SharedLibBuilder::new(lib_name)
+    }
+}
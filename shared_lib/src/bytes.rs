@@ -0,0 +1,47 @@
+//! Loading of libraries from memory, through a temporary file.
+
+use crate::{ExtractionGuard, SharedLib, SharedLibError};
+
+/// Replace the characters of `name` which could change the directory of a file or directory named after it.
+pub(crate) fn sanitize_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    // `.` and `..` refer to existing directories.
+    if name.trim_matches('.').is_empty() {
+        return name.replace('.', "_") + "_";
+    }
+    name
+}
+
+impl SharedLib {
+    /// Create a new shared library from the contents of a library file, e.g. received over the network.
+    ///
+    /// The bytes are written to a new file in the temporary directory, which is loaded and removed
+    /// once the library is unloaded, unless its [`SharedLib::extraction`] is persisted. The library path of the library refers to that file,
+    /// and its library name is `name` followed by a unique suffix.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let bytes = std::fs::read("path/to/shared/library/libshared_library.so").expect("Failed to read shared library");
+    ///     let lib = SharedLib::from_bytes("shared_library", &bytes).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn from_bytes(name: &str, bytes: &[u8]) -> Result<SharedLib, SharedLibError> {
+        let (extraction, lib_path) = ExtractionGuard::create_lib(name, bytes).map_err(|e| SharedLibError::LoadFailure {
+            path: std::env::temp_dir().join(name),
+            msg: format!("Failed to write the library to a temporary file. {}", e),
+            raw_os_error: e.raw_os_error(),
+            source: None,
+        })?;
+        let mut lib = SharedLib::new(lib_path)?;
+        lib.extraction = Some(extraction);
+        Ok(lib)
+    }
+}
@@ -0,0 +1,124 @@
+//! Support for passing Rust closures as C callbacks taking a `void* user_data` argument.
+//!
+//! A [`Callback`] owns the closure and provides an `extern "C"` trampoline together with the user data pointer
+//! to register with the library. The trampoline calls the closure with the arguments it was called with.
+//!
+//! # Example
+//! ```no_run
+//! use std::ffi::c_void;
+//! use std::path::PathBuf;
+//! use shared_lib::*;
+//! unsafe {
+//!     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+//!     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+//!     let for_each_fn = lib
+//!         .get_fn::<unsafe extern "C" fn(unsafe extern "C" fn(i32, *mut c_void), *mut c_void)>("for_each")
+//!         .expect("Failed to get 'for_each' function from shared library");
+//!     let mut sum = 0;
+//!     let callback = Callback::<(i32,), ()>::new(move |value| sum += value);
+//!     for_each_fn.run(callback.trampoline(), callback.user_data());
+//! }
+//! ```
+
+use std::ffi::c_void;
+use std::ptr::NonNull;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Closures which can be wrapped in a [`Callback`], i.e. `FnMut` closures taking the arguments of the tuple `Args`.
+pub trait CallbackFn<Args, Ret> {
+    #[doc(hidden)]
+    fn call(&mut self, args: Args) -> Ret;
+}
+
+/// Tuples of the arguments of a [`Callback`], excluding the user data pointer.
+pub trait CallbackArgs<Ret>: sealed::Sealed + Sized {
+    /// Type of the trampoline taking the user data pointer as its last argument.
+    type Trampoline: Copy;
+    /// Type of the trampoline taking the user data pointer as its first argument.
+    type TrampolineDataFirst: Copy;
+    #[doc(hidden)]
+    fn trampoline() -> Self::Trampoline;
+    #[doc(hidden)]
+    fn trampoline_data_first() -> Self::TrampolineDataFirst;
+}
+
+type Closure<Args, Ret> = Box<dyn CallbackFn<Args, Ret>>;
+
+/// Structure representing a Rust closure which can be called from C through a trampoline and a user data pointer.
+///
+/// The closure is dropped together with the callback, so the callback has to outlive its registration with the library,
+/// e.g. by unregistering it before dropping the callback.
+/// The arguments are given as a tuple, e.g. `Callback<(i32, *const c_char), bool>` for a closure `FnMut(i32, *const c_char) -> bool`
+/// called through `unsafe extern "C" fn(i32, *const c_char, *mut c_void) -> bool`.
+pub struct Callback<Args, Ret> {
+    // Boxed twice, so the user data is a thin pointer to the boxed closure.
+    closure: NonNull<Closure<Args, Ret>>,
+}
+impl<Args: CallbackArgs<Ret>, Ret> Callback<Args, Ret> {
+    /// Create a new callback calling the given closure.
+    pub fn new(closure: impl CallbackFn<Args, Ret> + 'static) -> Callback<Args, Ret> {
+        let closure: Box<Closure<Args, Ret>> = Box::new(Box::new(closure));
+        Callback { closure: NonNull::from(Box::leak(closure)) }
+    }
+    /// Get the user data pointer to pass to the library, together with the trampoline.
+    pub fn user_data(&self) -> *mut c_void {
+        self.closure.as_ptr().cast()
+    }
+    /// Get the trampoline taking the user data pointer as its last argument, e.g. `unsafe extern "C" fn(i32, *mut c_void)`.
+    ///
+    /// The trampoline must only be called with the user data of a callback of the same type which is still alive,
+    /// and not from several threads at once or from within the closure itself. A panic in the closure aborts the process,
+    /// since it cannot unwind out of the `extern "C"` trampoline.
+    pub fn trampoline(&self) -> Args::Trampoline {
+        Args::trampoline()
+    }
+    /// Get the trampoline taking the user data pointer as its first argument, e.g. `unsafe extern "C" fn(*mut c_void, i32)`.
+    ///
+    /// The same rules apply as for [`Callback::trampoline`].
+    pub fn trampoline_data_first(&self) -> Args::TrampolineDataFirst {
+        Args::trampoline_data_first()
+    }
+}
+impl<Args, Ret> Drop for Callback<Args, Ret> {
+    fn drop(&mut self) {
+        // Safety: the closure was leaked in `Callback::new` and is only freed here.
+        drop(unsafe { Box::from_raw(self.closure.as_ptr()) });
+    }
+}
+
+macro_rules! impl_callback {
+    ($($a:ident: $A:ident),*) => {
+        impl<$($A),*> sealed::Sealed for ($($A,)*) {}
+        impl<Func: FnMut($($A),*) -> Ret, Ret, $($A),*> CallbackFn<($($A,)*), Ret> for Func {
+            fn call(&mut self, ($($a,)*): ($($A,)*)) -> Ret {
+                self($($a),*)
+            }
+        }
+        impl<Ret, $($A),*> CallbackArgs<Ret> for ($($A,)*) {
+            type Trampoline = unsafe extern "C" fn($($A,)* *mut c_void) -> Ret;
+            type TrampolineDataFirst = unsafe extern "C" fn(*mut c_void, $($A),*) -> Ret;
+            fn trampoline() -> Self::Trampoline {
+                unsafe extern "C" fn trampoline<Ret, $($A),*>($($a: $A,)* user_data: *mut c_void) -> Ret {
+                    (*user_data.cast::<Closure<($($A,)*), Ret>>()).call(($($a,)*))
+                }
+                trampoline::<Ret, $($A),*>
+            }
+            fn trampoline_data_first() -> Self::TrampolineDataFirst {
+                unsafe extern "C" fn trampoline<Ret, $($A),*>(user_data: *mut c_void, $($a: $A),*) -> Ret {
+                    (*user_data.cast::<Closure<($($A,)*), Ret>>()).call(($($a,)*))
+                }
+                trampoline::<Ret, $($A),*>
+            }
+        }
+    };
+}
+impl_callback!();
+impl_callback!(a1: A1);
+impl_callback!(a1: A1, a2: A2);
+impl_callback!(a1: A1, a2: A2, a3: A3);
+impl_callback!(a1: A1, a2: A2, a3: A3, a4: A4);
+impl_callback!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5);
+impl_callback!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6);
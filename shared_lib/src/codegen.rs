@@ -0,0 +1,171 @@
+//! Generation of dynamic bindings from C headers at build time, see [`DynamicBindings`].
+
+use crate::SharedLibError;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use syn::{FnArg, ForeignItem, Item, Pat};
+
+/// Structure representing the generation of a struct of dynamically looked up functions from a C header,
+/// like the `dynamic_library` mode of bindgen, to be called from a build script.
+///
+/// The header is translated by the `bindgen` command line tool, which must be installed, e.g. with `cargo install bindgen-cli`.
+/// Its functions are then declared with [`shared_lib_extern!`](crate::shared_lib_extern), so the generated struct is
+/// loaded from a [`SharedLib`](crate::SharedLib) and reports missing functions like any other API of this crate.
+/// The types, constants and other items of the header are kept as bindgen generated them. Variables and variadic
+/// functions cannot be declared with [`shared_lib_extern!`](crate::shared_lib_extern), so they are left out.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// // In build.rs
+/// let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+/// DynamicBindings::new("include/calculator.h", "CalculatorApi")
+///     .allowlist_function("calculator_.*")
+///     .write_to_file(out_dir.join("calculator.rs"))
+///     .expect("Failed to generate calculator bindings");
+/// // In the crate, which uses the generated struct like `CalculatorApi::load(&lib)`
+/// // include!(concat!(env!("OUT_DIR"), "/calculator.rs"));
+/// ```
+pub struct DynamicBindings {
+    header: PathBuf,
+    struct_name: String,
+    bindgen: PathBuf,
+    bindgen_args: Vec<OsString>,
+    clang_args: Vec<OsString>,
+}
+impl DynamicBindings {
+    /// Create a new generation of the struct named `struct_name` from the C header at `header`.
+    pub fn new(header: impl Into<PathBuf>, struct_name: impl Into<String>) -> DynamicBindings {
+        DynamicBindings {
+            header: header.into(),
+            struct_name: struct_name.into(),
+            bindgen: PathBuf::from("bindgen"),
+            bindgen_args: Vec::new(),
+            clang_args: Vec::new(),
+        }
+    }
+    /// Set the path of the `bindgen` executable, which is looked up in `PATH` by default.
+    pub fn bindgen_path(mut self, path: impl Into<PathBuf>) -> DynamicBindings {
+        self.bindgen = path.into();
+        self
+    }
+    /// Only generate the functions whose name matches the regular expression `pattern`, and the types they use.
+    ///
+    /// Can be added multiple times. Without any, every function declared by the header and the headers it includes is generated.
+    pub fn allowlist_function(mut self, pattern: impl Into<OsString>) -> DynamicBindings {
+        self.bindgen_args.push("--allowlist-function".into());
+        self.bindgen_args.push(pattern.into());
+        self
+    }
+    /// Pass an argument to clang when it parses the header, e.g. `-Iinclude` or `-DCALCULATOR_API=`.
+    pub fn clang_arg(mut self, arg: impl Into<OsString>) -> DynamicBindings {
+        self.clang_args.push(arg.into());
+        self
+    }
+    /// Run bindgen on the header and generate the Rust source of the bindings.
+    /// # Errors
+    /// Returns [`SharedLibError::BindingsFailure`] if bindgen cannot be run or fails to translate the header.
+    pub fn generate(&self) -> Result<String, SharedLibError> {
+        let output = Command::new(&self.bindgen)
+            .arg(&self.header)
+            .arg("--no-layout-tests")
+            .args(&self.bindgen_args)
+            .arg("--")
+            .args(&self.clang_args)
+            .output()
+            .map_err(|e| SharedLibError::BindingsFailure(format!("Failed to run '{}'. {}", self.bindgen.display(), e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(SharedLibError::BindingsFailure(format!("bindgen failed to translate header '{}'. {}", self.header.display(), stderr.trim())));
+        }
+        dynamic_bindings(&String::from_utf8_lossy(&output.stdout), &self.struct_name)
+    }
+    /// Generate the bindings like [`DynamicBindings::generate`] and write them to the file at `path`,
+    /// telling cargo to rerun the build script when the header changes.
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> Result<(), SharedLibError> {
+        let bindings = self.generate()?;
+        let path = path.as_ref();
+        std::fs::write(path, bindings)
+            .map_err(|e| SharedLibError::BindingsFailure(format!("Failed to write bindings to '{}'. {}", path.display(), e)))?;
+        println!("cargo:rerun-if-changed={}", self.header.display());
+        Ok(())
+    }
+}
+
+/// Turn the Rust source generated by bindgen into dynamic bindings, declaring the functions of its `extern` blocks
+/// with [`shared_lib_extern!`](crate::shared_lib_extern) as a struct named `struct_name`.
+///
+/// Every other item is kept as is. The functions must share the same ABI, since they are declared in a single block.
+/// # Errors
+/// Returns [`SharedLibError::BindingsFailure`] if the source cannot be parsed, the functions have different ABIs,
+/// or `struct_name` is not a valid identifier.
+pub fn dynamic_bindings(bindings: &str, struct_name: &str) -> Result<String, SharedLibError> {
+    let file = syn::parse_file(bindings).map_err(|e| SharedLibError::BindingsFailure(format!("Failed to parse the generated bindings. {}", e)))?;
+    let struct_name = syn::parse_str::<syn::Ident>(struct_name)
+        .map_err(|_| SharedLibError::BindingsFailure(format!("'{}' is not a valid struct name.", struct_name)))?;
+    let (mut items, mut fns) = (Vec::new(), Vec::new());
+    let mut abi: Option<String> = None;
+    for item in file.items {
+        let Item::ForeignMod(block) = item else {
+            items.push(item);
+            continue;
+        };
+        let block_abi = block.abi.name.as_ref().map_or_else(|| "C".to_owned(), |name| name.value());
+        for item in block.items {
+            let ForeignItem::Fn(func) = item else {
+                continue;
+            };
+            if func.sig.variadic.is_some() {
+                continue;
+            }
+            match &abi {
+                Some(abi) if *abi != block_abi => {
+                    return Err(SharedLibError::BindingsFailure(format!(
+                        "Function '{}' has ABI \"{}\", while the other functions have \"{}\".",
+                        func.sig.ident, block_abi, abi
+                    )))
+                }
+                _ => abi = Some(block_abi.clone()),
+            }
+            fns.push(extern_fn(func));
+        }
+    }
+    let abi = abi.unwrap_or_else(|| "C".to_owned());
+    let tokens = quote! {
+        #(#items)*
+        ::shared_lib::shared_lib_extern! {
+            pub struct #struct_name;
+            extern #abi {
+                #(#fns)*
+            }
+        }
+    };
+    Ok(tokens.to_string())
+}
+
+/// Declare a function of an `extern` block in the form [`shared_lib_extern!`](crate::shared_lib_extern) accepts,
+/// keeping its documentation and link name and naming its unnamed arguments.
+fn extern_fn(func: syn::ForeignItemFn) -> TokenStream {
+    let docs = func.attrs.iter().filter(|attr| attr.path().is_ident("doc"));
+    let link_name = func.attrs.iter().filter(|attr| attr.path().is_ident("link_name"));
+    let name = &func.sig.ident;
+    let args = func.sig.inputs.iter().enumerate().filter_map(|(i, arg)| match arg {
+        FnArg::Typed(arg) => {
+            let arg_name = match &*arg.pat {
+                Pat::Ident(pat) => pat.ident.clone(),
+                _ => format_ident!("arg{}", i + 1),
+            };
+            let ty = &arg.ty;
+            Some(quote!(#arg_name: #ty))
+        }
+        FnArg::Receiver(_) => None,
+    });
+    let output = &func.sig.output;
+    quote! {
+        #(#docs)*
+        #(#link_name)*
+        fn #name(#(#args),*) #output;
+    }
+}
@@ -0,0 +1,196 @@
+//! Creating COM objects from in-process COM servers, i.e. DLLs exporting `DllGetClassObject`, see [`SharedLib::create_com_instance`].
+
+use crate::{SharedLib, SharedLibError};
+use crate::loader::Library;
+use std::ffi::c_void;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+type HResult = i32;
+const S_OK: HResult = 0;
+const E_POINTER: HResult = 0x8000_4003_u32 as HResult;
+
+/// Globally unique identifier of a COM class (CLSID) or interface (IID), laid out like the Windows `GUID`.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// const CLSID_CODEC: Guid = Guid::from_u128(0x7c3a1e20_5b1f_4c8a_9d2e_0123456789ab);
+/// assert_eq!("{7C3A1E20-5B1F-4C8A-9D2E-0123456789AB}".parse::<Guid>().unwrap(), CLSID_CODEC);
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+impl Guid {
+    /// IID of `IUnknown`, implemented by every COM object.
+    pub const IUNKNOWN: Guid = Guid::from_u128(0x00000000_0000_0000_c000_000000000046);
+    /// IID of `IClassFactory`, the interface of the class objects returned by `DllGetClassObject`.
+    pub const ICLASSFACTORY: Guid = Guid::from_u128(0x00000001_0000_0000_c000_000000000046);
+
+    /// Create a GUID from its value written as a number, e.g. `0x00000000_0000_0000_c000_000000000046` for `IUnknown`.
+    pub const fn from_u128(uuid: u128) -> Guid {
+        Guid {
+            data1: (uuid >> 96) as u32,
+            data2: (uuid >> 80) as u16,
+            data3: (uuid >> 64) as u16,
+            data4: (uuid as u64).to_be_bytes(),
+        }
+    }
+}
+/// Displays the GUID in the registry format, e.g. `{00000000-0000-0000-C000-000000000046}`.
+impl std::fmt::Display for Guid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let d = &self.data4;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}}}",
+            self.data1, self.data2, self.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7]
+        )
+    }
+}
+/// Parses a GUID in the registry format, with or without the braces.
+impl std::str::FromStr for Guid {
+    type Err = SharedLibError;
+    fn from_str(s: &str) -> Result<Guid, SharedLibError> {
+        let invalid = || SharedLibError::InvalidGuid(s.to_owned());
+        let trimmed = s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).unwrap_or(s);
+        let groups: Vec<&str> = trimmed.split('-').collect();
+        if groups.iter().map(|group| group.len()).ne([8, 4, 4, 4, 12]) {
+            return Err(invalid());
+        }
+        let hex: String = groups.concat();
+        u128::from_str_radix(&hex, 16).map(Guid::from_u128).map_err(|_| invalid())
+    }
+}
+
+/// Methods of `IUnknown`, which start the virtual table of every COM interface.
+#[repr(C)]
+struct IUnknownVtbl {
+    query_interface: unsafe extern "system" fn(*mut c_void, *const Guid, *mut *mut c_void) -> HResult,
+    add_ref: unsafe extern "system" fn(*mut c_void) -> u32,
+    release: unsafe extern "system" fn(*mut c_void) -> u32,
+}
+#[repr(C)]
+struct IClassFactoryVtbl {
+    unknown: IUnknownVtbl,
+    create_instance: unsafe extern "system" fn(*mut c_void, *mut c_void, *const Guid, *mut *mut c_void) -> HResult,
+    #[allow(dead_code)]
+    lock_server: unsafe extern "system" fn(*mut c_void, i32) -> HResult,
+}
+
+/// Structure representing a reference to a COM object created by a library, which keeps the library loaded.
+///
+/// The reference is released when it is dropped, and cloning it adds a reference like `AddRef`. Like an [`OwnedSharedLibFn`](crate::OwnedSharedLibFn),
+/// it holds onto the library, so the library cannot be unloaded before every object created through it is released.
+pub struct ComPtr {
+    ptr: NonNull<c_void>,
+    path: String,
+    // Declared after `ptr`, so the library is released after the object.
+    lib: Arc<Library>,
+}
+// COM objects are shared between threads according to their threading model, which is up to the caller.
+unsafe impl Send for ComPtr {}
+unsafe impl Sync for ComPtr {}
+impl ComPtr {
+    /// Get the interface pointer, e.g. to pass it to another COM API, without releasing it.
+    pub fn as_raw(&self) -> *mut c_void {
+        self.ptr.as_ptr()
+    }
+    /// Get the virtual table of the interface, whose layout `V` has to match it, starting with the methods of `IUnknown`.
+    /// Its methods take [`ComPtr::as_raw`] as their first argument.
+    /// # Safety
+    /// This function is unsafe because the layout of the virtual table is not checked.
+    pub unsafe fn vtable<V>(&self) -> &V {
+        &**self.ptr.as_ptr().cast::<*const V>()
+    }
+    /// Get another interface of the object by its IID, like `QueryInterface`.
+    pub fn query_interface(&self, iid: &Guid) -> Result<ComPtr, SharedLibError> {
+        let mut ptr = std::ptr::null_mut();
+        // Safety: every COM interface starts with the methods of `IUnknown`.
+        let result = unsafe { (self.unknown().query_interface)(self.as_raw(), iid, &mut ptr) };
+        ComPtr::new(ptr, result, "QueryInterface", &self.path, Arc::clone(&self.lib))
+    }
+    fn unknown(&self) -> &IUnknownVtbl {
+        unsafe { self.vtable::<IUnknownVtbl>() }
+    }
+    /// Wrap the interface pointer returned by a COM method together with its result.
+    fn new(ptr: *mut c_void, result: HResult, method: &str, path: &str, lib: Arc<Library>) -> Result<ComPtr, SharedLibError> {
+        let failure = |hresult| SharedLibError::ComFailure { path: path.to_owned(), method: method.to_owned(), hresult };
+        match (result, NonNull::new(ptr)) {
+            (S_OK, Some(ptr)) => Ok(ComPtr { ptr, path: path.to_owned(), lib }),
+            (S_OK, None) => Err(failure(E_POINTER)),
+            (hresult, _) => Err(failure(hresult)),
+        }
+    }
+}
+impl Clone for ComPtr {
+    fn clone(&self) -> ComPtr {
+        unsafe { (self.unknown().add_ref)(self.as_raw()) };
+        ComPtr { ptr: self.ptr, path: self.path.clone(), lib: Arc::clone(&self.lib) }
+    }
+}
+impl Drop for ComPtr {
+    fn drop(&mut self) {
+        unsafe { (self.unknown().release)(self.as_raw()) };
+    }
+}
+
+impl SharedLib {
+    /// Get the class object of the COM class `clsid` from the library with `DllGetClassObject`, as its interface `iid`,
+    /// e.g. [`Guid::ICLASSFACTORY`].
+    ///
+    /// Returns [`SharedLibError::ComFailure`] with the `HRESULT` if the library cannot provide it,
+    /// e.g. `CLASS_E_CLASSNOTAVAILABLE` (`0x80040111`) for an unknown CLSID.
+    /// # Safety
+    /// This function is unsafe because it calls a function of the shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn get_class_object(&self, clsid: &Guid, iid: &Guid) -> Result<ComPtr, SharedLibError> {
+        type DllGetClassObject = unsafe extern "system" fn(*const Guid, *const Guid, *mut *mut c_void) -> HResult;
+        let get_class_object = self.get_fn::<DllGetClassObject>("DllGetClassObject")?;
+        let mut ptr = std::ptr::null_mut();
+        // `run` is only implemented for the Rust and C ABIs, which differ from `system` on 32-bit Windows.
+        let result = (get_class_object.symbol)(clsid, iid, &mut ptr);
+        ComPtr::new(ptr, result, "DllGetClassObject", &self.lib_path.lossy_path(), Arc::clone(self.library()?))
+    }
+    /// Create an object of the COM class `clsid` implemented by the library, as its interface `iid`,
+    /// without registering the library or going through `CoCreateInstance`.
+    ///
+    /// The class factory is looked up with [`SharedLib::get_class_object`], asked to create the object and released.
+    /// The returned [`ComPtr`] keeps the library loaded, so closing or reloading the library fails while it is alive.
+    /// COM itself has to be initialized on the calling thread, e.g. with `CoInitializeEx`, if the object uses it.
+    /// # Safety
+    /// This function is unsafe because it calls functions of the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// const CLSID_CODEC: Guid = Guid::from_u128(0x7c3a1e20_5b1f_4c8a_9d2e_0123456789ab);
+    /// const IID_ICODEC: Guid = Guid::from_u128(0x1b9e4c70_2d3a_4f6e_8a1b_fedcba987654);
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "codec".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let codec = lib.create_com_instance(&CLSID_CODEC, &IID_ICODEC).expect("Failed to create the codec");
+    ///     let unknown = codec.query_interface(&Guid::IUNKNOWN).expect("Failed to query IUnknown");
+    /// }
+    /// ```
+    pub unsafe fn create_com_instance(&self, clsid: &Guid, iid: &Guid) -> Result<ComPtr, SharedLibError> {
+        let factory = self.get_class_object(clsid, &Guid::ICLASSFACTORY)?;
+        let mut ptr = std::ptr::null_mut();
+        let result = (factory.vtable::<IClassFactoryVtbl>().create_instance)(factory.as_raw(), std::ptr::null_mut(), iid, &mut ptr);
+        ComPtr::new(ptr, result, "IClassFactory::CreateInstance", &factory.path, Arc::clone(&factory.lib))
+    }
+    /// Check if the library can be unloaded, i.e. none of its COM objects are in use, with `DllCanUnloadNow`.
+    ///
+    /// Objects handed out by the library to other code, or locked with `IClassFactory::LockServer`, are not tracked
+    /// by [`ComPtr`], so this should return `true` before the library is closed.
+    /// # Safety
+    /// This function is unsafe because it calls a function of the shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn can_unload_now(&self) -> Result<bool, SharedLibError> {
+        let can_unload_now = self.get_fn::<unsafe extern "system" fn() -> HResult>("DllCanUnloadNow")?;
+        Ok((can_unload_now.symbol)() == S_OK)
+    }
+}
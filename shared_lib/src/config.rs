@@ -0,0 +1,151 @@
+//! Loading of the libraries listed in a TOML config file, see [`load_from_config`].
+//!
+//! The config file lists every library with the directories it is searched in, the symbols it has to export
+//! and whether the application can run without it:
+//! ```toml
+//! [[libraries]]
+//! # Library name without the platform specific extension and prefix.
+//! name = "audio"
+//! # Directories the library is searched in, relative to the config file. The system search is used if empty.
+//! search_dirs = ["plugins", "/usr/local/lib"]
+//! # Symbols the library has to export, see `SharedLibBuilder::require_symbols`.
+//! required_symbols = ["audio_init"]
+//! # Whether a failure to load the library is only reported instead of failing the whole config.
+//! optional = true
+//! # Flags the library is loaded with, see `LoadFlags`.
+//! flags = { now = true }
+//! ```
+
+use crate::{ErrorContext, LoadFlags, SharedLib, SharedLibError};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Structure representing a config file listing the libraries to load, see [`load_from_config`].
+///
+/// Besides reading it from a TOML file with [`LibConfig::from_file`], it can be deserialized from any other format supported by serde, e.g. JSON.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LibConfig {
+    /// Libraries to load, in order.
+    #[serde(default)]
+    pub libraries: Vec<LibConfigEntry>,
+}
+
+/// Structure representing a library listed in a [`LibConfig`].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LibConfigEntry {
+    /// Name of the library without the platform specific extension and prefix, which has to be unique in the config.
+    pub name: String,
+    /// Directories the library is searched in, in order, see [`SharedLibBuilder::search_dirs`](crate::SharedLibBuilder::search_dirs).
+    #[serde(default)]
+    pub search_dirs: Vec<PathBuf>,
+    /// Symbols the library has to export, see [`SharedLibBuilder::require_symbols`](crate::SharedLibBuilder::require_symbols).
+    #[serde(default)]
+    pub required_symbols: Vec<String>,
+    /// Whether a failure to load the library is only reported in [`LoadedLibs::report`] instead of failing the whole config.
+    #[serde(default)]
+    pub optional: bool,
+    /// Flags the library is loaded with.
+    #[serde(default)]
+    pub flags: LoadFlags,
+}
+
+/// Structure representing the libraries loaded from a [`LibConfig`].
+pub struct LoadedLibs {
+    /// Loaded libraries by their names.
+    pub libs: HashMap<String, SharedLib>,
+    /// Outcome of loading every library of the config, in order.
+    pub report: Vec<LibLoadOutcome>,
+}
+
+/// Structure representing the outcome of loading a library listed in a [`LibConfig`].
+#[derive(Clone, Debug)]
+pub struct LibLoadOutcome {
+    /// Name of the library.
+    pub name: String,
+    /// Error the library failed to load with, if any, which only happens for optional libraries.
+    pub error: Option<SharedLibError>,
+}
+
+impl LibConfig {
+    /// Read and validate the TOML config file at `path`.
+    ///
+    /// Relative search directories are resolved against the directory of the config file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<LibConfig, SharedLibError> {
+        let path = path.as_ref();
+        let invalid = |msg: String| SharedLibError::InvalidConfig { path: path.to_string_lossy().to_string(), msg };
+        let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let mut config: LibConfig = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        config.validate().map_err(invalid)?;
+        let config_dir = path.parent().unwrap_or(Path::new(""));
+        for entry in &mut config.libraries {
+            for dir in &mut entry.search_dirs {
+                if dir.is_relative() {
+                    *dir = config_dir.join(&*dir);
+                }
+            }
+        }
+        Ok(config)
+    }
+    /// Check that the libraries have unique, non-empty names.
+    fn validate(&self) -> Result<(), String> {
+        for (index, entry) in self.libraries.iter().enumerate() {
+            if entry.name.is_empty() {
+                return Err("The library name is empty.".into());
+            }
+            if self.libraries[..index].iter().any(|other| other.name == entry.name) {
+                return Err(format!("Library '{}' is listed more than once.", entry.name));
+            }
+        }
+        Ok(())
+    }
+    /// Load the libraries in order, see [`load_from_config`].
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    pub unsafe fn load(&self) -> Result<LoadedLibs, SharedLibError> {
+        self.validate().map_err(|msg| SharedLibError::InvalidConfig { path: String::new(), msg })?;
+        let mut loaded = LoadedLibs { libs: HashMap::new(), report: Vec::new() };
+        for entry in &self.libraries {
+            let result = SharedLib::builder(&entry.name)
+                .search_dirs(entry.search_dirs.iter().cloned())
+                .require_symbols(&entry.required_symbols)
+                .flags(entry.flags)
+                .load();
+            match result {
+                Ok(lib) => {
+                    loaded.libs.insert(entry.name.clone(), lib);
+                    loaded.report.push(LibLoadOutcome { name: entry.name.clone(), error: None });
+                }
+                Err(e) if entry.optional => loaded.report.push(LibLoadOutcome { name: entry.name.clone(), error: Some(e) }),
+                Err(e) => return Err(e).with_context(|| format!("Loading required library '{}'", entry.name)),
+            }
+        }
+        Ok(loaded)
+    }
+}
+
+/// Load the libraries listed in the TOML config file at `path`, see [`LibConfig`].
+///
+/// Libraries are loaded in the order they are listed. Returns the loaded libraries by their names together with
+/// the outcome of every library. A failure to load an optional library is only reported, while a failure to load
+/// any other library unloads the libraries loaded before it and returns the error.
+/// # Safety
+/// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// unsafe {
+///     let loaded = load_from_config("libraries.toml").expect("Failed to load the required libraries");
+///     for outcome in &loaded.report {
+///         if let Some(error) = &outcome.error {
+///             eprintln!("Optional library '{}' is not available. {}", outcome.name, error);
+///         }
+///     }
+///     let audio = loaded.libs.get("audio");
+/// }
+/// ```
+pub unsafe fn load_from_config(path: impl AsRef<Path>) -> Result<LoadedLibs, SharedLibError> {
+    LibConfig::from_file(path)?.load()
+}
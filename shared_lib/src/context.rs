@@ -0,0 +1,50 @@
+//! Attaching the context an error occurred in, see [`SharedLibError::context`] and [`ErrorContext`].
+
+use crate::SharedLibError;
+
+impl SharedLibError {
+    /// Wrap the error into [`SharedLibError::Context`] describing what was being done when it occurred,
+    /// e.g. which subsystem failed to load its library when several libraries are loaded at startup.
+    ///
+    /// The context is prepended to the message of the error, and the wrapped error is its [`source`](std::error::Error::source).
+    /// The error is classified like the wrapped one, e.g. by [`SharedLibError::is_load_error`].
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// let err = SharedLibError::PathEmpty.context("loading audio backend");
+    /// assert_eq!(err.to_string(), "loading audio backend: Path is empty.");
+    /// ```
+    pub fn context(self, context: impl Into<String>) -> SharedLibError {
+        SharedLibError::Context { context: context.into(), source: Box::new(self) }
+    }
+    /// Get the error wrapped by the contexts attached with [`SharedLibError::context`], or the error itself if there are none.
+    pub fn without_context(&self) -> &SharedLibError {
+        match self {
+            SharedLibError::Context { source, .. } => source.without_context(),
+            _ => self,
+        }
+    }
+}
+
+/// Extension trait attaching context to the error of a [`Result`], see [`SharedLibError::context`].
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// unsafe {
+///     let lib = SharedLib::new(LibPath::new_no_path("audio_backend".into())).context("loading audio backend");
+/// }
+/// ```
+pub trait ErrorContext<T> {
+    /// Attach `context` to the error, if any.
+    fn context(self, context: impl Into<String>) -> Result<T, SharedLibError>;
+    /// Attach the context returned by `f` to the error, if any, only building it when there is an error.
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> Result<T, SharedLibError>;
+}
+impl<T> ErrorContext<T> for Result<T, SharedLibError> {
+    fn context(self, context: impl Into<String>) -> Result<T, SharedLibError> {
+        self.map_err(|e| e.context(context))
+    }
+    fn with_context<C: Into<String>>(self, f: impl FnOnce() -> C) -> Result<T, SharedLibError> {
+        self.map_err(|e| e.context(f()))
+    }
+}
@@ -0,0 +1,87 @@
+//! Lookup of functions exported by 32-bit Windows DLLs, by their decorated names or by ordinal.
+//!
+//! Functions using the `stdcall`, `fastcall` or `vectorcall` calling conventions are exported with decorated names,
+//! e.g. `_Foo@8`, `@Foo@8` or `Foo@@8`, unless the DLL is built with a module-definition file undecorating them.
+
+use crate::{inspect, FnSignature, SharedLib, SharedLibError, SharedLibFn};
+
+/// Get the name of a function from its `stdcall`, `fastcall` or `vectorcall` decorated name, e.g. `Foo` of `_Foo@8`.
+///
+/// Returns `None` if the name is not decorated.
+pub(crate) fn undecorate(symbol: &str) -> Option<&str> {
+    let (name, arg_size) = symbol.rsplit_once('@')?;
+    if arg_size.is_empty() || !arg_size.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let name = match name.strip_suffix('@') {
+        Some(name) => name,
+        None => name.strip_prefix(['_', '@']).unwrap_or(name),
+    };
+    (!name.is_empty()).then_some(name)
+}
+
+impl SharedLib {
+    /// Find the decorated name of the function named `fn_name` in the export table of the library file.
+    pub(crate) fn find_decorated(&self, fn_name: &str) -> Option<String> {
+        let symbols = inspect::exported_symbols(self.lib_path.resolve().ok()?).ok()?;
+        symbols.into_iter().find(|symbol| undecorate(symbol) == Some(fn_name))
+    }
+    /// Get a function by the ordinal it is exported with from the DLL, e.g. for functions exported without a name.
+    ///
+    /// Ordinals are only supported on Windows, so other platforms return [`SharedLibError::Unsupported`].
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn_by_ordinal::<extern "system" fn(i32, i32) -> i32>(12).expect("Failed to get function #12 from shared library");
+    /// }
+    /// ```
+    #[cfg(windows)]
+    pub unsafe fn get_fn_by_ordinal<T: FnSignature>(&self, ordinal: u16) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        use crate::FnSymbol;
+        use std::ffi::c_void;
+        use std::os::windows::ffi::OsStrExt;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            fn GetModuleHandleExW(flags: u32, module_name: *const u16, module: *mut *mut c_void) -> i32;
+            fn GetProcAddress(module: *mut c_void, proc_name: *const u8) -> *mut c_void;
+        }
+        const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: u32 = 0x2;
+
+        assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<*mut c_void>(), "the function type must be a function pointer");
+        self.library()?;
+        let symbol_not_found = |e: std::io::Error| SharedLibError::symbol_not_found(format!("#{}", ordinal), self.lib_path.error_path(), e);
+        let name: Option<Vec<u16>> = match self.lib_path.lib_name.is_empty() {
+            true => None,
+            false => Some(self.lib_path.path()?.as_os_str().encode_wide().chain(Some(0)).collect()),
+        };
+        let mut module = std::ptr::null_mut();
+        // The reference count is left unchanged, since the library stays loaded through the handle it was loaded with.
+        let name_ptr = name.as_ref().map_or(std::ptr::null(), |name| name.as_ptr());
+        if GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT, name_ptr, &mut module) == 0 {
+            return Err(symbol_not_found(std::io::Error::last_os_error()));
+        }
+        // An ordinal is passed as the name, in the low word of a pointer whose high word is zero.
+        let pointer = GetProcAddress(module, usize::from(ordinal) as *const u8);
+        if pointer.is_null() {
+            return Err(symbol_not_found(std::io::Error::last_os_error()));
+        }
+        let func = std::mem::transmute_copy::<*mut c_void, T>(&pointer);
+        Ok(SharedLibFn { symbol: FnSymbol::Resolved(func, std::marker::PhantomData), metrics: None })
+    }
+    /// Get a function by the ordinal it is exported with from the DLL, e.g. for functions exported without a name.
+    ///
+    /// Ordinals are only supported on Windows, so other platforms return [`SharedLibError::Unsupported`].
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    #[cfg(not(windows))]
+    pub unsafe fn get_fn_by_ordinal<T: FnSignature>(&self, _ordinal: u16) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        Err(SharedLibError::Unsupported("Looking up functions by ordinal".into()))
+    }
+}
@@ -0,0 +1,37 @@
+//! Helpers for displaying and looking up mangled C++ symbol names.
+
+/// Format a symbol name for display, followed by its demangled form if it is a mangled C++ name.
+pub(crate) fn display_name(name: &str) -> String {
+    #[cfg(feature = "demangle")]
+    if let Some(demangled) = demangle(name) {
+        return format!("'{}' ({})", name, demangled);
+    }
+    format!("'{}'", name)
+}
+
+/// Demangle an Itanium or MSVC C++ symbol name, returning `None` if the name is not mangled.
+///
+/// MSVC names are demangled without the return type, calling convention and access specifiers,
+/// so both schemes produce names like `math::add(int, int)`.
+#[cfg(feature = "demangle")]
+pub(crate) fn demangle(name: &str) -> Option<String> {
+    if name.starts_with('?') {
+        use msvc_demangler::DemangleFlags;
+        let flags = DemangleFlags::NO_FUNCTION_RETURNS
+            | DemangleFlags::NO_MS_KEYWORDS
+            | DemangleFlags::NO_ACCESS_SPECIFIERS
+            | DemangleFlags::NO_MEMBER_TYPE
+            | DemangleFlags::NO_THISTYPE
+            | DemangleFlags::NO_CLASS_TYPE
+            | DemangleFlags::SPACE_AFTER_COMMA
+            | DemangleFlags::HUG_TYPE;
+        return msvc_demangler::demangle(name, flags).ok();
+    }
+    cpp_demangle::Symbol::new(name).ok()?.demangle().ok()
+}
+
+/// Get the qualified name of a demangled function without its parameters, e.g. `math::add` of `math::add(int, int)`.
+#[cfg(feature = "demangle")]
+pub(crate) fn function_name(demangled: &str) -> &str {
+    demangled.split_once('(').map_or(demangled, |(name, _)| name).trim()
+}
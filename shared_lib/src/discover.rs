@@ -0,0 +1,204 @@
+//! Discovery of the shared libraries in a directory, e.g. the plugins of an application.
+
+use crate::{LibPath, SharedLibError};
+use std::collections::VecDeque;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+/// Get the library paths of every file in `dir` whose filename matches the platform library naming convention,
+/// e.g. `libfoo.so` and `libbar.so.1` on Linux, sorted by filename.
+///
+/// Subdirectories are not searched. A version in the filename is split off into [`LibPath::version`],
+/// so a library with versioned links, e.g. `libfoo.so` and `libfoo.so.1`, is listed once for every link.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// for lib_path in discover_libs("plugins").expect("Failed to read the plugin directory") {
+///     println!("Found plugin '{}'", lib_path.lib_name());
+/// }
+/// ```
+pub fn discover_libs(dir: impl AsRef<Path>) -> Result<Vec<LibPath>, SharedLibError> {
+    discover_libs_matching(dir, "*")
+}
+
+/// Get the library paths of every file in `dir` like [`discover_libs`], keeping the ones whose library name
+/// matches the glob `pattern`, e.g. `plugin_*`.
+///
+/// The pattern is matched against the library name without the platform prefix and extension,
+/// where `*` matches any number of characters and `?` matches a single one.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// let lib_paths = discover_libs_matching("plugins", "plugin_*").expect("Failed to read the plugin directory");
+/// ```
+pub fn discover_libs_matching(dir: impl AsRef<Path>, pattern: &str) -> Result<Vec<LibPath>, SharedLibError> {
+    let dir = dir.as_ref();
+    let entries = read_dir(dir)?;
+    Ok(entries.libs.into_iter().map(|(_, lib_path)| lib_path).filter(|lib_path| glob_match(pattern, &lib_path.lib_name)).collect())
+}
+
+/// Library files and subdirectories of a directory, both sorted by name.
+struct DirEntries {
+    /// Filenames and library paths of the library files.
+    libs: Vec<(OsString, LibPath)>,
+    /// Names of the subdirectories, without the links to directories, so walking them cannot loop.
+    dirs: Vec<OsString>,
+}
+
+/// Get the library files and subdirectories of `dir`.
+fn read_dir(dir: &Path) -> Result<DirEntries, SharedLibError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| SharedLibError::LoadFailure {
+        path: dir.to_path_buf(),
+        msg: format!("Failed to read the library directory. {}", e),
+        raw_os_error: e.raw_os_error(),
+        source: None,
+    })?;
+    let (mut filenames, mut dir_names) = (Vec::new(), Vec::new());
+    for entry in entries.filter_map(Result::ok) {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            dir_names.push(entry.file_name());
+        } else if entry.path().is_file() {
+            filenames.push(entry.file_name());
+        }
+    }
+    filenames.sort();
+    dir_names.sort();
+    let libs = filenames.into_iter().filter_map(|filename| Some((filename.clone(), LibPath::from_filename(dir.to_path_buf(), &filename).ok()?)));
+    Ok(DirEntries { libs: libs.collect(), dirs: dir_names })
+}
+
+/// Structure representing a recursive search for shared libraries in a directory tree, e.g. plugins nested per vendor.
+///
+/// Directories are walked depth first in order of their names, listing the libraries of a directory
+/// before the ones of its subdirectories. Links to directories are not followed.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// let walk = LibWalker::new("plugins")
+///     .max_depth(2)
+///     .ignore("target")
+///     .matching("plugin_*")
+///     .filter(|lib_path| lib_path.version().is_none())
+///     .walk();
+/// for lib_path in walk {
+///     match lib_path {
+///         Ok(lib_path) => println!("Found plugin '{}'", lib_path),
+///         Err(e) => eprintln!("{}", e),
+///     }
+/// }
+/// ```
+pub struct LibWalker {
+    root: PathBuf,
+    max_depth: Option<usize>,
+    ignore: Vec<String>,
+    pattern: String,
+    filter: Option<LibFilter>,
+}
+type LibFilter = Box<dyn FnMut(&LibPath) -> bool>;
+impl LibWalker {
+    /// Create a new search of the directory tree rooted at `root`, without any limits or filters.
+    pub fn new(root: impl Into<PathBuf>) -> LibWalker {
+        LibWalker { root: root.into(), max_depth: None, ignore: Vec::new(), pattern: "*".into(), filter: None }
+    }
+    /// Only search directories up to `max_depth` levels below the root, where 0 only searches the root itself.
+    pub fn max_depth(mut self, max_depth: usize) -> LibWalker {
+        self.max_depth = Some(max_depth);
+        self
+    }
+    /// Skip the files and directories whose name matches the glob `pattern`, e.g. `target` or `*.bak`.
+    ///
+    /// Patterns are matched like in [`discover_libs_matching`], and can be added multiple times.
+    pub fn ignore(mut self, pattern: impl Into<String>) -> LibWalker {
+        self.ignore.push(pattern.into());
+        self
+    }
+    /// Only list the libraries whose library name matches the glob `pattern`, see [`discover_libs_matching`].
+    pub fn matching(mut self, pattern: impl Into<String>) -> LibWalker {
+        self.pattern = pattern.into();
+        self
+    }
+    /// Only list the libraries for which `filter` returns `true`, e.g. to check them against a list of allowed vendors.
+    pub fn filter(mut self, filter: impl FnMut(&LibPath) -> bool + 'static) -> LibWalker {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+    /// Start the search, returning an iterator over the libraries found.
+    ///
+    /// Directories are only read when the iterator gets to them, and a directory that cannot be read
+    /// is reported as an error item without ending the search.
+    pub fn walk(self) -> LibWalk {
+        let dirs = vec![(self.root.clone(), 0)];
+        LibWalk { walker: self, dirs, libs: VecDeque::new() }
+    }
+}
+
+/// Iterator over the libraries found by a [`LibWalker`].
+pub struct LibWalk {
+    walker: LibWalker,
+    // Directories left to read with their depth, the next one last.
+    dirs: Vec<(PathBuf, usize)>,
+    // Filenames and library paths of the libraries of the directory read last which are not listed yet.
+    libs: VecDeque<(OsString, LibPath)>,
+}
+impl LibWalk {
+    fn is_ignored(&self, name: &OsStr) -> bool {
+        let name = name.to_string_lossy();
+        self.walker.ignore.iter().any(|pattern| glob_match(pattern, &name))
+    }
+}
+impl Iterator for LibWalk {
+    type Item = Result<LibPath, SharedLibError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while let Some((filename, lib_path)) = self.libs.pop_front() {
+                if self.is_ignored(&filename) || !glob_match(&self.walker.pattern, &lib_path.lib_name) {
+                    continue;
+                }
+                if self.walker.filter.as_mut().is_none_or(|filter| filter(&lib_path)) {
+                    return Some(Ok(lib_path));
+                }
+            }
+            let (dir, depth) = self.dirs.pop()?;
+            let entries = match read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => return Some(Err(e)),
+            };
+            self.libs.extend(entries.libs);
+            if self.walker.max_depth.is_none_or(|max_depth| depth < max_depth) {
+                let dirs = entries.dirs.into_iter().rev().filter(|name| !self.is_ignored(name)).map(|name| (dir.join(name), depth + 1));
+                let dirs: Vec<_> = dirs.collect();
+                self.dirs.extend(dirs);
+            }
+        }
+    }
+}
+
+/// Check if `name` matches the glob `pattern`, in which `*` matches any number of characters and `?` a single one.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and of the name it was matched at, to backtrack to.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the last `*` match one more character.
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,55 @@
+//! Bindings to the parts of the dynamic loader API which libloading does not expose.
+
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::path::Path;
+
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlclose(handle: *mut c_void) -> c_int;
+    fn dlerror() -> *mut c_char;
+}
+
+#[cfg(target_os = "macos")]
+const RTLD_NOLOAD: c_int = 0x10;
+#[cfg(not(target_os = "macos"))]
+const RTLD_NOLOAD: c_int = 0x4;
+
+/// Get the message of the last error of the dynamic loader, which is empty if there was none.
+pub(crate) unsafe fn last_error() -> String {
+    let msg = dlerror();
+    if msg.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(msg).to_string_lossy().to_string()
+    }
+}
+
+/// Another handle of an already loaded library, closed when dropped.
+///
+/// libloading does not expose the handles of its libraries, so a new one is opened with `RTLD_NOLOAD`.
+pub(crate) struct NoLoadHandle(pub(crate) *mut c_void);
+impl NoLoadHandle {
+    /// Get a new handle of the library at `path`, which is already loaded, or of the main program if `path` is `None`.
+    pub(crate) unsafe fn open(path: Option<&Path>) -> Result<NoLoadHandle, String> {
+        let filename = path.map(|path| CString::new(path.as_os_str().as_encoded_bytes())).transpose().map_err(|e| e.to_string())?;
+        let handle = dlopen(filename.as_ref().map_or(std::ptr::null(), |filename| filename.as_ptr()), libloading::os::unix::RTLD_LAZY | RTLD_NOLOAD);
+        if handle.is_null() {
+            return Err(format!("Failed to get the handle of the library. {}", last_error()));
+        }
+        // Clear any earlier error, so the next error is caused by the use of the handle.
+        dlerror();
+        Ok(NoLoadHandle(handle))
+    }
+}
+impl NoLoadHandle {
+    /// Take the handle without closing it, e.g. to keep the library loaded through it.
+    pub(crate) fn into_raw(self) -> *mut c_void {
+        std::mem::ManuallyDrop::new(self).0
+    }
+}
+impl Drop for NoLoadHandle {
+    fn drop(&mut self) {
+        // The library stays loaded through the handle it was loaded with.
+        unsafe { dlclose(self.0) };
+    }
+}
@@ -0,0 +1,123 @@
+//! Directories added to the DLL search path of the process on Windows, see [`DllDirectory`].
+
+use crate::{LibPath, LoadFlags, SharedLib, SharedLibError};
+use std::ffi::c_void;
+use std::os::windows::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+/// The parts of `libloaderapi.h` used to change the DLL search path of the process.
+mod libloaderapi {
+    use std::ffi::c_void;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub(super) fn AddDllDirectory(new_directory: *const u16) -> *mut c_void;
+        pub(super) fn RemoveDllDirectory(cookie: *mut c_void) -> i32;
+        pub(super) fn SetDefaultDllDirectories(directory_flags: u32) -> i32;
+    }
+}
+
+/// Structure representing a directory added to the DLL search path of the process with `AddDllDirectory`,
+/// which is removed again when it is dropped.
+///
+/// Unlike adding the directory to the `PATH` environment variable, this does not affect child processes or the
+/// search for executables. The directory is searched for the libraries loaded with
+/// [`LoadFlags::with_search_dll_load_dir`], including their dependent DLLs, and for every library once the default
+/// DLL directories of the process include `LOAD_LIBRARY_SEARCH_USER_DIRS`, see [`set_default_dll_directories`].
+/// The order in which several added directories are searched is unspecified.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// let _runtime_dir = DllDirectory::add("plugins/runtime").expect("Failed to add DLL directory");
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("plugins"), "plugin".into());
+///     let lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_search_dll_load_dir(true)).expect("Failed to load shared library");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DllDirectory {
+    // Null once the directory was removed.
+    cookie: *mut c_void,
+    path: PathBuf,
+}
+// The cookie only identifies the directory in the search path of the process, so it can be removed from any thread.
+unsafe impl Send for DllDirectory {}
+unsafe impl Sync for DllDirectory {}
+
+impl DllDirectory {
+    /// Add the directory at `dir_path` to the DLL search path of the process, making a relative path absolute first.
+    ///
+    /// Returns [`SharedLibError::DllDirectoryFailure`] if the directory cannot be added, e.g. because it does not exist.
+    pub fn add(dir_path: impl AsRef<Path>) -> Result<DllDirectory, SharedLibError> {
+        let failure = |path: &Path, msg: String| SharedLibError::DllDirectoryFailure { path: path.to_string_lossy().to_string(), msg };
+        let dir_path = dir_path.as_ref();
+        let path = std::path::absolute(dir_path).map_err(|e| failure(dir_path, e.to_string()))?;
+        let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let cookie = unsafe { libloaderapi::AddDllDirectory(wide.as_ptr()) };
+        if cookie.is_null() {
+            return Err(failure(&path, std::io::Error::last_os_error().to_string()));
+        }
+        Ok(DllDirectory { cookie, path })
+    }
+    /// Get the absolute path of the directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Remove the directory from the DLL search path of the process, like dropping it but reporting errors.
+    pub fn remove(mut self) -> Result<(), SharedLibError> {
+        let cookie = std::mem::replace(&mut self.cookie, std::ptr::null_mut());
+        if unsafe { libloaderapi::RemoveDllDirectory(cookie) } == 0 {
+            let msg = std::io::Error::last_os_error().to_string();
+            return Err(SharedLibError::DllDirectoryFailure { path: self.path.to_string_lossy().to_string(), msg });
+        }
+        Ok(())
+    }
+}
+impl Drop for DllDirectory {
+    fn drop(&mut self) {
+        if !self.cookie.is_null() {
+            unsafe { libloaderapi::RemoveDllDirectory(self.cookie) };
+        }
+    }
+}
+
+/// Set the directories searched for every library loaded by the process with `SetDefaultDllDirectories`,
+/// e.g. `LOAD_LIBRARY_SEARCH_DEFAULT_DIRS` to search the directories added with [`DllDirectory::add`] and never the
+/// current directory or `PATH`.
+///
+/// `flags` is a combination of the `LOAD_LIBRARY_SEARCH_*` constants of [`libloading::os::windows`], which is not
+/// reverted when the process loads libraries any further, so it is best called once at startup.
+/// Returns [`SharedLibError::DllDirectoryFailure`] if the flags are invalid.
+pub fn set_default_dll_directories(flags: u32) -> Result<(), SharedLibError> {
+    if unsafe { libloaderapi::SetDefaultDllDirectories(flags) } == 0 {
+        return Err(SharedLibError::DllDirectoryFailure { path: String::new(), msg: std::io::Error::last_os_error().to_string() });
+    }
+    Ok(())
+}
+
+impl SharedLib {
+    /// Create a new shared library from the given path, resolving its dependent DLLs in the given directories
+    /// as well as in the directory of the library and the default DLL directories of the process.
+    ///
+    /// The directories are added to the DLL search path of the process with [`DllDirectory::add`] while the library
+    /// is loaded, and removed again afterwards, so the `PATH` environment variable is left unchanged. Libraries loaded
+    /// later, e.g. delay-loaded dependencies, are not searched for in the directories, which can be kept in the search path
+    /// with a [`DllDirectory`] instead.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("plugins/audio"), "audio_plugin".into());
+    ///     let runtime = PathBuf::from("plugins/runtime");
+    ///     let lib = SharedLib::new_with_dll_dirs(lib_path, &[runtime]).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_with_dll_dirs(lib_path: LibPath, dll_dirs: &[PathBuf]) -> Result<SharedLib, SharedLibError> {
+        let _dll_dirs = dll_dirs.iter().map(DllDirectory::add).collect::<Result<Vec<_>, _>>()?;
+        SharedLib::new_with_flags(lib_path, LoadFlags::new().with_search_dll_load_dir(true))
+    }
+}
@@ -0,0 +1,121 @@
+//! Loading of libraries embedded in the executable, e.g. with `include_bytes!`.
+
+use crate::bytes::sanitize_name;
+use crate::{ExtractionGuard, LibPath, SharedLib, SharedLibError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Structure representing a shared library embedded in the executable.
+///
+/// The library is extracted to a per-user cache directory, in a subdirectory for its name and version,
+/// so every version is only written once and can be loaded by all processes of the user.
+/// The extracted file is kept after the library is unloaded, unless [`EmbeddedLib::with_persist`] is unset.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// // e.g. `include_bytes!(concat!(env!("OUT_DIR"), "/libcalculator.so"))`
+/// static CALCULATOR_BYTES: &[u8] = &[];
+/// static CALCULATOR: EmbeddedLib = EmbeddedLib::new("calculator", "1.0.0", CALCULATOR_BYTES);
+/// unsafe {
+///     let lib = CALCULATOR.load().expect("Failed to load embedded library");
+///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+///     let result = add_fn.run(1, 2);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct EmbeddedLib {
+    lib_name: &'static str,
+    version: &'static str,
+    bytes: &'static [u8],
+    cache_dir: Option<PathBuf>,
+    persist: bool,
+}
+impl EmbeddedLib {
+    /// Create a new embedded library from its name (without the platform specific extension and prefix), version and contents.
+    pub const fn new(lib_name: &'static str, version: &'static str, bytes: &'static [u8]) -> EmbeddedLib {
+        EmbeddedLib { lib_name, version, bytes, cache_dir: None, persist: true }
+    }
+    /// Extract the library to the given directory instead of the per-user cache directory.
+    pub fn with_cache_dir(mut self, cache_dir: PathBuf) -> EmbeddedLib {
+        self.cache_dir = Some(cache_dir);
+        self
+    }
+    /// Set whether the extracted file is kept after the library is unloaded, which it is by default so later loads reuse it.
+    ///
+    /// Unset it to remove the file once the library is unloaded, see [`ExtractionGuard`].
+    pub fn with_persist(mut self, persist: bool) -> EmbeddedLib {
+        self.persist = persist;
+        self
+    }
+    /// Get the directory the library is extracted to.
+    ///
+    /// This is a subdirectory for the library name and version in the cache directory, which defaults to
+    /// `$XDG_CACHE_HOME` or `~/.cache` on Linux and other Unix platforms, `~/Library/Caches` on macOS
+    /// and `%LOCALAPPDATA%` on Windows, falling back to the temporary directory.
+    pub fn extract_dir(&self) -> PathBuf {
+        let cache_dir = self.cache_dir.clone().unwrap_or_else(|| user_cache_dir().join("shared_lib"));
+        cache_dir.join(sanitize_name(self.lib_name)).join(sanitize_name(self.version))
+    }
+    /// Extract the library to its extract directory, unless a file with the same contents is already there.
+    ///
+    /// The file is written next to its final path and then renamed, so a partially written library is never loaded.
+    pub fn extract(&self) -> Result<LibPath, SharedLibError> {
+        let lib_path = LibPath::new(self.extract_dir(), self.lib_name.to_owned());
+        let path = lib_path.path()?;
+        let load_failure = |e: std::io::Error| SharedLibError::LoadFailure {
+            path: path.clone(),
+            msg: format!("Failed to extract the embedded library. {}", e),
+            raw_os_error: e.raw_os_error(),
+            source: None,
+        };
+        if std::fs::read(&path).is_ok_and(|contents| contents == self.bytes) {
+            return Ok(lib_path);
+        }
+        std::fs::create_dir_all(&lib_path.dir_path).map_err(load_failure)?;
+        write_replace(&path, self.bytes).map_err(load_failure)?;
+        Ok(lib_path)
+    }
+    /// Extract the library with [`EmbeddedLib::extract`] and load it.
+    ///
+    /// The extracted file is attached to the library as its [`SharedLib::extraction`], persisted as set by [`EmbeddedLib::with_persist`].
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn load(&self) -> Result<SharedLib, SharedLibError> {
+        let lib_path = self.extract()?;
+        let path = lib_path.path()?;
+        let mut lib = SharedLib::new(lib_path)?;
+        lib.extraction = Some(ExtractionGuard::existing(path, self.persist));
+        Ok(lib)
+    }
+}
+
+/// Write `bytes` to a new file next to `path` and rename it to `path`.
+pub(crate) fn write_replace(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(file_name);
+    let result = std::fs::File::create(&temp_path)
+        .and_then(|mut file| file.write_all(bytes))
+        .and_then(|()| std::fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[cfg(windows)]
+pub(crate) fn user_cache_dir() -> PathBuf {
+    std::env::var_os("LOCALAPPDATA").map_or_else(std::env::temp_dir, PathBuf::from)
+}
+#[cfg(target_os = "macos")]
+pub(crate) fn user_cache_dir() -> PathBuf {
+    std::env::var_os("HOME").map_or_else(std::env::temp_dir, |home| PathBuf::from(home).join("Library/Caches"))
+}
+#[cfg(not(any(windows, target_os = "macos")))]
+pub(crate) fn user_cache_dir() -> PathBuf {
+    match (std::env::var_os("XDG_CACHE_HOME"), std::env::var_os("HOME")) {
+        (Some(cache_home), _) if !cache_home.is_empty() => PathBuf::from(cache_home),
+        (_, Some(home)) => PathBuf::from(home).join(".cache"),
+        _ => std::env::temp_dir(),
+    }
+}
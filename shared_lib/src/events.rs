@@ -0,0 +1,87 @@
+//! Events of loading libraries, resolving symbols, reloading and unloading, delivered to an [`EventSink`].
+
+use crate::{LibPath, SharedLib, SharedLibError};
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::time::SystemTime;
+
+/// Structure representing an event of a library, delivered to the [`EventSink`]s subscribed with [`LibManager::subscribe`](crate::LibManager::subscribe)
+/// or [`SharedLib::subscribe`].
+#[derive(Clone, Debug)]
+pub struct LibEvent {
+    /// Time the event happened at.
+    pub time: SystemTime,
+    /// Path of the library, or its name if it has no valid path.
+    pub path: PathBuf,
+    /// What happened.
+    pub kind: LibEventKind,
+}
+
+/// Enum representing what happened in a [`LibEvent`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum LibEventKind {
+    /// The library was loaded by a [`LibManager`](crate::LibManager).
+    Loaded,
+    /// A [`LibManager`](crate::LibManager) failed to load the library.
+    LoadFailed(SharedLibError),
+    /// The library was reloaded, see [`SharedLib::reload`].
+    Reloaded { generation: u64 },
+    /// A symbol was resolved in the library, which only happens the first time for the functions kept in the symbol cache.
+    SymbolResolved { symbol_name: String },
+    /// The library was unloaded, by [`SharedLib::close`], [`SharedLib::reload`] or dropping it.
+    Unloaded,
+}
+
+/// Receiver of the events of libraries, e.g. to drive a UI, write an audit log or collect telemetry.
+///
+/// Implemented for closures and for channel senders, which ignore the events once the receiver is dropped.
+/// Events are delivered on the thread they happen on, while the library is being loaded, reloaded or unloaded,
+/// so sinks should not block and must not load, reload or unload the library themselves.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let events = LibManager::global().events();
+///     std::thread::spawn(move || {
+///         for event in events {
+///             println!("{:?} {} {:?}", event.time, event.path.display(), event.kind);
+///         }
+///     });
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let lib = LibManager::global().open(lib_path).expect("Failed to load shared library");
+/// }
+/// ```
+pub trait EventSink: Send + Sync {
+    /// Receive an event.
+    fn event(&self, event: &LibEvent);
+}
+impl<F: Fn(&LibEvent) + Send + Sync> EventSink for F {
+    fn event(&self, event: &LibEvent) {
+        self(event)
+    }
+}
+impl EventSink for Sender<LibEvent> {
+    fn event(&self, event: &LibEvent) {
+        // The receiver is no longer interested in the events.
+        let _ = self.send(event.clone());
+    }
+}
+
+impl LibEvent {
+    /// Create a new event of the library at `lib_path` happening now.
+    pub(crate) fn now(lib_path: &LibPath, kind: LibEventKind) -> LibEvent {
+        LibEvent { time: SystemTime::now(), path: lib_path.error_path(), kind }
+    }
+}
+
+impl SharedLib {
+    /// Subscribe `sink` to the events of the library, see [`EventSink`].
+    ///
+    /// The library is already loaded when the sink is subscribed, so it only receives the symbols resolved, the reloads
+    /// and the unload of the library, except for the sinks subscribed with [`LibManager::subscribe`](crate::LibManager::subscribe).
+    pub fn subscribe(&mut self, sink: impl EventSink + 'static) {
+        self.hooks.subscribe(sink);
+    }
+}
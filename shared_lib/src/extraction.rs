@@ -0,0 +1,161 @@
+//! Cleanup of the files written to load a library, e.g. by [`SharedLib::from_bytes`], see [`ExtractionGuard`].
+
+use crate::bytes::sanitize_name;
+use crate::{LibPath, SharedLib, SharedLibError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Files and directories which could not be removed yet, e.g. a DLL still loaded by another handle on Windows,
+/// with whether they are directories. Removing them is retried whenever a guard is dropped.
+static PENDING: Mutex<Vec<(PathBuf, bool)>> = Mutex::new(Vec::new());
+
+/// Structure representing the file or directory a library was extracted to in order to load it, which is removed
+/// with its contents when the library is dropped or closed, see [`SharedLib::extraction`].
+///
+/// Attached to the libraries loaded with [`SharedLib::from_bytes`], [`EmbeddedLib::load`](crate::EmbeddedLib::load),
+/// [`SharedLib::from_archive`] and [`LoadFlags::with_shadow_copy`](crate::LoadFlags::with_shadow_copy).
+/// The library is unloaded before its extraction is removed. If removing fails, e.g. because a DLL is still loaded
+/// by another handle on Windows, it is retried whenever another guard is dropped and by [`ExtractionGuard::remove_pending`].
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// unsafe {
+///     let bytes = std::fs::read("path/to/shared/library/libshared_library.so").expect("Failed to read shared library");
+///     let mut lib = SharedLib::from_bytes("shared_library", &bytes).expect("Failed to load shared library");
+///     // Keep the extracted file after the library is dropped, e.g. to load it again on the next run.
+///     if let Some(extraction) = lib.extraction_mut() {
+///         extraction.set_persist(true);
+///         println!("Extracted to '{}'", extraction.path().display());
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ExtractionGuard {
+    path: PathBuf,
+    is_dir: bool,
+    persist: bool,
+}
+impl ExtractionGuard {
+    /// Get the path of the extracted file or directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Set whether the extraction is kept when the library is dropped, e.g. to use it as a cache, instead of being removed.
+    pub fn set_persist(&mut self, persist: bool) {
+        self.persist = persist;
+    }
+    /// Check if the extraction is kept when the library is dropped.
+    pub fn is_persisted(&self) -> bool {
+        self.persist
+    }
+    /// Retry removing the extractions which could not be removed when their guards were dropped,
+    /// e.g. before the application exits, returning the number of them which still cannot be removed.
+    pub fn remove_pending() -> usize {
+        let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+        pending.retain(|(path, is_dir)| remove(path, *is_dir).is_err());
+        pending.len()
+    }
+    /// Create a guard of an existing file, kept when dropped if `persist` is set.
+    pub(crate) fn existing(path: PathBuf, persist: bool) -> ExtractionGuard {
+        ExtractionGuard { path, is_dir: false, persist }
+    }
+    /// Write `bytes` to a new library file in the temporary directory, named after `name` with a unique suffix.
+    /// Characters of `name` which could change the directory of the file are replaced.
+    ///
+    /// The file is created exclusively, so an existing file or link is never written to, and is only accessible by the current user on Unix.
+    pub(crate) fn create_lib(name: &str, bytes: &[u8]) -> std::io::Result<(ExtractionGuard, LibPath)> {
+        let name = sanitize_name(name);
+        loop {
+            let lib_path = LibPath::new(std::env::temp_dir(), unique_name(&name));
+            let path = lib_path.path().map_err(std::io::Error::other)?;
+            let mut options = std::fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o700);
+            let mut file = match options.open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            let extraction = ExtractionGuard { path, is_dir: false, persist: false };
+            file.write_all(bytes)?;
+            return Ok((extraction, lib_path));
+        }
+    }
+    /// Create a new directory in the temporary directory, named after `name` with a unique suffix like [`ExtractionGuard::create_lib`].
+    ///
+    /// The directory is only accessible by the current user on Unix.
+    #[cfg(any(feature = "archive", feature = "testing"))]
+    pub(crate) fn create_dir(name: &str) -> std::io::Result<ExtractionGuard> {
+        let name = sanitize_name(name);
+        loop {
+            let path = std::env::temp_dir().join(unique_name(&name));
+            #[cfg_attr(not(unix), allow(unused_mut))]
+            let mut builder = std::fs::DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            match builder.create(&path) {
+                Ok(()) => return Ok(ExtractionGuard { path, is_dir: true, persist: false }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    /// Copy the library file of `lib_path` to a new library file in the temporary directory, see [`ExtractionGuard::create_lib`].
+    ///
+    /// Every copy gets a new name, even of the same file. Returns the copy and its path.
+    pub(crate) fn copy_lib(lib_path: &LibPath) -> Result<(ExtractionGuard, PathBuf), SharedLibError> {
+        let path = lib_path.resolve()?;
+        let copy_failure = |e: std::io::Error| SharedLibError::LoadFailure {
+            path: path.clone(),
+            msg: format!("Failed to copy the library to a temporary file. {}", e),
+            raw_os_error: e.raw_os_error(),
+            source: None,
+        };
+        let bytes = std::fs::read(&path).map_err(copy_failure)?;
+        let (extraction, copy_path) = ExtractionGuard::create_lib(&lib_path.lib_name, &bytes).map_err(copy_failure)?;
+        let copy_path = copy_path.path()?;
+        Ok((extraction, copy_path))
+    }
+}
+impl Drop for ExtractionGuard {
+    fn drop(&mut self) {
+        ExtractionGuard::remove_pending();
+        if !self.persist && remove(&self.path, self.is_dir).is_err() {
+            PENDING.lock().unwrap_or_else(|e| e.into_inner()).push((self.path.clone(), self.is_dir));
+        }
+    }
+}
+
+/// Remove the file or directory at `path`, succeeding if it does not exist anymore.
+fn remove(path: &Path, is_dir: bool) -> std::io::Result<()> {
+    let result = match is_dir {
+        true => std::fs::remove_dir_all(path),
+        false => std::fs::remove_file(path),
+    };
+    match result {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Get a name starting with `name` which is unique across the processes of the system, e.g. `name-1234-5678-0`.
+fn unique_name(name: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.subsec_nanos());
+    format!("{}-{}-{}-{}", name, std::process::id(), nanos, COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+impl SharedLib {
+    /// Get the file or directory the library was extracted to in order to load it, if any, see [`ExtractionGuard`].
+    pub fn extraction(&self) -> Option<&ExtractionGuard> {
+        self.extraction.as_ref()
+    }
+    /// Get the file or directory the library was extracted to like [`SharedLib::extraction`], e.g. to persist it.
+    pub fn extraction_mut(&mut self) -> Option<&mut ExtractionGuard> {
+        self.extraction.as_mut()
+    }
+}
@@ -0,0 +1,23 @@
+// Jump buffers of the guarded calls, see `fault.rs`.
+#include <setjmp.h>
+
+// Call `call(data)`, returning 1 if a fault handler jumped back with `shared_lib_guarded_jump`, and 0 otherwise.
+// `*active` points to the jump buffer of the innermost guarded call of the thread, and is restored on return.
+int shared_lib_guarded_call(void **active, void (*call)(void *), void *data) {
+    sigjmp_buf env;
+    // Not modified after `sigsetjmp`, so it is still valid when `sigsetjmp` returns again.
+    void *enclosing = *active;
+    if (sigsetjmp(env, 1) != 0) {
+        *active = enclosing;
+        return 1;
+    }
+    *active = &env;
+    call(data);
+    *active = enclosing;
+    return 0;
+}
+
+// Jump back to the guarded call of `env`, restoring the signal mask it was set up with.
+void shared_lib_guarded_jump(void *env) {
+    siglongjmp(*(sigjmp_buf *)env, 1);
+}
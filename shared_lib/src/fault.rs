@@ -0,0 +1,253 @@
+//! Calling functions which may crash, converting the fault into an error instead of terminating the process,
+//! see [`SharedLibFn::run_guarded`].
+
+use crate::{SharedLibError, SharedLibFn};
+
+macro_rules! impl_run_guarded {
+    ([$($fn_ty:tt)+] $($a:ident: $A:ident),*) => {
+        #[allow(clippy::too_many_arguments)]
+        impl<'a, Ret, $($A),*> SharedLibFn<'a, $($fn_ty)+ ($($A),*) -> Ret> {
+            /// Call the function, returning [`SharedLibError::Fault`] if it causes an access violation or another hardware fault
+            /// instead of terminating the process, e.g. to survive a bad call of a third-party plugin.
+            ///
+            /// Segmentation faults, bus errors, illegal instructions and arithmetic errors of the calling thread are caught, including
+            /// stack overflows, for which an alternate signal stack is installed on threads that do not have one.
+            /// The signal handlers installed before the first guarded call still handle the faults outside of guarded calls.
+            /// On Windows, access violations, illegal instructions, integer division errors and stack overflows are caught with
+            /// structured exception handling, while other exceptions pass through.
+            /// It is supported on Linux with glibc, MacOS and Windows with MSVC, while other platforms return
+            /// [`SharedLibError::Unsupported`].
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            /// A fault abandons the function where it happened without any cleanup, so locks it holds stay locked and the state
+            /// of the library may be left inconsistent. Memory the function corrupted without faulting is not detected.
+            ///
+            /// On Linux and MacOS, a fault jumps straight back into this function, over every frame in between without dropping
+            /// anything in them. Those are the frames of the function, of any Rust code it called back into, and of the crate's
+            /// own frames running it, which catch its panics. On Windows, the frames in between are unwound instead, which runs
+            /// the destructors of their Rust values.
+            pub unsafe fn run_guarded(&self, $($a: $A),*) -> Result<Ret, SharedLibError> {
+                #[cfg(feature = "wasm")]
+                if let crate::FnSymbol::Wasm(func) = &self.symbol {
+                    return self.measured(|| func.call(($($a,)*)));
+                }
+                let mut call = Some(move || (self.symbol)($($a),*));
+                let mut result = None;
+                // Measured outside of the guarded call, which a fault leaves without running anything after the function.
+                self.measured(|| guarded(&mut || result = call.take().map(|call| call())))?;
+                Ok(result.expect("the guarded function returned"))
+            }
+        }
+    };
+}
+macro_rules! impl_run_guarded_all {
+    ($($a:ident: $A:ident),*) => {
+        impl_run_guarded!([fn] $($a: $A),*);
+        impl_run_guarded!([unsafe fn] $($a: $A),*);
+        impl_run_guarded!([extern "C" fn] $($a: $A),*);
+        impl_run_guarded!([unsafe extern "C" fn] $($a: $A),*);
+    };
+}
+impl_run_guarded_all!();
+impl_run_guarded_all!(a1: A1);
+impl_run_guarded_all!(a1: A1, a2: A2);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15);
+impl_run_guarded_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15, a16: A16);
+
+/// Run `call`, returning [`SharedLibError::Fault`] if it faults.
+unsafe fn guarded(call: &mut dyn FnMut()) -> Result<(), SharedLibError> {
+    #[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos"))]
+    return signals::guarded(call).map_err(|address| SharedLibError::Fault { address });
+    #[cfg(all(windows, target_env = "msvc"))]
+    return exceptions::guarded(call).map_err(|address| SharedLibError::Fault { address });
+    #[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", all(windows, target_env = "msvc"))))]
+    return Err(SharedLibError::Unsupported("Calling a function guarded against faults".into()));
+}
+
+/// The call run by the C shim, with the panic it raised, if any.
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", all(windows, target_env = "msvc")))]
+struct Call<'c> {
+    call: &'c mut dyn FnMut(),
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", all(windows, target_env = "msvc")))]
+impl Call<'_> {
+    /// Run the call, catching its panic, which must not unwind into the C shim.
+    fn run(&mut self) {
+        if let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| (self.call)())) {
+            self.panic = Some(payload);
+        }
+    }
+    /// Resume the panic the call raised, if any.
+    fn resume_panic(self) {
+        if let Some(payload) = self.panic {
+            std::panic::resume_unwind(payload);
+        }
+    }
+}
+
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos"))]
+mod signals {
+    use super::Call;
+    use std::cell::Cell;
+    use std::ffi::c_void;
+    use std::sync::OnceLock;
+
+    // Defined in `fault.c`, since `sigsetjmp` returns twice, which Rust cannot express.
+    extern "C" {
+        fn shared_lib_guarded_call(active: *mut *mut c_void, call: extern "C" fn(*mut c_void), data: *mut c_void) -> i32;
+        fn shared_lib_guarded_jump(env: *mut c_void) -> !;
+    }
+
+    const SIGNALS: [i32; 4] = [libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGFPE];
+    /// Actions of `SIGNALS` before the fault handler was installed, which handle the faults outside of guarded calls.
+    static PREVIOUS: OnceLock<[libc::sigaction; 4]> = OnceLock::new();
+    /// Size of the alternate signal stack installed for threads without one.
+    const ALT_STACK_SIZE: usize = 64 * 1024;
+
+    thread_local! {
+        // Jump buffer of the innermost guarded call of the thread, if any, set by `shared_lib_guarded_call`.
+        static ACTIVE: Cell<*mut c_void> = const { Cell::new(std::ptr::null_mut()) };
+        static FAULT_ADDRESS: Cell<usize> = const { Cell::new(0) };
+        static ALT_STACK: AltStack = AltStack::install();
+    }
+
+    /// Alternate signal stack of the thread, which lets the handler run when the fault is a stack overflow.
+    ///
+    /// The standard library already installs one for the threads it starts, in which case it is kept.
+    struct AltStack(Option<Vec<u8>>);
+    impl AltStack {
+        fn install() -> AltStack {
+            unsafe {
+                let mut current: libc::stack_t = std::mem::zeroed();
+                if libc::sigaltstack(std::ptr::null(), &mut current) != 0 || current.ss_flags & libc::SS_DISABLE == 0 {
+                    return AltStack(None);
+                }
+                let mut memory = vec![0u8; ALT_STACK_SIZE.max(libc::SIGSTKSZ)];
+                let mut stack: libc::stack_t = std::mem::zeroed();
+                stack.ss_sp = memory.as_mut_ptr().cast();
+                stack.ss_size = memory.len();
+                match libc::sigaltstack(&stack, std::ptr::null_mut()) {
+                    0 => AltStack(Some(memory)),
+                    _ => AltStack(None),
+                }
+            }
+        }
+    }
+    impl Drop for AltStack {
+        fn drop(&mut self) {
+            if self.0.is_some() {
+                unsafe {
+                    let mut stack: libc::stack_t = std::mem::zeroed();
+                    stack.ss_flags = libc::SS_DISABLE;
+                    libc::sigaltstack(&stack, std::ptr::null_mut());
+                }
+            }
+        }
+    }
+
+    /// Run the `Call` at `data`, see `SharedLibFn::run_guarded` for what a fault jumps over.
+    extern "C" fn run_call(data: *mut c_void) {
+        unsafe { &mut *data.cast::<Call>() }.run();
+    }
+
+    /// Run `call`, returning the faulting address if it faults.
+    pub(super) unsafe fn guarded(call: &mut dyn FnMut()) -> Result<(), usize> {
+        install();
+        ALT_STACK.with(|_| {});
+        let mut call = Call { call, panic: None };
+        let data = (&mut call as *mut Call).cast::<c_void>();
+        let faulted = ACTIVE.with(|active| shared_lib_guarded_call(active.as_ptr(), run_call, data));
+        call.resume_panic();
+        match faulted {
+            0 => Ok(()),
+            _ => Err(FAULT_ADDRESS.with(Cell::get)),
+        }
+    }
+
+    fn install() {
+        PREVIOUS.get_or_init(|| {
+            SIGNALS.map(|signal| unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = handle_fault as extern "C" fn(i32, *mut libc::siginfo_t, *mut c_void) as usize;
+                // Run on the alternate stack of the thread, see `AltStack`.
+                action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut previous: libc::sigaction = std::mem::zeroed();
+                libc::sigaction(signal, &action, &mut previous);
+                previous
+            })
+        });
+    }
+
+    extern "C" fn handle_fault(signal: i32, info: *mut libc::siginfo_t, context: *mut c_void) {
+        let env = ACTIVE.with(|active| active.replace(std::ptr::null_mut()));
+        unsafe {
+            if !env.is_null() {
+                FAULT_ADDRESS.with(|address| address.set((*info).si_addr() as usize));
+                shared_lib_guarded_jump(env);
+            }
+            let index = SIGNALS.iter().position(|&s| s == signal).expect("the handler is only installed for the fault signals");
+            let Some(previous) = PREVIOUS.get().map(|previous| &previous[index]) else {
+                // The fault happened while the handler was being installed, so the default action terminates the process.
+                libc::signal(signal, libc::SIG_DFL);
+                return;
+            };
+            match previous.sa_sigaction {
+                // Returning runs the faulting instruction again, which now gets the previous action.
+                libc::SIG_DFL | libc::SIG_IGN => {
+                    libc::sigaction(signal, previous, std::ptr::null_mut());
+                }
+                handler if previous.sa_flags & libc::SA_SIGINFO != 0 => {
+                    let handler: extern "C" fn(i32, *mut libc::siginfo_t, *mut c_void) = std::mem::transmute(handler);
+                    handler(signal, info, context);
+                }
+                handler => {
+                    let handler: extern "C" fn(i32) = std::mem::transmute(handler);
+                    handler(signal);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(windows, target_env = "msvc"))]
+mod exceptions {
+    use super::Call;
+    use std::ffi::c_void;
+
+    // Defined in `fault_windows.c`, since `__try` and `__except` have no Rust equivalent.
+    // Exceptions which are not faults, like C++ exceptions, unwind out of the shim.
+    extern "C-unwind" {
+        fn shared_lib_guarded_call(call: extern "C-unwind" fn(*mut c_void), data: *mut c_void, address: *mut *mut c_void) -> i32;
+    }
+
+    /// Run the `Call` at `data`, letting the exception of a fault unwind through it to the shim.
+    extern "C-unwind" fn run_call(data: *mut c_void) {
+        unsafe { &mut *data.cast::<Call>() }.run();
+    }
+
+    /// Run `call`, returning the faulting address if it faults.
+    pub(super) unsafe fn guarded(call: &mut dyn FnMut()) -> Result<(), usize> {
+        let mut call = Call { call, panic: None };
+        let mut address = std::ptr::null_mut();
+        let faulted = shared_lib_guarded_call(run_call, (&mut call as *mut Call).cast(), &mut address);
+        call.resume_panic();
+        match faulted {
+            0 => Ok(()),
+            _ => Err(address as usize),
+        }
+    }
+}
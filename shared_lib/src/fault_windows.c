@@ -0,0 +1,40 @@
+// Structured exception handling of the guarded calls, see `fault.rs`.
+#include <windows.h>
+#include <malloc.h>
+
+// Check if the exception of `pointers` is a fault, storing its address in `*address` if it is.
+static int fault_filter(EXCEPTION_POINTERS *pointers, void **address) {
+    EXCEPTION_RECORD *record = pointers->ExceptionRecord;
+    switch (record->ExceptionCode) {
+    case EXCEPTION_ACCESS_VIOLATION:
+    case EXCEPTION_IN_PAGE_ERROR:
+        // The second parameter is the address that was accessed.
+        *address = (void *)record->ExceptionInformation[1];
+        return EXCEPTION_EXECUTE_HANDLER;
+    case EXCEPTION_DATATYPE_MISALIGNMENT:
+    case EXCEPTION_ILLEGAL_INSTRUCTION:
+    case EXCEPTION_PRIV_INSTRUCTION:
+    case EXCEPTION_INT_DIVIDE_BY_ZERO:
+    case EXCEPTION_INT_OVERFLOW:
+    case EXCEPTION_STACK_OVERFLOW:
+        *address = record->ExceptionAddress;
+        return EXCEPTION_EXECUTE_HANDLER;
+    default:
+        // Other exceptions, like C++ exceptions and Rust panics, are not faults.
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+}
+
+// Call `call(data)`, returning 1 if it faulted, with the address of the fault in `*address`, and 0 otherwise.
+int shared_lib_guarded_call(void (*call)(void *), void *data, void **address) {
+    __try {
+        call(data);
+        return 0;
+    } __except (fault_filter(GetExceptionInformation(), address)) {
+        // The guard page of the stack is consumed by the overflow, so it is restored for the next one.
+        if (GetExceptionCode() == EXCEPTION_STACK_OVERFLOW) {
+            _resetstkoflw();
+        }
+        return 1;
+    }
+}
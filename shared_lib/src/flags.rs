@@ -0,0 +1,226 @@
+//! Flags controlling how the dynamic loader loads a library.
+
+use crate::RetryPolicy;
+
+/// Flags controlling how a shared library is loaded, see [`SharedLib::new_with_flags`](crate::SharedLib::new_with_flags).
+///
+/// The default flags resolve functions lazily on first use and keep the symbols of the library local to it
+/// (`RTLD_LAZY | RTLD_LOCAL` on Unix), which is how [`SharedLib::new`](crate::SharedLib::new) loads libraries.
+///
+/// Flags that have no equivalent on the current platform are ignored. On Windows, the imports of a DLL are always
+/// resolved when it is loaded and its exports are always available to other modules, while the flags
+/// controlling where the dependencies of a DLL are searched for only apply to Windows.
+///
+/// With the `serde` feature, the flags are (de)serialized as a struct of booleans named like the methods
+/// without the `with_` prefix, e.g. `now` and `global`, of which missing ones are `false`, and the [`RetryPolicy`] as `retry`.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let flags = LoadFlags::new().with_now(true).with_global(true);
+///     let lib = SharedLib::new_with_flags(lib_path, flags).expect("Failed to load shared library");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct LoadFlags {
+    now: bool,
+    global: bool,
+    altered_search_path: bool,
+    search_dll_load_dir: bool,
+    isolated_namespace: bool,
+    decorated_names: bool,
+    shadow_copy: bool,
+    symbol_suggestions: bool,
+    call_metrics: bool,
+    lifecycle: bool,
+    canonical_path: bool,
+    retry: RetryPolicy,
+}
+impl LoadFlags {
+    /// Create the default load flags.
+    pub fn new() -> LoadFlags {
+        LoadFlags::default()
+    }
+    /// Resolve all undefined symbols of the library when it is loaded (`RTLD_NOW`)
+    /// instead of when they are first used (`RTLD_LAZY`), so missing symbols are reported by the load.
+    pub fn with_now(mut self, now: bool) -> LoadFlags {
+        self.now = now;
+        self
+    }
+    /// Make the symbols of the library available to the libraries loaded after it (`RTLD_GLOBAL`)
+    /// instead of keeping them local to the library (`RTLD_LOCAL`).
+    pub fn with_global(mut self, global: bool) -> LoadFlags {
+        self.global = global;
+        self
+    }
+    /// Search the dependencies of the DLL starting in the directory of the DLL instead of the directory
+    /// of the executable (`LOAD_WITH_ALTERED_SEARCH_PATH` on Windows).
+    ///
+    /// Cannot be combined with [`LoadFlags::with_search_dll_load_dir`].
+    pub fn with_altered_search_path(mut self, altered_search_path: bool) -> LoadFlags {
+        self.altered_search_path = altered_search_path;
+        self
+    }
+    /// Search the dependencies of the DLL in the directory of the DLL, followed by the default
+    /// DLL directories of the process (`LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR | LOAD_LIBRARY_SEARCH_DEFAULT_DIRS` on Windows).
+    ///
+    /// Cannot be combined with [`LoadFlags::with_altered_search_path`].
+    pub fn with_search_dll_load_dir(mut self, search_dll_load_dir: bool) -> LoadFlags {
+        self.search_dll_load_dir = search_dll_load_dir;
+        self
+    }
+    /// Load the library into a new, isolated linker namespace (`dlmopen` with `LM_ID_NEWLM` on Linux with glibc),
+    /// together with its own copies of all its dependencies, e.g. to load two versions of the same library.
+    ///
+    /// On MacOS and Windows, symbols are always resolved within the library that exports them, so libraries with
+    /// different paths are already isolated and the library is loaded normally. Other platforms return
+    /// [`SharedLibError::Unsupported`](crate::SharedLibError::Unsupported). Cannot be combined with
+    /// [`LoadFlags::with_global`], and glibc only supports a small number of namespaces per process.
+    pub fn with_isolated_namespace(mut self, isolated_namespace: bool) -> LoadFlags {
+        self.isolated_namespace = isolated_namespace;
+        self
+    }
+    /// Look up functions which are not found by their plain name by their `stdcall`, `fastcall` or `vectorcall`
+    /// decorated names in [`SharedLib::get_fn`](crate::SharedLib::get_fn), e.g. `_Foo@8` for `Foo`, as exported by 32-bit Windows DLLs.
+    ///
+    /// The decorated name is looked up in the export table of the library file, which is read on every such lookup.
+    pub fn with_decorated_names(mut self, decorated_names: bool) -> LoadFlags {
+        self.decorated_names = decorated_names;
+        self
+    }
+    /// Load a copy of the library file made in the temporary directory instead of the file itself, removing the copy
+    /// once the library is unloaded, so the original file is not locked and can be rebuilt, e.g. by `cargo build` on Windows.
+    ///
+    /// The library is copied again by every [`SharedLib::reload`](crate::SharedLib::reload), to a file with a new unique name,
+    /// so the dynamic loader cannot map an image it cached for the old path and always loads the rebuilt file.
+    /// Dependencies located next to the original file are not found next to the copy, unless they are loaded first,
+    /// e.g. with [`SharedLib::new_with_sibling_dependencies`](crate::SharedLib::new_with_sibling_dependencies).
+    /// Lookups that ask the dynamic loader about the file by its path, e.g. [`SharedLib::module_info`](crate::SharedLib::module_info),
+    /// do not find the original file loaded.
+    pub fn with_shadow_copy(mut self, shadow_copy: bool) -> LoadFlags {
+        self.shadow_copy = shadow_copy;
+        self
+    }
+    /// Suggest the closest names exported by the library in the [`SharedLibError::SymbolNotFound`](crate::SharedLibError::SymbolNotFound)
+    /// returned by [`SharedLib::get_fn`](crate::SharedLib::get_fn), e.g. `Did you mean 'add'?` for `ad`.
+    ///
+    /// Names mangled from a path ending with the missing name are suggested as well, e.g. `_ZN4math3add17h0123456789abcdefE`
+    /// for `add` exported by a Rust function missing `#[no_mangle]`. The export table of the library file is only read
+    /// when a symbol is not found.
+    pub fn with_symbol_suggestions(mut self, symbol_suggestions: bool) -> LoadFlags {
+        self.symbol_suggestions = symbol_suggestions;
+        self
+    }
+    /// Count the calls of the functions looked up from the library and measure how long they take, see [`SharedLib::stats`](crate::SharedLib::stats).
+    ///
+    /// Every call through a measured function reads the clock twice, so this is meant for profiling,
+    /// e.g. to find the entry point of a plugin taking the most time.
+    pub fn with_call_metrics(mut self, call_metrics: bool) -> LoadFlags {
+        self.call_metrics = call_metrics;
+        self
+    }
+    /// Call [`INIT_SYMBOL`](crate::INIT_SYMBOL) (`extern "C" fn lib_init() -> i32`) after the library is loaded,
+    /// and [`SHUTDOWN_SYMBOL`](crate::SHUTDOWN_SYMBOL) (`extern "C" fn lib_shutdown()`) before it is unloaded, if the library exports them.
+    ///
+    /// Loading the library fails with [`SharedLibError::InitFailure`](crate::SharedLibError::InitFailure) if the initialization
+    /// function returns a non-zero code, in which case the library is unloaded without calling the shutdown function.
+    /// Both are called again when the library is reloaded, and the shutdown function is not called while functions
+    /// obtained with [`SharedLib::get_fn_owned`](crate::SharedLib::get_fn_owned) keep the library loaded.
+    pub fn with_lifecycle(mut self, lifecycle: bool) -> LoadFlags {
+        self.lifecycle = lifecycle;
+        self
+    }
+    /// Resolve symbolic links and `.` and `..` components in the path of the library file before loading it, see [`LibPath::canonical_path`](crate::LibPath::canonical_path),
+    /// so the dynamic loader and the errors get the path of the actual file, e.g. of a plugin in a symlinked directory.
+    ///
+    /// The path is resolved again by every [`SharedLib::reload`](crate::SharedLib::reload), so a symbolic link switched to a new version
+    /// of the library is followed. Libraries without a directory are left to the dynamic loader to search for.
+    pub fn with_canonical_path(mut self, canonical_path: bool) -> LoadFlags {
+        self.canonical_path = canonical_path;
+        self
+    }
+    /// Retry loading the library with the given policy if its file is missing or still being written, e.g. by the compiler
+    /// when a watcher reloads the library. The policy also applies to every [`SharedLib::reload`](crate::SharedLib::reload),
+    /// and to every search directory the library is missing from when loaded with a [`SharedLibBuilder`](crate::SharedLibBuilder).
+    pub fn with_retry(mut self, retry: RetryPolicy) -> LoadFlags {
+        self.retry = retry;
+        self
+    }
+    /// Check if all undefined symbols are resolved when the library is loaded.
+    pub fn is_now(&self) -> bool {
+        self.now
+    }
+    /// Check if the symbols of the library are available to the libraries loaded after it.
+    pub fn is_global(&self) -> bool {
+        self.global
+    }
+    /// Check if the dependencies of the DLL are searched starting in the directory of the DLL.
+    pub fn is_altered_search_path(&self) -> bool {
+        self.altered_search_path
+    }
+    /// Check if the dependencies of the DLL are searched in the directory of the DLL.
+    pub fn is_search_dll_load_dir(&self) -> bool {
+        self.search_dll_load_dir
+    }
+    /// Check if the library is loaded into a new, isolated linker namespace.
+    pub fn is_isolated_namespace(&self) -> bool {
+        self.isolated_namespace
+    }
+    /// Check if functions are also looked up by their decorated names.
+    pub fn is_decorated_names(&self) -> bool {
+        self.decorated_names
+    }
+    /// Check if a copy of the library file is loaded instead of the file itself.
+    pub fn is_shadow_copy(&self) -> bool {
+        self.shadow_copy
+    }
+    /// Check if the closest exported names are suggested for symbols that are not found.
+    pub fn is_symbol_suggestions(&self) -> bool {
+        self.symbol_suggestions
+    }
+    /// Check if the calls of the functions looked up from the library are counted and measured.
+    pub fn is_call_metrics(&self) -> bool {
+        self.call_metrics
+    }
+    /// Check if the initialization and shutdown functions of the library are called when it is loaded and unloaded.
+    pub fn is_lifecycle(&self) -> bool {
+        self.lifecycle
+    }
+    /// Check if the path of the library file is canonicalized before it is loaded.
+    pub fn is_canonical_path(&self) -> bool {
+        self.canonical_path
+    }
+    /// Get the policy retrying the load of the library.
+    pub fn retry(&self) -> RetryPolicy {
+        self.retry
+    }
+    /// Get the `dlopen` flags.
+    #[cfg(unix)]
+    pub(crate) fn unix_flags(&self) -> std::ffi::c_int {
+        use libloading::os::unix;
+        let binding = if self.now { unix::RTLD_NOW } else { unix::RTLD_LAZY };
+        let visibility = if self.global { unix::RTLD_GLOBAL } else { unix::RTLD_LOCAL };
+        binding | visibility
+    }
+    /// Get the `LoadLibraryExW` flags.
+    #[cfg(windows)]
+    pub(crate) fn windows_flags(&self) -> u32 {
+        use libloading::os::windows;
+        let mut flags = 0;
+        if self.altered_search_path {
+            flags |= windows::LOAD_WITH_ALTERED_SEARCH_PATH;
+        }
+        if self.search_dll_load_dir {
+            flags |= windows::LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR | windows::LOAD_LIBRARY_SEARCH_DEFAULT_DIRS;
+        }
+        flags
+    }
+    /// Check if any flag requires the library to be loaded from an absolute path.
+    #[cfg(windows)]
+    pub(crate) fn needs_absolute_path(&self) -> bool {
+        self.altered_search_path || self.search_dll_load_dir
+    }
+}
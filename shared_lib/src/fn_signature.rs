@@ -0,0 +1,80 @@
+//! Restriction of the types functions are looked up as to function pointers, see [`FnSignature`].
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Function pointer types a function of a library can be looked up as, e.g. with [`SharedLib::get_fn`](crate::SharedLib::get_fn).
+///
+/// Implemented for `fn`, `extern "C" fn` and `extern "system" fn` pointers and their `unsafe` variants with up to 16 arguments,
+/// and for C-variadic `extern "C" fn` pointers with up to 16 fixed arguments. Looking up a function as any other type,
+/// e.g. `String`, is a compile error instead of reading the code of the function as a value of that type.
+/// Variables are looked up with [`SharedLib::get_var`](crate::SharedLib::get_var) instead.
+/// Function pointers taking references, e.g. `fn(&str)`, are generic over the lifetime of the reference and are not implemented,
+/// so functions taking references are looked up with raw pointer arguments instead.
+/// The trait is sealed, so it cannot be implemented outside of this crate.
+/// ```compile_fail
+/// use shared_lib::*;
+/// unsafe {
+///     let lib = SharedLib::new(LibPath::new_no_path("shared_library".into())).expect("Failed to load shared library");
+///     let name = lib.get_fn::<String>("name");
+/// }
+/// ```
+pub trait FnSignature: sealed::Sealed + Copy + 'static {
+    /// Get the type of the function as a WebAssembly function, if its arguments and its result are WebAssembly values.
+    #[cfg(feature = "wasm")]
+    #[doc(hidden)]
+    fn wasm_signature() -> Option<crate::wasm::WasmSignature> {
+        None
+    }
+}
+
+macro_rules! impl_fn_signature {
+    ([$($fn_ty:tt)+] $($A:ident),*) => {
+        impl<Ret: 'static, $($A: 'static),*> sealed::Sealed for $($fn_ty)+ ($($A),*) -> Ret {}
+        impl<Ret: 'static, $($A: 'static),*> FnSignature for $($fn_ty)+ ($($A),*) -> Ret {
+            #[cfg(feature = "wasm")]
+            fn wasm_signature() -> Option<crate::wasm::WasmSignature> {
+                crate::wasm::WasmSignature::of::<($($A,)*), Ret>()
+            }
+        }
+    };
+}
+macro_rules! impl_variadic_fn_signature {
+    ([$($fn_ty:tt)+] $($A:ident),+) => {
+        impl<Ret: 'static, $($A: 'static),+> sealed::Sealed for $($fn_ty)+ ($($A),+, ...) -> Ret {}
+        impl<Ret: 'static, $($A: 'static),+> FnSignature for $($fn_ty)+ ($($A),+, ...) -> Ret {}
+    };
+}
+macro_rules! impl_fn_signature_all {
+    ($($A:ident),*) => {
+        impl_fn_signature!([fn] $($A),*);
+        impl_fn_signature!([unsafe fn] $($A),*);
+        impl_fn_signature!([extern "C" fn] $($A),*);
+        impl_fn_signature!([unsafe extern "C" fn] $($A),*);
+        impl_fn_signature!([extern "system" fn] $($A),*);
+        impl_fn_signature!([unsafe extern "system" fn] $($A),*);
+    };
+    ($($A:ident),+ ; variadic) => {
+        impl_fn_signature_all!($($A),+);
+        impl_variadic_fn_signature!([extern "C" fn] $($A),+);
+        impl_variadic_fn_signature!([unsafe extern "C" fn] $($A),+);
+    };
+}
+impl_fn_signature_all!();
+impl_fn_signature_all!(A1; variadic);
+impl_fn_signature_all!(A1, A2; variadic);
+impl_fn_signature_all!(A1, A2, A3; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15; variadic);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8, A9, A10, A11, A12, A13, A14, A15, A16; variadic);
@@ -0,0 +1,123 @@
+//! Tracking of the calls in flight into a library, so it is not unloaded while they run, see [`CallGate`].
+
+use crate::SharedLib;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// State shared by a library and its call gates.
+#[derive(Default)]
+pub(crate) struct GateState {
+    calls: Mutex<Calls>,
+    idle: Condvar,
+}
+#[derive(Default)]
+struct Calls {
+    active: usize,
+    // Incremented every time the library is unloaded, which makes the gates of the previous library stale.
+    generation: u64,
+    unloading: bool,
+}
+impl GateState {
+    fn lock(&self) -> MutexGuard<'_, Calls> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Stop new calls and, if `wait` is set, wait for the calls in flight to finish.
+    ///
+    /// Returns the number of calls in flight without stopping new calls if `wait` is unset and there are any,
+    /// otherwise returns 0, after which the gates are either retired or resumed.
+    pub(crate) fn stop(&self, wait: bool) -> usize {
+        let mut calls = self.lock();
+        if !wait && calls.active > 0 {
+            return calls.active;
+        }
+        calls.unloading = true;
+        drop(self.idle.wait_while(calls, |calls| calls.active > 0).unwrap_or_else(|e| e.into_inner()));
+        0
+    }
+    /// Make every gate stale once the library they were stopped for is unloaded.
+    pub(crate) fn retire(&self) {
+        let mut calls = self.lock();
+        calls.generation += 1;
+        calls.unloading = false;
+    }
+    /// Let new calls in again if the library they were stopped for stayed loaded.
+    pub(crate) fn resume(&self) {
+        self.lock().unloading = false;
+    }
+}
+
+/// Handle marking calls into a library as in flight, so the library is not unloaded or reloaded while they run.
+///
+/// Functions borrowed from a library with [`SharedLib::get_fn`] cannot outlive it, but plain function pointers copied
+/// out of it, e.g. the ones of a struct loaded with `#[derive(SharedLibApi)]`, can still be called by other threads while
+/// the library is reloaded. Calls made while holding a [`CallGuard`] of the library's gate are waited for by
+/// [`SharedLib::reload`], [`SharedLib::close`] and dropping the library, and make [`SharedLib::try_reload`] fail.
+///
+/// A gate belongs to the library loaded when it was created, so once the library is unloaded or reloaded,
+/// [`CallGate::enter`] returns `None` and the functions have to be looked up again.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let mut lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+///     let add: fn(usize, usize) -> usize = std::mem::transmute(lib.get_symbol_address("add").expect("Failed to get 'add' function from shared library"));
+///     let gate = lib.call_gate();
+///     let worker = std::thread::spawn(move || match gate.enter() {
+///         Some(_call) => Some(add(1, 2)),
+///         None => None, // The library was reloaded, so `add` must be looked up again.
+///     });
+///     // Waits for the call of the worker to finish, if it started.
+///     lib.reload().expect("Failed to reload shared library");
+///     worker.join().unwrap();
+/// }
+/// ```
+#[derive(Clone)]
+pub struct CallGate {
+    state: Arc<GateState>,
+    generation: u64,
+}
+impl CallGate {
+    /// Mark a call as in flight until the returned guard is dropped.
+    ///
+    /// Returns `None` if the library the gate was created for was unloaded, reloaded, or is being unloaded.
+    pub fn enter(&self) -> Option<CallGuard> {
+        let mut calls = self.state.lock();
+        if calls.unloading || calls.generation != self.generation {
+            return None;
+        }
+        calls.active += 1;
+        Some(CallGuard { state: Arc::clone(&self.state) })
+    }
+    /// Check if the library the gate was created for is still loaded, so [`CallGate::enter`] can succeed.
+    pub fn is_valid(&self) -> bool {
+        let calls = self.state.lock();
+        !calls.unloading && calls.generation == self.generation
+    }
+}
+
+/// Guard of a call in flight into a library, returned by [`CallGate::enter`].
+#[must_use = "the call is only marked as in flight while the guard is alive"]
+pub struct CallGuard {
+    state: Arc<GateState>,
+}
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        let mut calls = self.state.lock();
+        calls.active -= 1;
+        if calls.active == 0 {
+            self.state.idle.notify_all();
+        }
+    }
+}
+
+impl SharedLib {
+    /// Get a gate marking calls into the currently loaded library as in flight, see [`CallGate`].
+    pub fn call_gate(&self) -> CallGate {
+        CallGate { state: Arc::clone(&self.calls), generation: self.calls.lock().generation }
+    }
+    /// Get the number of calls into the library in flight, marked with a [`CallGate`].
+    pub fn calls_in_flight(&self) -> usize {
+        self.calls.lock().active
+    }
+}
@@ -0,0 +1,111 @@
+//! Loading of libraries in the order of their declared dependencies, see [`LibGraph`].
+
+use crate::{LibPath, LoadFlags, SharedLibError, SharedLibSet};
+
+/// Structure representing libraries and their dependencies on each other, which are loaded in topological order
+/// into a [`SharedLibSet`], e.g. plugins depending on shared core libraries.
+///
+/// Libraries are identified by their library names, see [`LibPath::lib_name`]. Every library is loaded after the libraries it depends on,
+/// and otherwise in the order the libraries were added, and the set unloads them in reverse order, so the libraries
+/// a library depends on are unloaded after it.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let dir = PathBuf::from("path/to/shared/library");
+///     let libs = LibGraph::new()
+///         .add(LibPath::new(dir.clone(), "audio_plugin".into()), &["audio_core", "core"])
+///         .add(LibPath::new(dir.clone(), "audio_core".into()), &["core"])
+///         .add(LibPath::new(dir, "core".into()), &[])
+///         .with_flags(LoadFlags::new().with_global(true))
+///         .load()
+///         .expect("Failed to load shared libraries");
+///     let names: Vec<_> = libs.libs().iter().map(|lib| lib.lib_path().lib_name()).collect();
+///     assert_eq!(names, ["core", "audio_core", "audio_plugin"]);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct LibGraph {
+    nodes: Vec<Node>,
+    flags: LoadFlags,
+}
+#[derive(Clone, Debug)]
+struct Node {
+    lib_path: LibPath,
+    dependencies: Vec<String>,
+}
+/// State of a library while sorting the graph.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    InProgress,
+    Done,
+}
+impl LibGraph {
+    /// Create a new empty graph.
+    pub fn new() -> LibGraph {
+        LibGraph::default()
+    }
+    /// Add the library at `lib_path`, which depends on the libraries with the given names, replacing the library added before with the same name.
+    pub fn add(mut self, lib_path: LibPath, dependencies: &[&str]) -> LibGraph {
+        let node = Node { lib_path, dependencies: dependencies.iter().map(|&dependency| dependency.to_owned()).collect() };
+        match self.nodes.iter_mut().find(|added| added.lib_path.lib_name() == node.lib_path.lib_name()) {
+            Some(added) => *added = node,
+            None => self.nodes.push(node),
+        }
+        self
+    }
+    /// Set the flags the libraries are loaded with, e.g. [`LoadFlags::with_global`] to make the symbols of a library available
+    /// to the libraries depending on it without being linked against it.
+    pub fn with_flags(mut self, flags: LoadFlags) -> LibGraph {
+        self.flags = flags;
+        self
+    }
+    /// Get the paths of the libraries in the order they are loaded.
+    /// # Errors
+    /// Returns [`SharedLibError::UnknownDependency`] if a library depends on a library which is not in the graph,
+    /// and [`SharedLibError::DependencyCycle`] if libraries depend on each other in a cycle.
+    pub fn load_order(&self) -> Result<Vec<&LibPath>, SharedLibError> {
+        let mut visits = vec![Visit::New; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        for index in 0..self.nodes.len() {
+            self.visit(index, &mut visits, &mut Vec::new(), &mut order)?;
+        }
+        Ok(order.into_iter().map(|index| &self.nodes[index].lib_path).collect())
+    }
+    /// Add the library at `index` to `order` after its dependencies, with `path` holding the libraries depending on it.
+    fn visit(&self, index: usize, visits: &mut [Visit], path: &mut Vec<usize>, order: &mut Vec<usize>) -> Result<(), SharedLibError> {
+        match visits[index] {
+            Visit::Done => return Ok(()),
+            Visit::InProgress => {
+                let start = path.iter().position(|&i| i == index).expect("the library in progress is on the path");
+                let cycle = path[start..].iter().chain([&index]).map(|&i| self.nodes[i].lib_path.lib_name().to_owned()).collect();
+                return Err(SharedLibError::DependencyCycle(cycle));
+            }
+            Visit::New => {}
+        }
+        visits[index] = Visit::InProgress;
+        path.push(index);
+        let node = &self.nodes[index];
+        for dependency in &node.dependencies {
+            let Some(dep_index) = self.nodes.iter().position(|dep| dep.lib_path.lib_name() == dependency) else {
+                return Err(SharedLibError::UnknownDependency { lib_name: node.lib_path.lib_name().to_owned(), dependency: dependency.clone() });
+            };
+            self.visit(dep_index, visits, path, order)?;
+        }
+        path.pop();
+        visits[index] = Visit::Done;
+        order.push(index);
+        Ok(())
+    }
+    /// Load the libraries in topological order, see [`LibGraph::load_order`].
+    ///
+    /// If a library fails to load, the libraries loaded before it are unloaded in reverse order and the error is returned.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    pub unsafe fn load(&self) -> Result<SharedLibSet, SharedLibError> {
+        let order = self.load_order()?;
+        SharedLibSet::open(order.into_iter().cloned(), self.flags)
+    }
+}
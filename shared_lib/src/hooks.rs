@@ -0,0 +1,83 @@
+//! Callbacks run when a library is loaded, unloaded or reloaded, see [`SharedLib::on_load`].
+
+use crate::{EventSink, LibEvent, LibEventKind, LibPath, SharedLib};
+use std::sync::Arc;
+
+type Hook = Arc<dyn Fn(&SharedLib) + Send + Sync>;
+
+/// Callbacks and event sinks registered on a library, or on a [`LibManager`](crate::LibManager) for every library it loads.
+#[derive(Clone, Default)]
+pub(crate) struct LibHooks {
+    on_load: Vec<Hook>,
+    before_unload: Vec<Hook>,
+    after_reload: Vec<Hook>,
+    sinks: Vec<Arc<dyn EventSink>>,
+}
+impl LibHooks {
+    pub(crate) fn on_load(&mut self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.on_load.push(Arc::new(hook));
+    }
+    pub(crate) fn before_unload(&mut self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.before_unload.push(Arc::new(hook));
+    }
+    pub(crate) fn after_reload(&mut self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.after_reload.push(Arc::new(hook));
+    }
+    pub(crate) fn subscribe(&mut self, sink: impl EventSink + 'static) {
+        self.sinks.push(Arc::new(sink));
+    }
+    pub(crate) fn run_on_load(&self, lib: &SharedLib) {
+        self.on_load.iter().for_each(|hook| hook(lib));
+    }
+    pub(crate) fn run_before_unload(&self, lib: &SharedLib) {
+        self.before_unload.iter().for_each(|hook| hook(lib));
+    }
+    pub(crate) fn run_after_reload(&self, lib: &SharedLib) {
+        self.after_reload.iter().for_each(|hook| hook(lib));
+    }
+    /// Deliver the event of the library at `lib_path` to the sinks, only creating it if there are any.
+    pub(crate) fn emit(&self, lib_path: &LibPath, kind: impl FnOnce() -> LibEventKind) {
+        if self.sinks.is_empty() {
+            return;
+        }
+        let event = LibEvent::now(lib_path, kind());
+        self.sinks.iter().for_each(|sink| sink.event(&event));
+    }
+}
+
+impl SharedLib {
+    /// Register a callback run every time the library is loaded again by [`SharedLib::reload`], before the
+    /// [`SharedLib::after_reload`] callbacks, e.g. to resubscribe callbacks passed to the library.
+    ///
+    /// The library is already loaded when the callback is registered, so it is not run for the first load,
+    /// except for the callbacks registered with [`LibManager::on_load`](crate::LibManager::on_load).
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let mut lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     lib.on_load(|lib| println!("Loaded {}", lib.lib_path()));
+    ///     lib.before_unload(|lib| println!("Unloading {}", lib.lib_path()));
+    ///     lib.after_reload(|lib| println!("Reloaded {}, generation {}", lib.lib_path(), lib.generation()));
+    ///     lib.reload().expect("Failed to reload shared library");
+    /// }
+    /// ```
+    pub fn on_load(&mut self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.hooks.on_load(hook);
+    }
+    /// Register a callback run before the library is unloaded, by [`SharedLib::close`], [`SharedLib::reload`] or dropping it,
+    /// while its functions can still be called, e.g. to invalidate caches of values owned by the library.
+    ///
+    /// The callback is not run if unloading fails before anything is unloaded, e.g. because of functions obtained with
+    /// [`SharedLib::get_fn_owned`] still using the library.
+    pub fn before_unload(&mut self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.hooks.before_unload(hook);
+    }
+    /// Register a callback run after the library is reloaded by [`SharedLib::reload`], with the generation already
+    /// incremented, e.g. to log an audit event.
+    pub fn after_reload(&mut self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.hooks.after_reload(hook);
+    }
+}
@@ -0,0 +1,79 @@
+//! Tables of host functions passed to plugins when they are loaded, see [`PluginRegistry::with_host_api`].
+
+use crate::{trace, PluginRegistry, SharedLib, SharedLibError};
+use std::any::Any;
+use std::ffi::c_void;
+use std::sync::Arc;
+
+/// Name of the function called with a pointer to the host API right after a plugin library is loaded, see [`PluginRegistry::with_host_api`].
+pub const HOST_BIND_SYMBOL: &str = "plugin_bind_host";
+
+type BindFn = unsafe extern "C" fn(*const c_void);
+
+/// Host API shared by a registry and the plugins bound to it.
+#[derive(Clone)]
+pub(crate) struct SharedHostApi(Arc<dyn Any + Send + Sync>);
+
+impl PluginRegistry {
+    /// Set the table of host functions passed to the plugins loaded after it, e.g. a `#[repr(C)]` struct of `extern "C"` function pointers.
+    ///
+    /// Right after a plugin library is loaded, and before its plugin is created, the registry calls its
+    /// `plugin_bind_host(api: *const HostApi)` function ([`HOST_BIND_SYMBOL`]) with a pointer to `api`, if the library exports it.
+    /// Every plugin keeps the table alive until its library is unloaded, even if the registry is given another table
+    /// in the meantime, so the plugin can use the pointer for as long as the registry keeps it loaded.
+    /// # Example
+    /// In the plugin:
+    /// ```no_run
+    /// use std::sync::atomic::{AtomicPtr, Ordering};
+    ///
+    /// #[repr(C)]
+    /// pub struct HostApi {
+    ///     pub log: extern "C" fn(level: u32, msg: *const std::ffi::c_char),
+    /// }
+    /// static HOST: AtomicPtr<HostApi> = AtomicPtr::new(std::ptr::null_mut());
+    ///
+    /// #[no_mangle]
+    /// pub extern "C" fn plugin_bind_host(api: *const HostApi) {
+    ///     HOST.store(api.cast_mut(), Ordering::Release);
+    /// }
+    /// ```
+    /// In the host:
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// #[repr(C)]
+    /// pub struct HostApi {
+    ///     pub log: extern "C" fn(level: u32, msg: *const std::ffi::c_char),
+    /// }
+    /// extern "C" fn log(level: u32, msg: *const std::ffi::c_char) {
+    ///     println!("[{}] {}", level, unsafe { std::ffi::CStr::from_ptr(msg) }.to_string_lossy());
+    /// }
+    ///
+    /// let mut registry = PluginRegistry::new().with_host_api(HostApi { log });
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/plugins"), "greeter".into());
+    ///     registry.load(lib_path).expect("Failed to load plugin");
+    /// }
+    /// ```
+    pub fn with_host_api<T: Send + Sync + 'static>(mut self, api: T) -> PluginRegistry {
+        self.host_api = Some(SharedHostApi(Arc::new(api)));
+        self
+    }
+    /// Get the table of host functions passed to the plugins, if it is set and of type `T`.
+    pub fn host_api<T: 'static>(&self) -> Option<&T> {
+        self.host_api.as_ref()?.0.downcast_ref()
+    }
+}
+
+impl SharedLib {
+    /// Call [`HOST_BIND_SYMBOL`] of the library with a pointer to `api`, if it exports it.
+    pub(crate) unsafe fn bind_host(&self, api: &SharedHostApi) -> Result<(), SharedLibError> {
+        if !self.has_symbol(HOST_BIND_SYMBOL) {
+            return Ok(());
+        }
+        trace::event!(debug, path = %self.lib_path, "Binding host API");
+        self.get_fn::<BindFn>(HOST_BIND_SYMBOL)?.run(Arc::as_ptr(&api.0) as *const c_void);
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+//! Decompression of deflate streams (RFC 1951) and gzip files (RFC 1952), for the archives of [`SharedLib::from_archive`](crate::SharedLib::from_archive).
+
+/// Bits of a deflate stream, read from the least significant bit of every byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader { data, pos: 0, bit_buf: 0, bit_count: 0 }
+    }
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.pos).ok_or("Unexpected end of the compressed data.")?;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.pos += 1;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u64 << count) - 1) as u32;
+        self.bit_buf >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+    /// Skip the bits left of the current byte.
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// Canonical Huffman code, decoded by the number of codes of every length.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate().filter(|(_, &len)| len != 0) {
+            symbols[offsets[len as usize] as usize] = symbol as u16;
+            offsets[len as usize] += 1;
+        }
+        Huffman { counts, symbols }
+    }
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("Invalid Huffman code in the compressed data.".into())
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+/// Order in which the lengths of the code length code are stored in a dynamic block.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompress a raw deflate stream, failing once the decompressed data exceeds `max_len` bytes, e.g. for a zip bomb.
+pub(crate) fn inflate(data: &[u8], max_len: usize) -> Result<Vec<u8>, String> {
+    inflate_stream(data, max_len).map(|(out, _)| out)
+}
+
+/// Decompress a raw deflate stream like [`inflate`], also returning the number of bytes of `data` it took up.
+fn inflate_stream(data: &[u8], max_len: usize) -> Result<(Vec<u8>, usize), String> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::new();
+    loop {
+        let is_last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let header = data.get(reader.pos..reader.pos + 4).ok_or("Unexpected end of the compressed data.")?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                if len != !u16::from_le_bytes([header[2], header[3]]) as usize {
+                    return Err("Invalid length of a stored block.".into());
+                }
+                let start = reader.pos + 4;
+                check_len(out.len() + len, max_len)?;
+                out.extend_from_slice(data.get(start..start + len).ok_or("Unexpected end of the compressed data.")?);
+                reader.pos = start + len;
+            }
+            1 => {
+                let mut lengths = [0u8; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                inflate_block(&mut reader, &mut out, &Huffman::new(&lengths[..288]), &Huffman::new(&lengths[288..]), max_len)?;
+            }
+            2 => {
+                let (lit_len, dist) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut out, &lit_len, &dist, max_len)?;
+            }
+            _ => return Err("Invalid block type in the compressed data.".into()),
+        }
+        if is_last {
+            return Ok((out, reader.pos));
+        }
+    }
+}
+
+/// Read the literal/length and distance codes of a dynamic block.
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let lit_len_count = reader.bits(5)? as usize + 257;
+    let dist_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+    let mut lengths = Vec::with_capacity(lit_len_count + dist_count);
+    while lengths.len() < lit_len_count + dist_count {
+        let (len, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (*lengths.last().ok_or("Repeated code length without a previous one.")?, 3 + reader.bits(2)?),
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(len, repeat as usize));
+    }
+    if lengths.len() > lit_len_count + dist_count {
+        return Err("Too many code lengths in a dynamic block.".into());
+    }
+    Ok((Huffman::new(&lengths[..lit_len_count]), Huffman::new(&lengths[lit_len_count..])))
+}
+
+/// Check that `len` bytes of decompressed data do not exceed `max_len`.
+fn check_len(len: usize, max_len: usize) -> Result<(), String> {
+    match len > max_len {
+        true => Err(format!("The decompressed data exceeds {} bytes.", max_len)),
+        false => Ok(()),
+    }
+}
+
+/// Decode the symbols of a compressed block until its end.
+fn inflate_block(reader: &mut BitReader, out: &mut Vec<u8>, lit_len: &Huffman, dist: &Huffman, max_len: usize) -> Result<(), String> {
+    loop {
+        let symbol = lit_len.decode(reader)? as usize;
+        match symbol {
+            0..=255 => {
+                check_len(out.len() + 1, max_len)?;
+                out.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let i = symbol - 257;
+                let (&base, &extra) = LENGTH_BASE.get(i).zip(LENGTH_EXTRA.get(i)).ok_or("Invalid length in the compressed data.")?;
+                let len = base as usize + reader.bits(extra as u32)? as usize;
+                let i = dist.decode(reader)? as usize;
+                let (&base, &extra) = DIST_BASE.get(i).zip(DIST_EXTRA.get(i)).ok_or("Invalid distance in the compressed data.")?;
+                let distance = base as usize + reader.bits(extra as u32)? as usize;
+                if distance > out.len() {
+                    return Err("Distance too far back in the compressed data.".into());
+                }
+                check_len(out.len() + len, max_len)?;
+                // The copied bytes may overlap the ones being written, so they are copied one by one.
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+}
+
+/// Decompress the contents of a gzip file, of which only the first member is read, failing once it exceeds `max_len` bytes.
+pub(crate) fn gunzip(data: &[u8], max_len: usize) -> Result<Vec<u8>, String> {
+    const FEXTRA: u8 = 4;
+    const FNAME: u8 = 8;
+    const FCOMMENT: u8 = 16;
+    const FHCRC: u8 = 2;
+    if data.len() < 18 || data[..3] != [0x1f, 0x8b, 8] {
+        return Err("Not a gzip file compressed with deflate.".into());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let len = data.get(pos..pos + 2).ok_or("Truncated gzip header.")?;
+        pos += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            pos += data.get(pos..).and_then(|rest| rest.iter().position(|&b| b == 0)).ok_or("Truncated gzip header.")? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    let (out, len) = inflate_stream(data.get(pos..).ok_or("Truncated gzip header.")?, max_len)?;
+    let trailer = data.get(pos + len..pos + len + 8).ok_or("Missing the gzip trailer.")?;
+    let (crc, size) = (u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]), u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]));
+    if size != out.len() as u32 {
+        return Err(format!("The decompressed data has {} bytes, expected {} modulo 2^32.", out.len(), size));
+    }
+    if crc != crc32(&out) {
+        return Err("The CRC-32 of the decompressed data does not match.".into());
+    }
+    Ok(out)
+}
+
+/// Table of the CRC-32 of every byte value, for the polynomial used by zip and gzip.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => 0xedb8_8320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Compute the CRC-32 of `data`, as stored by zip and gzip to check the decompressed contents.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| CRC32_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
@@ -0,0 +1,379 @@
+//! Static inspection of shared library files, without loading them.
+//!
+//! # Example
+//! ```no_run
+//! use shared_lib::*;
+//! use std::path::PathBuf;
+//!
+//! let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+//! let symbols = inspect::exported_symbols(lib_path.path().unwrap()).expect("Failed to read exported symbols");
+//! ```
+
+use crate::SharedLibError;
+use object::read::elf::{ElfFile, FileHeader};
+use object::read::macho::{FatArch, LoadCommandVariant, MachHeader, MachOFatFile32, MachOFatFile64, MachOFile};
+use object::read::pe::{ImageNtHeaders, PeFile, ResourceDirectoryEntryData};
+use object::{elf, pe, Architecture, BinaryFormat, FileKind, LittleEndian, NameOrOrdinal, Object, ReadCache, ReadRef};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Check that the library file at `path` was built for the architecture of the current process.
+///
+/// Only the headers of the file are read. Files that cannot be opened or parsed are not rejected,
+/// so the dynamic loader can report a more specific error for them.
+pub(crate) fn check_arch(path: &Path) -> Result<(), SharedLibError> {
+    let expected = host_architecture();
+    if expected == Architecture::Unknown {
+        return Ok(());
+    }
+    let Ok(file) = std::fs::File::open(path) else {
+        return Ok(());
+    };
+    let cache = ReadCache::new(file);
+    let Ok(found) = file_architectures(&cache) else {
+        return Ok(());
+    };
+    if found.is_empty() || found.contains(&expected) {
+        return Ok(());
+    }
+    let found = found.iter().map(|arch| format!("{:?}", arch)).collect::<Vec<_>>().join(", ");
+    Err(SharedLibError::ArchMismatch {
+        path: path.to_string_lossy().to_string(),
+        expected: format!("{:?}", expected),
+        found,
+    })
+}
+
+/// Report of the checks of a library file done by [`diagnose`], without loading it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibDiagnosis {
+    /// Path of the checked library file.
+    pub path: PathBuf,
+    /// Architectures the library file was built for, e.g. `X86_64`, or several for universal Mach-O binaries.
+    /// Empty if the file could not be read.
+    pub architectures: Vec<String>,
+    /// Problems preventing the library from being loaded, in the order they were found.
+    pub problems: Vec<LibProblem>,
+}
+impl LibDiagnosis {
+    /// Check if no problem was found, in which case loading can still fail, e.g. because of a missing dependency.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+impl fmt::Display for LibDiagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.problems.is_empty() {
+            true => write!(f, "Library '{}' has no problems.", self.path.display()),
+            false => {
+                let problems = self.problems.iter().map(|problem| problem.to_string()).collect::<Vec<_>>().join(" ");
+                write!(f, "Library '{}' cannot be loaded. {}", self.path.display(), problems)
+            }
+        }
+    }
+}
+
+/// Problem with a library file found by [`diagnose`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LibProblem {
+    /// The file does not exist.
+    NotFound,
+    /// The file cannot be opened, e.g. for lack of permissions.
+    NotReadable(String),
+    /// The file is not an ELF, Mach-O or PE library, e.g. because it is truncated.
+    InvalidFormat(String),
+    /// The file was built for other architectures than the current process, e.g. `Aarch64` instead of `X86_64`.
+    ArchMismatch { expected: String, found: Vec<String> },
+    /// The file was built for another pointer width than the current process, e.g. a 32-bit library in a 64-bit process.
+    BitnessMismatch { expected: u8, found: u8 },
+}
+impl fmt::Display for LibProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibProblem::NotFound => write!(f, "The file does not exist."),
+            LibProblem::NotReadable(msg) => write!(f, "The file cannot be read. {}", msg),
+            LibProblem::InvalidFormat(msg) => write!(f, "The file is not a valid library. {}", msg),
+            LibProblem::ArchMismatch { expected, found } => {
+                write!(f, "The file was built for {} architecture, but the current process is {}.", found.join(", "), expected)
+            }
+            LibProblem::BitnessMismatch { expected, found } => {
+                write!(f, "The file is a {}-bit library, but the current process is {}-bit.", found, expected)
+            }
+        }
+    }
+}
+
+/// Check that the library file at `path` exists, can be read and was built for the architecture of the current process.
+///
+/// Every problem found is listed in the report instead of being returned as an error, so it can be shown to the user
+/// when loading the library fails with an unclear message of the dynamic loader.
+pub fn diagnose(path: impl AsRef<Path>) -> LibDiagnosis {
+    let mut diagnosis = LibDiagnosis { path: path.as_ref().to_path_buf(), architectures: Vec::new(), problems: Vec::new() };
+    if let Ok(false) = diagnosis.path.try_exists() {
+        diagnosis.problems.push(LibProblem::NotFound);
+        return diagnosis;
+    }
+    let file = match std::fs::File::open(&diagnosis.path) {
+        Ok(file) => file,
+        Err(e) => {
+            diagnosis.problems.push(LibProblem::NotReadable(e.to_string()));
+            return diagnosis;
+        }
+    };
+    let cache = ReadCache::new(file);
+    let found = match file_architectures(&cache) {
+        Ok(found) => found,
+        Err(e) => {
+            diagnosis.problems.push(LibProblem::InvalidFormat(e.to_string()));
+            return diagnosis;
+        }
+    };
+    diagnosis.architectures = found.iter().map(|arch| format!("{:?}", arch)).collect();
+    let expected = host_architecture();
+    if expected == Architecture::Unknown || found.contains(&expected) {
+        return diagnosis;
+    }
+    diagnosis.problems.push(LibProblem::ArchMismatch { expected: format!("{:?}", expected), found: diagnosis.architectures.clone() });
+    let bits = |arch: &Architecture| arch.address_size().map(|size| size.bytes() * 8);
+    let found_bits: Vec<u8> = found.iter().filter_map(bits).collect();
+    if let (Some(expected), Some(&found)) = (bits(&expected), found_bits.first()) {
+        if !found_bits.contains(&expected) {
+            diagnosis.problems.push(LibProblem::BitnessMismatch { expected, found });
+        }
+    }
+    diagnosis
+}
+
+/// Get the architectures the library image was built for.
+/// Universal Mach-O binaries contain an image for each of the architectures.
+fn file_architectures<'data, R: ReadRef<'data>>(data: R) -> object::Result<Vec<Architecture>> {
+    match FileKind::parse(data)? {
+        FileKind::MachOFat32 => Ok(MachOFatFile32::parse(data)?.arches().iter().map(|arch| arch.architecture()).collect()),
+        FileKind::MachOFat64 => Ok(MachOFatFile64::parse(data)?.arches().iter().map(|arch| arch.architecture()).collect()),
+        _ => Ok(vec![object::File::parse(data)?.architecture()]),
+    }
+}
+
+/// Get the names of all symbols exported by the library file at `path`, in the order of its export table.
+///
+/// ELF, Mach-O (including universal binaries, of which the image for the current architecture is read) and PE files are supported.
+/// Names are returned in the form expected by [`SharedLib::get_fn`](crate::SharedLib::get_fn),
+/// so the leading underscore Mach-O adds to every symbol is stripped.
+pub fn exported_symbols(path: impl AsRef<Path>) -> Result<Vec<String>, SharedLibError> {
+    let path = path.as_ref();
+    let inspect_failure = |msg: String| SharedLibError::InspectFailure {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    };
+    let data = std::fs::read(path).map_err(|e| inspect_failure(e.to_string()))?;
+    image_exports(&data).map_err(|e| inspect_failure(e.to_string()))
+}
+
+fn image_exports(data: &[u8]) -> object::Result<Vec<String>> {
+    let file = object::File::parse(host_image(data)?)?;
+    let mut names = Vec::new();
+    for export in file.exports()? {
+        let export = export?;
+        let NameOrOrdinal::Name(name) = export.name() else {
+            continue;
+        };
+        let name = match file.format() {
+            BinaryFormat::MachO => name.strip_prefix(b"_").unwrap_or(name),
+            _ => name,
+        };
+        names.push(String::from_utf8_lossy(name).to_string());
+    }
+    Ok(names)
+}
+
+/// Get the names of the libraries the library file at `path` declares as dependencies, in the order they are declared.
+///
+/// These are the `DT_NEEDED` entries of ELF files, the dylib load commands of Mach-O files and the import tables of PE files.
+/// The names are returned as written in the file, e.g. `libc.so.6`, `@rpath/libfoo.dylib` or `KERNEL32.dll`.
+/// Dependencies of the dependencies are not read.
+pub fn dependencies(path: impl AsRef<Path>) -> Result<Vec<String>, SharedLibError> {
+    let path = path.as_ref();
+    let inspect_failure = |msg: String| SharedLibError::InspectFailure {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    };
+    let data = std::fs::read(path).map_err(|e| inspect_failure(e.to_string()))?;
+    image_dependencies(&data).map_err(|e| inspect_failure(e.to_string()))
+}
+
+fn image_dependencies(data: &[u8]) -> object::Result<Vec<String>> {
+    let file = object::File::parse(host_image(data)?)?;
+    let mut names: Vec<String> = Vec::new();
+    for lib in file.import_libraries()? {
+        let name = String::from_utf8_lossy(lib?.name()).to_string();
+        // PE files list libraries again in the delay-load import table.
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Version information embedded in a library file, see [`metadata`].
+///
+/// Every field is `None` if the file does not record it, as each format stores a different part of it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibMetadata {
+    /// Name the library identifies itself with, i.e. the ELF `DT_SONAME`, e.g. `libfoo.so.1`,
+    /// or the Mach-O install name, e.g. `@rpath/libfoo.dylib`.
+    pub name: Option<String>,
+    /// Version of the library, i.e. the version suffix of the ELF `DT_SONAME`, e.g. `1`,
+    /// the Mach-O current version, e.g. `1.2.3`, or the PE file version, e.g. `1.2.3.4`.
+    pub version: Option<String>,
+    /// Version the library stays compatible with, i.e. the Mach-O compatibility version
+    /// or the PE product version.
+    pub compatibility_version: Option<String>,
+    /// Unique identifier of the build as a hex string, i.e. the ELF GNU build ID note or the Mach-O UUID.
+    pub build_id: Option<String>,
+}
+
+/// Get the version information embedded in the library file at `path`, without loading it.
+///
+/// This reads the `DT_SONAME` entry and the build ID note of ELF files, the `LC_ID_DYLIB` and `LC_UUID` load commands
+/// of Mach-O files and the fixed part of the `VERSIONINFO` resource of PE files.
+pub fn metadata(path: impl AsRef<Path>) -> Result<LibMetadata, SharedLibError> {
+    let path = path.as_ref();
+    let inspect_failure = |msg: String| SharedLibError::InspectFailure {
+        path: path.to_string_lossy().to_string(),
+        msg,
+    };
+    let data = std::fs::read(path).map_err(|e| inspect_failure(e.to_string()))?;
+    image_metadata(&data).map_err(|e| inspect_failure(e.to_string()))
+}
+
+fn image_metadata(data: &[u8]) -> object::Result<LibMetadata> {
+    let file = object::File::parse(host_image(data)?)?;
+    let mut metadata = match &file {
+        object::File::Elf32(elf) => elf_metadata(elf)?,
+        object::File::Elf64(elf) => elf_metadata(elf)?,
+        object::File::MachO32(macho) => macho_metadata(macho)?,
+        object::File::MachO64(macho) => macho_metadata(macho)?,
+        object::File::Pe32(pe) => pe_metadata(pe)?,
+        object::File::Pe64(pe) => pe_metadata(pe)?,
+        _ => LibMetadata::default(),
+    };
+    let build_id = match file.build_id()? {
+        Some(build_id) => Some(build_id.to_vec()),
+        None => file.mach_uuid()?.map(|uuid| uuid.to_vec()),
+    };
+    metadata.build_id = build_id.map(|id| id.iter().map(|b| format!("{:02x}", b)).collect());
+    Ok(metadata)
+}
+
+fn elf_metadata<'data, Elf: FileHeader>(file: &ElfFile<'data, Elf>) -> object::Result<LibMetadata> {
+    let dynamic = file.elf_section_table().dynamic_table(file.endian(), file.data())?;
+    let mut metadata = LibMetadata::default();
+    for entry in &dynamic {
+        if entry.tag == elf::DT_SONAME {
+            let name = String::from_utf8_lossy(dynamic.string(entry)?).to_string();
+            // The version is whatever follows the extension, e.g. `1.2` of `libfoo.so.1.2`.
+            metadata.version = name.split_once(".so.").map(|(_, version)| version.to_string());
+            metadata.name = Some(name);
+        }
+    }
+    Ok(metadata)
+}
+
+fn macho_metadata<'data, Mach: MachHeader>(file: &MachOFile<'data, Mach>) -> object::Result<LibMetadata> {
+    let endian = file.endian();
+    let mut metadata = LibMetadata::default();
+    let mut commands = file.macho_load_commands()?;
+    while let Some(command) = commands.next()? {
+        if let LoadCommandVariant::IdDylib(dylib) = command.variant()? {
+            let dylib = &dylib.dylib;
+            metadata.name = Some(String::from_utf8_lossy(command.string(endian, dylib.name)?).to_string());
+            metadata.version = Some(dylib.current_version.get(endian).to_string());
+            metadata.compatibility_version = Some(dylib.compatibility_version.get(endian).to_string());
+        }
+    }
+    Ok(metadata)
+}
+
+fn pe_metadata<'data, Pe: ImageNtHeaders>(file: &PeFile<'data, Pe>) -> object::Result<LibMetadata> {
+    let mut metadata = LibMetadata::default();
+    let Some(info) = pe_version_info(file)? else {
+        return Ok(metadata);
+    };
+    // VS_FIXEDFILEINFO starts with its signature, and is aligned to 4 bytes in the VS_VERSIONINFO structure.
+    const FIXED_FILE_INFO_SIGNATURE: [u8; 4] = 0xFEEF04BDu32.to_le_bytes();
+    let fixed = info.chunks_exact(4).position(|chunk| chunk == FIXED_FILE_INFO_SIGNATURE).map(|i| &info[i * 4..]);
+    let Some(fixed) = fixed.filter(|fixed| fixed.len() >= 24) else {
+        return Ok(metadata);
+    };
+    let word = |offset: usize| u32::from_le_bytes([fixed[offset], fixed[offset + 1], fixed[offset + 2], fixed[offset + 3]]);
+    let version = |ms: u32, ls: u32| format!("{}.{}.{}.{}", ms >> 16, ms & 0xFFFF, ls >> 16, ls & 0xFFFF);
+    metadata.version = Some(version(word(8), word(12)));
+    metadata.compatibility_version = Some(version(word(16), word(20)));
+    Ok(metadata)
+}
+
+/// Get the data of the first `VERSIONINFO` resource of the PE file, in any of its languages.
+fn pe_version_info<'data, Pe: ImageNtHeaders>(file: &PeFile<'data, Pe>) -> object::Result<Option<&'data [u8]>> {
+    let sections = file.section_table();
+    let Some(resources) = file.data_directories().resource_directory(file.data(), &sections)? else {
+        return Ok(None);
+    };
+    // Resources are a tree of types, then names, then languages.
+    let mut table = resources.root()?;
+    let Some(entry) = table.entries.iter().find(|entry| entry.name_or_id().id() == Some(pe::RT_VERSION)) else {
+        return Ok(None);
+    };
+    let mut entry = *entry;
+    loop {
+        match entry.data(resources)? {
+            ResourceDirectoryEntryData::Table(next) => {
+                table = next;
+                let Some(first) = table.entries.first() else {
+                    return Ok(None);
+                };
+                entry = *first;
+            }
+            ResourceDirectoryEntryData::Data(data) => {
+                let size = data.size.get(LittleEndian) as usize;
+                let bytes = sections.pe_data_at(file.data(), data.offset_to_data.get(LittleEndian));
+                return Ok(bytes.map(|bytes| &bytes[..bytes.len().min(size)]));
+            }
+        }
+    }
+}
+
+/// Get the image matching the host architecture from a universal Mach-O binary,
+/// or the whole data for any other kind of file.
+fn host_image(data: &[u8]) -> object::Result<&[u8]> {
+    match FileKind::parse(data)? {
+        FileKind::MachOFat32 => select_arch(MachOFatFile32::parse(data)?.arches(), data),
+        FileKind::MachOFat64 => select_arch(MachOFatFile64::parse(data)?.arches(), data),
+        _ => Ok(data),
+    }
+}
+
+fn select_arch<'data, Fat: FatArch>(arches: &[Fat], data: &'data [u8]) -> object::Result<&'data [u8]> {
+    let arch = arches
+        .iter()
+        .find(|arch| arch.architecture() == host_architecture())
+        .or_else(|| arches.first());
+    match arch {
+        Some(arch) => arch.data(data),
+        None => Ok(data),
+    }
+}
+
+fn host_architecture() -> Architecture {
+    if cfg!(target_arch = "x86_64") {
+        Architecture::X86_64
+    } else if cfg!(target_arch = "aarch64") {
+        Architecture::Aarch64
+    } else if cfg!(target_arch = "x86") {
+        Architecture::I386
+    } else if cfg!(target_arch = "arm") {
+        Architecture::Arm
+    } else {
+        Architecture::Unknown
+    }
+}
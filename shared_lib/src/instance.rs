@@ -0,0 +1,89 @@
+//! Creation of trait objects by the constructor and destructor functions exported by a library.
+
+use crate::{OwnedSharedLibFn, SharedLib, SharedLibError};
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// Structure representing an object created by a shared library, e.g. a trait object, destroyed by the library when dropped.
+///
+/// It keeps the library loaded like [`OwnedSharedLibFn`], so the code of the object stays available
+/// for as long as the object is alive. It dereferences to the object.
+pub struct LibInstance<T: ?Sized + 'static> {
+    ptr: NonNull<T>,
+    destructor: OwnedSharedLibFn<fn(*mut T)>,
+}
+impl<T: ?Sized + 'static> Deref for LibInstance<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // Safety: the pointer returned by the constructor is valid until it is passed to the destructor.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+impl<T: ?Sized + 'static> DerefMut for LibInstance<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: the pointer returned by the constructor is valid until it is passed to the destructor.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+impl<T: ?Sized + 'static> Drop for LibInstance<T> {
+    fn drop(&mut self) {
+        // Safety: the destructor was paired with the constructor by the caller of `SharedLib::get_instance`.
+        unsafe { self.destructor.run(self.ptr.as_ptr()) }
+    }
+}
+// Safety: the instance owns the object like a `Box` does.
+unsafe impl<T: ?Sized + Send + 'static> Send for LibInstance<T> {}
+unsafe impl<T: ?Sized + Sync + 'static> Sync for LibInstance<T> {}
+
+impl SharedLib {
+    /// Create an object, e.g. a trait object, with the constructor exported by the shared library as `ctor_symbol`.
+    ///
+    /// The constructor must have the signature `fn() -> *mut T` and the destructor exported as `dtor_symbol`
+    /// the signature `fn(*mut T)`. The destructor is called with the created object when the returned instance is dropped.
+    /// Both functions are looked up before the object is created, and a null pointer returned by the constructor
+    /// is reported as [`SharedLibError::NullInstance`].
+    /// # Safety
+    /// This function is unsafe because it calls a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// For trait objects, the library has to be built with the same compiler and the same definition of the trait.
+    /// # Example
+    /// In the library:
+    /// ```no_run
+    /// pub trait Shape {
+    ///     fn area(&self) -> f64;
+    /// }
+    /// struct Square(f64);
+    /// impl Shape for Square {
+    ///     fn area(&self) -> f64 {
+    ///         self.0 * self.0
+    ///     }
+    /// }
+    /// #[no_mangle]
+    /// pub fn shape_create() -> *mut dyn Shape {
+    ///     Box::into_raw(Box::new(Square(2.0)))
+    /// }
+    /// #[no_mangle]
+    /// pub unsafe fn shape_destroy(shape: *mut dyn Shape) {
+    ///     drop(Box::from_raw(shape));
+    /// }
+    /// ```
+    /// In the host:
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// # pub trait Shape {
+    /// #     fn area(&self) -> f64;
+    /// # }
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shapes".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let shape = lib.get_instance::<dyn Shape>("shape_create", "shape_destroy").expect("Failed to create shape");
+    ///     println!("{}", shape.area());
+    /// }
+    /// ```
+    pub unsafe fn get_instance<T: ?Sized + 'static>(&self, ctor_symbol: &str, dtor_symbol: &str) -> Result<LibInstance<T>, SharedLibError> {
+        let destructor = self.get_fn_owned::<fn(*mut T)>(dtor_symbol)?;
+        let ptr = self.get_fn::<fn() -> *mut T>(ctor_symbol)?.run();
+        let ptr = NonNull::new(ptr).ok_or_else(|| SharedLibError::NullInstance { symbol_name: ctor_symbol.to_owned(), path: self.lib_path.lossy_path() })?;
+        Ok(LibInstance { ptr, destructor })
+    }
+}
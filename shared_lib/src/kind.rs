@@ -0,0 +1,115 @@
+//! Stable categories of errors, see [`ErrorKind`].
+
+use crate::SharedLibError;
+
+/// Enum representing the category of a [`SharedLibError`], see [`SharedLibError::kind`].
+///
+/// Unlike the variants of [`SharedLibError`], the kinds are stable, so callers can branch on them instead of matching
+/// variants or messages. New kinds may be added in future versions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The library path is invalid, e.g. empty or not matching the naming convention.
+    InvalidPath,
+    /// The library file does not exist.
+    NotFound,
+    /// The dynamic loader failed to load the library.
+    LoadFailed,
+    /// The library file or a file describing it is malformed, or built for another architecture.
+    BadFormat,
+    /// The library file failed the integrity or signature check.
+    VerificationFailed,
+    /// A symbol is missing from the library.
+    SymbolMissing,
+    /// A function was called after its library was reloaded or unloaded.
+    StaleSymbol,
+    /// The library or one of its symbols is not compatible with the expected ABI version or signature.
+    AbiMismatch,
+    /// The library is already loaded.
+    AlreadyLoaded,
+    /// The dependencies declared between libraries are missing or form a cycle.
+    InvalidDependencies,
+    /// The initialization function of the library failed, or a constructor of the library failed to create an object.
+    InitFailed,
+    /// The library failed to unload.
+    UnloadFailed,
+    /// A function of the library panicked, timed out, faulted or returned an error, or could not be called.
+    CallFailed,
+    /// The library failed to download.
+    FetchFailed,
+    /// The library or its bindings failed to be generated or compiled.
+    BuildFailed,
+    /// The operation is not supported on this platform.
+    Unsupported,
+    /// The error does not belong to any of the other kinds.
+    Other,
+}
+
+impl SharedLibError {
+    /// Get the kind of the error, looking through [`SharedLibError::LoadReport`] and [`SharedLibError::Context`].
+    ///
+    /// Errors aggregating the attempts to load a library, e.g. [`SharedLibError::NotInSearchPath`], are
+    /// [`ErrorKind::NotFound`] if the library was not found in any of the attempts, and [`ErrorKind::LoadFailed`] otherwise.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     match SharedLib::new(LibPath::new_no_path("optional_plugin".into())) {
+    ///         Ok(lib) => {}
+    ///         Err(e) if e.kind() == ErrorKind::NotFound => eprintln!("Plugin not installed"),
+    ///         Err(e) => panic!("Failed to load plugin. {}", e),
+    ///     }
+    /// }
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SharedLibError::LoadReport(report) => report.error.kind(),
+            SharedLibError::Context { source, .. } => source.kind(),
+            SharedLibError::PathEmpty | SharedLibError::PathConversion(..) | SharedLibError::EnvVarNotSet(_) | SharedLibError::InvalidFilename(_) => {
+                ErrorKind::InvalidPath
+            }
+            SharedLibError::LibNotFound { .. } | SharedLibError::NotFound { .. } => ErrorKind::NotFound,
+            // ERROR_BAD_EXE_FORMAT
+            SharedLibError::LoadFailure { raw_os_error: Some(193), .. } if cfg!(windows) => ErrorKind::BadFormat,
+            SharedLibError::LoadFailure { .. } | SharedLibError::DllDirectoryFailure { .. } => ErrorKind::LoadFailed,
+            SharedLibError::NotInSearchPath { attempts, .. } | SharedLibError::NoMatchingFilename { attempts, .. } => aggregate_kind(attempts.iter()),
+            SharedLibError::NoCandidateLoaded { attempts } => aggregate_kind(attempts.iter().map(|(_, e)| e)),
+            SharedLibError::ArchMismatch { .. }
+            | SharedLibError::InspectFailure { .. }
+            | SharedLibError::InvalidManifest { .. }
+            | SharedLibError::InvalidArchive { .. }
+            | SharedLibError::InvalidConfig { .. } => ErrorKind::BadFormat,
+            SharedLibError::IntegrityMismatch { .. }
+            | SharedLibError::ChecksumMismatch { .. }
+            | SharedLibError::VerificationFailure { .. } => ErrorKind::VerificationFailed,
+            SharedLibError::SymbolNotFound { .. } | SharedLibError::SymbolsNotFound { .. } => ErrorKind::SymbolMissing,
+            SharedLibError::StaleSymbol { .. } => ErrorKind::StaleSymbol,
+            SharedLibError::AbiMismatch { .. } | SharedLibError::SignatureMismatch { .. } | SharedLibError::IncompatibleVersion { .. } => {
+                ErrorKind::AbiMismatch
+            }
+            SharedLibError::PluginAlreadyLoaded(_) => ErrorKind::AlreadyLoaded,
+            SharedLibError::DependencyCycle(_) | SharedLibError::UnknownDependency { .. } => ErrorKind::InvalidDependencies,
+            SharedLibError::InitFailure { .. } | SharedLibError::NullInstance { .. } => ErrorKind::InitFailed,
+            SharedLibError::UnloadFailure { .. } => ErrorKind::UnloadFailed,
+            SharedLibError::CallPanicked(_)
+            | SharedLibError::Timeout(_)
+            | SharedLibError::SandboxFailure { .. }
+            | SharedLibError::ComFailure { .. }
+            | SharedLibError::Fault { .. }
+            | SharedLibError::WorkerFailure { .. }
+            | SharedLibError::WasmTrap { .. } => ErrorKind::CallFailed,
+            SharedLibError::FetchFailure { .. } => ErrorKind::FetchFailed,
+            SharedLibError::BindingsFailure(_) | SharedLibError::CompileFailure { .. } => ErrorKind::BuildFailed,
+            SharedLibError::Unsupported(_) => ErrorKind::Unsupported,
+            SharedLibError::InvalidGuid(_) | SharedLibError::InvalidSha256(_) => ErrorKind::Other,
+        }
+    }
+}
+
+/// Get the kind of an error aggregating the errors of several attempts to load a library.
+fn aggregate_kind<'a>(mut attempts: impl Iterator<Item = &'a SharedLibError>) -> ErrorKind {
+    match attempts.all(|e| e.kind() == ErrorKind::NotFound) {
+        true => ErrorKind::NotFound,
+        false => ErrorKind::LoadFailed,
+    }
+}
@@ -0,0 +1,97 @@
+//! Libraries and functions in statics that are loaded on first use, declared with [`lazy_lib!`](crate::lazy_lib)
+//! and [`lazy_fn!`](crate::lazy_fn).
+//!
+//! # Example
+//! ```no_run
+//! use shared_lib::*;
+//!
+//! lazy_lib!(static CALCULATOR = "calculator");
+//! lazy_fn!(static ADD: fn(usize, usize) -> usize = (CALCULATOR, "add"));
+//! lazy_fn!(static SUB: fn(usize, usize) -> usize = ("calculator", "sub"));
+//!
+//! unsafe {
+//!     match ADD.get() {
+//!         Ok(add_fn) => println!("1 + 2 = {}", add_fn.run(1, 2)),
+//!         Err(e) => println!("Calculator is not available. {}", e),
+//!     }
+//! }
+//! ```
+
+use crate::{FnSignature, LibPath, SharedLib, SharedLibError, SharedLibFn};
+use std::sync::OnceLock;
+
+/// Structure representing a shared library in a static, loaded on first use.
+///
+/// The library is looked up by its name in the system search path. The result of loading it is kept,
+/// so if loading fails, the same error is returned on every later use.
+pub struct LazyLib {
+    lib_name: &'static str,
+    lib: OnceLock<Result<SharedLib, SharedLibError>>,
+}
+impl LazyLib {
+    /// Create a new lazily loaded library from its name (without the platform specific extension and prefix).
+    pub const fn new(lib_name: &'static str) -> LazyLib {
+        LazyLib { lib_name, lib: OnceLock::new() }
+    }
+    /// Get the library, loading it if this is the first use.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn get(&self) -> Result<&SharedLib, SharedLibError> {
+        let lib = self.lib.get_or_init(|| SharedLib::new(LibPath::new_no_path(self.lib_name.to_owned())));
+        lib.as_ref().map_err(Clone::clone)
+    }
+}
+
+/// Structure representing a function of a [`LazyLib`] in a static, looked up on first use.
+///
+/// The result of the lookup is kept, so if loading the library or looking up the function fails,
+/// the same error is returned on every later use.
+pub struct LazyFn<T: FnSignature + 'static> {
+    lib: &'static LazyLib,
+    fn_name: &'static str,
+    func: OnceLock<Result<SharedLibFn<'static, T>, SharedLibError>>,
+}
+impl<T: FnSignature + 'static> LazyFn<T> {
+    /// Create a new lazily looked up function of the given library.
+    pub const fn new(lib: &'static LazyLib, fn_name: &'static str) -> LazyFn<T> {
+        LazyFn { lib, fn_name, func: OnceLock::new() }
+    }
+    /// Get the function, loading the library and looking up the function if this is the first use.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and a function from it, which is generally unsafe as it is a foregin code.
+    /// The function type is not checked against the actual type of the function.
+    pub unsafe fn get(&self) -> Result<&SharedLibFn<'static, T>, SharedLibError> {
+        let func = self.func.get_or_init(|| self.lib.get()?.get_fn::<T>(self.fn_name));
+        func.as_ref().map_err(Clone::clone)
+    }
+}
+
+/// Declare a static [`LazyLib`] from its library name, e.g. `lazy_lib!(static CALCULATOR = "calculator");`.
+#[macro_export]
+macro_rules! lazy_lib {
+    ($vis:vis static $name:ident = $lib_name:expr) => {
+        $vis static $name: $crate::LazyLib = $crate::LazyLib::new($lib_name);
+    };
+}
+
+/// Declare a static [`LazyFn`] from its type, the library and the function name.
+///
+/// The library is either a [`LazyLib`] static declared with [`lazy_lib!`](crate::lazy_lib),
+/// e.g. `lazy_fn!(static ADD: fn(usize, usize) -> usize = (CALCULATOR, "add"));`,
+/// or a library name, e.g. `lazy_fn!(static ADD: fn(usize, usize) -> usize = ("calculator", "add"));`,
+/// in which case the function has its own [`LazyLib`].
+#[macro_export]
+macro_rules! lazy_fn {
+    ($vis:vis static $name:ident: $fn_type:ty = ($lib:ident, $fn_name:expr)) => {
+        $vis static $name: $crate::LazyFn<$fn_type> = $crate::LazyFn::new(&$lib, $fn_name);
+    };
+    ($vis:vis static $name:ident: $fn_type:ty = ($lib_name:expr, $fn_name:expr)) => {
+        $vis static $name: $crate::LazyFn<$fn_type> = $crate::LazyFn::new(
+            {
+                static LIB: $crate::LazyLib = $crate::LazyLib::new($lib_name);
+                &LIB
+            },
+            $fn_name,
+        );
+    };
+}
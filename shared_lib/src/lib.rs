@@ -17,20 +17,457 @@
 //! ```
 
 use thiserror::Error;
-use libloading::{library_filename, Library, Symbol};
-use std::{ffi::OsString, path::PathBuf};
+use libloading::Symbol;
+use loader::{open_existing_library, Library, Loader};
+use std::{collections::HashMap, ffi::{c_char, CStr, OsStr, OsString}, ops::Deref, path::{Component, Path, PathBuf}, ptr::NonNull, sync::{Arc, Mutex}, time::Duration};
+
+pub mod prelude;
+
+#[cfg(feature = "archive")]
+mod archive;
+mod bindings;
+mod builder;
+mod bytes;
+mod callback;
+#[cfg(windows)]
+mod com;
+#[cfg(feature = "bindgen")]
+mod codegen;
+#[cfg(feature = "config")]
+mod config;
+mod context;
+mod decorated;
+mod demangle;
+mod discover;
+#[cfg(windows)]
+mod dll_dir;
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos"))]
+mod dl;
+mod embed;
+mod events;
+mod extraction;
+#[cfg(feature = "guarded")]
+mod fault;
+mod flags;
+mod fn_signature;
+mod gate;
+mod graph;
+mod hooks;
+mod host;
+#[cfg(feature = "archive")]
+mod inflate;
+pub mod inspect;
+mod instance;
+mod kind;
+#[cfg(target_os = "macos")]
+mod macos;
+mod lazy;
+mod lifecycle;
+mod loader;
+mod manager;
+mod module;
+#[cfg(feature = "manifest")]
+mod manifest;
+mod metrics;
+mod mock;
+mod naming;
+mod plugin;
+mod pool;
+mod prefetch;
+mod raw;
+#[cfg(feature = "remote")]
+mod remote;
+mod report;
+mod retry;
+#[cfg(all(feature = "sandbox", target_pointer_width = "64"))]
+mod sandbox;
+mod search;
+mod set;
+mod siblings;
+#[cfg(feature = "codegen")]
+mod spec;
+mod stub;
+pub mod state;
+mod suggest;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod trace;
+mod tracked;
+mod typed;
+mod variadic;
+mod versioned;
+#[cfg(feature = "signature")]
+mod signature;
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "wasm")]
+mod wasm_vm;
+
+pub use builder::SharedLibBuilder;
+pub use callback::{Callback, CallbackArgs, CallbackFn};
+#[cfg(windows)]
+pub use com::{ComPtr, Guid};
+#[cfg(feature = "bindgen")]
+pub use codegen::{dynamic_bindings, DynamicBindings};
+#[cfg(feature = "config")]
+pub use config::{load_from_config, LibConfig, LibConfigEntry, LibLoadOutcome, LoadedLibs};
+pub use context::ErrorContext;
+pub use discover::{discover_libs, discover_libs_matching, LibWalk, LibWalker};
+#[cfg(windows)]
+pub use dll_dir::{set_default_dll_directories, DllDirectory};
+pub use embed::EmbeddedLib;
+pub use events::{EventSink, LibEvent, LibEventKind};
+pub use extraction::ExtractionGuard;
+pub use flags::LoadFlags;
+pub use fn_signature::FnSignature;
+pub use gate::{CallGate, CallGuard};
+pub use graph::LibGraph;
+pub use host::HOST_BIND_SYMBOL;
+pub use instance::LibInstance;
+pub use kind::ErrorKind;
+pub use lazy::{LazyFn, LazyLib};
+pub use lifecycle::{INIT_SYMBOL, SHUTDOWN_SYMBOL};
+pub use manager::LibManager;
+pub use module::{loaded_libraries, ModuleInfo};
+#[cfg(feature = "manifest")]
+pub use manifest::{PluginManifest, MANIFEST_FILE_NAME};
+pub use metrics::CallStats;
+pub use mock::{MockLib, SymbolSource};
+pub use naming::{FilenameConvention, TargetOs};
+pub use plugin::{Plugin, PluginRegistry, PLUGIN_CREATE_SYMBOL};
+pub use pool::LibPool;
+#[cfg(feature = "remote")]
+pub use remote::{DefaultFetcher, Fetcher, RemoteLib};
+pub use report::{LoadAttempt, LoadReport, MissingDependency};
+pub use retry::RetryPolicy;
+#[cfg(all(feature = "sandbox", target_pointer_width = "64"))]
+pub use sandbox::{SandboxValue, SandboxedFn, SandboxedLib};
+pub use search::LibSearchPath;
+pub use set::{ResolutionOrder, SharedLibSet};
+#[cfg(feature = "codegen")]
+pub use spec::{shared_lib_codegen, SymbolSpec, SymbolSpecFn};
+pub use state::{RESTORE_STATE_SYMBOL, SAVE_STATE_SYMBOL};
+pub use tracked::TrackedSharedLibFn;
+pub use typed::{combine_signatures, signature_of, TypeSignature, SIGNATURE_SUFFIX};
+#[cfg(feature = "signature")]
+pub use signature::{Ed25519Verifier, Verifier, SIGNATURE_EXTENSION};
+pub use variadic::{VarArg, VarArgs};
+#[cfg(feature = "watch")]
+pub use watch::WatchedSharedLib;
+/// Derive a `load` function that resolves every field of a struct of function pointers from a shared library.
+///
+/// Each field is resolved by its name, or by the name given with the `#[symbol = "name"]` attribute.
+/// The generated function is unsafe, since the types of the fields are not checked against the actual types of the functions,
+/// and the functions must not be called after the shared library is unloaded.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+///
+/// #[derive(SharedLibApi)]
+/// struct CalculatorApi {
+///     add: fn(usize, usize) -> usize,
+///     #[symbol = "calculator_sub"]
+///     sub: extern "C" fn(i32, i32) -> i32,
+/// }
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+///     let api = CalculatorApi::load(&lib).expect("Failed to load calculator API");
+///     let result = (api.add)(1, 2);
+/// }
+/// ```
+#[cfg(feature = "derive")]
+pub use shared_lib_derive::SharedLibApi;
+
+/// Items used by the code generated by the derive macros, not part of the public API.
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    use crate::{SharedLib, SharedLibError};
+
+    /// Get a function by name from the shared library as a plain function pointer.
+    /// # Safety
+    /// The function pointer must not be called after the shared library is unloaded.
+    pub unsafe fn raw_symbol<T: crate::FnSignature>(lib: &SharedLib, name: &str) -> Result<T, SharedLibError> {
+        let func = lib.get_fn::<T>(name)?;
+        #[cfg(feature = "wasm")]
+        if let crate::FnSymbol::Wasm(_) = func.symbol {
+            return Err(SharedLibError::Unsupported("Taking the function pointer of a WebAssembly function".into()));
+        }
+        Ok(*func.symbol)
+    }
+}
 
 /// Enum representing the possible errors that can occur when working with shared libraries.
-#[derive(Debug, Error)]
+///
+/// New variants may be added in future versions, use [`SharedLibError::kind`] or the classification methods like
+/// [`SharedLibError::is_symbol_error`] to handle categories of errors.
+///
+/// [`SharedLibError::LoadFailure`] and [`SharedLibError::SymbolNotFound`] keep the error of libloading they were created from
+/// as their [`source`](std::error::Error::source), if any, which downcasts to `Arc<libloading::Error>`.
+#[derive(Clone, Debug, Error)]
+#[non_exhaustive]
 pub enum SharedLibError {
     #[error("Path is empty.")]
     PathEmpty,
     #[error("Failed to convert path '{0}' to {1}.")]
     PathConversion(PathBuf, String),
-    #[error("Failed to load library from path '{path}'. {msg}")]
-    LoadFailure { path: String, msg: String },
-    #[error("Failed to find symbol '{symbol_name}' in library '{lib_name}'. {msg}")]
-    SymbolNotFound { symbol_name: String, lib_name: String, msg: String }
+    #[error("Failed to load library from path '{}'. {msg}", .path.display())]
+    LoadFailure { path: PathBuf, msg: String, raw_os_error: Option<i32>, source: Option<Arc<libloading::Error>> },
+    #[error("Failed to find symbol {} in library '{}'. {msg}", demangle::display_name(.symbol_name), .lib_name.to_string_lossy())]
+    SymbolNotFound { symbol_name: String, lib_name: OsString, msg: String, raw_os_error: Option<i32>, source: Option<Arc<libloading::Error>> },
+    #[error("Environment variable '{0}' referenced in the library path is not set.")]
+    EnvVarNotSet(String),
+    #[error("Library '{path}' was built for {found} architecture, but the current process is {expected}.")]
+    ArchMismatch { path: String, expected: String, found: String },
+    #[error("Filename '{}' does not match the platform library naming convention.", .0.to_string_lossy())]
+    InvalidFilename(OsString),
+    #[error("SHA-256 hash of library '{path}' is {actual}, expected {expected}.")]
+    IntegrityMismatch { path: String, expected: String, actual: String },
+    #[error("Failed to unload library '{path}'. {msg}")]
+    UnloadFailure { path: String, msg: String },
+    #[error("Failed to load library '{lib_name}' from any of the search paths. {}", join_errors(.attempts))]
+    NotInSearchPath { lib_name: String, attempts: Vec<SharedLibError> },
+    #[error("Library '{lib_name}' was not found in any of the searched paths: {}.", join_paths(.searched))]
+    LibNotFound { lib_name: String, searched: Vec<PathBuf> },
+    #[error("Failed to read the export table of library '{path}'. {msg}")]
+    InspectFailure { path: String, msg: String },
+    #[error("Plugin '{0}' is already loaded.")]
+    PluginAlreadyLoaded(String),
+    #[error("Invalid plugin manifest '{path}'. {msg}")]
+    InvalidManifest { path: String, msg: String },
+    #[error("Library '{path}' has ABI version {found}, expected {expected}.")]
+    AbiMismatch { path: String, expected: u32, found: u32 },
+    #[error("Library '{path}' failed verification. {msg}")]
+    VerificationFailure { path: String, msg: String },
+    #[error("Function panicked. {0}")]
+    CallPanicked(String),
+    #[error("Failed to find symbols {} in library '{lib_name}'.", join_symbols(.symbol_names))]
+    SymbolsNotFound { symbol_names: Vec<String>, lib_name: String },
+    #[error("{0} is not supported on this platform.")]
+    Unsupported(String),
+    #[error("Failed to load library '{lib_name}' with any of the filename conventions. {}", join_errors(.attempts))]
+    NoMatchingFilename { lib_name: String, attempts: Vec<SharedLibError> },
+    #[error("Failed to load the library from any of the candidates. {}", join_candidates(.attempts))]
+    NoCandidateLoaded { attempts: Vec<(LibPath, SharedLibError)> },
+    #[error("Library file '{}' does not exist.", .path.display())]
+    NotFound { path: PathBuf },
+    #[error("{0}")]
+    LoadReport(Box<LoadReport>),
+    #[error("Function '{symbol_name}' of library '{path}' was not exported as '{expected}'.")]
+    SignatureMismatch { symbol_name: String, path: String, expected: String },
+    #[error("Failed to generate the dynamic bindings. {0}")]
+    BindingsFailure(String),
+    #[error("Failed to download library from '{url}'. {msg}")]
+    FetchFailure { url: String, msg: String },
+    #[error("Invalid library archive '{path}'. {msg}")]
+    InvalidArchive { path: String, msg: String },
+    #[error("Function did not return within {0:?}.")]
+    Timeout(Duration),
+    #[error("Sandbox of library '{path}' failed. {msg}")]
+    SandboxFailure { path: String, msg: String },
+    #[error("COM method '{method}' of library '{path}' failed with HRESULT {hresult:#010x}.")]
+    ComFailure { path: String, method: String, hresult: i32 },
+    #[error("Invalid GUID '{0}', expected e.g. '{{00000000-0000-0000-C000-000000000046}}'.")]
+    InvalidGuid(String),
+    #[error("Library '{path}' failed to initialize with code {code}.")]
+    InitFailure { path: String, code: i32 },
+    #[error("Failed to compile library '{lib_name}'. {msg}")]
+    CompileFailure { lib_name: String, msg: String },
+    #[error("Function caused a fault accessing address {address:#x}.")]
+    Fault { address: usize },
+    #[error("{context}: {source}")]
+    Context { context: String, source: Box<SharedLibError> },
+    #[error("Function '{symbol_name}' of generation {generation} of library '{path}' was called after the library was reloaded or unloaded.")]
+    StaleSymbol { symbol_name: String, path: String, generation: u64 },
+    #[error("Libraries depend on each other in a cycle: {}.", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+    #[error("Library '{lib_name}' depends on library '{dependency}', which was not added.")]
+    UnknownDependency { lib_name: String, dependency: String },
+    #[error("Plugin '{plugin}' requires host API version {required}, but the host API version is {host}.")]
+    IncompatibleVersion { plugin: String, required: String, host: String },
+    #[error("Invalid library config{}. {msg}", quoted_path(.path))]
+    InvalidConfig { path: String, msg: String },
+    #[error("Failed to update the DLL directories of the process{}. {msg}", quoted_path(.path))]
+    DllDirectoryFailure { path: String, msg: String },
+    #[error("Failed to call the function on a worker thread. {msg}")]
+    WorkerFailure {
+        msg: String,
+        #[source]
+        source: Option<Arc<std::io::Error>>,
+    },
+    #[error("Constructor '{symbol_name}' of library '{path}' returned a null pointer.")]
+    NullInstance { symbol_name: String, path: String },
+    #[error("WebAssembly function '{symbol_name}' of module '{path}' trapped. {msg}")]
+    WasmTrap { symbol_name: String, path: String, msg: String },
+    #[error("SHA-256 checksum of library '{path}' is {actual}, expected {expected}.")]
+    ChecksumMismatch { path: String, expected: String, actual: String },
+    #[error("Invalid SHA-256 hash '{0}', expected 64 hex digits.")]
+    InvalidSha256(String),
+}
+impl SharedLibError {
+    /// Get the error code reported by the operating system, if any,
+    /// e.g. `ERROR_MOD_NOT_FOUND` or `ERROR_BAD_EXE_FORMAT` when loading a DLL fails on Windows.
+    ///
+    /// `dlopen` and `dlsym` only report messages, so there is no code for errors of the dynamic loader on Unix.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            SharedLibError::LoadReport(report) => report.error.raw_os_error(),
+            SharedLibError::Context { source, .. } => source.raw_os_error(),
+            SharedLibError::LoadFailure { raw_os_error, .. } | SharedLibError::SymbolNotFound { raw_os_error, .. } => *raw_os_error,
+            _ => None,
+        }
+    }
+    /// Check if the error was caused by an invalid library path.
+    pub fn is_path_error(&self) -> bool {
+        if let SharedLibError::LoadReport(report) = self {
+            return report.error.is_path_error();
+        }
+        if let SharedLibError::Context { source, .. } = self {
+            return source.is_path_error();
+        }
+        matches!(
+            self,
+            SharedLibError::PathEmpty
+                | SharedLibError::PathConversion(..)
+                | SharedLibError::EnvVarNotSet(_)
+                | SharedLibError::InvalidFilename(_)
+                | SharedLibError::LibNotFound { .. }
+        )
+    }
+    /// Check if the error occurred while loading or unloading the library.
+    pub fn is_load_error(&self) -> bool {
+        if let SharedLibError::LoadReport(report) = self {
+            return report.error.is_load_error();
+        }
+        if let SharedLibError::Context { source, .. } = self {
+            return source.is_load_error();
+        }
+        matches!(
+            self,
+            SharedLibError::LoadFailure { .. }
+                | SharedLibError::ArchMismatch { .. }
+                | SharedLibError::IntegrityMismatch { .. }
+                | SharedLibError::ChecksumMismatch { .. }
+                | SharedLibError::UnloadFailure { .. }
+                | SharedLibError::NotInSearchPath { .. }
+                | SharedLibError::InspectFailure { .. }
+                | SharedLibError::PluginAlreadyLoaded(_)
+                | SharedLibError::InvalidManifest { .. }
+                | SharedLibError::AbiMismatch { .. }
+                | SharedLibError::VerificationFailure { .. }
+                | SharedLibError::NoMatchingFilename { .. }
+                | SharedLibError::NoCandidateLoaded { .. }
+                | SharedLibError::NotFound { .. }
+                | SharedLibError::FetchFailure { .. }
+                | SharedLibError::InvalidArchive { .. }
+                | SharedLibError::InitFailure { .. }
+                | SharedLibError::IncompatibleVersion { .. }
+                | SharedLibError::InvalidConfig { .. }
+                | SharedLibError::DllDirectoryFailure { .. }
+        )
+    }
+    /// Check if the error occurred while looking up a symbol in a loaded library.
+    pub fn is_symbol_error(&self) -> bool {
+        if let SharedLibError::LoadReport(report) = self {
+            return report.error.is_symbol_error();
+        }
+        if let SharedLibError::Context { source, .. } = self {
+            return source.is_symbol_error();
+        }
+        matches!(
+            self,
+            SharedLibError::SymbolNotFound { .. }
+                | SharedLibError::SymbolsNotFound { .. }
+                | SharedLibError::SignatureMismatch { .. }
+                | SharedLibError::StaleSymbol { .. }
+        )
+    }
+    /// Check if the error was caused by something that does not exist.
+    pub fn is_not_found(&self) -> bool {
+        if let SharedLibError::LoadReport(report) = self {
+            return report.error.is_not_found();
+        }
+        if let SharedLibError::Context { source, .. } = self {
+            return source.is_not_found();
+        }
+        matches!(
+            self,
+            SharedLibError::SymbolNotFound { .. }
+                | SharedLibError::SymbolsNotFound { .. }
+                | SharedLibError::EnvVarNotSet(_)
+                | SharedLibError::LibNotFound { .. }
+                | SharedLibError::NotFound { .. }
+                | SharedLibError::UnknownDependency { .. }
+        )
+    }
+}
+
+/// Error of a failed call to the dynamic loader or the operating system.
+pub(crate) struct LoaderError {
+    pub(crate) msg: String,
+    pub(crate) raw_os_error: Option<i32>,
+    pub(crate) source: Option<Arc<libloading::Error>>,
+}
+impl From<libloading::Error> for LoaderError {
+    fn from(e: libloading::Error) -> LoaderError {
+        // libloading reports the error of the operating system as the source on Windows.
+        let raw_os_error = std::error::Error::source(&e)
+            .and_then(|source| source.downcast_ref::<std::io::Error>())
+            .and_then(std::io::Error::raw_os_error);
+        LoaderError { msg: e.to_string(), raw_os_error, source: Some(Arc::new(e)) }
+    }
+}
+impl From<std::io::Error> for LoaderError {
+    fn from(e: std::io::Error) -> LoaderError {
+        LoaderError { msg: e.to_string(), raw_os_error: e.raw_os_error(), source: None }
+    }
+}
+impl From<String> for LoaderError {
+    fn from(msg: String) -> LoaderError {
+        LoaderError { msg, raw_os_error: None, source: None }
+    }
+}
+impl SharedLibError {
+    /// Create a [`SharedLibError::LoadFailure`] of the library at `path`.
+    pub(crate) fn load_failure(path: impl Into<PathBuf>, e: impl Into<LoaderError>) -> SharedLibError {
+        let LoaderError { msg, raw_os_error, source } = e.into();
+        SharedLibError::LoadFailure { path: path.into(), msg, raw_os_error, source }
+    }
+    /// Create a [`SharedLibError::SymbolNotFound`] of the symbol in the library at `lib_name`.
+    pub(crate) fn symbol_not_found(symbol_name: impl Into<String>, lib_name: impl Into<OsString>, e: impl Into<LoaderError>) -> SharedLibError {
+        let LoaderError { msg, raw_os_error, source } = e.into();
+        SharedLibError::SymbolNotFound { symbol_name: symbol_name.into(), lib_name: lib_name.into(), msg, raw_os_error, source }
+    }
+}
+
+/// Join the messages of the given errors, e.g. the failed attempts of [`SharedLibError::NotInSearchPath`].
+fn join_errors(errors: &[SharedLibError]) -> String {
+    errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(" ")
+}
+/// Join the given candidates with their errors for an error message, e.g. the attempts of [`SharedLibError::NoCandidateLoaded`].
+fn join_candidates(attempts: &[(LibPath, SharedLibError)]) -> String {
+    attempts.iter().map(|(lib_path, e)| format!("'{}': {}", lib_path, e)).collect::<Vec<_>>().join(" ")
+}
+/// Join the given symbol names for an error message, e.g. the missing symbols of [`SharedLibError::SymbolsNotFound`].
+fn join_symbols(symbol_names: &[String]) -> String {
+    symbol_names.iter().map(|name| demangle::display_name(name)).collect::<Vec<_>>().join(", ")
+}
+/// Join the given paths for an error message, e.g. the searched paths of [`SharedLibError::LibNotFound`].
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths.iter().map(|path| format!("'{}'", path.display())).collect::<Vec<_>>().join(", ")
+}
+/// Format an optional path for error messages, which is empty if there is no path, e.g. for configs not read from a file.
+fn quoted_path(path: &str) -> String {
+    match path.is_empty() {
+        true => String::new(),
+        false => format!(" '{}'", path),
+    }
 }
 
 /// Structure representing a shared library path.
@@ -38,24 +475,72 @@ pub enum SharedLibError {
 /// `dir_path` is the directory path where the library is located.
 ///
 /// `lib_name` is the library name without the platform specific extension and prefix.
+///
+/// `version` is the optional version included in the filename, e.g. `1` or `1.2.3`.
+///
+/// `convention` is the prefix and extension the filename is built with, the platform convention by default.
+///
+/// Library paths are equal if their `dir_path` and `lib_name` are, regardless of their `version` and `convention`.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LibPath {
     pub dir_path: PathBuf,
     pub lib_name: String,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub version: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub convention: FilenameConvention,
+}
+impl PartialEq for LibPath {
+    fn eq(&self, other: &LibPath) -> bool {
+        self.dir_path == other.dir_path && self.lib_name == other.lib_name
+    }
+}
+impl Eq for LibPath {}
+impl std::hash::Hash for LibPath {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.dir_path.hash(state);
+        self.lib_name.hash(state);
+    }
+}
+/// Displays the library filepath, see [`LibPath::path`], replacing invalid unicode lossily.
+/// The library name is displayed as is if it is empty.
+impl std::fmt::Display for LibPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.lossy_path())
+    }
 }
-impl ToString for LibPath {
-    fn to_string(&self) -> String {
-        let binding = self.path().unwrap();
-        binding.to_str().unwrap().to_string()
+/// Parses a library path from the full path of a library file, see [`LibPath::from_full_path`],
+/// e.g. `plugins/libcalculator.so` from the displayed [`LibPath`].
+///
+/// If the filename of `s` does not match any of the [`FilenameConvention::fallbacks`], it is taken as the library name,
+/// e.g. `plugins/calculator` is the library `calculator` in the `plugins` directory.
+/// # Example
+/// ```
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+///
+/// let lib_path: LibPath = "plugins/calculator".parse().unwrap();
+/// assert_eq!(lib_path, LibPath::new(PathBuf::from("plugins"), "calculator".into()));
+/// assert_eq!(lib_path.to_string().parse::<LibPath>().unwrap(), lib_path);
+/// ```
+impl std::str::FromStr for LibPath {
+    type Err = SharedLibError;
+    fn from_str(s: &str) -> Result<LibPath, SharedLibError> {
+        let path = Path::new(s);
+        let Some(lib_name) = path.file_name().and_then(OsStr::to_str) else {
+            return Err(SharedLibError::PathEmpty);
+        };
+        LibPath::from_full_path(path).or_else(|_| {
+            let dir_path = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            Ok(LibPath::new(dir_path, lib_name.to_owned()))
+        })
     }
 }
 impl TryInto<OsString> for LibPath {
     type Error = SharedLibError;
     fn try_into(self) -> Result<OsString, Self::Error> {
-        let path = self.path()?;
-        path.clone().try_into().map_err(|_| {
-            SharedLibError::PathConversion(path, "OsString".into())
-        })
+        Ok(self.path()?.into())
     }
 }
 impl LibPath {
@@ -65,7 +550,7 @@ impl LibPath {
     ///
     /// `lib_name` is the library name without the platform specific extension and prefix.
     pub fn new(dir_path: PathBuf, lib_name: String) -> LibPath {
-        LibPath { dir_path, lib_name }
+        LibPath { dir_path, lib_name, version: None, convention: FilenameConvention::default() }
     }
     /// Create a new shared library path without a directory path.
     /// Using this function will mean that the library is located in the current directory.
@@ -75,7 +560,195 @@ impl LibPath {
         LibPath {
             dir_path: PathBuf::new(),
             lib_name,
+            version: None,
+            convention: FilenameConvention::default(),
+        }
+    }
+    /// Create a new shared library path in `dir_path` relative to the directory of the running executable,
+    /// e.g. for plugins bundled with an application, which can be launched from any working directory.
+    ///
+    /// Symbolic links to the executable are resolved, so the directory is the one of the actual executable file.
+    /// An absolute `dir_path` is used as is.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     // `plugins/libfoo.so` next to the executable on Linux.
+    ///     let lib_path = LibPath::relative_to_exe("plugins", "foo").expect("Failed to get the path of the executable");
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub fn relative_to_exe(dir_path: impl AsRef<Path>, lib_name: impl Into<String>) -> Result<LibPath, SharedLibError> {
+        let exe_dir = exe_dir().map_err(|msg| SharedLibError::load_failure(dir_path.as_ref(), msg))?;
+        Ok(LibPath::new(exe_dir.join(dir_path.as_ref()), lib_name.into()))
+    }
+    /// Create a new shared library path of the cdylib built by cargo for the crate `crate_name` in the same workspace,
+    /// e.g. for tests and examples loading a plugin crate.
+    ///
+    /// The library is looked up in the profile directory of the running executable, e.g. `target/debug` for `target/debug/deps/my_test`,
+    /// so the active profile and target triple are followed. If `CARGO_TARGET_DIR` is set and the executable is not in that directory,
+    /// the library is looked up in `debug` or `release` of the target directory instead, depending on whether debug assertions are enabled.
+    /// Hyphens in the crate name are replaced with underscores, as cargo does for the library filename.
+    /// The library must be built before, e.g. by being a dependency of the crate or with `cargo build -p my_plugin_crate`.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::from_cargo_target("my_plugin_crate").expect("Failed to get the cargo target directory");
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub fn from_cargo_target(crate_name: &str) -> Result<LibPath, SharedLibError> {
+        let lib_name = crate_name.replace('-', "_");
+        let cargo_failure = |msg: String| SharedLibError::load_failure(Path::new(&lib_name), msg);
+        let mut profile_dir = exe_dir().map_err(cargo_failure)?;
+        // Tests and examples are built into subdirectories of the profile directory.
+        if profile_dir.file_name().is_some_and(|name| name == "deps" || name == "examples") {
+            profile_dir.pop();
+        }
+        if let Some(target_dir) = std::env::var_os("CARGO_TARGET_DIR").filter(|target_dir| !target_dir.is_empty()) {
+            let target_dir = std::fs::canonicalize(&target_dir).unwrap_or_else(|_| PathBuf::from(target_dir));
+            if !profile_dir.starts_with(&target_dir) {
+                profile_dir = target_dir.join(if cfg!(debug_assertions) { "debug" } else { "release" });
+            }
+        }
+        Ok(LibPath::new(profile_dir, lib_name))
+    }
+    /// Create a new shared library path from the platform specific library filename, e.g. `libcalculator.so`.
+    ///
+    /// This reverses [`LibPath::filename`] by stripping the platform specific prefix and extension,
+    /// returning [`SharedLibError::InvalidFilename`] if the filename does not have them.
+    /// A version in the filename, e.g. `libcalculator.so.1`, is split off into [`LibPath::version`].
+    /// # Example
+    /// ```
+    /// use std::ffi::OsStr;
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let filename = LibPath::new_no_path("calculator".into()).filename().unwrap();
+    /// let lib_path = LibPath::from_filename(PathBuf::from("path/to/dir"), &filename).unwrap();
+    /// assert_eq!(lib_path.lib_name(), "calculator");
+    /// ```
+    pub fn from_filename(dir_path: PathBuf, filename: &OsStr) -> Result<LibPath, SharedLibError> {
+        parse_filename(dir_path, filename, FilenameConvention::platform()).ok_or_else(|| SharedLibError::InvalidFilename(filename.to_owned()))
+    }
+    /// Create a new shared library path from the full path of a library file, e.g. `/opt/app/libfoo.so.2`.
+    ///
+    /// The path is split into the directory and the filename, which is parsed like in [`LibPath::from_filename`].
+    /// Filenames that do not match the platform convention are parsed with the other [`FilenameConvention::fallbacks`],
+    /// e.g. `foo.so` on Linux, which is kept in [`LibPath::convention`]. Returns [`SharedLibError::InvalidFilename`]
+    /// if the filename matches none of them.
+    /// # Example
+    /// ```
+    /// use std::path::Path;
+    /// use shared_lib::*;
+    ///
+    /// let path = Path::new("/opt/app").join(LibPath::new_no_path("foo".into()).with_version("2".into()).filename().unwrap());
+    /// let lib_path = LibPath::from_full_path(&path).unwrap();
+    /// assert_eq!((lib_path.dir(), lib_path.lib_name(), lib_path.version()), (Path::new("/opt/app"), "foo", Some("2")));
+    /// ```
+    pub fn from_full_path(path: impl AsRef<Path>) -> Result<LibPath, SharedLibError> {
+        let path = path.as_ref();
+        let filename = path.file_name().ok_or_else(|| SharedLibError::InvalidFilename(path.as_os_str().to_owned()))?;
+        let dir_path = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        FilenameConvention::fallbacks()
+            .into_iter()
+            .find_map(|convention| parse_filename(dir_path.clone(), filename, convention))
+            .ok_or_else(|| SharedLibError::InvalidFilename(filename.to_owned()))
+    }
+    /// Get a copy of the library path with the given version included in the filename.
+    ///
+    /// The version is placed where the platform expects it,
+    /// e.g. `libfoo.so.1.2.3` on Linux, `libfoo.1.dylib` on MacOS and `foo-1.dll` on Windows.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new_no_path("ssl".into()).with_version("3".into());
+    /// let filename = lib_path.filename().expect("Failed to get library name"); // `libssl.so.3` on Linux
+    /// ```
+    pub fn with_version(mut self, version: String) -> LibPath {
+        self.version = Some(version);
+        self
+    }
+    /// Get a copy of the library path with the filename built with the given convention instead of the platform one,
+    /// e.g. for plugins built without the `lib` prefix.
+    /// # Example
+    /// ```
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new_no_path("plugin".into()).with_convention(FilenameConvention::no_prefix());
+    /// assert_eq!(lib_path.filename().unwrap(), format!("plugin{}", std::env::consts::DLL_SUFFIX).as_str());
+    /// ```
+    pub fn with_convention(mut self, convention: FilenameConvention) -> LibPath {
+        self.convention = convention;
+        self
+    }
+    /// Get a copy of the library path with the given filename prefix instead of the one of its convention,
+    /// e.g. an empty one for libraries built without the `lib` prefix, see [`FilenameConvention::with_prefix`].
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> LibPath {
+        self.convention = self.convention.with_prefix(prefix);
+        self
+    }
+    /// Get a copy of the library path with the given filename extension instead of the one of its convention,
+    /// e.g. `node` for Node.js addons, see [`FilenameConvention::with_extension`].
+    /// # Example
+    /// ```
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("build/Release"), "addon".into()).with_prefix("").with_extension("node");
+    /// assert_eq!(lib_path.filename().unwrap(), "addon.node");
+    /// ```
+    pub fn with_extension(mut self, extension: &str) -> LibPath {
+        self.convention = self.convention.with_extension(extension);
+        self
+    }
+    /// Get the library name without the platform specific extension and prefix.
+    pub fn lib_name(&self) -> &str {
+        &self.lib_name
+    }
+    /// Get the version included in the filename, if any.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+    /// Get the convention the filename is built with.
+    pub fn convention(&self) -> &FilenameConvention {
+        &self.convention
+    }
+    /// Get the directory path where the library is located.
+    pub fn dir(&self) -> &Path {
+        &self.dir_path
+    }
+    /// Get a copy of the library path with `.` and `..` components removed from `dir_path`.
+    ///
+    /// The normalization is purely lexical and does not access the filesystem, so symbolic links are not resolved.
+    /// Leading `..` components of a relative path are kept.
+    /// # Example
+    /// ```
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("./path/to/../shared/library"), "shared_library".into());
+    /// assert_eq!(lib_path.normalized().dir(), PathBuf::from("path/shared/library"));
+    /// ```
+    pub fn normalized(&self) -> LibPath {
+        let mut dir_path = PathBuf::new();
+        for component in self.dir_path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => match dir_path.components().next_back() {
+                    Some(Component::Normal(_)) => {
+                        dir_path.pop();
+                    }
+                    // `..` of the root is the root itself.
+                    Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                    _ => dir_path.push(component),
+                },
+                _ => dir_path.push(component),
+            }
         }
+        LibPath { dir_path, lib_name: self.lib_name.clone(), version: self.version.clone(), convention: self.convention.clone() }
     }
     /// Get the platform specific library filename.
     ///
@@ -84,6 +757,13 @@ impl LibPath {
     /// For MacOS, it will return the library name with `lib` prefix and `.dylib` extension.
     ///
     /// For Linux, it will return the library name with `lib` prefix and `.so` extension.
+    ///
+    /// If the library path has a version, it is included as `foo-1.dll`, `libfoo.1.dylib` or `libfoo.so.1` respectively.
+    ///
+    /// Android and Emscripten are named like Linux, and iOS, tvOS, watchOS and visionOS like MacOS, see [`TargetOs`].
+    /// WebAssembly targets without Emscripten cannot load libraries, so [`SharedLibError::Unsupported`] is returned there.
+    ///
+    /// The prefix and extension are replaced by the ones of the [`LibPath::convention`] if it is not the platform one.
     /// # Example
     /// ```no_run
     /// use std::ffi::OsString;
@@ -93,10 +773,31 @@ impl LibPath {
     /// let lib_name: OsString = lib_path.filename().expect("Failed to get library name");
     /// ```
     pub fn filename(&self) -> Result<OsString, SharedLibError> {
+        self.filename_for(TargetOs::host())
+    }
+    /// Get the library filename for the given operating system instead of the host one, see [`LibPath::filename`].
+    ///
+    /// The prefix and extension of the platform convention are replaced by the ones of `os`, e.g. `foo.dll`
+    /// instead of `libfoo.so`, while a custom [`LibPath::convention`] is kept as is. The version is included
+    /// the way `os` versions filenames.
+    /// # Example
+    /// ```
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new_no_path("foo".into()).with_version("1".into());
+    /// assert_eq!(lib_path.filename_for(TargetOs::Linux).unwrap(), "libfoo.so.1");
+    /// assert_eq!(lib_path.filename_for(TargetOs::MacOs).unwrap(), "libfoo.1.dylib");
+    /// assert_eq!(lib_path.filename_for(TargetOs::Windows).unwrap(), "foo-1.dll");
+    /// ```
+    pub fn filename_for(&self, os: TargetOs) -> Result<OsString, SharedLibError> {
         if self.lib_name.is_empty() {
             return Err(SharedLibError::PathEmpty);
         }
-        Ok(library_filename(self.lib_name.clone()))
+        let convention = match self.convention == FilenameConvention::platform() {
+            true => os.convention(),
+            false => self.convention.clone(),
+        };
+        Ok(os.filename(&convention, &self.lib_name, self.version.as_deref())?.into())
     }
     /// Get the platform specific library filepath.
     ///
@@ -114,78 +815,286 @@ impl LibPath {
     pub fn path(&self) -> Result<PathBuf, SharedLibError> {
         Ok(self.dir_path.join(self.filename()?))
     }
+    /// Get the path of the library binary in a framework bundle, e.g. `dir/Foo.framework/Foo`, as libraries are shipped on iOS.
+    ///
+    /// If the library path has a version, the binary of that version of a MacOS framework is used,
+    /// e.g. `dir/Foo.framework/Versions/A/Foo`. The filename convention is not used, as framework binaries have no prefix or extension.
+    /// # Example
+    /// ```
+    /// use std::path::{Path, PathBuf};
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("Frameworks"), "Foo".into());
+    /// assert_eq!(lib_path.framework_path().unwrap(), Path::new("Frameworks/Foo.framework/Foo"));
+    /// ```
+    pub fn framework_path(&self) -> Result<PathBuf, SharedLibError> {
+        if self.lib_name.is_empty() {
+            return Err(SharedLibError::PathEmpty);
+        }
+        let framework = self.dir_path.join(format!("{}.framework", self.lib_name));
+        match &self.version {
+            Some(version) if !version.is_empty() => Ok(framework.join("Versions").join(version).join(&self.lib_name)),
+            _ => Ok(framework.join(&self.lib_name)),
+        }
+    }
+    /// Get the path of the library file with symbolic links and `.` and `..` components resolved, see [`std::fs::canonicalize`].
+    ///
+    /// Unlike [`LibPath::normalized`], this accesses the filesystem, so it fails with [`SharedLibError::NotFound`] if the file does not exist.
+    /// A library without a directory is looked up in the current directory.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("plugins/current"), "shared_library".into());
+    /// let path = lib_path.canonical_path().expect("Failed to resolve library path");
+    /// println!("plugins/current links to {}", path.parent().unwrap().display());
+    /// ```
+    pub fn canonical_path(&self) -> Result<PathBuf, SharedLibError> {
+        let path = self.path()?;
+        match std::fs::canonicalize(&path) {
+            Ok(canonical) => Ok(canonical),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(SharedLibError::NotFound { path }),
+            Err(e) => Err(SharedLibError::load_failure(path, e)),
+        }
+    }
+    /// Check that the library file exists before it is loaded, returning [`SharedLibError::NotFound`] otherwise.
+    ///
+    /// Without a directory the dynamic loader searches for the library, so there is no known file to check.
+    /// Files whose existence cannot be checked, e.g. for lack of permissions, are left to the dynamic loader to report.
+    pub(crate) fn check_exists(&self) -> Result<(), SharedLibError> {
+        if self.dir_path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        let path = self.path()?;
+        match path.try_exists() {
+            Ok(false) => Err(SharedLibError::NotFound { path }),
+            _ => Ok(()),
+        }
+    }
+    /// Get the library path for error messages, falling back to the library name if it is not a valid path.
+    pub(crate) fn lossy_path(&self) -> String {
+        self.error_path().to_string_lossy().to_string()
+    }
+    /// Get the library path for errors, falling back to the library name if it is not a valid path.
+    pub(crate) fn error_path(&self) -> PathBuf {
+        self.path().unwrap_or_else(|_| PathBuf::from(&self.lib_name))
+    }
+    /// Substitute environment variables referenced in `dir_path`.
+    ///
+    /// On Windows, variables are referenced as `%NAME%`.
+    ///
+    /// On other platforms, variables are referenced as `$NAME` or `${NAME}`.
+    ///
+    /// Returns an error if a referenced variable is not set, in which case `dir_path` is left unchanged.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let mut lib_path = LibPath::new(PathBuf::from("$HOME/.local/lib"), "shared_library".into());
+    /// lib_path.expand_vars().expect("Failed to expand environment variables");
+    /// ```
+    pub fn expand_vars(&mut self) -> Result<(), SharedLibError> {
+        let dir_path = self.dir_path.to_str().ok_or_else(|| {
+            SharedLibError::PathConversion(self.dir_path.clone(), "String".into())
+        })?;
+        self.dir_path = expand_env_vars(dir_path)?.into();
+        Ok(())
+    }
+    /// Get the names of the libraries this library declares as dependencies, see [`inspect::dependencies`].
+    ///
+    /// The library file is read without loading it, and is found with [`LibPath::resolve`] if the library path has no directory.
+    /// This helps to find out which dependency is missing when loading the library fails.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    /// for dependency in lib_path.dependencies().expect("Failed to read dependencies") {
+    ///     println!("{}", dependency);
+    /// }
+    /// ```
+    pub fn dependencies(&self) -> Result<Vec<String>, SharedLibError> {
+        inspect::dependencies(self.resolve()?)
+    }
+    /// Get the version information embedded in the library file, see [`inspect::metadata`].
+    ///
+    /// The library file is read without loading it, and is found with [`LibPath::resolve`] if the library path has no directory.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    /// let metadata = lib_path.metadata().expect("Failed to read metadata");
+    /// if let Some(version) = metadata.version {
+    ///     println!("Library version {}", version);
+    /// }
+    /// ```
+    pub fn metadata(&self) -> Result<inspect::LibMetadata, SharedLibError> {
+        inspect::metadata(self.resolve()?)
+    }
+    /// Check that the library file exists, can be read and was built for the architecture of the current process,
+    /// see [`inspect::diagnose`].
+    ///
+    /// If the library path has no directory, the file is found with [`LibPath::resolve`], and reported as not found
+    /// at its filename if it is not in any of the searched directories.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    /// if let Err(e) = unsafe { SharedLib::new(lib_path.clone()) } {
+    ///     eprintln!("{}", e);
+    ///     eprintln!("{}", lib_path.diagnose().expect("Invalid library path"));
+    /// }
+    /// ```
+    pub fn diagnose(&self) -> Result<inspect::LibDiagnosis, SharedLibError> {
+        match self.resolve() {
+            Ok(path) => Ok(inspect::diagnose(path)),
+            Err(SharedLibError::LibNotFound { .. }) => {
+                Ok(inspect::LibDiagnosis { path: self.path()?, architectures: Vec::new(), problems: vec![inspect::LibProblem::NotFound] })
+            }
+            Err(e) => Err(e),
+        }
+    }
 }
 
-/// Structure representing a shared library function.
-#[derive(Clone)]
-pub struct SharedLibFn<'a, Fn> {
-    symbol: Symbol<'a, Fn>,
-}
-impl<'a, Fn> SharedLibFn<'a, Fn> {
-    pub unsafe fn new(symbol: Symbol<'a, Fn>) -> SharedLibFn<'a, Fn> {
-        SharedLibFn { symbol }
+/// Parse a filename built with the given convention into a library path, returning `None` if it does not match the convention.
+fn parse_filename(dir_path: PathBuf, filename: &OsStr, convention: FilenameConvention) -> Option<LibPath> {
+    let name = filename.to_str()?.strip_prefix(convention.prefix())?;
+    let (lib_name, version) = match name.strip_suffix(convention.suffix()) {
+        Some(lib_name) => split_version(lib_name, convention.suffix()).map_or((lib_name, None), |(lib_name, version)| (lib_name, Some(version))),
+        None => split_version(name, convention.suffix()).map(|(lib_name, version)| (lib_name, Some(version)))?,
+    };
+    if lib_name.is_empty() {
+        return None;
     }
+    Some(LibPath { dir_path, lib_name: lib_name.to_owned(), version: version.map(str::to_owned), convention })
 }
-impl<'a, Ret> SharedLibFn<'a, fn() -> Ret> {
-    pub unsafe fn run(&self) -> Ret {
-        (self.symbol)()
-    }
+
+/// Split a filename stripped of its prefix into the library name and version.
+///
+/// `name` still ends with the version on ELF platforms (`foo.so.1`), and has the `suffix` stripped on other platforms (`foo-1`, `foo.1`).
+fn split_version<'a>(name: &'a str, suffix: &str) -> Option<(&'a str, &'a str)> {
+    let is_version = |version: &str| version.starts_with(|c: char| c.is_ascii_digit()) && version.chars().all(|c| c.is_ascii_digit() || c == '.');
+    let separator = TargetOs::host().version_separator(suffix);
+    name.match_indices(&separator)
+        .map(|(index, _)| (&name[..index], &name[index + separator.len()..]))
+        .find(|(_, version)| is_version(version))
 }
-// === Implementations for functions with arguments (Rust does not support variadic functions yet)
-impl<'a, Ret, A1> SharedLibFn<'a, fn(A1) -> Ret> {
-    pub unsafe fn run(&self, a1: A1) -> Ret {
-        (self.symbol)(a1)
+
+#[cfg(windows)]
+fn expand_env_vars(s: &str) -> Result<OsString, SharedLibError> {
+    let mut expanded = OsString::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        expanded.push(&rest[..start]);
+        if name.is_empty() {
+            // `%%` is kept as is.
+            expanded.push("%%");
+        } else {
+            expanded.push(env_var(name)?);
+        }
+        rest = &rest[start + len + 2..];
     }
+    expanded.push(rest);
+    Ok(expanded)
 }
-impl<'a, Ret, A1, A2> SharedLibFn<'a, fn(A1, A2) -> Ret> {
-    pub unsafe fn run(&self, a1: A1, a2: A2) -> Ret {
-        (self.symbol)(a1, a2)
+
+#[cfg(not(windows))]
+fn expand_env_vars(s: &str) -> Result<OsString, SharedLibError> {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut expanded = OsString::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        expanded.push(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, len) = match after.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], end + 2),
+                None => ("", 0),
+            },
+            None => {
+                let end = after.find(|c| !is_name_char(c)).unwrap_or(after.len());
+                (&after[..end], end)
+            }
+        };
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || !name.chars().all(is_name_char) {
+            // Not a variable reference, keep the `$` as is.
+            expanded.push("$");
+            rest = after;
+            continue;
+        }
+        expanded.push(env_var(name)?);
+        rest = &after[len..];
     }
+    expanded.push(rest);
+    Ok(expanded)
+}
+
+fn env_var(name: &str) -> Result<OsString, SharedLibError> {
+    std::env::var_os(name).ok_or_else(|| SharedLibError::EnvVarNotSet(name.to_owned()))
 }
-impl<'a, Ret, A1, A2, A3> SharedLibFn<'a, fn(A1, A2, A3) -> Ret> {
-    pub unsafe fn run(&self, a1: A1, a2: A2, a3: A3) -> Ret {
-        (self.symbol)(a1, a2, a3)
+
+/// Get the directory of the running executable, resolving symbolic links to it.
+fn exe_dir() -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to get the path of the executable. {}", e))?;
+    let exe = std::fs::canonicalize(&exe).unwrap_or(exe);
+    match exe.parent() {
+        Some(exe_dir) => Ok(exe_dir.to_path_buf()),
+        None => Err(format!("Failed to get the directory of the executable. '{}' has no parent directory.", exe.display())),
     }
 }
-impl<'a, Ret, A1, A2, A3, A4> SharedLibFn<'a, fn(A1, A2, A3, A4) -> Ret> {
-    pub unsafe fn run(&self, a1: A1, a2: A2, a3: A3, a4: A4) -> Ret {
-        (self.symbol)(a1, a2, a3, a4)
+
+/// Raw pointer types returned by functions called with [`SharedLibFn::run_checked_ptr`].
+pub trait RawPtr {
+    type Target;
+    /// Convert the pointer to `NonNull`, returning `None` if it is null.
+    fn into_non_null(self) -> Option<NonNull<Self::Target>>;
+}
+impl<T> RawPtr for *mut T {
+    type Target = T;
+    fn into_non_null(self) -> Option<NonNull<T>> {
+        NonNull::new(self)
     }
 }
-impl<'a, Ret, A1, A2, A3, A4, A5> SharedLibFn<'a, fn(A1, A2, A3, A4, A5) -> Ret> {
-    pub unsafe fn run(&self, a1: A1, a2: A2, a3: A3, a4: A4, a5: A5) -> Ret {
-        (self.symbol)(a1, a2, a3, a4, a5)
+impl<T> RawPtr for *const T {
+    type Target = T;
+    fn into_non_null(self) -> Option<NonNull<T>> {
+        NonNull::new(self.cast_mut())
     }
 }
-// ===
 
-/// Structure representing a shared library.
-pub struct SharedLib {
-    lib: Library,
-    lib_path: LibPath
+/// Structure representing a shared library function.
+#[derive(Clone)]
+pub struct SharedLibFn<'a, Fn> {
+    symbol: FnSymbol<'a, Fn>,
+    // Counters of the calls, if the library was loaded with `LoadFlags::with_call_metrics`.
+    metrics: Option<Arc<metrics::SymbolMetrics>>,
 }
-impl SharedLib {
-    /// Create a new shared library from the given path.
+impl<'a, Fn> SharedLibFn<'a, Fn> {
+    /// Create a new shared library function from the given symbol.
     /// # Safety
-    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
-    pub unsafe fn new(lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
-        let os_str: OsString = lib_path.clone().try_into()?;
-        let lib = match Library::new(os_str) {
-            Ok(lib) => lib,
-            Err(e) => {
-                let path_str: OsString = lib_path.try_into()?;
-                let path_str: String = path_str.to_string_lossy().to_string();
-                return Err(SharedLibError::LoadFailure {
-                    path: path_str, 
-                    msg: e.to_string()
-                });
-            }
-        };
-        Ok(SharedLib { lib, lib_path })
+    /// This function is unsafe because the symbol type is not checked against the actual type of the function.
+    pub unsafe fn new(symbol: Symbol<'a, Fn>) -> SharedLibFn<'a, Fn> {
+        SharedLibFn { symbol: FnSymbol::Loaded(symbol), metrics: None }
     }
-    /// Get a function by name from the shared library.
+    /// Take the bare function pointer out of the function, e.g. to call it in a tight loop without going through [`SharedLibFn::run`].
+    ///
+    /// Calls of the pointer are not counted by [`LoadFlags::with_call_metrics`].
     /// # Safety
-    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// This function is unsafe because the pointer is not bound to the lifetime of the library. It must not be called after
+    /// the library is unloaded or reloaded, which the borrow of the library otherwise prevents.
+    /// # Panics
+    /// Panics if the function is exported by a WebAssembly module loaded with the `wasm` feature, which has no function pointer.
     /// # Example
     /// ```no_run
     /// use std::path::PathBuf;
@@ -193,25 +1102,1239 @@ impl SharedLib {
     /// unsafe {
     ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
     ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
-    ///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
-    ///     let result = add_fn.run(1, 2);
+    ///     let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("add").expect("Failed to get 'add' function from library").into_raw_fn();
+    ///     let sum = (0..1_000_000).fold(0, |sum, i| add_fn(sum, i % 2));
+    ///     drop(lib);
+    ///     // Calling `add_fn` here is undefined behavior.
     /// }
     /// ```
-    pub unsafe fn get_fn<T>(&self, fn_name: &str) -> Result<SharedLibFn<T>, SharedLibError> {
-        let symbol = match self.lib.get(fn_name.as_bytes()) {
-            Ok(symbol) => symbol,
-            Err(e) => {
-                return Err(SharedLibError::SymbolNotFound { 
-                    symbol_name: fn_name.to_owned(), 
-                    lib_name: self.lib_path.path()?.to_string_lossy().to_string(),
-                    msg: e.to_string(), 
-                });
+    pub unsafe fn into_raw_fn(self) -> Fn
+    where
+        Fn: Copy,
+    {
+        *self.symbol
+    }
+}
+
+/// Function of a [`SharedLibFn`], either looked up by libloading or resolved directly, e.g. with `dlvsym`,
+/// or exported by a WebAssembly module, which has no function pointer.
+#[derive(Clone)]
+enum FnSymbol<'a, Fn> {
+    Loaded(Symbol<'a, Fn>),
+    Resolved(Fn, std::marker::PhantomData<&'a Library>),
+    #[cfg(feature = "wasm")]
+    Wasm(wasm::WasmFn),
+}
+impl<Fn> Deref for FnSymbol<'_, Fn> {
+    type Target = Fn;
+    fn deref(&self) -> &Fn {
+        match self {
+            FnSymbol::Loaded(symbol) => symbol,
+            FnSymbol::Resolved(func, _) => func,
+            #[cfg(feature = "wasm")]
+            FnSymbol::Wasm(_) => panic!("WebAssembly functions have no function pointer"),
+        }
+    }
+}
+// === Implementations of `run` for functions with up to 16 arguments (Rust does not support variadic functions yet, see `variadic` for C-variadic functions)
+macro_rules! impl_run {
+    ([$($fn_ty:tt)+] $($a:ident: $A:ident),*) => {
+        #[allow(clippy::too_many_arguments)]
+        impl<'a, Ret, $($A),*> SharedLibFn<'a, $($fn_ty)+ ($($A),*) -> Ret> {
+            /// Call the function.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            pub unsafe fn run(&self, $($a: $A),*) -> Ret {
+                #[cfg(feature = "wasm")]
+                if let FnSymbol::Wasm(func) = &self.symbol {
+                    return self.measured(|| func.call(($($a,)*))).unwrap_or_else(|e| panic!("{}", e));
+                }
+                self.measured(|| (self.symbol)($($a),*))
+            }
+            /// Call the function, returning `None` if it returned a null pointer.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            pub unsafe fn run_checked_ptr(&self, $($a: $A),*) -> Option<NonNull<Ret::Target>>
+            where
+                Ret: RawPtr,
+            {
+                self.run($($a),*).into_non_null()
+            }
+            /// Call the function returning a C string, returning `None` if it returned a null pointer.
+            ///
+            /// The string is borrowed from the library, so it must stay valid for as long as the library is loaded,
+            /// e.g. a string literal.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            /// The returned pointer must be null or point to a nul-terminated string.
+            pub unsafe fn run_cstr(&self, $($a: $A),*) -> Option<&'a CStr>
+            where
+                Ret: RawPtr<Target = c_char>,
+            {
+                self.run_checked_ptr($($a),*).map(|ptr| CStr::from_ptr(ptr.as_ptr()))
+            }
+            /// Call the function returning a C string and copy it, returning `None` if it returned a null pointer.
+            ///
+            /// Invalid UTF-8 is replaced with U+FFFD, like `CStr::to_string_lossy` does.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            /// The returned pointer must be null or point to a nul-terminated string.
+            pub unsafe fn run_string(&self, $($a: $A),*) -> Option<String>
+            where
+                Ret: RawPtr<Target = c_char>,
+            {
+                self.run_checked_ptr($($a),*).map(|ptr| CStr::from_ptr(ptr.as_ptr()).to_string_lossy().into_owned())
+            }
+            /// Get the address of the function, e.g. to pass it to another FFI layer.
+            ///
+            /// It is null for functions exported by a WebAssembly module loaded with the `wasm` feature.
+            pub fn as_raw_ptr(&self) -> *mut std::ffi::c_void {
+                #[cfg(feature = "wasm")]
+                if let FnSymbol::Wasm(_) = self.symbol {
+                    return std::ptr::null_mut();
+                }
+                *self.symbol as *mut std::ffi::c_void
+            }
+        }
+    };
+}
+// Unwinding out of `extern "C"` functions aborts the process, so only Rust ABI functions can be called catching panics.
+macro_rules! impl_run_catching {
+    ([$($fn_ty:tt)+] $($a:ident: $A:ident),*) => {
+        #[allow(clippy::too_many_arguments)]
+        impl<'a, Ret, $($A),*> SharedLibFn<'a, $($fn_ty)+ ($($A),*) -> Ret> {
+            /// Call the function, returning [`SharedLibError::CallPanicked`] if it panicked instead of unwinding into the caller.
+            ///
+            /// The panic hook still runs as usual. The panic can only be caught if the library and the caller share the same
+            /// Rust standard library, i.e. the library is a `dylib` and the caller also links the standard library dynamically
+            /// (`-C prefer-dynamic`). A panic from another copy of the standard library is a foreign exception, which aborts the process.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            /// Any state the function was modifying when it panicked may be left inconsistent.
+            pub unsafe fn run_catching(&self, $($a: $A),*) -> Result<Ret, SharedLibError> {
+                #[cfg(feature = "wasm")]
+                if let FnSymbol::Wasm(func) = &self.symbol {
+                    return self.measured(|| func.call(($($a,)*)));
+                }
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.measured(|| (self.symbol)($($a),*))))
+                    .map_err(|payload| SharedLibError::CallPanicked(panic_message(payload.as_ref())))
+            }
+        }
+    };
+}
+// The worker thread may outlive the call, so only owned functions, which keep their library loaded, can be called with a timeout.
+macro_rules! impl_run_with_timeout {
+    ([$($fn_ty:tt)+] $($a:ident: $A:ident),*) => {
+        #[allow(clippy::too_many_arguments)]
+        impl<Ret: Send + 'static, $($A: Send + 'static),*> OwnedSharedLibFn<$($fn_ty)+ ($($A),*) -> Ret> {
+            /// Call the function on a new worker thread, returning [`SharedLibError::Timeout`] if it did not return within `timeout`.
+            ///
+            /// A function which times out is left running on the worker thread, which keeps the library loaded until the function
+            /// returns, so reloading or closing the library fails in the meantime. A panic of the function is returned as
+            /// [`SharedLibError::CallPanicked`], under the same conditions as with [`SharedLibFn::run_catching`], and a failure
+            /// to start the worker thread as [`SharedLibError::WorkerFailure`].
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            /// The function is called from another thread, so it must be safe to call from any thread.
+            pub unsafe fn run_with_timeout(&self, timeout: Duration, $($a: $A),*) -> Result<Ret, SharedLibError> {
+                let (func, lib) = (self.func.clone(), Arc::clone(&self.lib));
+                let (sender, receiver) = std::sync::mpsc::channel();
+                std::thread::Builder::new()
+                    .name("shared_lib-call".into())
+                    .spawn(move || {
+                        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| func.run($($a),*)));
+                        let _ = sender.send(result.map_err(|payload| panic_message(payload.as_ref())));
+                        drop(lib);
+                    })
+                    .map_err(|e| SharedLibError::WorkerFailure { msg: format!("Failed to spawn the worker thread. {}", e), source: Some(Arc::new(e)) })?;
+                match receiver.recv_timeout(timeout) {
+                    Ok(result) => result.map_err(SharedLibError::CallPanicked),
+                    Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err(SharedLibError::Timeout(timeout)),
+                    Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                        Err(SharedLibError::WorkerFailure { msg: "The worker thread exited without returning.".into(), source: None })
+                    }
+                }
+            }
+        }
+    };
+}
+macro_rules! impl_run_all {
+    ($($a:ident: $A:ident),*) => {
+        impl_run!([fn] $($a: $A),*);
+        impl_run!([unsafe fn] $($a: $A),*);
+        impl_run_catching!([fn] $($a: $A),*);
+        impl_run_catching!([unsafe fn] $($a: $A),*);
+        impl_run!([extern "C" fn] $($a: $A),*);
+        impl_run!([unsafe extern "C" fn] $($a: $A),*);
+        impl_run_with_timeout!([fn] $($a: $A),*);
+        impl_run_with_timeout!([unsafe fn] $($a: $A),*);
+        impl_run_with_timeout!([extern "C" fn] $($a: $A),*);
+        impl_run_with_timeout!([unsafe extern "C" fn] $($a: $A),*);
+    };
+}
+
+impl_run_all!();
+impl_run_all!(a1: A1);
+impl_run_all!(a1: A1, a2: A2);
+impl_run_all!(a1: A1, a2: A2, a3: A3);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15);
+impl_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15, a16: A16);
+// ===
+
+/// Get the message of a caught panic, which is usually a `&str` or a `String`.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()) {
+        (Some(msg), _) => (*msg).to_owned(),
+        (_, Some(msg)) => msg.clone(),
+        _ => "The panic payload is not a string.".to_owned(),
+    }
+}
+
+/// Structure representing a shared library function that keeps its library loaded.
+///
+/// Unlike [`SharedLibFn`], it does not borrow the [`SharedLib`] it was resolved from,
+/// so it can be stored or returned freely. It dereferences to [`SharedLibFn`] to call the function.
+#[derive(Clone)]
+pub struct OwnedSharedLibFn<Fn: 'static> {
+    // Declared before `lib`, so the symbol is dropped before the library is released.
+    func: SharedLibFn<'static, Fn>,
+    lib: Arc<Library>,
+}
+impl<Fn: 'static> Deref for OwnedSharedLibFn<Fn> {
+    type Target = SharedLibFn<'static, Fn>;
+    fn deref(&self) -> &Self::Target {
+        &self.func
+    }
+}
+
+/// Structure representing a shared library.
+///
+/// With the `tracing` feature, loading the library, resolving functions, reloading and unloading it emit `tracing` events
+/// with the `shared_lib` target, carrying the library path as the `path` field and the function name as the `symbol` field.
+///
+/// # Thread safety
+/// `SharedLib` is `Send` and `Sync`. Functions are looked up through `&self`, so a library can be shared between threads,
+/// e.g. in an [`Arc`] or with [`LibManager`], while reloading and unloading it take `&mut self` or `self` and cannot race
+/// with the lookups. A [`SharedLibFn`] borrows the library it was looked up from, while an [`OwnedSharedLibFn`] holds
+/// an [`Arc`] to it and can be sent to other threads on its own, e.g. the ones returned by [`SharedLib::get_fn_cached`].
+/// Both are `Send` and `Sync` if the function type is. Whether calling the function from several threads at once is sound
+/// depends on the library itself. Plain function pointers copied out of the library are not tied to it, so calls through
+/// them on other threads can be marked with a [`CallGate`] to make reloading and unloading the library wait for them.
+pub struct SharedLib {
+    // `None` if loading the library again in `reload` failed.
+    lib: Option<Arc<Library>>,
+    lib_path: LibPath,
+    flags: LoadFlags,
+    generation: u64,
+    // Addresses of the functions looked up by name, cleared when the library is unloaded.
+    symbols: Mutex<HashMap<String, usize>>,
+    // Symbols resolved whenever the library is loaded, see `SharedLib::new_with_symbols`.
+    required_symbols: Vec<String>,
+    // Dependencies are only kept loaded and are declared after `lib`, so they are unloaded after the library that uses them.
+    deps: Vec<Library>,
+    // The file or directory the library was extracted to, e.g. by `from_bytes` or with `LoadFlags::with_shadow_copy`,
+    // declared after `lib` so it is removed after the library is unloaded.
+    extraction: Option<ExtractionGuard>,
+    // Counters of the calls of the functions looked up by name, kept across reloads.
+    metrics: Mutex<HashMap<String, Arc<metrics::SymbolMetrics>>>,
+    // Calls in flight marked with a `CallGate`, waited for before the library is unloaded.
+    calls: Arc<gate::GateState>,
+    // Callbacks run when the library is loaded, unloaded or reloaded.
+    hooks: hooks::LibHooks,
+    // Functions returned instead of the ones of the library, which is not loaded, see `SharedLib::set_dry_run`.
+    stub: Option<Arc<MockLib>>,
+    // Module the functions are exported by instead of the library, which is not loaded, see the `wasm` module.
+    #[cfg(feature = "wasm")]
+    wasm: Option<Arc<wasm::WasmModule>>,
+}
+impl Drop for SharedLib {
+    fn drop(&mut self) {
+        if let Some(lib) = &self.lib {
+            self.calls.stop(true);
+            self.calls.retire();
+            // Functions obtained with `get_fn_owned` keep using the library after it is dropped.
+            if Arc::strong_count(lib) == 1 {
+                self.hooks.run_before_unload(self);
+                unsafe { self.shutdown() };
+                // Unloaded before the event, like by `SharedLib::close`.
+                drop(self.lib.take());
+                self.hooks.emit(&self.lib_path, || LibEventKind::Unloaded);
+            }
+            trace::event!(debug, path = %self.lib_path, "Dropped library");
+        }
+    }
+}
+impl SharedLib {
+    /// Create a new shared library from the given path.
+    ///
+    /// If the library path has a directory, [`SharedLibError::NotFound`] is returned if the library file does not exist,
+    /// and the architecture of the library file is checked against the current process before loading it,
+    /// returning [`SharedLibError::ArchMismatch`] if they differ.
+    ///
+    /// With the `wasm` feature, a library file which is a WebAssembly module, or a `{lib_name}.wasm` module in the directory of
+    /// the library path if the native library does not exist, is run by a bundled interpreter instead, so plugins can be built
+    /// as either. Its exported functions are looked up with [`SharedLib::get_fn`] as functions taking and returning integers,
+    /// floats and `bool`, which is checked against the types of the exports. A trap of a function, e.g. an out of bounds memory
+    /// access, is returned as [`SharedLibError::WasmTrap`] by [`SharedLibFn::run_catching`], while [`SharedLibFn::run`] panics
+    /// with it. The module has its own memory and cannot import functions, and the operations on the native library,
+    /// e.g. [`SharedLib::get_var`], fail with [`SharedLibError::LoadFailure`].
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn new(lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
+        SharedLib::open(lib_path, LoadFlags::default())
+    }
+    /// Create a new shared library from the given path, loading it with the given flags.
+    ///
+    /// For example, [`LoadFlags::with_global`] makes the symbols of the library available to the libraries loaded after it.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_global(true)).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_with_flags(lib_path: LibPath, flags: LoadFlags) -> Result<SharedLib, SharedLibError> {
+        SharedLib::open(lib_path, flags)
+    }
+    /// Create a new shared library from the given path without blocking the async runtime.
+    ///
+    /// The library is loaded on the blocking thread pool of the tokio runtime, since loading reads the library file
+    /// and runs its initializers. Resolving functions with [`SharedLib::get_fn`] is cheap and can stay synchronous.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// # async fn load() {
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new_async(lib_path).await.expect("Failed to load shared library");
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async unsafe fn new_async(lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
+        let path = lib_path.error_path();
+        SharedLib::spawn_blocking(path, move || SharedLib::new(lib_path)).await
+    }
+    /// Run `load` on the blocking thread pool of the tokio runtime, reporting a failure to run it for the library at `path`.
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn spawn_blocking(
+        path: PathBuf,
+        load: impl FnOnce() -> Result<SharedLib, SharedLibError> + Send + 'static,
+    ) -> Result<SharedLib, SharedLibError> {
+        match tokio::task::spawn_blocking(load).await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(SharedLibError::load_failure(path, e.to_string())),
+        }
+    }
+    /// Open the current process itself, to look up symbols linked into the main executable.
+    ///
+    /// On Unix, symbols are looked up in the executable, the libraries loaded with it at startup
+    /// and the libraries loaded with [`LoadFlags::with_global`]. On Windows, they are only looked up in the executable.
+    /// Note that executables usually only export symbols on request, e.g. when linked with `-rdynamic` on Linux.
+    ///
+    /// The library path of the returned library is empty, so it cannot be reloaded or inspected.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let this = SharedLib::this().expect("Failed to open the current process");
+    ///     let getpid_fn = this.get_fn::<extern "C" fn() -> i32>("getpid").expect("Failed to get 'getpid' function");
+    ///     let pid = getpid_fn.run();
+    /// }
+    /// ```
+    pub fn this() -> Result<SharedLib, SharedLibError> {
+        let lib_path = LibPath::new_no_path(String::new());
+        let lib = Library::this().map_err(|e| SharedLibError::load_failure(std::env::current_exe().unwrap_or_default(), e))?;
+        Ok(SharedLib::from_library(Some(lib), lib_path, LoadFlags::default()))
+    }
+    /// Open a library which is already loaded by the process, e.g. a dependency of the executable or a library loaded by other code,
+    /// failing with [`SharedLibError::LoadFailure`] instead of loading it if it is not.
+    ///
+    /// The library is looked up by its path, or by its filename for a path created with [`LibPath::new_no_path`].
+    /// It is opened with `dlopen` and `RTLD_NOLOAD` on Linux with glibc and MacOS, and with `GetModuleHandleExW` on Windows,
+    /// while other platforms return [`SharedLibError::Unsupported`]. Either way the reference count of the library is incremented
+    /// until the returned library is dropped, so it stays loaded even if the code that loaded it unloads it.
+    /// # Safety
+    /// This function is unsafe because it opens a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new_no_path("plugin_host".into());
+    ///     let lib = SharedLib::open_existing(lib_path).expect("The library is not loaded");
+    ///     let version_fn = lib.get_fn::<extern "C" fn() -> u32>("host_version").expect("Failed to get 'host_version' function");
+    ///     let version = version_fn.run();
+    /// }
+    /// ```
+    pub unsafe fn open_existing(lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
+        if cfg!(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows))) {
+            return Err(SharedLibError::Unsupported("Opening loaded libraries".into()));
+        }
+        let path = lib_path.path()?;
+        let lib = match open_existing_library(&path) {
+            Ok(lib) => lib,
+            Err(e) => {
+                trace::event!(debug, path = %lib_path, error = %e.msg, "Failed to open loaded library");
+                return Err(SharedLibError::load_failure(path, e));
+            }
+        };
+        trace::event!(debug, path = %lib_path, "Opened loaded library");
+        Ok(SharedLib::from_library(Some(lib), lib_path, LoadFlags::default()))
+    }
+    /// Create a shared library from a library loaded with the given flags, or `None` for a library which is not loaded, e.g. a stub,
+    /// without any of the state the other constructors add, e.g. dependencies or extracted files.
+    pub(crate) fn from_library(lib: Option<Library>, lib_path: LibPath, flags: LoadFlags) -> SharedLib {
+        SharedLib {
+            lib: lib.map(Arc::new),
+            lib_path,
+            flags,
+            generation: 0,
+            symbols: Mutex::default(),
+            required_symbols: Vec::new(),
+            deps: Vec::new(),
+            extraction: None,
+            metrics: Mutex::default(),
+            calls: Arc::default(),
+            hooks: hooks::LibHooks::default(),
+            stub: None,
+            #[cfg(feature = "wasm")]
+            wasm: None,
+        }
+    }
+    /// Load the library with the given flags.
+    pub(crate) unsafe fn open(lib_path: LibPath, flags: LoadFlags) -> Result<SharedLib, SharedLibError> {
+        if let Some(lib) = SharedLib::open_stub(&lib_path, flags) {
+            return Ok(lib);
+        }
+        flags.retry().run(&lib_path, || SharedLib::open_once(lib_path.clone(), flags))
+    }
+    /// Load the library with the given flags, without retrying.
+    unsafe fn open_once(lib_path: LibPath, flags: LoadFlags) -> Result<SharedLib, SharedLibError> {
+        #[cfg(feature = "wasm")]
+        let lib_path = wasm::module_path(lib_path);
+        lib_path.check_exists()?;
+        if flags.is_shadow_copy() {
+            let (extraction, copy_path) = ExtractionGuard::copy_lib(&lib_path)?;
+            let mut lib = SharedLib::open_file(lib_path, copy_path.into_os_string(), flags)?;
+            lib.extraction = Some(extraction);
+            return Ok(lib);
+        }
+        let file = match flags.is_canonical_path() && !lib_path.dir_path.as_os_str().is_empty() {
+            true => lib_path.canonical_path()?,
+            false => lib_path.path()?,
+        };
+        SharedLib::open_file(lib_path, file.into_os_string(), flags)
+    }
+    /// Load the library from `file`, which refers to the same file as `lib_path`, e.g. through a file descriptor.
+    unsafe fn open_file(lib_path: LibPath, file: OsString, flags: LoadFlags) -> Result<SharedLib, SharedLibError> {
+        #[cfg(feature = "wasm")]
+        if wasm::is_module(Path::new(&file)) {
+            return SharedLib::open_wasm(lib_path, Path::new(&file), flags);
+        }
+        // Without a directory the dynamic loader searches for the library, so there is no known file to check.
+        if !lib_path.dir_path.as_os_str().is_empty() {
+            inspect::check_arch(Path::new(&file))?;
+        }
+        #[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows)))]
+        if flags.is_isolated_namespace() {
+            return Err(SharedLibError::Unsupported("Loading libraries into isolated namespaces".into()));
+        }
+        let lib = match Library::open(&file, flags) {
+            Ok(lib) => lib,
+            Err(e) => {
+                trace::event!(debug, path = %lib_path, error = %e.msg, "Failed to load library");
+                return Err(SharedLibError::load_failure(lib_path.path()?, e));
+            }
+        };
+        trace::event!(debug, path = %lib_path, ?flags, "Loaded library");
+        let mut lib = SharedLib::from_library(Some(lib), lib_path, flags);
+        if let Err(e) = lib.init() {
+            // Unloaded without calling the shutdown function, since the library was not initialized.
+            lib.lib = None;
+            return Err(e);
+        }
+        Ok(lib)
+    }
+    /// Create a new shared library from the given path, after verifying the SHA-256 hash of the library file.
+    ///
+    /// The file is hashed before it is loaded, so none of its code (including initializers) runs if the hash does not match
+    /// `expected_sha256`, in which case [`SharedLibError::ChecksumMismatch`] is returned. The expected hash is a hex string.
+    ///
+    /// On Linux, the library is loaded through the file descriptor that was hashed, so the file cannot be replaced in between.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    ///     let lib = SharedLib::new_verified(lib_path, sha256).expect("Failed to load shared library");
+    /// }
+    /// ```
+    #[cfg(feature = "verify")]
+    pub unsafe fn new_verified(lib_path: LibPath, expected_sha256: &str) -> Result<SharedLib, SharedLibError> {
+        SharedLib::open_checked(lib_path, LoadFlags::default(), |path, file| verify::check_sha256(path, file, expected_sha256))
+    }
+    /// Open the library file, run `check` on it and load the library with the given flags if the check passes.
+    ///
+    /// On Linux, the library is loaded through the file descriptor that was checked, so the file cannot be replaced in between.
+    #[cfg(any(feature = "verify", feature = "signature"))]
+    pub(crate) unsafe fn open_checked(
+        lib_path: LibPath,
+        flags: LoadFlags,
+        check: impl FnOnce(&Path, &mut std::fs::File) -> Result<(), SharedLibError>,
+    ) -> Result<SharedLib, SharedLibError> {
+        lib_path.check_exists()?;
+        let path = lib_path.path()?;
+        let mut file = std::fs::File::open(&path).map_err(|e| SharedLibError::load_failure(&path, e))?;
+        check(&path, &mut file)?;
+        #[cfg(target_os = "linux")]
+        let path = {
+            use std::os::fd::AsRawFd;
+            PathBuf::from(format!("/proc/self/fd/{}", file.as_raw_fd()))
+        };
+        // `file` stays open until the library is loaded.
+        SharedLib::open_file(lib_path, path.into_os_string(), flags)
+    }
+    /// Create a new shared library from the given path, resolving its `@rpath` dependencies in the given directories.
+    ///
+    /// Every `@rpath/` dependency of the library (and of its dependencies) that is found in one of `rpaths`
+    /// is loaded before the library itself and kept loaded for as long as the library is alive.
+    /// Directories are searched in the given order. Dependencies that are not found are left
+    /// for the dynamic loader to resolve using the `LC_RPATH` entries of the binaries.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("MyApp.app/Contents/PlugIns"), "plugin".into());
+    ///     let frameworks = PathBuf::from("MyApp.app/Contents/Frameworks");
+    ///     let lib = SharedLib::new_with_rpaths(lib_path, &[frameworks]).expect("Failed to load shared library");
+    /// }
+    /// ```
+    #[cfg(target_os = "macos")]
+    pub unsafe fn new_with_rpaths(lib_path: LibPath, rpaths: &[PathBuf]) -> Result<SharedLib, SharedLibError> {
+        let mut loaded = Vec::new();
+        macos::load_rpath_dependencies(&lib_path.path()?, rpaths, &mut loaded)?;
+        let mut lib = SharedLib::new(lib_path)?;
+        // Unload in reverse order of loading, so each library is unloaded before its dependencies.
+        lib.deps = loaded.into_iter().rev().map(|(_, dep)| dep).collect();
+        Ok(lib)
+    }
+    /// Get a function by name from the shared library.
+    ///
+    /// The address of every function found is cached by its name until the library is unloaded, so looking up the same function again,
+    /// e.g. every frame, does not go through the dynamic loader. See [`SharedLib::clear_symbol_cache`].
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// The function type is not checked against the actual type of the function, nor against the type it was cached with.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn get_fn<T: FnSignature>(&self, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        self.resolve_fn(fn_name, fn_name.as_bytes())
+    }
+    /// Get a function by its nul-terminated name from the shared library, like [`SharedLib::get_fn`] without copying the name
+    /// to terminate it, e.g. for startup code looking up many functions.
+    ///
+    /// Byte strings ending with a nul byte are converted with [`CStr::from_bytes_with_nul`]. The name must be valid UTF-8,
+    /// otherwise [`SharedLibError::SymbolNotFound`] is returned.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn_cstr::<fn(usize, usize) -> usize>(c"add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn get_fn_cstr<T: FnSignature>(&self, fn_name: &CStr) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        let name = fn_name.to_str().map_err(|e| SharedLibError::symbol_not_found(fn_name.to_string_lossy(), self.lib_path.error_path(), e.to_string()))?;
+        self.resolve_fn(name, fn_name.to_bytes_with_nul())
+    }
+    /// Get a function by name from the shared library like [`SharedLib::get_fn`], and call `f` with it, returning its result.
+    ///
+    /// The function is only borrowed by the closure, so it cannot escape it, e.g. when a function is only needed for a few calls
+    /// and storing the [`SharedLibFn`] next to the library would not pass the borrow checker.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let result = lib.get_fn_with::<fn(usize, usize) -> usize, _>("add", |add_fn| add_fn.run(1, 2)).expect("Failed to get 'add' function from shared library");
+    /// }
+    /// ```
+    pub unsafe fn get_fn_with<T: FnSignature, R>(&self, fn_name: &str, f: impl FnOnce(&SharedLibFn<'_, T>) -> R) -> Result<R, SharedLibError> {
+        Ok(f(&self.get_fn::<T>(fn_name)?))
+    }
+    /// Get the function `fn_name`, looking it up in the library as `symbol`, which is `fn_name` optionally terminated with a nul byte.
+    unsafe fn resolve_fn<T: FnSignature>(&self, fn_name: &str, symbol: &[u8]) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        if let Some(stub) = &self.stub {
+            return SymbolSource::get_fn(&**stub, fn_name);
+        }
+        #[cfg(feature = "wasm")]
+        if let Some(module) = &self.wasm {
+            return self.wasm_fn(module, fn_name);
+        }
+        let lib = self.library()?;
+        if let Some(func) = self.cached_fn::<T>(fn_name) {
+            return Ok(self.with_metrics(fn_name, SharedLibFn { symbol: FnSymbol::Resolved(func, std::marker::PhantomData), metrics: None }));
+        }
+        let address = match lib.symbol(symbol) {
+            Ok(address) => address,
+            Err(e) if self.flags.is_decorated_names() => match self.find_decorated(fn_name) {
+                Some(decorated) => lib.symbol(decorated.as_bytes()).map_err(|e| SharedLibError::symbol_not_found(&decorated, self.lib_path.error_path(), e))?,
+                None => return Err(self.symbol_not_found(fn_name, e)),
+            },
+            Err(e) => {
+                trace::event!(debug, path = %self.lib_path, symbol = fn_name, error = %e.msg, "Failed to resolve symbol");
+                return Err(self.symbol_not_found(fn_name, e));
             }
         };
-        Ok(SharedLibFn::new(symbol))
+        if address.is_null() {
+            return Err(self.symbol_not_found(fn_name, String::from("The address of the function is null.")));
+        }
+        trace::event!(trace, path = %self.lib_path, symbol = fn_name, "Resolved symbol");
+        self.hooks.emit(&self.lib_path, || LibEventKind::SymbolResolved { symbol_name: fn_name.to_owned() });
+        // Function pointers are pointer sized, which `FnSignature` is only implemented for.
+        let func = std::mem::transmute_copy::<*mut std::ffi::c_void, T>(&address);
+        self.cache_fn(fn_name, &func);
+        Ok(self.with_metrics(fn_name, SharedLibFn { symbol: FnSymbol::Resolved(func, std::marker::PhantomData), metrics: None }))
+    }
+    /// Get the function `fn_name` from the symbol cache, if it is a function pointer.
+    unsafe fn cached_fn<T>(&self, fn_name: &str) -> Option<T> {
+        // Other types are left to libloading, which rejects the ones that are not pointer sized.
+        if std::mem::size_of::<T>() != std::mem::size_of::<usize>() {
+            return None;
+        }
+        let symbols = self.symbols.lock().unwrap_or_else(|e| e.into_inner());
+        symbols.get(fn_name).map(|address| std::mem::transmute_copy::<usize, T>(address))
+    }
+    /// Add the function `fn_name` to the symbol cache, if it is a function pointer.
+    unsafe fn cache_fn<T>(&self, fn_name: &str, func: &T) {
+        if std::mem::size_of::<T>() == std::mem::size_of::<usize>() {
+            let mut symbols = self.symbols.lock().unwrap_or_else(|e| e.into_inner());
+            symbols.insert(fn_name.to_owned(), std::mem::transmute_copy::<T, usize>(func));
+        }
+    }
+    /// Clear the cache of the addresses of the functions looked up by name, see [`SharedLib::get_fn`].
+    ///
+    /// The cache is cleared when the library is unloaded or reloaded, so this is only needed if the functions exported by the library
+    /// change while it stays loaded, e.g. when they are patched. Functions looked up before keep their addresses.
+    pub fn clear_symbol_cache(&self) {
+        self.symbols.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+    /// Get several functions of the same type by name from the shared library at once.
+    ///
+    /// Returns [`SharedLibError::SymbolsNotFound`] listing every missing function if any of them is not exported.
+    /// # Safety
+    /// This function is unsafe because it loads functions from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let [add_fn, sub_fn] = lib.get_fns::<fn(usize, usize) -> usize, 2>(["add", "sub"]).expect("Failed to get functions from shared library");
+    ///     let result = sub_fn.run(add_fn.run(1, 2), 3);
+    /// }
+    /// ```
+    pub unsafe fn get_fns<T: FnSignature, const N: usize>(&self, fn_names: [&str; N]) -> Result<[SharedLibFn<'_, T>; N], SharedLibError> {
+        self.check_symbols(&fn_names)?;
+        let mut funcs = Vec::with_capacity(N);
+        for fn_name in fn_names {
+            funcs.push(self.get_fn::<T>(fn_name)?);
+        }
+        Ok(funcs.try_into().unwrap_or_else(|_| unreachable!("one function is resolved per name")))
+    }
+    /// Check that the shared library exports all symbols with the given names.
+    ///
+    /// Returns [`SharedLibError::SymbolsNotFound`] listing every missing symbol if any of them is not exported,
+    /// e.g. to report everything a plugin is missing at once before resolving its functions.
+    pub fn check_symbols(&self, symbol_names: &[&str]) -> Result<(), SharedLibError> {
+        let lib = self.library()?;
+        // Safety: the symbols are only looked up as opaque pointers, never dereferenced or called.
+        let symbol_names: Vec<String> = symbol_names
+            .iter()
+            .filter(|name| unsafe { lib.symbol(name.as_bytes()) }.is_err())
+            .map(|name| (*name).to_owned())
+            .collect();
+        if !symbol_names.is_empty() {
+            return Err(SharedLibError::SymbolsNotFound { symbol_names, lib_name: self.lib_path.lossy_path() });
+        }
+        Ok(())
+    }
+    /// Get a function by name from the shared library, returning `None` if the library does not export it,
+    /// e.g. to detect features missing in older versions of the library.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     match lib.try_get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to look up 'add' function") {
+    ///         Some(add_fn) => println!("1 + 2 = {}", add_fn.run(1, 2)),
+    ///         None => println!("The library does not support adding"),
+    ///     }
+    /// }
+    /// ```
+    pub unsafe fn try_get_fn<T: FnSignature>(&self, fn_name: &str) -> Result<Option<SharedLibFn<'_, T>>, SharedLibError> {
+        match self.get_fn::<T>(fn_name) {
+            Ok(func) => Ok(Some(func)),
+            Err(SharedLibError::SymbolNotFound { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    /// Get a function from the shared library by the first of `aliases` it exports, returning the function with the alias that matched,
+    /// e.g. for functions whose names differ between builds or platforms of the library.
+    ///
+    /// Returns [`SharedLibError::SymbolsNotFound`] listing every alias if none of them is exported.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "png".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let (create_fn, name) = lib
+    ///         .get_fn_any::<extern "C" fn() -> *mut std::ffi::c_void>(&["png_create_read_struct", "_png_create_read_struct", "png_create_read_struct_2"])
+    ///         .expect("Failed to get 'png_create_read_struct' function from shared library");
+    ///     println!("Found as '{}'", name);
+    /// }
+    /// ```
+    pub unsafe fn get_fn_any<'n, T: FnSignature>(&self, aliases: &[&'n str]) -> Result<(SharedLibFn<'_, T>, &'n str), SharedLibError> {
+        for alias in aliases {
+            if let Some(func) = self.try_get_fn::<T>(alias)? {
+                trace::event!(debug, path = %self.lib_path, symbol = alias, "Resolved symbol by alias");
+                return Ok((func, alias));
+            }
+        }
+        let symbol_names = aliases.iter().map(|alias| (*alias).to_owned()).collect();
+        Err(SharedLibError::SymbolsNotFound { symbol_names, lib_name: self.lib_path.lossy_path() })
+    }
+    /// Get a reference to a variable exported by the shared library, e.g. a version string or a configuration table.
+    ///
+    /// Returns [`SharedLibError::SymbolNotFound`] if the variable is not exported or its address is null.
+    /// # Safety
+    /// This function is unsafe because the variable type is not checked against the actual type of the variable,
+    /// and the variable must not be modified while the reference is alive.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let answer = lib.get_var::<usize>("ANSWER").expect("Failed to get 'ANSWER' variable from shared library");
+    /// }
+    /// ```
+    pub unsafe fn get_var<T>(&self, var_name: &str) -> Result<&T, SharedLibError> {
+        Ok(&*self.get_var_ptr::<T>(var_name)?)
+    }
+    /// Get a raw pointer to a variable exported by the shared library, which is valid for as long as the library is loaded.
+    ///
+    /// Returns [`SharedLibError::SymbolNotFound`] if the variable is not exported or its address is null.
+    /// # Safety
+    /// This function is unsafe because the variable type is not checked against the actual type of the variable.
+    pub unsafe fn get_var_ptr<T>(&self, var_name: &str) -> Result<*mut T, SharedLibError> {
+        let symbol_not_found = |e: LoaderError| SharedLibError::symbol_not_found(var_name, self.lib_path.error_path(), e);
+        let address = self.library()?.symbol(var_name.as_bytes()).map_err(symbol_not_found)?;
+        if address.is_null() {
+            return Err(symbol_not_found(String::from("The address of the variable is null.").into()));
+        }
+        Ok(address.cast())
+    }
+    /// Get the address of a symbol exported by the shared library, e.g. to pass it to another FFI layer.
+    ///
+    /// Returns [`SharedLibError::SymbolNotFound`] if the symbol is not exported or its address is null.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "vulkan".into());
+    /// let lib = unsafe { SharedLib::new(lib_path).expect("Failed to load shared library") };
+    /// let address = lib.get_symbol_address("vkGetInstanceProcAddr").expect("Failed to get 'vkGetInstanceProcAddr' address");
+    /// ```
+    pub fn get_symbol_address(&self, symbol_name: &str) -> Result<*mut std::ffi::c_void, SharedLibError> {
+        // Safety: the address is only returned, never dereferenced.
+        unsafe { self.get_var_ptr::<std::ffi::c_void>(symbol_name) }
+    }
+    /// Check the ABI version of the shared library, returned by the function exported as `symbol`.
+    ///
+    /// The function must have the signature `extern "C" fn() -> u32`.
+    /// Returns [`SharedLibError::AbiMismatch`] if the version differs from `expected`.
+    /// # Safety
+    /// This function is unsafe because it calls a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     lib.check_abi("abi_version", 1).expect("Shared library has an incompatible ABI");
+    /// }
+    /// ```
+    pub unsafe fn check_abi(&self, symbol: &str, expected: u32) -> Result<(), SharedLibError> {
+        let found = self.get_fn::<extern "C" fn() -> u32>(symbol)?.run();
+        if found != expected {
+            return Err(SharedLibError::AbiMismatch { path: self.lib_path.lossy_path(), expected, found });
+        }
+        Ok(())
+    }
+    /// Check if the shared library exports a symbol with the given name.
+    pub(crate) unsafe fn has_symbol(&self, symbol_name: &str) -> bool {
+        self.lib.as_ref().is_some_and(|lib| lib.symbol(symbol_name.as_bytes()).is_ok())
+    }
+    fn library(&self) -> Result<&Arc<Library>, SharedLibError> {
+        self.lib.as_ref().ok_or_else(|| {
+            let msg = match self.stub.is_some() {
+                true => "The library is not loaded, because it is stubbed in dry-run mode.",
+                #[cfg(feature = "wasm")]
+                false if self.wasm.is_some() => "The library is a WebAssembly module, which is not loaded by the dynamic loader.",
+                false => "The library is not loaded, because reloading it failed.",
+            };
+            SharedLibError::load_failure(self.lib_path.error_path(), String::from(msg))
+        })
+    }
+    /// Get the path the shared library was loaded from.
+    pub fn lib_path(&self) -> &LibPath {
+        &self.lib_path
+    }
+    /// Get the path of the library file with symbolic links and `.` and `..` components resolved, see [`LibPath::canonical_path`],
+    /// e.g. to compare libraries loaded from symlinked directories.
+    ///
+    /// For a library without a directory, which the dynamic loader searched for, the path of the file it loaded is returned,
+    /// see [`SharedLib::module_info`].
+    pub fn resolved_path(&self) -> Result<PathBuf, SharedLibError> {
+        match self.lib_path.dir_path.as_os_str().is_empty() {
+            true => std::fs::canonicalize(self.module_info()?.path).map_err(|e| SharedLibError::load_failure(self.lib_path.error_path(), e)),
+            false => self.lib_path.canonical_path(),
+        }
+    }
+    /// Unload the shared library, reporting any error returned by the operating system.
+    ///
+    /// Dropping the library unloads it as well, but ignores failures. Functions obtained with
+    /// [`SharedLib::get_fn_owned`] keep the library loaded, so closing it while any of them is alive
+    /// returns [`SharedLibError::UnloadFailure`] and leaves the library to be unloaded when the last of them is dropped.
+    /// Calls in flight marked with a [`CallGate`] are waited for before the library is unloaded, both when closing and dropping it.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     lib.close().expect("Failed to unload shared library");
+    /// }
+    /// ```
+    pub fn close(mut self) -> Result<(), SharedLibError> {
+        self.unload(true)?;
+        let path = self.lib_path.lossy_path();
+        for dep in std::mem::take(&mut self.deps) {
+            Loader::close(dep).map_err(|e| SharedLibError::UnloadFailure { path: path.clone(), msg: e.msg })?;
+        }
+        Ok(())
+    }
+    /// Load the shared library, pass it to `f` and close it once `f` returns, returning the result of `f`.
+    ///
+    /// The library is closed like by [`SharedLib::close`], so an error unloading it is returned instead of the result,
+    /// e.g. if the result or anything else still holds a function obtained with [`SharedLib::get_fn_owned`].
+    /// If `f` panics, the library is dropped while unwinding, which unloads it and removes its temporary files as well.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let result = SharedLib::with(lib_path, |lib| {
+    ///         let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add")?;
+    ///         Ok::<_, SharedLibError>(add_fn.run(1, 2))
+    ///     });
+    ///     let sum = result.expect("Failed to unload shared library").expect("Failed to get 'add' function from shared library");
+    /// }
+    /// ```
+    pub unsafe fn with<R>(lib_path: LibPath, f: impl FnOnce(&SharedLib) -> R) -> Result<R, SharedLibError> {
+        let lib = SharedLib::new(lib_path)?;
+        let result = f(&lib);
+        lib.close()?;
+        Ok(result)
+    }
+    /// Keep the shared library loaded until the process exits, returning a reference to it for the rest of the program.
+    ///
+    /// For libraries which crash when they are unloaded, e.g. GPU drivers or libraries registering thread local destructors
+    /// or `atexit` handlers. Functions and variables looked up through the returned reference have the `'static` lifetime,
+    /// so they can be stored anywhere, e.g. in a static. The library, its dependencies and its [`SharedLib::extraction`]
+    /// are never released, and the library cannot be reloaded anymore.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib: &'static SharedLib = SharedLib::new(lib_path).expect("Failed to load shared library").leak();
+    ///     let add_fn: SharedLibFn<'static, fn(usize, usize) -> usize> = lib.get_fn("add").expect("Failed to get 'add' function from shared library");
+    /// }
+    /// ```
+    pub fn leak(self) -> &'static SharedLib {
+        trace::event!(debug, path = %self.lib_path, "Leaked library");
+        Box::leak(Box::new(self))
+    }
+    /// Unload the shared library and load it again from its library path, e.g. after the library file was rebuilt.
+    ///
+    /// Functions obtained with [`SharedLib::get_fn`] borrow the library, so none of them can be used across a reload.
+    /// Functions obtained with [`SharedLib::get_fn_owned`] would keep the old library loaded, so reloading while any of them
+    /// is alive returns [`SharedLibError::UnloadFailure`] and leaves the library untouched.
+    /// Calls in flight marked with a [`CallGate`] are waited for before the library is unloaded, see [`SharedLib::try_reload`]
+    /// to fail instead. Use [`SharedLib::generation`] to tell whether a library was reloaded since a function was looked up,
+    /// or [`SharedLib::get_fn_tracked`] for functions which fail with [`SharedLibError::StaleSymbol`] once it is.
+    ///
+    /// If loading the library again fails, the error is returned and the library stays unloaded until a later reload succeeds.
+    /// The library is loaded again with the same flags. Dependencies loaded with [`SharedLib::new_with_rpaths`]
+    /// or [`SharedLib::new_with_sibling_dependencies`] stay loaded, and the library file is not verified again.
+    /// Note that the operating system only loads the new library file if nothing else keeps the old one loaded,
+    /// e.g. another [`SharedLib`] with the same path, and may map the cached old image when the file is replaced
+    /// quickly. Libraries loaded with [`LoadFlags::with_shadow_copy`] are copied to a file with a new name
+    /// on every reload, so the new file is always the one loaded.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let mut lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     // Rebuild the library...
+    ///     lib.reload().expect("Failed to reload shared library");
+    /// }
+    /// ```
+    pub unsafe fn reload(&mut self) -> Result<(), SharedLibError> {
+        self.reload_waiting(true)
+    }
+    /// Reload the shared library like [`SharedLib::reload`], unless calls marked with a [`CallGate`] are in flight,
+    /// in which case [`SharedLibError::UnloadFailure`] is returned right away instead of waiting for them.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let mut lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     // Rebuild the library...
+    ///     if let Err(e) = lib.try_reload() {
+    ///         println!("Not reloaded yet: {}", e);
+    ///     }
+    /// }
+    /// ```
+    pub unsafe fn try_reload(&mut self) -> Result<(), SharedLibError> {
+        self.reload_waiting(false)
+    }
+    /// Reload the library, waiting for the calls in flight if `wait` is set and failing otherwise.
+    unsafe fn reload_waiting(&mut self, wait: bool) -> Result<(), SharedLibError> {
+        // Check the path before unloading, so e.g. `SharedLib::this` is not left unloaded.
+        self.lib_path.path()?;
+        self.unload(wait)?;
+        let lib = SharedLib::open(self.lib_path.clone(), self.flags)?;
+        self.finish_reload(lib)
+    }
+    /// Unload the shared library and load it again from its library path like [`SharedLib::reload`],
+    /// without blocking the async runtime.
+    ///
+    /// The library is unloaded on the current thread, and loaded again on the blocking thread pool of the tokio runtime.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// # async fn reload() {
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let mut lib = SharedLib::new_async(lib_path).await.expect("Failed to load shared library");
+    ///     // Rebuild the library...
+    ///     lib.reload_async().await.expect("Failed to reload shared library");
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async unsafe fn reload_async(&mut self) -> Result<(), SharedLibError> {
+        let path = self.lib_path.path()?;
+        self.unload(true)?;
+        let (lib_path, flags) = (self.lib_path.clone(), self.flags);
+        let lib = SharedLib::spawn_blocking(path, move || SharedLib::open(lib_path, flags)).await?;
+        self.finish_reload(lib)
+    }
+    /// Take over the library loaded again by `reload` or `reload_async`, after prefetching the required symbols from it.
+    unsafe fn finish_reload(&mut self, mut lib: SharedLib) -> Result<(), SharedLibError> {
+        lib.prefetch(&self.required_symbols)?;
+        self.replace_lib(lib);
+        self.generation += 1;
+        trace::event!(info, path = %self.lib_path, generation = self.generation, "Reloaded library");
+        self.hooks.run_on_load(self);
+        self.hooks.run_after_reload(self);
+        self.hooks.emit(&self.lib_path, || LibEventKind::Reloaded { generation: self.generation });
+        Ok(())
+    }
+    /// Get the number of times the shared library was reloaded with [`SharedLib::reload`].
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// Take over the library loaded again by `reload`, together with its shadow copy and its prefetched symbols.
+    fn replace_lib(&mut self, mut lib: SharedLib) {
+        self.lib = lib.lib.take();
+        self.stub = lib.stub.take();
+        #[cfg(feature = "wasm")]
+        {
+            self.wasm = lib.wasm.take();
+        }
+        *self.symbols.get_mut().unwrap_or_else(|e| e.into_inner()) = std::mem::take(lib.symbols.get_mut().unwrap_or_else(|e| e.into_inner()));
+        // The file of a library loaded with `from_bytes` is loaded again as it is, so it has to be kept.
+        if let Some(extraction) = lib.extraction.take() {
+            self.extraction = Some(extraction);
+        }
+    }
+    /// Unload the library, unless functions obtained with `get_fn_owned` still use it,
+    /// waiting for the calls in flight marked with a `CallGate` if `wait` is set and failing otherwise.
+    fn unload(&mut self, wait: bool) -> Result<(), SharedLibError> {
+        let unload_failure = |msg: String| SharedLibError::UnloadFailure { path: self.lib_path.lossy_path(), msg };
+        let owned_failure = || unload_failure("The library is still used by functions obtained with `get_fn_owned`.".into());
+        #[cfg(feature = "wasm")]
+        if self.wasm.take().is_some() {
+            trace::event!(debug, path = %self.lib_path, "Unloaded WebAssembly module");
+            self.hooks.emit(&self.lib_path, || LibEventKind::Unloaded);
+            return Ok(());
+        }
+        let Some(lib) = &self.lib else {
+            return Ok(());
+        };
+        // Checked before the calls in flight, so a failing reload does not wait for them. Nothing else can
+        // take a reference to the library while it is borrowed mutably.
+        if Arc::strong_count(lib) > 1 {
+            return Err(owned_failure());
+        }
+        match self.calls.stop(wait) {
+            0 => {}
+            active => return Err(unload_failure(format!("{} calls into the library are in flight.", active))),
+        }
+        self.hooks.run_before_unload(self);
+        // Safety: the shutdown function is only called if the library was loaded to be shut down with it.
+        unsafe { self.shutdown() };
+        let Some(lib) = self.lib.take() else {
+            self.calls.retire();
+            return Ok(());
+        };
+        match Arc::try_unwrap(lib) {
+            Ok(lib) => {
+                self.symbols.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+                // The gates stay valid if the dynamic loader keeps the library loaded.
+                if let Err(e) = Loader::close(lib) {
+                    self.calls.resume();
+                    return Err(unload_failure(e.msg));
+                }
+                self.calls.retire();
+                trace::event!(debug, path = %self.lib_path, "Unloaded library");
+                self.hooks.emit(&self.lib_path, || LibEventKind::Unloaded);
+                Ok(())
+            }
+            Err(lib) => {
+                self.lib = Some(lib);
+                self.calls.resume();
+                Err(owned_failure())
+            }
+        }
+    }
+    /// Get the names of all symbols exported by the shared library, see [`inspect::exported_symbols`].
+    ///
+    /// The export table is read from the library file, which is found with [`LibPath::resolve`] if the library path has no directory.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     for symbol in lib.exported_symbols().expect("Failed to read exported symbols") {
+    ///         println!("{}", symbol);
+    ///     }
+    /// }
+    /// ```
+    pub fn exported_symbols(&self) -> Result<Vec<String>, SharedLibError> {
+        inspect::exported_symbols(self.lib_path.resolve()?)
+    }
+    /// Get a function from the shared library by its demangled C++ name.
+    ///
+    /// The export table of the library file located at the library path is searched for a symbol
+    /// whose demangled name is exactly `demangled_name`, e.g. `math::add(int, int)`. Both Itanium (GCC, Clang)
+    /// and MSVC mangled names are demangled, MSVC names without their return type and calling convention.
+    /// If no symbol matches, the [`SharedLibError::SymbolNotFound`] error lists the demangled names of the
+    /// exported functions with the same name, e.g. the other overloads of `math::add`.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn_demangled::<extern "C" fn(i32, i32) -> i32>("math::add(int, int)").expect("Failed to get 'math::add' function from shared library");
+    /// }
+    /// ```
+    #[cfg(feature = "demangle")]
+    pub unsafe fn get_fn_demangled<T: FnSignature>(&self, demangled_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        let path = self.lib_path.path()?;
+        let symbol_not_found = |msg: String| SharedLibError::symbol_not_found(demangled_name, &path, msg);
+        let symbols = inspect::exported_symbols(&path).map_err(|e| symbol_not_found(e.to_string()))?;
+        let demangled: Vec<_> = symbols.iter().filter_map(|symbol| Some((symbol, demangle::demangle(symbol)?))).collect();
+        if let Some((symbol, _)) = demangled.iter().find(|(_, demangled)| demangled == demangled_name) {
+            return self.get_fn(symbol);
+        }
+        let name = demangle::function_name(demangled_name);
+        let candidates: Vec<_> = demangled.iter().map(|(_, demangled)| demangled.as_str()).filter(|demangled| demangle::function_name(demangled) == name).collect();
+        match candidates.is_empty() {
+            true => Err(symbol_not_found("No exported symbol has a matching demangled name.".into())),
+            false => Err(symbol_not_found(format!("No exported symbol has a matching demangled name. Candidates: {}.", candidates.join(", ")))),
+        }
+    }
+    /// Load a shared library from the given path and get a function by name from it in one call.
+    ///
+    /// Returns the library together with the function, which keeps the library loaded on its own.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and a function from it, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let (lib, add_fn) = SharedLib::load_fn::<fn(usize, usize) -> usize>(lib_path, "add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn load_fn<T: FnSignature + 'static>(lib_path: LibPath, fn_name: &str) -> Result<(SharedLib, OwnedSharedLibFn<T>), SharedLibError> {
+        let lib = SharedLib::new(lib_path)?;
+        let func = lib.get_fn_owned(fn_name)?;
+        Ok((lib, func))
+    }
+    /// Get a function by name from the shared library, which keeps the library loaded on its own.
+    ///
+    /// Unlike [`SharedLib::get_fn`], the returned function does not borrow the library,
+    /// so it can be stored alongside it, returned from a function, or kept after the library is dropped.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    ///
+    /// unsafe fn load_add() -> OwnedSharedLibFn<fn(usize, usize) -> usize> {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     lib.get_fn_owned("add").expect("Failed to get 'add' function from shared library")
+    /// }
+    /// unsafe {
+    ///     let add_fn = load_add();
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn get_fn_owned<T: FnSignature + 'static>(&self, fn_name: &str) -> Result<OwnedSharedLibFn<T>, SharedLibError> {
+        let func = self.get_fn::<T>(fn_name)?;
+        // The symbol stays valid for as long as the library is loaded, which `OwnedSharedLibFn` guarantees by holding onto it.
+        let func = std::mem::transmute::<SharedLibFn<'_, T>, SharedLibFn<'static, T>>(func);
+        Ok(OwnedSharedLibFn { func, lib: Arc::clone(self.library()?) })
+    }
+    /// Get a function by name from the shared library like [`SharedLib::get_fn_owned`], looking it up only once.
+    ///
+    /// The address of the function is kept in the symbol cache of the library like by [`SharedLib::get_fn`], which is synchronized,
+    /// so functions can be looked up from several threads at once. The cache is cleared when the library is reloaded.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// The function type is not checked against the actual type of the function, nor against the type it was cached with.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use std::sync::Arc;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = Arc::new(SharedLib::new(lib_path).expect("Failed to load shared library"));
+    ///     let worker_lib = Arc::clone(&lib);
+    ///     std::thread::spawn(move || {
+    ///         let add_fn = worker_lib.get_fn_cached::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function");
+    ///         add_fn.run(1, 2)
+    ///     });
+    /// }
+    /// ```
+    pub unsafe fn get_fn_cached<T: FnSignature + 'static>(&self, fn_name: &str) -> Result<OwnedSharedLibFn<T>, SharedLibError> {
+        assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<usize>(), "the function type must be a function pointer");
+        let lib = Arc::clone(self.library()?);
+        // Looked up from the symbol cache by `get_fn` after the first time.
+        let func = self.get_fn::<T>(fn_name)?;
+        let func = SharedLibFn { symbol: FnSymbol::Resolved(*func.symbol, std::marker::PhantomData), metrics: func.metrics };
+        Ok(OwnedSharedLibFn { func, lib })
     }
 }
 
+// `SharedLib` and the errors are shared between threads, e.g. by `LibManager` and `LazyLib`.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedLib>();
+    assert_send_sync::<SharedLibError>();
+    assert_send_sync::<OwnedSharedLibFn<fn()>>();
+    assert_send_sync::<SharedLibFn<'static, fn()>>();
+};
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -222,12 +2345,10 @@ mod tests {
         let lib_os_string: OsString = lib_path.try_into().unwrap();
         if cfg!(target_os = "windows") {
             assert_eq!(lib_os_string, OsString::from("test_name.dll"));
-        } else if cfg!(target_os = "macos") {
+        } else if cfg!(target_vendor = "apple") {
             assert_eq!(lib_os_string, OsString::from("libtest_name.dylib"));
-        } else if cfg!(target_os = "linux") {
-            assert_eq!(lib_os_string, OsString::from("libtest_name.so"));
         } else {
-            panic!("Unknown target OS: {}", std::env::consts::OS);
+            assert_eq!(lib_os_string, OsString::from("libtest_name.so"));
         }
     }
     #[test]
@@ -242,12 +2363,10 @@ mod tests {
         let lib_os_string: OsString = lib_path.try_into().unwrap();
         if cfg!(target_os = "windows") {
             assert_eq!(lib_os_string, OsString::from("test_dir\\test_name.dll"));
-        } else if cfg!(target_os = "macos") {
+        } else if cfg!(target_vendor = "apple") {
             assert_eq!(lib_os_string, OsString::from("test_dir/libtest_name.dylib"));
-        } else if cfg!(target_os = "linux") {
-            assert_eq!(lib_os_string, OsString::from("test_dir/libtest_name.so"));
         } else {
-            panic!("Unknown target OS: {}", std::env::consts::OS);
+            assert_eq!(lib_os_string, OsString::from("test_dir/libtest_name.so"));
         }
     }
     #[test]
@@ -256,4 +2375,590 @@ mod tests {
         let lib_path = LibPath::new(PathBuf::from("test_dir"), "".into());
         let _: OsString = lib_path.try_into().unwrap();
     }
+    #[test]
+    fn dedupe_lib_paths() {
+        let mut lib_paths = std::collections::HashSet::new();
+        lib_paths.insert(LibPath::new(PathBuf::from("test_dir"), "test_name".into()));
+        lib_paths.insert(LibPath::new(PathBuf::from("test_dir"), "test_name".into()).with_version("1".into()));
+        lib_paths.insert(LibPath::new(PathBuf::from("test_dir"), "test_name".into()).with_convention(FilenameConvention::new("", ".wasm")));
+        assert_eq!(lib_paths.len(), 1);
+        assert_ne!(LibPath::new_no_path("test_name".into()), LibPath::new(PathBuf::from("test_dir"), "test_name".into()));
+    }
+    #[test]
+    #[cfg(not(windows))]
+    fn expand_vars_in_dir_path() {
+        std::env::set_var("SHARED_LIB_TEST_EXPAND", "expanded");
+        let mut lib_path = LibPath::new(PathBuf::from("$SHARED_LIB_TEST_EXPAND/dir/${SHARED_LIB_TEST_EXPAND}_$"), "test_name".into());
+        lib_path.expand_vars().unwrap();
+        assert_eq!(lib_path.dir_path, PathBuf::from("expanded/dir/expanded_$"));
+    }
+    #[test]
+    #[cfg(windows)]
+    fn expand_vars_in_dir_path() {
+        std::env::set_var("SHARED_LIB_TEST_EXPAND", "expanded");
+        let mut lib_path = LibPath::new(PathBuf::from("%SHARED_LIB_TEST_EXPAND%\\dir\\%%"), "test_name".into());
+        lib_path.expand_vars().unwrap();
+        assert_eq!(lib_path.dir_path, PathBuf::from("expanded\\dir\\%%"));
+    }
+    #[test]
+    fn expand_vars_unset() {
+        let dir_path = if cfg!(windows) { "%SHARED_LIB_TEST_UNSET%\\dir" } else { "$SHARED_LIB_TEST_UNSET/dir" };
+        let mut lib_path = LibPath::new(PathBuf::from(dir_path), "test_name".into());
+        let err = lib_path.expand_vars().unwrap_err();
+        assert!(matches!(err, SharedLibError::EnvVarNotSet(name) if name == "SHARED_LIB_TEST_UNSET"));
+        assert_eq!(lib_path.dir_path, PathBuf::from(dir_path));
+    }
+    #[test]
+    fn lib_path_from_filename() {
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "test_name".into());
+        let filename = lib_path.filename().unwrap();
+        assert_eq!(LibPath::from_filename(PathBuf::from("test_dir"), &filename).unwrap(), lib_path);
+    }
+    #[test]
+    fn lib_path_from_filename_invalid() {
+        for filename in ["test_name.txt", "", std::env::consts::DLL_SUFFIX] {
+            let err = LibPath::from_filename(PathBuf::from("test_dir"), OsStr::new(filename)).unwrap_err();
+            assert!(matches!(err, SharedLibError::InvalidFilename(_)));
+        }
+    }
+    #[test]
+    fn lib_path_versioned_filename() {
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "test_name".into()).with_version("1.2.3".into());
+        let filename = lib_path.filename().unwrap();
+        if cfg!(target_os = "windows") {
+            assert_eq!(filename, OsString::from("test_name-1.2.3.dll"));
+        } else if cfg!(target_vendor = "apple") {
+            assert_eq!(filename, OsString::from("libtest_name.1.2.3.dylib"));
+        } else {
+            assert_eq!(filename, OsString::from("libtest_name.so.1.2.3"));
+        }
+        assert_eq!(LibPath::from_filename(PathBuf::from("test_dir"), &filename).unwrap(), lib_path);
+    }
+    #[test]
+    fn lib_path_filename_for_target() {
+        let lib_path = LibPath::new_no_path("test_name".into());
+        assert_eq!(lib_path.filename_for(TargetOs::Linux).unwrap(), OsString::from("libtest_name.so"));
+        assert_eq!(lib_path.filename_for(TargetOs::MacOs).unwrap(), OsString::from("libtest_name.dylib"));
+        assert_eq!(lib_path.filename_for(TargetOs::Windows).unwrap(), OsString::from("test_name.dll"));
+        assert_eq!(lib_path.filename_for(TargetOs::host()).unwrap(), lib_path.filename().unwrap());
+        let lib_path = lib_path.with_convention(FilenameConvention::new("", ".node")).with_version("2".into());
+        assert_eq!(lib_path.filename_for(TargetOs::Linux).unwrap(), OsString::from("test_name.node.2"));
+        assert_eq!(lib_path.filename_for(TargetOs::Windows).unwrap(), OsString::from("test_name-2.node"));
+        let err = LibPath::new_no_path("".into()).filename_for(TargetOs::Windows).unwrap_err();
+        assert!(matches!(err, SharedLibError::PathEmpty));
+    }
+    #[test]
+    fn lib_path_filename_for_mobile_and_wasm() {
+        let lib_path = LibPath::new_no_path("test_name".into()).with_version("1".into());
+        assert_eq!(lib_path.filename_for(TargetOs::Android).unwrap(), OsString::from("libtest_name.so.1"));
+        assert_eq!(lib_path.filename_for(TargetOs::Ios).unwrap(), OsString::from("libtest_name.1.dylib"));
+        assert_eq!(lib_path.filename_for(TargetOs::Emscripten).unwrap(), OsString::from("libtest_name.so.1"));
+        let err = lib_path.filename_for(TargetOs::Wasm).unwrap_err();
+        assert!(matches!(err, SharedLibError::Unsupported(_)));
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "Test".into());
+        assert_eq!(lib_path.framework_path().unwrap(), Path::new("test_dir/Test.framework/Test"));
+        let lib_path = lib_path.with_version("A".into());
+        assert_eq!(lib_path.framework_path().unwrap(), Path::new("test_dir/Test.framework/Versions/A/Test"));
+    }
+    #[test]
+    fn lib_path_convention_filename() {
+        let lib_path = LibPath::new_no_path("test_name".into()).with_convention(FilenameConvention::new("", ".so"));
+        assert_eq!(lib_path.filename().unwrap(), OsString::from("test_name.so"));
+        let filename = lib_path.with_version("1".into()).filename().unwrap();
+        if cfg!(target_os = "windows") {
+            assert_eq!(filename, OsString::from("test_name-1.so"));
+        } else if cfg!(target_os = "macos") {
+            assert_eq!(filename, OsString::from("test_name.1.so"));
+        } else {
+            assert_eq!(filename, OsString::from("test_name.so.1"));
+        }
+        assert_eq!(LibPath::new_no_path("test_name".into()).convention(), &FilenameConvention::platform());
+        assert_eq!(FilenameConvention::fallbacks()[0], FilenameConvention::platform());
+    }
+    #[test]
+    fn lib_path_from_full_path() {
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "test_name".into()).with_version("2".into());
+        assert_eq!(LibPath::from_full_path(lib_path.path().unwrap()).unwrap(), lib_path);
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "test_name".into()).with_convention(FilenameConvention::no_prefix());
+        assert_eq!(LibPath::from_full_path(lib_path.path().unwrap()).unwrap(), lib_path);
+        let err = LibPath::from_full_path("test_dir/test_name.txt").unwrap_err();
+        assert!(matches!(err, SharedLibError::InvalidFilename(filename) if filename == "test_name.txt"));
+    }
+    #[test]
+    fn lib_path_display_and_parse() {
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "test_name".into());
+        assert_eq!(lib_path.to_string(), lib_path.path().unwrap().to_string_lossy());
+        assert_eq!(lib_path.to_string().parse::<LibPath>().unwrap(), lib_path);
+        assert_eq!("test_name".parse::<LibPath>().unwrap(), LibPath::new_no_path("test_name".into()));
+        assert_eq!(LibPath::new_no_path("".into()).to_string(), "");
+        assert!(matches!("".parse::<LibPath>(), Err(SharedLibError::PathEmpty)));
+    }
+    #[test]
+    fn lib_path_prefix_and_extension() {
+        let lib_path = LibPath::new_no_path("test_name".into()).with_prefix("mod_").with_extension("node");
+        assert_eq!(lib_path.filename().unwrap(), OsString::from("mod_test_name.node"));
+        assert_eq!(lib_path.clone().with_extension(".so").filename().unwrap(), OsString::from("mod_test_name.so"));
+        assert_eq!(lib_path.with_extension("").filename().unwrap(), OsString::from("mod_test_name"));
+    }
+    #[test]
+    fn caught_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("test_msg {}", 1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "test_msg 1");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(1)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "The panic payload is not a string.");
+    }
+    #[test]
+    fn lib_path_accessors() {
+        let lib_path = LibPath::new(PathBuf::from("test_dir"), "test_name".into());
+        assert_eq!(lib_path.version(), None);
+        assert_eq!(lib_path.lib_name(), "test_name");
+        assert_eq!(lib_path.dir(), Path::new("test_dir"));
+    }
+    #[test]
+    fn search_path_lib_paths() {
+        let search_path = LibSearchPath::new(vec![PathBuf::from("first_dir"), PathBuf::from("second_dir")], "test_name".into());
+        let lib_paths: Vec<_> = search_path.lib_paths().collect();
+        assert_eq!(lib_paths, [
+            LibPath::new(PathBuf::from("first_dir"), "test_name".into()),
+            LibPath::new(PathBuf::from("second_dir"), "test_name".into()),
+        ]);
+    }
+    #[test]
+    #[cfg(unix)]
+    fn unix_load_flags() {
+        use libloading::os::unix;
+        assert_eq!(LoadFlags::new().unix_flags(), unix::RTLD_LAZY | unix::RTLD_LOCAL);
+        assert_eq!(LoadFlags::new().with_now(true).with_global(true).unix_flags(), unix::RTLD_NOW | unix::RTLD_GLOBAL);
+    }
+    #[test]
+    #[cfg(windows)]
+    fn windows_load_flags() {
+        use libloading::os::windows;
+        assert_eq!(LoadFlags::new().windows_flags(), 0);
+        assert_eq!(LoadFlags::new().with_altered_search_path(true).windows_flags(), windows::LOAD_WITH_ALTERED_SEARCH_PATH);
+        assert_eq!(
+            LoadFlags::new().with_search_dll_load_dir(true).windows_flags(),
+            windows::LOAD_LIBRARY_SEARCH_DLL_LOAD_DIR | windows::LOAD_LIBRARY_SEARCH_DEFAULT_DIRS
+        );
+    }
+    #[test]
+    #[cfg(windows)]
+    fn parse_guid() {
+        let guid: Guid = "{00000001-0000-0000-C000-000000000046}".parse().unwrap();
+        assert_eq!(guid, Guid::ICLASSFACTORY);
+        assert_eq!(guid.to_string().parse::<Guid>().unwrap(), guid);
+        assert_eq!("00000000-0000-0000-c000-000000000046".parse::<Guid>().unwrap(), Guid::IUNKNOWN);
+        assert!("{00000000-0000-0000-C000}".parse::<Guid>().is_err());
+        assert!("{0000000G-0000-0000-C000-000000000046}".parse::<Guid>().is_err());
+    }
+    #[test]
+    fn sanitize_file_names() {
+        assert_eq!(bytes::sanitize_name("calculator-1.2.3_x"), "calculator-1.2.3_x");
+        assert_eq!(bytes::sanitize_name("../dir/name"), ".._dir_name");
+        assert_eq!(bytes::sanitize_name(".."), "___");
+    }
+    #[test]
+    fn normalize_lib_path() {
+        let normalize = |dir: &str| LibPath::new(PathBuf::from(dir), "test_name".into()).normalized().dir_path;
+        assert_eq!(normalize("./test_dir/./nested/../lib"), PathBuf::from("test_dir/lib"));
+        assert_eq!(normalize("../test_dir/../../lib/"), PathBuf::from("../../lib"));
+        assert_eq!(normalize("test_dir/.."), PathBuf::new());
+        assert_eq!(normalize("/../test_dir/./lib/.."), PathBuf::from("/test_dir"));
+        let lib_path = LibPath::new(PathBuf::from("./test_dir/nested/.."), "test_name".into());
+        assert_eq!(lib_path.normalized(), LibPath::new(PathBuf::from("test_dir"), "test_name".into()));
+    }
+    #[test]
+    fn clone_errors() {
+        for err in all_errors() {
+            assert_eq!(err.clone().to_string(), err.to_string());
+        }
+    }
+    #[test]
+    fn classify_errors() {
+        let classify = |err: &SharedLibError| (err.is_path_error(), err.is_load_error(), err.is_symbol_error(), err.is_not_found());
+        let classes: Vec<_> = all_errors().iter().map(classify).collect();
+        assert_eq!(classes, [
+            (true, false, false, false),
+            (true, false, false, false),
+            (false, true, false, false),
+            (false, false, true, true),
+            (true, false, false, true),
+            (false, true, false, false),
+            (true, false, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (true, false, false, true),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, false, false, false),
+            (false, false, true, true),
+            (false, false, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, true, false, true),
+            (true, false, false, false),
+            (false, false, true, false),
+            (false, false, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, false, false, false),
+            (false, false, false, false),
+            (false, false, false, false),
+            (false, false, false, false),
+            (false, true, false, false),
+            (false, false, false, false),
+            (false, false, false, false),
+            (true, false, false, false),
+            (false, false, true, false),
+            (false, false, false, false),
+            (false, false, false, true),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, true, false, false),
+            (false, false, false, false),
+            (false, false, false, false),
+            (false, false, false, false),
+            (false, true, false, false),
+            (false, false, false, false),
+        ]);
+    }
+    #[test]
+    fn error_kinds() {
+        use ErrorKind::*;
+        let kinds: Vec<_> = all_errors().iter().map(SharedLibError::kind).collect();
+        assert_eq!(kinds, [
+            InvalidPath,
+            InvalidPath,
+            LoadFailed,
+            SymbolMissing,
+            InvalidPath,
+            BadFormat,
+            InvalidPath,
+            VerificationFailed,
+            UnloadFailed,
+            LoadFailed,
+            NotFound,
+            BadFormat,
+            AlreadyLoaded,
+            BadFormat,
+            AbiMismatch,
+            VerificationFailed,
+            CallFailed,
+            SymbolMissing,
+            Unsupported,
+            LoadFailed,
+            LoadFailed,
+            NotFound,
+            InvalidPath,
+            AbiMismatch,
+            BuildFailed,
+            FetchFailed,
+            BadFormat,
+            CallFailed,
+            CallFailed,
+            CallFailed,
+            Other,
+            InitFailed,
+            BuildFailed,
+            CallFailed,
+            InvalidPath,
+            StaleSymbol,
+            InvalidDependencies,
+            InvalidDependencies,
+            AbiMismatch,
+            BadFormat,
+            LoadFailed,
+            CallFailed,
+            InitFailed,
+            CallFailed,
+            VerificationFailed,
+            Other,
+        ]);
+        let not_found = SharedLibError::NotInSearchPath { lib_name: "test_lib".into(), attempts: vec![SharedLibError::NotFound { path: PathBuf::from("test_path") }] };
+        assert_eq!(not_found.kind(), NotFound);
+    }
+    #[test]
+    fn error_source() {
+        use std::error::Error;
+        // Without a directory, the dynamic loader reports the missing library.
+        let lib_path = LibPath::new_no_path("missing_lib".into());
+        let err = unsafe { SharedLib::new(lib_path) }.err().unwrap();
+        assert!(err.source().is_some_and(|source| source.downcast_ref::<Arc<libloading::Error>>().is_some()));
+        assert!(SharedLibError::load_failure("test_path", String::from("test_msg")).source().is_none());
+    }
+    #[test]
+    fn error_raw_os_error() {
+        let err = SharedLibError::load_failure("test_path", std::io::Error::from_raw_os_error(2));
+        assert_eq!(err.raw_os_error(), Some(2));
+        assert!(matches!(&err, SharedLibError::LoadFailure { path, .. } if path == Path::new("test_path")));
+        let err = SharedLibError::symbol_not_found("test_symbol", "test_lib", String::from("test_msg"));
+        assert_eq!(err.raw_os_error(), None);
+        assert_eq!(SharedLibError::PathEmpty.raw_os_error(), None);
+    }
+    /// One error of every variant.
+    fn all_errors() -> Vec<SharedLibError> {
+        vec![
+            SharedLibError::PathEmpty,
+            SharedLibError::PathConversion(PathBuf::from("test_dir"), "String".into()),
+            SharedLibError::LoadFailure { path: PathBuf::from("test_path"), msg: "test_msg".into(), raw_os_error: None, source: None },
+            SharedLibError::SymbolNotFound { symbol_name: "test_symbol".into(), lib_name: "test_lib".into(), msg: "test_msg".into(), raw_os_error: Some(127), source: None },
+            SharedLibError::EnvVarNotSet("TEST_VAR".into()),
+            SharedLibError::ArchMismatch { path: "test_path".into(), expected: "X86_64".into(), found: "Aarch64".into() },
+            SharedLibError::InvalidFilename(OsString::from("test_name.txt")),
+            SharedLibError::IntegrityMismatch { path: "test_path".into(), expected: "00".into(), actual: "ff".into() },
+            SharedLibError::UnloadFailure { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::NotInSearchPath { lib_name: "test_lib".into(), attempts: vec![SharedLibError::PathEmpty] },
+            SharedLibError::LibNotFound { lib_name: "test_lib".into(), searched: vec![PathBuf::from("test_path")] },
+            SharedLibError::InspectFailure { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::PluginAlreadyLoaded("test_plugin".into()),
+            SharedLibError::InvalidManifest { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::AbiMismatch { path: "test_path".into(), expected: 1, found: 2 },
+            SharedLibError::VerificationFailure { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::CallPanicked("test_msg".into()),
+            SharedLibError::SymbolsNotFound { symbol_names: vec!["test_symbol".into()], lib_name: "test_lib".into() },
+            SharedLibError::Unsupported("test_feature".into()),
+            SharedLibError::NoMatchingFilename { lib_name: "test_lib".into(), attempts: vec![SharedLibError::PathEmpty] },
+            SharedLibError::NoCandidateLoaded { attempts: vec![(LibPath::new_no_path("test_lib".into()), SharedLibError::PathEmpty)] },
+            SharedLibError::NotFound { path: PathBuf::from("test_path") },
+            SharedLibError::LoadReport(Box::new(LoadReport::new("test_lib".into(), &[PathBuf::from("test_dir")], Vec::new(), SharedLibError::PathEmpty))),
+            SharedLibError::SignatureMismatch { symbol_name: "test_symbol".into(), path: "test_path".into(), expected: "fn()".into() },
+            SharedLibError::BindingsFailure("test_msg".into()),
+            SharedLibError::FetchFailure { url: "test_url".into(), msg: "test_msg".into() },
+            SharedLibError::InvalidArchive { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::Timeout(Duration::from_secs(1)),
+            SharedLibError::SandboxFailure { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::ComFailure { path: "test_path".into(), method: "test_method".into(), hresult: -1 },
+            SharedLibError::InvalidGuid("test_guid".into()),
+            SharedLibError::InitFailure { path: "test_path".into(), code: 1 },
+            SharedLibError::CompileFailure { lib_name: "test_lib".into(), msg: "test_msg".into() },
+            SharedLibError::Fault { address: 0x10 },
+            SharedLibError::PathEmpty.context("test_context"),
+            SharedLibError::StaleSymbol { symbol_name: "test_symbol".into(), path: "test_path".into(), generation: 1 },
+            SharedLibError::DependencyCycle(vec!["test_lib".into(), "test_dep".into(), "test_lib".into()]),
+            SharedLibError::UnknownDependency { lib_name: "test_lib".into(), dependency: "test_dep".into() },
+            SharedLibError::IncompatibleVersion { plugin: "test_plugin".into(), required: "^1.2".into(), host: "2.0.0".into() },
+            SharedLibError::InvalidConfig { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::DllDirectoryFailure { path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::WorkerFailure { msg: "test_msg".into(), source: None },
+            SharedLibError::NullInstance { symbol_name: "test_symbol".into(), path: "test_path".into() },
+            SharedLibError::WasmTrap { symbol_name: "test_symbol".into(), path: "test_path".into(), msg: "test_msg".into() },
+            SharedLibError::ChecksumMismatch { path: "test_path".into(), expected: "00".into(), actual: "ff".into() },
+            SharedLibError::InvalidSha256("test_hash".into()),
+        ]
+    }
+    /// Minimal 64-bit little endian ELF shared object header for the given machine type.
+    fn elf_header(machine: u16) -> Vec<u8> {
+        let mut header = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&3u16.to_le_bytes()); // e_type: ET_DYN
+        header.extend_from_slice(&machine.to_le_bytes()); // e_machine
+        header.extend_from_slice(&1u32.to_le_bytes()); // e_version
+        header.extend_from_slice(&[0; 24]); // e_entry, e_phoff, e_shoff
+        header.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        header.extend_from_slice(&64u16.to_le_bytes()); // e_ehsize
+        header.extend_from_slice(&56u16.to_le_bytes()); // e_phentsize
+        header.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+        header.extend_from_slice(&64u16.to_le_bytes()); // e_shentsize
+        header.extend_from_slice(&[0; 4]); // e_shnum, e_shstrndx
+        header
+    }
+    #[test]
+    fn load_lib_arch_mismatch() {
+        const EM_RISCV: u16 = 243;
+        let dir = std::env::temp_dir().join("shared_lib_arch_mismatch");
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib_path = LibPath::new(dir, "riscv_lib".into());
+        std::fs::write(lib_path.path().unwrap(), elf_header(EM_RISCV)).unwrap();
+        let err = unsafe { SharedLib::new(lib_path) }.err().unwrap();
+        assert!(matches!(err, SharedLibError::ArchMismatch { found, .. } if found == "Riscv64"));
+    }
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn diagnose_lib_files() {
+        const EM_386: u16 = 3;
+        let dir = std::env::temp_dir().join("shared_lib_diagnose");
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib_path = LibPath::new(dir.clone(), "i386_lib".into());
+        std::fs::write(lib_path.path().unwrap(), elf_header(EM_386)).unwrap();
+        let diagnosis = lib_path.diagnose().unwrap();
+        assert_eq!(diagnosis.architectures, ["I386"]);
+        assert_eq!(diagnosis.problems, [
+            inspect::LibProblem::ArchMismatch { expected: "X86_64".into(), found: vec!["I386".into()] },
+            inspect::LibProblem::BitnessMismatch { expected: 64, found: 32 },
+        ]);
+        assert!(diagnosis.to_string().contains("32-bit"));
+        let lib_path = LibPath::new(dir.clone(), "invalid_lib".into());
+        std::fs::write(lib_path.path().unwrap(), b"not a library").unwrap();
+        assert!(matches!(lib_path.diagnose().unwrap().problems[..], [inspect::LibProblem::InvalidFormat(_)]));
+        let lib_path = LibPath::new(dir, "missing_lib".into());
+        assert_eq!(lib_path.diagnose().unwrap().problems, [inspect::LibProblem::NotFound]);
+        let lib_path = LibPath::new_no_path("missing_lib".into());
+        assert_eq!(lib_path.diagnose().unwrap().problems, [inspect::LibProblem::NotFound]);
+    }
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn check_arch_match() {
+        const EM_X86_64: u16 = 62;
+        let dir = std::env::temp_dir().join("shared_lib_arch_match");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("x86_64_lib");
+        std::fs::write(&path, elf_header(EM_X86_64)).unwrap();
+        inspect::check_arch(&path).unwrap();
+    }
+    #[test]
+    #[cfg(feature = "demangle")]
+    fn demangle_itanium_and_msvc_names() {
+        assert_eq!(demangle::demangle("_ZN4math3addEii").as_deref(), Some("math::add(int, int)"));
+        assert_eq!(demangle::demangle("?add@math@@YAHHH@Z").as_deref(), Some("math::add(int, int)"));
+        assert_eq!(demangle::demangle("?name@Plugin@@QEBAPEBDXZ").as_deref(), Some("Plugin::name(void)"));
+        assert_eq!(demangle::demangle("add"), None);
+        assert_eq!(demangle::function_name("math::add(int, int)"), "math::add");
+    }
+    #[test]
+    fn glob_match_names() {
+        assert!(discover::glob_match("*", ""));
+        assert!(discover::glob_match("plugin_*", "plugin_foo"));
+        assert!(discover::glob_match("plugin_*", "plugin_"));
+        assert!(!discover::glob_match("plugin_*", "plugin"));
+        assert!(discover::glob_match("*_plugin", "foo_bar_plugin"));
+        assert!(discover::glob_match("a*b*c", "aXbYbZc"));
+        assert!(!discover::glob_match("a*b*c", "aXbYbZ"));
+        assert!(discover::glob_match("lib?", "lib1"));
+        assert!(!discover::glob_match("lib?", "lib"));
+        assert!(!discover::glob_match("foo", "foobar"));
+    }
+    #[test]
+    fn undecorate_names() {
+        assert_eq!(decorated::undecorate("_Foo@8"), Some("Foo"));
+        assert_eq!(decorated::undecorate("@Foo@12"), Some("Foo"));
+        assert_eq!(decorated::undecorate("Foo@@16"), Some("Foo"));
+        assert_eq!(decorated::undecorate("Foo@0"), Some("Foo"));
+        assert_eq!(decorated::undecorate("Foo"), None);
+        assert_eq!(decorated::undecorate("Foo@"), None);
+        assert_eq!(decorated::undecorate("Foo@GLIBC_2.14"), None);
+        assert_eq!(decorated::undecorate("_@8"), None);
+    }
+    #[test]
+    fn suggest_symbol_names() {
+        assert_eq!(suggest::edit_distance("add", "add"), 0);
+        assert_eq!(suggest::edit_distance("ad", "add"), 1);
+        assert_eq!(suggest::edit_distance("sub", "add"), 3);
+        assert_eq!(suggest::edit_distance("", "add"), 3);
+        let exported = ["add", "c_add", "answer_ptr", "_ZN4math3add17h0123456789abcdefE", "_ZN4calc3subEii"].map(String::from);
+        assert_eq!(suggest::suggestions("ad", &exported), ["add"]);
+        assert_eq!(suggest::suggestions("c_ad", &exported), ["c_add"]);
+        assert_eq!(suggest::suggestions("sub", &exported), ["_ZN4calc3subEii"]);
+        assert_eq!(suggest::suggestions("answer", &exported), Vec::<String>::new());
+        assert_eq!(suggest::suggestions("add_numbers", &["add_number".into()]), ["add_number"]);
+    }
+    #[test]
+    #[cfg(feature = "bindgen")]
+    fn generate_dynamic_bindings() {
+        let bindings = r#"
+            #[repr(C)]
+            pub struct point { pub x: i32, pub y: i32 }
+            extern "C" {
+                #[doc = " Add two numbers."]
+                pub fn calc_add(left: i32, right: i32) -> i32;
+                #[link_name = "\u{1}_calc_len"]
+                pub fn calc_len(arg1: *const point) -> f64;
+                pub fn calc_log(format: *const ::std::os::raw::c_char, ...);
+                pub static mut calc_errno: i32;
+            }
+        "#;
+        let generated = dynamic_bindings(bindings, "CalcApi").unwrap();
+        let file = syn::parse_file(&generated).unwrap();
+        assert!(matches!(&file.items[0], syn::Item::Struct(item) if item.ident == "point"));
+        let syn::Item::Macro(item) = &file.items[1] else { panic!("{}", generated) };
+        let body = item.mac.tokens.to_string();
+        assert!(body.starts_with("pub struct CalcApi ; extern \"C\""), "{}", body);
+        assert!(body.contains("# [doc = \" Add two numbers.\"] fn calc_add (left : i32 , right : i32) -> i32 ;"), "{}", body);
+        assert!(body.contains("# [link_name = \"\\u{1}_calc_len\"] fn calc_len (arg1 : * const point) -> f64 ;"), "{}", body);
+        assert!(!body.contains("calc_log") && !body.contains("calc_errno"), "{}", body);
+        let mixed = r#"extern "C" { pub fn a(); } extern "system" { pub fn b(); }"#;
+        assert!(matches!(dynamic_bindings(mixed, "MixedApi"), Err(SharedLibError::BindingsFailure(_))));
+        assert!(matches!(dynamic_bindings("", "not a name"), Err(SharedLibError::BindingsFailure(_))));
+    }
+    #[test]
+    #[cfg(feature = "codegen")]
+    fn generate_bindings_from_symbol_spec() {
+        let spec = SymbolSpec::from_toml(r#"
+            struct_name = "CalcApi"
+            [[functions]]
+            name = "add"
+            signature = "fn(left: i32, right: i32) -> i32"
+            doc = "Add two numbers."
+            link_name = "c_add"
+            safe = true
+            [[functions]]
+            name = "reset"
+            signature = "fn(*mut u8, _: usize)"
+        "#).unwrap();
+        assert_eq!(spec.abi, "C");
+        let generated = spec.generate().unwrap();
+        let file = syn::parse_file(&generated).unwrap();
+        let syn::Item::Macro(item) = &file.items[0] else { panic!("{}", generated) };
+        let body = item.mac.tokens.to_string();
+        assert!(body.starts_with("pub struct CalcApi ; extern \"C\""), "{}", body);
+        assert!(body.contains("# [doc = \" Add two numbers.\"] # [link_name = \"c_add\"] fn add (left : i32 , right : i32) -> i32 ;"), "{}", body);
+        assert!(body.contains("fn reset (arg1 : * mut u8 , arg2 : usize) ;"), "{}", body);
+        let syn::Item::Impl(item) = &file.items[1] else { panic!("{}", generated) };
+        assert!(matches!(&item.items[..], [syn::ImplItem::Fn(func)] if func.sig.ident == "add" && func.sig.unsafety.is_none()), "{}", generated);
+
+        let invalid = |functions: &str| SymbolSpec::from_toml(&format!("struct_name = \"CalcApi\"\n{}", functions)).unwrap().generate();
+        assert!(matches!(invalid(r#"functions = [{ name = "add", signature = "extern \"C\" fn()" }]"#), Err(SharedLibError::BindingsFailure(_))));
+        assert!(matches!(invalid(r#"functions = [{ name = "add", signature = "fn(i32, ...)" }]"#), Err(SharedLibError::BindingsFailure(_))));
+        assert!(matches!(invalid(r#"functions = [{ name = "add", signature = "add" }]"#), Err(SharedLibError::BindingsFailure(_))));
+        assert!(matches!(invalid(r#"functions = [{ name = "a b", signature = "fn()" }]"#), Err(SharedLibError::BindingsFailure(_))));
+        assert!(matches!(invalid(r#"functions = [{ name = "a", signature = "fn()" }, { name = "a", signature = "fn()" }]"#), Err(SharedLibError::BindingsFailure(_))));
+        assert!(matches!(SymbolSpec::from_toml("struct_name = 1"), Err(SharedLibError::BindingsFailure(_))));
+    }
+    #[test]
+    #[cfg(feature = "archive")]
+    fn extract_archive_entries() {
+        // A stored block and a block compressed with the fixed Huffman codes, encoding "x" and "a".
+        assert_eq!(inflate::inflate(&[0x01, 0x01, 0x00, 0xfe, 0xff, b'x'], 1).unwrap(), b"x");
+        assert_eq!(inflate::inflate(&[0x4b, 0x04, 0x00], 1).unwrap(), b"a");
+        assert!(inflate::inflate(&[0x01, 0x01, 0x00, 0xfe, 0xff, b'x'], 0).is_err());
+        assert!(inflate::inflate(&[0x4b, 0x04, 0x00], 0).is_err());
+        assert!(inflate::inflate(&[0x01, 0x01, 0x00, 0x00, 0x00, b'x'], 1).is_err());
+        assert!(inflate::inflate(&[0x07], 1).is_err());
+        assert!(inflate::gunzip(b"not gzip", 1).is_err());
+        assert_eq!(inflate::crc32(b"123456789"), 0xcbf4_3926);
+        let gzip = |crc: u32, size: u32| [&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 0x4b, 0x04, 0x00][..], &crc.to_le_bytes(), &size.to_le_bytes()].concat();
+        assert_eq!(inflate::gunzip(&gzip(0xe8b7_be43, 1), 1).unwrap(), b"a");
+        assert!(inflate::gunzip(&gzip(0xe8b7_be42, 1), 1).is_err());
+        assert!(inflate::gunzip(&gzip(0xe8b7_be43, 2), 1).is_err());
+        assert!(inflate::gunzip(&gzip(0xe8b7_be43, 1)[..20], 1).is_err());
+        assert!(archive::is_contained(Path::new("plugin/../libfoo.so")));
+        assert!(!archive::is_contained(Path::new("plugin/../../libfoo.so")));
+        assert!(!archive::is_contained(Path::new("/etc/libfoo.so")));
+        assert!(!archive::is_contained(Path::new(".")));
+    }
+    #[test]
+    fn type_signatures() {
+        type AddFn = extern "C" fn(u32, u32) -> u32;
+        assert_eq!(AddFn::SIGNATURE, <extern "C" fn(u32, u32) -> u32>::SIGNATURE);
+        assert_ne!(AddFn::SIGNATURE, <fn(u32, u32) -> u32>::SIGNATURE);
+        assert_ne!(AddFn::SIGNATURE, <unsafe extern "C" fn(u32, u32) -> u32>::SIGNATURE);
+        assert_ne!(AddFn::SIGNATURE, <extern "C" fn(u32, u64) -> u32>::SIGNATURE);
+        assert_ne!(AddFn::SIGNATURE, <extern "C" fn(u32)>::SIGNATURE);
+        assert_ne!(<*const u8>::SIGNATURE, <*mut u8>::SIGNATURE);
+        assert_ne!(<*const u8>::SIGNATURE, <*const *const u8>::SIGNATURE);
+        assert_ne!(<Option<fn()>>::SIGNATURE, <fn()>::SIGNATURE);
+    }
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn resolve_rpath_in_search_dirs() {
+        let dir = std::env::temp_dir().join("shared_lib_resolve_rpath");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("libtest_dep.dylib"), []).unwrap();
+        let rpaths = [PathBuf::from("non_existent_dir"), dir.clone()];
+        assert_eq!(macos::resolve_rpath("@rpath/libtest_dep.dylib", &rpaths), Some(dir.join("libtest_dep.dylib")));
+        assert_eq!(macos::resolve_rpath("@rpath/libmissing.dylib", &rpaths), None);
+        assert_eq!(macos::resolve_rpath("/usr/lib/libtest_dep.dylib", &rpaths), None);
+    }
 }
@@ -0,0 +1,35 @@
+//! Initialization and teardown functions called when a library is loaded and unloaded, see [`LoadFlags::with_lifecycle`](crate::LoadFlags::with_lifecycle).
+
+use crate::{trace, SharedLib, SharedLibError};
+
+/// Name of the function called after a library is loaded, see [`LoadFlags::with_lifecycle`](crate::LoadFlags::with_lifecycle).
+pub const INIT_SYMBOL: &str = "lib_init";
+/// Name of the function called before a library is unloaded, see [`LoadFlags::with_lifecycle`](crate::LoadFlags::with_lifecycle).
+pub const SHUTDOWN_SYMBOL: &str = "lib_shutdown";
+
+type InitFn = unsafe extern "C" fn() -> i32;
+type ShutdownFn = unsafe extern "C" fn();
+
+impl SharedLib {
+    /// Call [`INIT_SYMBOL`] of the library, if it exports it and was loaded with the lifecycle flag.
+    pub(crate) unsafe fn init(&self) -> Result<(), SharedLibError> {
+        if !self.flags.is_lifecycle() || !self.has_symbol(INIT_SYMBOL) {
+            return Ok(());
+        }
+        match self.get_fn::<InitFn>(INIT_SYMBOL)?.run() {
+            0 => Ok(()),
+            code => {
+                trace::event!(debug, path = %self.lib_path, code, "Failed to initialize library");
+                Err(SharedLibError::InitFailure { path: self.lib_path.lossy_path(), code })
+            }
+        }
+    }
+    /// Call [`SHUTDOWN_SYMBOL`] of the library, if it exports it and was loaded with the lifecycle flag.
+    pub(crate) unsafe fn shutdown(&self) {
+        if self.flags.is_lifecycle() && self.has_symbol(SHUTDOWN_SYMBOL) {
+            if let Ok(shutdown) = self.get_fn::<ShutdownFn>(SHUTDOWN_SYMBOL) {
+                shutdown.run();
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Abstraction over the dynamic loader the libraries are loaded with, see [`Loader`].
+
+use crate::{LoadFlags, LoaderError};
+use std::ffi::{c_void, OsStr};
+use std::path::Path;
+#[cfg(windows)]
+use std::path::PathBuf;
+
+/// Operations of the dynamic loader [`SharedLib`](crate::SharedLib) and [`SharedLibFn`](crate::SharedLibFn) are built on.
+///
+/// Functions and variables are looked up as the addresses returned by [`Loader::symbol`], so another crate, e.g. `dlopen2`,
+/// can back the libraries by implementing this trait for its library type and selecting it as [`Library`] behind a feature.
+/// Loading into an isolated namespace and opening loaded libraries use the handles of libloading, as do the raw accessors
+/// like [`SharedLib::as_libloading`](crate::SharedLib::as_libloading), which are only available with the default loader.
+pub(crate) trait Loader: Sized + Send + Sync + 'static {
+    /// Load the library from the file at `path` with the given flags.
+    unsafe fn open(path: &OsStr, flags: LoadFlags) -> Result<Self, LoaderError>;
+    /// Open the current process itself, see [`SharedLib::this`](crate::SharedLib::this).
+    fn this() -> Result<Self, LoaderError>;
+    /// Get the address of the symbol `name`, which is optionally terminated with a nul byte.
+    unsafe fn symbol(&self, name: &[u8]) -> Result<*mut c_void, LoaderError>;
+    /// Unload the library, reporting the error of the dynamic loader.
+    fn close(self) -> Result<(), LoaderError>;
+}
+
+/// Library type of the loader the libraries are loaded with.
+pub(crate) type Library = libloading::Library;
+
+impl Loader for libloading::Library {
+    unsafe fn open(path: &OsStr, flags: LoadFlags) -> Result<Self, LoaderError> {
+        open_library(path, flags)
+    }
+    fn this() -> Result<Self, LoaderError> {
+        #[cfg(unix)]
+        return Ok(libloading::os::unix::Library::this().into());
+        #[cfg(windows)]
+        return Ok(libloading::os::windows::Library::this()?.into());
+    }
+    unsafe fn symbol(&self, name: &[u8]) -> Result<*mut c_void, LoaderError> {
+        // libloading only copies the name if it is not terminated.
+        Ok(*self.get::<*mut c_void>(name)?)
+    }
+    fn close(self) -> Result<(), LoaderError> {
+        Ok(libloading::Library::close(self)?)
+    }
+}
+
+#[cfg(unix)]
+unsafe fn open_library(path: &OsStr, flags: LoadFlags) -> Result<Library, LoaderError> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    if flags.is_isolated_namespace() {
+        return open_isolated_library(path, flags).map_err(LoaderError::from);
+    }
+    Ok(libloading::os::unix::Library::open(Some(path), flags.unix_flags())?.into())
+}
+/// Load the library into a new linker namespace with `dlmopen`, which libloading does not support.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+unsafe fn open_isolated_library(path: &OsStr, flags: LoadFlags) -> Result<Library, String> {
+    use std::ffi::{c_char, c_int, c_long, CString};
+    extern "C" {
+        fn dlmopen(lmid: c_long, filename: *const c_char, flag: c_int) -> *mut c_void;
+    }
+    const LM_ID_NEWLM: c_long = -1;
+    let filename = CString::new(path.as_encoded_bytes()).map_err(|e| e.to_string())?;
+    let handle = dlmopen(LM_ID_NEWLM, filename.as_ptr(), flags.unix_flags());
+    if handle.is_null() {
+        return Err(crate::dl::last_error());
+    }
+    // libloading closes the handle with `dlclose` when the library is dropped, which also works for `dlmopen` handles.
+    Ok(Library::from(libloading::os::unix::Library::from_raw(handle)))
+}
+#[cfg(windows)]
+unsafe fn open_library(path: &OsStr, flags: LoadFlags) -> Result<Library, LoaderError> {
+    // Symbols of loaded DLLs are always visible to other modules on Windows.
+    // Both search flags are undefined for relative paths.
+    let path = match flags.needs_absolute_path() {
+        true => std::path::absolute(path).map_or(path.to_owned(), PathBuf::into_os_string),
+        false => path.to_owned(),
+    };
+    Ok(libloading::os::windows::Library::load_with_flags(path, flags.windows_flags())?.into())
+}
+
+/// Open the library at `path` if it is already loaded, without loading it.
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos"))]
+pub(crate) unsafe fn open_existing_library(path: &Path) -> Result<Library, LoaderError> {
+    let handle = crate::dl::NoLoadHandle::open(Some(path)).map_err(|e| format!("The library is not loaded. {}", e))?;
+    // libloading closes the handle with `dlclose` when the library is dropped, which balances the reference taken by `dlopen`.
+    Ok(Library::from(libloading::os::unix::Library::from_raw(handle.into_raw())))
+}
+#[cfg(windows)]
+pub(crate) unsafe fn open_existing_library(path: &Path) -> Result<Library, LoaderError> {
+    Ok(libloading::os::windows::Library::open_already_loaded(path)?.into())
+}
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows)))]
+pub(crate) unsafe fn open_existing_library(_path: &Path) -> Result<Library, LoaderError> {
+    unreachable!("opening loaded libraries is not supported on this platform")
+}
@@ -0,0 +1,106 @@
+//! macOS specific helpers for resolving `@rpath` relative dependencies.
+//!
+//! The dynamic loader on macOS resolves `@rpath/` install names using the `LC_RPATH` entries
+//! baked into the binaries, which cannot be extended at runtime. However, an `@rpath`
+//! dependency that is already loaded into the process is matched by its install name,
+//! so pre-loading the dependencies from the user provided directories lets the library load.
+
+use crate::SharedLibError;
+use crate::loader::{Library, Loader};
+use object::macho::{MachHeader32, MachHeader64};
+use object::read::macho::{FatArch, LoadCommandVariant, MachHeader, MachOFatFile32, MachOFatFile64};
+use object::{Endianness, FileKind};
+use std::path::{Path, PathBuf};
+
+const RPATH_PREFIX: &str = "@rpath/";
+
+/// Load every `@rpath` dependency of the library located at `path` that can be found in `rpaths`.
+///
+/// Dependencies are loaded depth first, so every library in `loaded` comes after the libraries it depends on.
+/// Dependencies that cannot be found in `rpaths` are skipped and left for the dynamic loader to resolve.
+/// # Safety
+/// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+pub(crate) unsafe fn load_rpath_dependencies(
+    path: &Path,
+    rpaths: &[PathBuf],
+    loaded: &mut Vec<(PathBuf, Library)>,
+) -> Result<(), SharedLibError> {
+    let load_failure = |msg: String| SharedLibError::load_failure(path, msg);
+    let data = std::fs::read(path).map_err(|e| load_failure(e.to_string()))?;
+    let install_names = dylib_dependencies(&data)
+        .map_err(|e| load_failure(format!("Failed to read Mach-O load commands. {}", e)))?;
+
+    for install_name in install_names {
+        let Some(dep_path) = resolve_rpath(&install_name, rpaths) else {
+            continue;
+        };
+        if loaded.iter().any(|(loaded_path, _)| *loaded_path == dep_path) {
+            continue;
+        }
+        load_rpath_dependencies(&dep_path, rpaths, loaded)?;
+        let lib = Library::open(dep_path.as_os_str(), crate::LoadFlags::default()).map_err(|e| SharedLibError::load_failure(&dep_path, e))?;
+        loaded.push((dep_path, lib));
+    }
+    Ok(())
+}
+
+/// Resolve an `@rpath/` install name against the given directories.
+///
+/// Returns the first existing candidate, or `None` if the install name is not `@rpath` relative
+/// or the dependency cannot be found in any of the directories.
+pub(crate) fn resolve_rpath(install_name: &str, rpaths: &[PathBuf]) -> Option<PathBuf> {
+    let relative = install_name.strip_prefix(RPATH_PREFIX)?;
+    rpaths
+        .iter()
+        .map(|dir| dir.join(relative))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Get the install names of all libraries the Mach-O image (thin or universal) depends on.
+fn dylib_dependencies(data: &[u8]) -> object::Result<Vec<String>> {
+    let mut names = Vec::new();
+    match FileKind::parse(data)? {
+        FileKind::MachO32 => collect_dylibs::<MachHeader32<Endianness>>(data, &mut names)?,
+        FileKind::MachO64 => collect_dylibs::<MachHeader64<Endianness>>(data, &mut names)?,
+        FileKind::MachOFat32 => {
+            for arch in MachOFatFile32::parse(data)?.arches() {
+                collect_fat_arch(arch.data(data)?, &mut names)?;
+            }
+        }
+        FileKind::MachOFat64 => {
+            for arch in MachOFatFile64::parse(data)?.arches() {
+                collect_fat_arch(arch.data(data)?, &mut names)?;
+            }
+        }
+        // Not a Mach-O image, loading it will fail with a more descriptive error.
+        _ => {}
+    }
+    Ok(names)
+}
+
+fn collect_fat_arch(data: &[u8], names: &mut Vec<String>) -> object::Result<()> {
+    match FileKind::parse(data)? {
+        FileKind::MachO32 => collect_dylibs::<MachHeader32<Endianness>>(data, names),
+        FileKind::MachO64 => collect_dylibs::<MachHeader64<Endianness>>(data, names),
+        _ => Ok(()),
+    }
+}
+
+fn collect_dylibs<Mach: MachHeader<Endian = Endianness>>(
+    data: &[u8],
+    names: &mut Vec<String>,
+) -> object::Result<()> {
+    let header = Mach::parse(data, 0)?;
+    let endian = header.endian()?;
+    let mut commands = header.load_commands(endian, data, 0)?;
+    while let Some(command) = commands.next()? {
+        if let LoadCommandVariant::Dylib(dylib) = command.variant()? {
+            let name = command.string(endian, dylib.dylib.name)?;
+            let name = String::from_utf8_lossy(name).to_string();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Sharing of loaded libraries between different parts of a program.
+
+use crate::hooks::LibHooks;
+use crate::{EventSink, LibEvent, LibEventKind, LibPath, SharedLib, SharedLibError};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+/// Structure representing a thread-safe cache of loaded libraries, keyed by their canonical path.
+///
+/// Opening a library that is already cached returns the cached library instead of loading it again.
+/// Libraries stay cached until they are evicted, and are unloaded once the last [`Arc`] referring to them is dropped.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let lib = LibManager::global().open(lib_path.clone()).expect("Failed to load shared library");
+///     let same_lib = SharedLib::open_cached(lib_path.clone()).expect("Failed to load shared library");
+///     assert!(std::sync::Arc::ptr_eq(&lib, &same_lib));
+///     LibManager::global().evict(&lib_path);
+/// }
+/// ```
+#[derive(Default)]
+pub struct LibManager {
+    libs: Mutex<HashMap<PathBuf, Arc<SharedLib>>>,
+    // Callbacks attached to every library loaded by the manager.
+    hooks: Mutex<LibHooks>,
+}
+impl LibManager {
+    /// Create a new empty library cache.
+    pub fn new() -> LibManager {
+        LibManager::default()
+    }
+    /// Get the process-wide library cache used by [`SharedLib::open_cached`].
+    pub fn global() -> &'static LibManager {
+        static GLOBAL: OnceLock<LibManager> = OnceLock::new();
+        GLOBAL.get_or_init(LibManager::new)
+    }
+    /// Get the cached library with the given path, loading and caching it if it is not cached yet.
+    ///
+    /// The library is loaded without holding the cache lock, so if two threads open the same library at once,
+    /// both load it and the library cached by the first one is returned to both.
+    ///
+    /// The callbacks registered with [`LibManager::on_load`] are run once the library is cached, without holding the cache lock,
+    /// and the sinks subscribed with [`LibManager::subscribe`] receive [`LibEventKind::Loaded`] or [`LibEventKind::LoadFailed`].
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn open(&self, lib_path: LibPath) -> Result<Arc<SharedLib>, SharedLibError> {
+        let key = cache_key(&lib_path)?;
+        if let Some(lib) = self.lock().get(&key) {
+            return Ok(Arc::clone(lib));
+        }
+        let mut lib = match SharedLib::new(lib_path.clone()) {
+            Ok(lib) => lib,
+            Err(e) => {
+                // Not holding the lock while the sinks run.
+                let hooks = self.hooks().clone();
+                hooks.emit(&lib_path, || LibEventKind::LoadFailed(e.clone()));
+                return Err(e);
+            }
+        };
+        let mut libs = self.lock();
+        if let Some(cached) = libs.get(&key) {
+            return Ok(Arc::clone(cached));
+        }
+        // Attached only once the library is sure to be cached, so the callbacks do not run for a library loaded twice.
+        lib.hooks = self.hooks().clone();
+        let lib = Arc::clone(libs.entry(key).or_insert(Arc::new(lib)));
+        drop(libs);
+        lib.hooks.run_on_load(&lib);
+        lib.hooks.emit(&lib_path, || LibEventKind::Loaded);
+        Ok(lib)
+    }
+    /// Get the cached library with the given path, without loading it.
+    pub fn get(&self, lib_path: &LibPath) -> Option<Arc<SharedLib>> {
+        self.lock().get(&cache_key(lib_path).ok()?).cloned()
+    }
+    /// Remove the library with the given path from the cache, returning it if it was cached.
+    ///
+    /// The library stays loaded until the returned [`Arc`] and all other references to it are dropped.
+    pub fn evict(&self, lib_path: &LibPath) -> Option<Arc<SharedLib>> {
+        self.lock().remove(&cache_key(lib_path).ok()?)
+    }
+    /// Remove all libraries from the cache.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+    /// Get the number of cached libraries.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+    /// Check if no library is cached.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+    /// Register a callback run every time the manager loads a library, e.g. to log an audit event.
+    ///
+    /// The callbacks registered with the manager are attached to the libraries it loads afterwards, like the ones registered with
+    /// [`SharedLib::on_load`], [`SharedLib::before_unload`] and [`SharedLib::after_reload`], so they are also run when
+    /// those libraries are reloaded or unloaded. Libraries already cached are not affected.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     LibManager::global().on_load(|lib| println!("Loaded {}", lib.lib_path()));
+    ///     LibManager::global().before_unload(|lib| println!("Unloading {}", lib.lib_path()));
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = LibManager::global().open(lib_path.clone()).expect("Failed to load shared library");
+    ///     LibManager::global().evict(&lib_path);
+    ///     drop(lib);
+    /// }
+    /// ```
+    pub fn on_load(&self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.hooks().on_load(hook);
+    }
+    /// Register a callback run before a library loaded by the manager afterwards is unloaded, see [`SharedLib::before_unload`].
+    pub fn before_unload(&self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.hooks().before_unload(hook);
+    }
+    /// Register a callback run after a library loaded by the manager afterwards is reloaded, see [`SharedLib::after_reload`].
+    pub fn after_reload(&self, hook: impl Fn(&SharedLib) + Send + Sync + 'static) {
+        self.hooks().after_reload(hook);
+    }
+    /// Subscribe `sink` to the events of the libraries the manager loads afterwards, see [`EventSink`].
+    ///
+    /// The sink receives the loads and the failed loads of the manager, and like the callbacks of [`LibManager::on_load`],
+    /// it is attached to the libraries it loads, so it also receives their resolved symbols, reloads and unloads.
+    pub fn subscribe(&self, sink: impl EventSink + 'static) {
+        self.hooks().subscribe(sink);
+    }
+    /// Subscribe a channel to the events of the libraries the manager loads afterwards like [`LibManager::subscribe`],
+    /// returning its receiver.
+    pub fn events(&self) -> Receiver<LibEvent> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.subscribe(sender);
+        receiver
+    }
+    fn hooks(&self) -> MutexGuard<'_, LibHooks> {
+        self.hooks.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    fn lock(&self) -> MutexGuard<'_, HashMap<PathBuf, Arc<SharedLib>>> {
+        self.libs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SharedLib {
+    /// Get the library with the given path from the process-wide [`LibManager::global`] cache,
+    /// loading and caching it if it is not cached yet.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn open_cached(lib_path: LibPath) -> Result<Arc<SharedLib>, SharedLibError> {
+        LibManager::global().open(lib_path)
+    }
+}
+
+/// Get the canonical path of the library, or its path as is if the file does not exist,
+/// e.g. for libraries without a directory that are found by the system search.
+fn cache_key(lib_path: &LibPath) -> Result<PathBuf, SharedLibError> {
+    let path = lib_path.path()?;
+    Ok(std::fs::canonicalize(&path).unwrap_or(path))
+}
@@ -0,0 +1,234 @@
+//! Discovery of plugins described by `plugin.toml` manifests.
+//!
+//! Every plugin lives in its own directory, next to a manifest describing it:
+//! ```toml
+//! name = "greeter"
+//! version = "0.1.0"
+//! # Library name without the platform specific extension and prefix, defaults to the plugin name.
+//! library = "greeter"
+//! # Constructor of the plugin, defaults to `_plugin_create`.
+//! entry = "_plugin_create"
+//! # Plugins that have to be loaded before this one.
+//! dependencies = ["logger"]
+//! # Host API version the plugin targets, also compatible with later host API versions of the same major version.
+//! host_api = "1.2"
+//! ```
+
+use crate::plugin::PluginRegistry;
+use crate::{LibPath, SharedLibError, PLUGIN_CREATE_SYMBOL};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// File name of a plugin manifest.
+pub const MANIFEST_FILE_NAME: &str = "plugin.toml";
+
+/// Structure representing a `plugin.toml` manifest describing a plugin library next to it.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginManifest {
+    /// Name of the plugin, which has to match [`Plugin::name`](crate::Plugin::name).
+    pub name: String,
+    /// Version of the plugin, which has to match [`Plugin::version`](crate::Plugin::version).
+    pub version: String,
+    /// Name of the library without the platform specific extension and prefix, the plugin name if not set.
+    pub library: Option<String>,
+    /// Name of the plugin constructor, [`PLUGIN_CREATE_SYMBOL`] if not set.
+    pub entry: Option<String>,
+    /// Names of the plugins that have to be loaded before this one.
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Semver requirement on the API version of the host the plugin targets, e.g. `1.2` for `^1.2`,
+    /// see [`PluginRegistry::with_host_api_version`]. The plugin is compatible with any host if not set.
+    pub host_api: Option<String>,
+}
+impl PluginManifest {
+    /// Read and validate the manifest file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<PluginManifest, SharedLibError> {
+        let path = path.as_ref();
+        let invalid = |msg: String| invalid_manifest(path, msg);
+        let contents = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let manifest: PluginManifest = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        if manifest.name.is_empty() {
+            return Err(invalid("The plugin name is empty.".into()));
+        }
+        if manifest.lib_name().is_empty() || manifest.entry_symbol().is_empty() {
+            return Err(invalid("The library name and entry symbol cannot be empty.".into()));
+        }
+        manifest.host_api_req().map_err(|e| invalid(format!("Invalid host API version. {}", e)))?;
+        Ok(manifest)
+    }
+    /// Check if the plugin is compatible with the given host API version, following the semver rules of its
+    /// [`PluginManifest::host_api`] requirement, e.g. `1.2` is compatible with `1.5.0` but not with `2.0.0`.
+    ///
+    /// Returns `false` if the requirement is not valid.
+    pub fn is_compatible_with(&self, host_api_version: &semver::Version) -> bool {
+        match self.host_api_req() {
+            Ok(Some(req)) => req.matches(host_api_version),
+            Ok(None) => true,
+            Err(_) => false,
+        }
+    }
+    fn host_api_req(&self) -> Result<Option<semver::VersionReq>, semver::Error> {
+        self.host_api.as_deref().map(semver::VersionReq::parse).transpose()
+    }
+    /// Get the name of the library without the platform specific extension and prefix.
+    pub fn lib_name(&self) -> &str {
+        self.library.as_deref().unwrap_or(&self.name)
+    }
+    /// Get the name of the plugin constructor.
+    pub fn entry_symbol(&self) -> &str {
+        self.entry.as_deref().unwrap_or(PLUGIN_CREATE_SYMBOL)
+    }
+}
+
+impl PluginRegistry {
+    /// Set the API version of the host, which [`PluginRegistry::discover`] checks the [`PluginManifest::host_api`]
+    /// of every plugin against, e.g. `env!("CARGO_PKG_VERSION")` of the crate defining the API plugins are built against.
+    ///
+    /// Discovering an incompatible plugin fails with [`SharedLibError::IncompatibleVersion`] by default,
+    /// see [`PluginRegistry::skip_incompatible`] to skip it instead.
+    /// # Panics
+    /// Panics if `version` is not a valid semver version.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// let mut registry = PluginRegistry::new().with_host_api_version("1.5.0").skip_incompatible(true);
+    /// unsafe {
+    ///     let names = registry.discover("path/to/plugins").expect("Failed to discover plugins");
+    /// }
+    /// ```
+    pub fn with_host_api_version(mut self, version: &str) -> PluginRegistry {
+        let version = semver::Version::parse(version).unwrap_or_else(|e| panic!("Invalid host API version '{}'. {}", version, e));
+        self.host_api_version = Some(version);
+        self
+    }
+    /// Skip the plugins incompatible with the host API version in [`PluginRegistry::discover`], together with the plugins
+    /// depending on them, instead of failing with [`SharedLibError::IncompatibleVersion`].
+    pub fn skip_incompatible(mut self, skip_incompatible: bool) -> PluginRegistry {
+        self.skip_incompatible = skip_incompatible;
+        self
+    }
+    /// Get the API version of the host the discovered plugins are checked against, if set.
+    pub fn host_api_version(&self) -> Option<&semver::Version> {
+        self.host_api_version.as_ref()
+    }
+    /// Load every plugin described by a manifest in `dir` or in one of its immediate subdirectories.
+    ///
+    /// All manifests are read and validated before any plugin is loaded. Plugins are loaded after their dependencies,
+    /// which have to be described by one of the manifests or already be loaded into the registry,
+    /// and otherwise in the order of their directory names. If the host API version is set with
+    /// [`PluginRegistry::with_host_api_version`], the plugins are checked against it before any of them is loaded.
+    /// After loading, the name and version of each plugin
+    /// are checked against its manifest. Returns the names of the loaded plugins in load order. If loading one
+    /// of them fails, the plugins loaded before it stay loaded.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries and calls their plugin constructors, which is generally unsafe as it is a foregin code.
+    /// See [`PluginRegistry::load_with_entry`] for the requirements on the constructors.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// let mut registry = PluginRegistry::new();
+    /// unsafe {
+    ///     let names = registry.discover("path/to/plugins").expect("Failed to discover plugins");
+    /// }
+    /// ```
+    pub unsafe fn discover(&mut self, dir: impl AsRef<Path>) -> Result<Vec<String>, SharedLibError> {
+        let mut manifests = Vec::new();
+        for path in manifest_paths(dir.as_ref())? {
+            let manifest = PluginManifest::from_file(&path)?;
+            if manifests.iter().any(|(_, other): &(PathBuf, PluginManifest)| other.name == manifest.name) {
+                return Err(invalid_manifest(&path, format!("Another manifest describes a plugin named '{}'.", manifest.name)));
+            }
+            manifests.push((path, manifest));
+        }
+        let manifests = self.compatible(manifests)?;
+
+        let mut names = Vec::new();
+        for (path, manifest) in self.dependency_order(manifests)? {
+            let dir_path = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            let loaded = self.create(LibPath::new(dir_path, manifest.lib_name().to_owned()), manifest.entry_symbol())?;
+            let (name, version) = (loaded.plugin.name(), loaded.plugin.version());
+            if name != manifest.name || version != manifest.version {
+                return Err(invalid_manifest(&path, format!(
+                    "The manifest describes plugin '{}' {}, but the library created plugin '{}' {}.",
+                    manifest.name, manifest.version, name, version
+                )));
+            }
+            self.insert(loaded)?;
+            names.push(manifest.name);
+        }
+        Ok(names)
+    }
+    /// Check the manifests against the host API version, removing the incompatible plugins and the plugins depending on them
+    /// if they are skipped.
+    fn compatible(&self, mut manifests: Vec<(PathBuf, PluginManifest)>) -> Result<Vec<(PathBuf, PluginManifest)>, SharedLibError> {
+        let Some(host_api_version) = &self.host_api_version else {
+            return Ok(manifests);
+        };
+        let mut skipped = Vec::new();
+        for (_, manifest) in &manifests {
+            if manifest.is_compatible_with(host_api_version) {
+                continue;
+            }
+            if !self.skip_incompatible {
+                return Err(SharedLibError::IncompatibleVersion {
+                    plugin: manifest.name.clone(),
+                    required: manifest.host_api.clone().unwrap_or_default(),
+                    host: host_api_version.to_string(),
+                });
+            }
+            crate::trace::event!(info, plugin = %manifest.name, required = ?manifest.host_api, host = %host_api_version, "Skipped incompatible plugin");
+            skipped.push(manifest.name.clone());
+        }
+        while let Some(index) = manifests.iter().position(|(_, manifest)| {
+            skipped.contains(&manifest.name) || manifest.dependencies.iter().any(|name| skipped.contains(name))
+        }) {
+            let (_, manifest) = manifests.remove(index);
+            if !skipped.contains(&manifest.name) {
+                skipped.push(manifest.name);
+            }
+        }
+        Ok(manifests)
+    }
+    /// Sort the manifests so every plugin comes after its dependencies, keeping their order otherwise.
+    fn dependency_order(&self, mut pending: Vec<(PathBuf, PluginManifest)>) -> Result<Vec<(PathBuf, PluginManifest)>, SharedLibError> {
+        let mut ordered: Vec<(PathBuf, PluginManifest)> = Vec::new();
+        while !pending.is_empty() {
+            let is_available = |name: &String| self.get(name).is_some() || ordered.iter().any(|(_, manifest)| manifest.name == *name);
+            let Some(index) = pending.iter().position(|(_, manifest)| manifest.dependencies.iter().all(is_available)) else {
+                let is_pending = |name: &String| pending.iter().any(|(_, manifest)| manifest.name == *name);
+                let missing = pending.iter().find_map(|(path, manifest)| {
+                    let name = manifest.dependencies.iter().find(|name| !is_available(name) && !is_pending(name))?;
+                    Some((path, name))
+                });
+                return Err(match missing {
+                    Some((path, name)) => invalid_manifest(
+                        path,
+                        format!("The plugin depends on '{}', which is neither loaded nor described by a manifest.", name),
+                    ),
+                    None => invalid_manifest(&pending[0].0, "The plugin is part of or depends on a dependency cycle.".into()),
+                });
+            };
+            ordered.push(pending.remove(index));
+        }
+        Ok(ordered)
+    }
+}
+
+/// Get the paths of the manifests in `dir` and its immediate subdirectories, sorted by directory.
+fn manifest_paths(dir: &Path) -> Result<Vec<PathBuf>, SharedLibError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| SharedLibError::LoadFailure {
+        path: dir.to_path_buf(),
+        msg: format!("Failed to read the plugin directory. {}", e),
+        raw_os_error: e.raw_os_error(),
+        source: None,
+    })?;
+    let mut dirs: Vec<PathBuf> = entries.filter_map(Result::ok).map(|entry| entry.path()).filter(|path| path.is_dir()).collect();
+    dirs.sort();
+    dirs.insert(0, dir.to_path_buf());
+    Ok(dirs.into_iter().map(|dir| dir.join(MANIFEST_FILE_NAME)).filter(|path| path.is_file()).collect())
+}
+
+fn invalid_manifest(path: &Path, msg: String) -> SharedLibError {
+    SharedLibError::InvalidManifest { path: path.to_string_lossy().to_string(), msg }
+}
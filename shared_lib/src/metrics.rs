@@ -0,0 +1,110 @@
+//! Counting and timing of the calls of the functions of a library, see [`LoadFlags::with_call_metrics`](crate::LoadFlags::with_call_metrics).
+
+use crate::{SharedLib, SharedLibFn};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Statistics of the calls of a function of a library, see [`SharedLib::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CallStats {
+    /// Number of times the function was called.
+    pub calls: u64,
+    /// Time spent in all calls of the function.
+    pub total: Duration,
+    /// Time spent in the last call of the function.
+    pub last: Duration,
+}
+impl CallStats {
+    /// Get the average time spent in a call of the function, zero if it was never called.
+    pub fn average(&self) -> Duration {
+        match self.calls {
+            0 => Duration::ZERO,
+            calls => Duration::from_nanos((self.total.as_nanos() / u128::from(calls)) as u64),
+        }
+    }
+}
+
+/// Counters of the calls of a function, updated by every [`SharedLibFn`] looked up by its name.
+#[derive(Default)]
+pub(crate) struct SymbolMetrics {
+    calls: AtomicU64,
+    total_nanos: AtomicU64,
+    last_nanos: AtomicU64,
+}
+impl SymbolMetrics {
+    fn record(&self, elapsed: Duration) {
+        let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.last_nanos.store(nanos, Ordering::Relaxed);
+    }
+    fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.total_nanos.store(0, Ordering::Relaxed);
+        self.last_nanos.store(0, Ordering::Relaxed);
+    }
+    pub(crate) fn stats(&self) -> CallStats {
+        CallStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            total: Duration::from_nanos(self.total_nanos.load(Ordering::Relaxed)),
+            last: Duration::from_nanos(self.last_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<Fn> SharedLibFn<'_, Fn> {
+    /// Make `call`, recording its duration if the function is measured.
+    pub(crate) fn measured<R>(&self, call: impl FnOnce() -> R) -> R {
+        let Some(metrics) = &self.metrics else {
+            return call();
+        };
+        let start = Instant::now();
+        let result = call();
+        metrics.record(start.elapsed());
+        result
+    }
+}
+
+impl SharedLib {
+    /// Attach the counters of `fn_name` to `func`, if the library was loaded with [`LoadFlags::with_call_metrics`](crate::LoadFlags::with_call_metrics).
+    pub(crate) fn with_metrics<'a, Fn>(&self, fn_name: &str, mut func: SharedLibFn<'a, Fn>) -> SharedLibFn<'a, Fn> {
+        if self.flags.is_call_metrics() {
+            let mut metrics = self.metrics.lock().unwrap_or_else(|e| e.into_inner());
+            func.metrics = Some(metrics.entry(fn_name.to_owned()).or_default().clone());
+        }
+        func
+    }
+    /// Get the statistics of the calls of every function looked up from the library by name, e.g. to find the one
+    /// taking the most time.
+    ///
+    /// Calls are only counted if the library was loaded with [`LoadFlags::with_call_metrics`](crate::LoadFlags::with_call_metrics),
+    /// and only through the functions returned by [`SharedLib::get_fn`], [`SharedLib::get_fn_owned`] and [`SharedLib::get_fn_cached`],
+    /// while plain function pointers copied out of them are not measured. The statistics are kept when the library is reloaded,
+    /// see [`SharedLib::reset_stats`].
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_call_metrics(true)).expect("Failed to load shared library");
+    ///     let update_fn = lib.get_fn::<extern "C" fn(f32)>("update").expect("Failed to get 'update' function from shared library");
+    ///     update_fn.run(0.016);
+    ///     for (name, stats) in lib.stats() {
+    ///         println!("{}: {} calls, {:?} on average, {:?} last", name, stats.calls, stats.average(), stats.last);
+    ///     }
+    /// }
+    /// ```
+    pub fn stats(&self) -> HashMap<String, CallStats> {
+        let metrics = self.metrics.lock().unwrap_or_else(|e| e.into_inner());
+        metrics.iter().map(|(name, metrics)| (name.clone(), metrics.stats())).collect()
+    }
+    /// Reset the statistics of the calls of every function of the library, see [`SharedLib::stats`].
+    ///
+    /// Functions looked up before are still measured, starting from zero.
+    pub fn reset_stats(&self) {
+        let metrics = self.metrics.lock().unwrap_or_else(|e| e.into_inner());
+        metrics.values().for_each(|metrics| metrics.reset());
+    }
+}
@@ -0,0 +1,100 @@
+//! Abstraction over where functions are looked up, so code using libraries can be tested with a [`MockLib`].
+
+use crate::metrics::SymbolMetrics;
+use crate::{FnSignature, FnSymbol, SharedLib, SharedLibError, SharedLibFn};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Source of the functions of a library, implemented by [`SharedLib`] and by [`MockLib`] for tests.
+///
+/// Code taking a `&impl SymbolSource` instead of a [`SharedLib`] can be unit tested without building a real library.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+///
+/// unsafe fn add_all(lib: &impl SymbolSource, values: &[usize]) -> Result<usize, SharedLibError> {
+///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add")?;
+///     Ok(values.iter().fold(0, |sum, value| add_fn.run(sum, *value)))
+/// }
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+///     assert_eq!(add_all(&lib, &[1, 2, 3]).unwrap(), 6);
+///
+///     let mock = MockLib::new("shared_library").with_fn::<fn(usize, usize) -> usize>("add", |a, b| a + b);
+///     assert_eq!(add_all(&mock, &[1, 2, 3]).unwrap(), 6);
+///     assert_eq!(mock.calls("add"), 3);
+/// }
+/// ```
+pub trait SymbolSource {
+    /// Get a function by its name, like [`SharedLib::get_fn`].
+    /// # Safety
+    /// This function is unsafe because the function may be loaded from a shared library, which is generally unsafe as it is a foregin code.
+    unsafe fn get_fn<T: FnSignature + 'static>(&self, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError>;
+}
+impl SymbolSource for SharedLib {
+    unsafe fn get_fn<T: FnSignature + 'static>(&self, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        SharedLib::get_fn(self, fn_name)
+    }
+}
+
+/// Structure representing a fake library for tests, whose functions are Rust functions or closures registered by name.
+///
+/// Functions are stored as function pointers of the type they are looked up with, so closures registered with
+/// [`MockLib::with_fn`] cannot capture anything, and state they share with the test has to be kept e.g. in a static.
+/// The calls of every function are counted, see [`MockLib::calls`].
+pub struct MockLib {
+    lib_name: String,
+    fns: HashMap<String, MockFn>,
+}
+struct MockFn {
+    func: Box<dyn Any + Send + Sync>,
+    metrics: Arc<SymbolMetrics>,
+}
+impl MockLib {
+    /// Create a new mock library without any functions, named `lib_name` in the errors it returns.
+    pub fn new(lib_name: impl Into<String>) -> MockLib {
+        MockLib { lib_name: lib_name.into(), fns: HashMap::new() }
+    }
+    /// Register the function `func` as `fn_name`, replacing the function registered with the same name before.
+    ///
+    /// The function has to be given as the function pointer type it is looked up with, e.g. `with_fn::<fn(usize, usize) -> usize>("add", |a, b| a + b)`,
+    /// otherwise looking it up fails with [`SharedLibError::SignatureMismatch`].
+    pub fn with_fn<T: FnSignature + Send + Sync + 'static>(mut self, fn_name: &str, func: T) -> MockLib {
+        let func = MockFn { func: Box::new(func), metrics: Arc::default() };
+        self.fns.insert(fn_name.to_owned(), func);
+        self
+    }
+    /// Get the name of the mock library.
+    pub fn lib_name(&self) -> &str {
+        &self.lib_name
+    }
+    /// Get the number of times the function `fn_name` was called through the functions returned by [`SymbolSource::get_fn`],
+    /// zero if it is not registered.
+    pub fn calls(&self, fn_name: &str) -> u64 {
+        self.fns.get(fn_name).map_or(0, |func| func.metrics.stats().calls)
+    }
+}
+impl SymbolSource for MockLib {
+    /// Get the function registered as `fn_name`.
+    ///
+    /// Returns [`SharedLibError::SymbolNotFound`] if no function is registered with the name, and
+    /// [`SharedLibError::SignatureMismatch`] if it was registered with a different type than `T`.
+    unsafe fn get_fn<T: FnSignature + 'static>(&self, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        let Some(mock) = self.fns.get(fn_name) else {
+            return Err(SharedLibError::SymbolNotFound {
+                symbol_name: fn_name.to_owned(),
+                lib_name: self.lib_name.clone().into(),
+                msg: "The function is not registered in the mock library.".into(),
+                raw_os_error: None,
+                source: None,
+            });
+        };
+        let Some(func) = mock.func.downcast_ref::<T>() else {
+            return Err(SharedLibError::SignatureMismatch { symbol_name: fn_name.to_owned(), path: self.lib_name.clone(), expected: std::any::type_name::<T>().to_owned() });
+        };
+        Ok(SharedLibFn { symbol: FnSymbol::Resolved(*func, std::marker::PhantomData), metrics: Some(Arc::clone(&mock.metrics)) })
+    }
+}
@@ -0,0 +1,334 @@
+//! Information about where a loaded library is mapped in memory, e.g. to symbolicate crash reports.
+
+use crate::{SharedLib, SharedLibError};
+use std::path::{Path, PathBuf};
+
+/// Structure representing where a loaded library is mapped in the address space of the process.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ModuleInfo {
+    /// Address the library is loaded at, i.e. the address of its first mapped byte.
+    pub base_address: usize,
+    /// Path of the library file, as resolved by the dynamic loader.
+    pub path: PathBuf,
+    /// Size of the address range the library is mapped to, if the platform reports it.
+    pub size: Option<usize>,
+}
+
+impl SharedLib {
+    /// Get where the library is mapped in memory and which file it was loaded from.
+    ///
+    /// The size is reported on Linux with glibc and on Windows, but not on MacOS. Other platforms return
+    /// [`SharedLibError::Unsupported`]. For [`SharedLib::this`], the information describes the main executable.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    /// let lib = unsafe { SharedLib::new(lib_path).expect("Failed to load shared library") };
+    /// let info = lib.module_info().expect("Failed to get module information");
+    /// println!("{} is loaded at {:#x}", info.path.display(), info.base_address);
+    /// ```
+    pub fn module_info(&self) -> Result<ModuleInfo, SharedLibError> {
+        self.library()?;
+        if cfg!(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows))) {
+            return Err(SharedLibError::Unsupported("Getting module information".into()));
+        }
+        let path = match self.lib_path.lib_name.is_empty() {
+            true => None,
+            false => Some(self.lib_path.path()?),
+        };
+        // Safety: the library stays loaded for the duration of the query, since `self` is borrowed.
+        unsafe { query_module(path.as_deref()) }.map_err(|msg| SharedLibError::InspectFailure { path: self.lib_path.lossy_path(), msg })
+    }
+}
+
+/// Get where every module loaded in the process is mapped in memory and which file it was loaded from, including the main executable,
+/// e.g. for diagnostics or to detect two versions of the same library loaded from different paths.
+///
+/// The modules are listed in the order reported by the dynamic loader, starting with the main executable, and on Linux they include
+/// objects without a file, e.g. `linux-vdso.so.1`. The same platforms are supported as by [`SharedLib::module_info`], which also report the sizes.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// for module in loaded_libraries().expect("Failed to enumerate the loaded libraries") {
+///     println!("{:#x} {}", module.base_address, module.path.display());
+/// }
+/// ```
+pub fn loaded_libraries() -> Result<Vec<ModuleInfo>, SharedLibError> {
+    if cfg!(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows))) {
+        return Err(SharedLibError::Unsupported("Getting module information".into()));
+    }
+    let path = || std::env::current_exe().unwrap_or_default().to_string_lossy().to_string();
+    // Safety: only the information of the modules is read, skipping the ones unloaded while they are enumerated.
+    unsafe { enumerate_modules() }.map_err(|msg| SharedLibError::InspectFailure { path: path(), msg })
+}
+
+/// The parts of `link.h` used to find where the loaded objects of the process are mapped.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+mod phdr {
+    use std::ffi::{c_char, c_int, c_void};
+
+    #[cfg(target_pointer_width = "64")]
+    #[repr(C)]
+    pub(super) struct ProgramHeader {
+        p_type: u32,
+        p_flags: u32,
+        p_offset: u64,
+        p_vaddr: u64,
+        p_paddr: u64,
+        p_filesz: u64,
+        p_memsz: u64,
+        p_align: u64,
+    }
+    #[cfg(target_pointer_width = "32")]
+    #[repr(C)]
+    pub(super) struct ProgramHeader {
+        p_type: u32,
+        p_offset: u32,
+        p_vaddr: u32,
+        p_paddr: u32,
+        p_filesz: u32,
+        p_memsz: u32,
+        p_flags: u32,
+        p_align: u32,
+    }
+    #[repr(C)]
+    pub(super) struct PhdrInfo {
+        pub(super) dlpi_addr: usize,
+        pub(super) dlpi_name: *const c_char,
+        dlpi_phdr: *const ProgramHeader,
+        dlpi_phnum: u16,
+    }
+    extern "C" {
+        pub(super) fn dl_iterate_phdr(callback: unsafe extern "C" fn(*mut PhdrInfo, usize, *mut c_void) -> c_int, data: *mut c_void) -> c_int;
+    }
+    const PT_LOAD: u32 = 1;
+
+    /// Get the start address and the size of the address range of the loadable segments of the object.
+    pub(super) unsafe fn loaded_range(info: &PhdrInfo) -> Option<(usize, usize)> {
+        let headers = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum.into());
+        let segments = headers.iter().filter(|header| header.p_type == PT_LOAD);
+        let start = segments.clone().map(|header| header.p_vaddr as usize).min()?;
+        let end = segments.map(|header| (header.p_vaddr + header.p_memsz) as usize).max()?;
+        Some((info.dlpi_addr + start, end - start))
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+unsafe fn query_module(path: Option<&Path>) -> Result<ModuleInfo, String> {
+    use crate::dl::{last_error, NoLoadHandle};
+    use phdr::{dl_iterate_phdr, loaded_range, PhdrInfo};
+    use std::ffi::{c_char, c_int, c_void, CStr};
+
+    #[repr(C)]
+    struct LinkMap {
+        l_addr: usize,
+        l_name: *const c_char,
+    }
+    struct Search {
+        link_map: *const LinkMap,
+        range: Option<(usize, usize)>,
+    }
+    extern "C" {
+        fn dlinfo(handle: *mut c_void, request: c_int, info: *mut c_void) -> c_int;
+    }
+    const RTLD_DI_LINKMAP: c_int = 2;
+
+    /// Find the address range of the loadable segments of the object described by the link map.
+    unsafe extern "C" fn find_range(info: *mut PhdrInfo, _size: usize, data: *mut c_void) -> c_int {
+        let (info, search) = (&*info, &mut *data.cast::<Search>());
+        let link_map = &*search.link_map;
+        if info.dlpi_addr != link_map.l_addr || CStr::from_ptr(info.dlpi_name) != CStr::from_ptr(link_map.l_name) {
+            return 0;
+        }
+        search.range = loaded_range(info);
+        1
+    }
+
+    let handle = NoLoadHandle::open(path)?;
+    let mut link_map: *const LinkMap = std::ptr::null();
+    if dlinfo(handle.0, RTLD_DI_LINKMAP, (&mut link_map as *mut *const LinkMap).cast()) != 0 || link_map.is_null() {
+        return Err(format!("Failed to get the link map of the library. {}", last_error()));
+    }
+    let mut search = Search { link_map, range: None };
+    dl_iterate_phdr(find_range, (&mut search as *mut Search).cast());
+    let (base_address, size) = search.range.ok_or("Failed to find the loaded segments of the library.")?;
+    let name = CStr::from_ptr((*link_map).l_name);
+    let path = match name.is_empty() {
+        // The main program has no name, see `SharedLib::this`.
+        true => std::env::current_exe().map_err(|e| e.to_string())?,
+        false => PathBuf::from(name.to_string_lossy().to_string()),
+    };
+    Ok(ModuleInfo { base_address, path, size: Some(size) })
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+unsafe fn enumerate_modules() -> Result<Vec<ModuleInfo>, String> {
+    use phdr::{dl_iterate_phdr, loaded_range, PhdrInfo};
+    use std::ffi::{c_int, c_void, CStr};
+
+    /// Collect the name and the address range of every object with loadable segments.
+    unsafe extern "C" fn collect(info: *mut PhdrInfo, _size: usize, data: *mut c_void) -> c_int {
+        let (info, modules) = (&*info, &mut *data.cast::<Vec<(String, usize, usize)>>());
+        if let (false, Some((base_address, size))) = (info.dlpi_name.is_null(), loaded_range(info)) {
+            modules.push((CStr::from_ptr(info.dlpi_name).to_string_lossy().to_string(), base_address, size));
+        }
+        0
+    }
+
+    let mut modules: Vec<(String, usize, usize)> = Vec::new();
+    dl_iterate_phdr(collect, (&mut modules as *mut Vec<(String, usize, usize)>).cast());
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let modules = modules.into_iter().map(|(name, base_address, size)| {
+        // The main program has no name, like in `query_module`.
+        let path = if name.is_empty() { exe.clone() } else { PathBuf::from(name) };
+        ModuleInfo { base_address, path, size: Some(size) }
+    });
+    Ok(modules.collect())
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn query_module(path: Option<&Path>) -> Result<ModuleInfo, String> {
+    use crate::dl::NoLoadHandle;
+    use std::ffi::{c_char, c_void, CStr};
+
+    extern "C" {
+        fn _dyld_image_count() -> u32;
+        fn _dyld_get_image_header(image_index: u32) -> *const c_void;
+        fn _dyld_get_image_name(image_index: u32) -> *const c_char;
+    }
+
+    let handle = NoLoadHandle::open(path)?;
+    // The main program is always the first image.
+    let start = if path.is_none() { 0 } else { 1 };
+    for index in start.._dyld_image_count() {
+        let name = _dyld_get_image_name(index);
+        if name.is_null() {
+            continue;
+        }
+        let path = Path::new(std::ffi::OsStr::from_encoded_bytes_unchecked(CStr::from_ptr(name).to_bytes()));
+        let is_same = index == 0 || NoLoadHandle::open(Some(path)).is_ok_and(|image| image.0 == handle.0);
+        if is_same {
+            let base_address = _dyld_get_image_header(index) as usize;
+            return Ok(ModuleInfo { base_address, path: path.to_path_buf(), size: None });
+        }
+    }
+    Err("Failed to find the library in the loaded images.".into())
+}
+
+#[cfg(target_os = "macos")]
+unsafe fn enumerate_modules() -> Result<Vec<ModuleInfo>, String> {
+    use std::ffi::{c_char, c_void, CStr};
+
+    extern "C" {
+        fn _dyld_image_count() -> u32;
+        fn _dyld_get_image_header(image_index: u32) -> *const c_void;
+        fn _dyld_get_image_name(image_index: u32) -> *const c_char;
+    }
+
+    let mut modules = Vec::new();
+    for index in 0.._dyld_image_count() {
+        let (header, name) = (_dyld_get_image_header(index), _dyld_get_image_name(index));
+        // Images may be unloaded by other threads while they are enumerated.
+        if header.is_null() || name.is_null() {
+            continue;
+        }
+        let path = PathBuf::from(std::ffi::OsStr::from_encoded_bytes_unchecked(CStr::from_ptr(name).to_bytes()));
+        modules.push(ModuleInfo { base_address: header as usize, path, size: None });
+    }
+    Ok(modules)
+}
+
+/// The parts of `psapi.h` and `libloaderapi.h` used to describe the modules of the process.
+#[cfg(windows)]
+mod psapi {
+    use std::ffi::c_void;
+
+    #[repr(C)]
+    pub(super) struct RawModuleInfo {
+        pub(super) base_of_dll: *mut c_void,
+        pub(super) size_of_image: u32,
+        pub(super) entry_point: *mut c_void,
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        pub(super) fn GetModuleHandleExW(flags: u32, module_name: *const u16, module: *mut *mut c_void) -> i32;
+        pub(super) fn GetModuleFileNameW(module: *mut c_void, filename: *mut u16, size: u32) -> u32;
+        pub(super) fn K32GetModuleInformation(process: *mut c_void, module: *mut c_void, info: *mut RawModuleInfo, size: u32) -> i32;
+        pub(super) fn K32EnumProcessModules(process: *mut c_void, modules: *mut *mut c_void, size: u32, needed: *mut u32) -> i32;
+        pub(super) fn GetCurrentProcess() -> *mut c_void;
+    }
+}
+
+#[cfg(windows)]
+unsafe fn query_module(path: Option<&Path>) -> Result<ModuleInfo, String> {
+    use psapi::GetModuleHandleExW;
+    use std::os::windows::ffi::OsStrExt;
+
+    const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: u32 = 0x2;
+
+    let name: Option<Vec<u16>> = path.map(|path| path.as_os_str().encode_wide().chain(Some(0)).collect());
+    let mut module = std::ptr::null_mut();
+    // The reference count is left unchanged, since the library stays loaded through the handle it was loaded with.
+    let name_ptr = name.as_ref().map_or(std::ptr::null(), |name| name.as_ptr());
+    if GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT, name_ptr, &mut module) == 0 {
+        return Err(format!("Failed to get the handle of the library. {}", std::io::Error::last_os_error()));
+    }
+    describe_module(module)
+}
+
+#[cfg(windows)]
+unsafe fn enumerate_modules() -> Result<Vec<ModuleInfo>, String> {
+    use psapi::{GetCurrentProcess, K32EnumProcessModules};
+
+    let mut modules = Vec::new();
+    let mut needed = 0;
+    // Modules may be loaded by other threads between the calls, so the buffer is grown until all of them fit.
+    loop {
+        let size = (modules.len() * std::mem::size_of::<*mut std::ffi::c_void>()) as u32;
+        if K32EnumProcessModules(GetCurrentProcess(), modules.as_mut_ptr(), size, &mut needed) == 0 {
+            return Err(format!("Failed to enumerate the modules of the process. {}", std::io::Error::last_os_error()));
+        }
+        if needed <= size {
+            break;
+        }
+        modules.resize(needed as usize / std::mem::size_of::<*mut std::ffi::c_void>(), std::ptr::null_mut());
+    }
+    modules.truncate(needed as usize / std::mem::size_of::<*mut std::ffi::c_void>());
+    // Modules unloaded after they were enumerated cannot be described anymore, so they are skipped.
+    Ok(modules.into_iter().filter_map(|module| describe_module(module).ok()).collect())
+}
+
+/// Get where the module with the given handle is mapped and which file it was loaded from.
+#[cfg(windows)]
+unsafe fn describe_module(module: *mut std::ffi::c_void) -> Result<ModuleInfo, String> {
+    use psapi::{GetCurrentProcess, GetModuleFileNameW, K32GetModuleInformation, RawModuleInfo};
+    use std::os::windows::ffi::OsStringExt;
+
+    let last_error = || std::io::Error::last_os_error().to_string();
+    let mut info = RawModuleInfo { base_of_dll: std::ptr::null_mut(), size_of_image: 0, entry_point: std::ptr::null_mut() };
+    if K32GetModuleInformation(GetCurrentProcess(), module, &mut info, std::mem::size_of::<RawModuleInfo>() as u32) == 0 {
+        return Err(format!("Failed to get the module information. {}", last_error()));
+    }
+    let mut filename = vec![0u16; 32 * 1024];
+    let len = GetModuleFileNameW(module, filename.as_mut_ptr(), filename.len() as u32);
+    if len == 0 {
+        return Err(format!("Failed to get the path of the library. {}", last_error()));
+    }
+    filename.truncate(len as usize);
+    Ok(ModuleInfo {
+        base_address: info.base_of_dll as usize,
+        path: std::ffi::OsString::from_wide(&filename).into(),
+        size: Some(info.size_of_image as usize),
+    })
+}
+
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows)))]
+unsafe fn query_module(_path: Option<&Path>) -> Result<ModuleInfo, String> {
+    unreachable!("module information is not supported on this platform")
+}
+
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows)))]
+unsafe fn enumerate_modules() -> Result<Vec<ModuleInfo>, String> {
+    unreachable!("module information is not supported on this platform")
+}
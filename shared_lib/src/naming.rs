@@ -0,0 +1,185 @@
+//! Conventions for the filename of a library, e.g. `libfoo.so` or `foo.so`.
+
+use crate::{LibPath, SharedLib, SharedLibError};
+
+/// Structure representing how a library filename is built from the library name, as `{prefix}{lib_name}{suffix}`.
+///
+/// The default is the platform convention, e.g. `libfoo.so` on Linux, `libfoo.dylib` on MacOS and `foo.dll` on Windows.
+/// Libraries built differently, e.g. plugins without the `lib` prefix or Python extensions, which use `.so` on MacOS,
+/// can be loaded with [`LibPath::with_convention`] or [`SharedLib::new_with_conventions`].
+/// # Example
+/// ```
+/// use shared_lib::*;
+///
+/// let lib_path = LibPath::new_no_path("foo".into()).with_convention(FilenameConvention::new("", ".so"));
+/// assert_eq!(lib_path.filename().unwrap(), "foo.so");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilenameConvention {
+    prefix: String,
+    suffix: String,
+}
+impl FilenameConvention {
+    /// Create a new filename convention from the prefix and suffix (including the `.` of the extension).
+    pub fn new(prefix: impl Into<String>, suffix: impl Into<String>) -> FilenameConvention {
+        FilenameConvention { prefix: prefix.into(), suffix: suffix.into() }
+    }
+    /// Get the platform convention, e.g. `libfoo.so` on Linux, `libfoo.dylib` on MacOS and `foo.dll` on Windows.
+    pub fn platform() -> FilenameConvention {
+        FilenameConvention::new(std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX)
+    }
+    /// Get the platform convention without the prefix, e.g. `foo.so` on Linux and `foo.dylib` on MacOS.
+    pub fn no_prefix() -> FilenameConvention {
+        FilenameConvention::new("", std::env::consts::DLL_SUFFIX)
+    }
+    /// Get the conventions libraries are commonly built with on this platform, most common first.
+    ///
+    /// These are the platform convention and the one without the prefix, followed by
+    /// `libfoo.so` and `foo.so` on MacOS, where bundles and Python extensions use the `.so` extension.
+    pub fn fallbacks() -> Vec<FilenameConvention> {
+        let mut conventions = vec![FilenameConvention::platform()];
+        if !std::env::consts::DLL_PREFIX.is_empty() {
+            conventions.push(FilenameConvention::no_prefix());
+        }
+        if cfg!(target_os = "macos") {
+            conventions.push(FilenameConvention::new("lib", ".so"));
+            conventions.push(FilenameConvention::new("", ".so"));
+        }
+        conventions
+    }
+    /// Get a copy of the convention with the given prefix, e.g. an empty one.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> FilenameConvention {
+        self.prefix = prefix.into();
+        self
+    }
+    /// Get a copy of the convention with the given extension, with or without the leading `.`, e.g. `node`.
+    ///
+    /// An empty extension builds filenames without one.
+    pub fn with_extension(mut self, extension: &str) -> FilenameConvention {
+        self.suffix = match extension.strip_prefix('.').unwrap_or(extension) {
+            "" => String::new(),
+            extension => format!(".{}", extension),
+        };
+        self
+    }
+    /// Get the prefix put before the library name.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+    /// Get the suffix put after the library name, including the `.` of the extension.
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+}
+impl Default for FilenameConvention {
+    fn default() -> FilenameConvention {
+        FilenameConvention::platform()
+    }
+}
+
+/// Operating systems a library filename can be built for with [`LibPath::filename_for`], e.g. to package the libraries
+/// of several platforms on one machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetOs {
+    /// Linux and the other platforms using ELF libraries, e.g. `libfoo.so` and `libfoo.so.1`.
+    Linux,
+    /// Android, e.g. `libfoo.so`. Libraries packaged in an APK must not be versioned, as only `lib*.so` files are extracted.
+    Android,
+    /// MacOS, e.g. `libfoo.dylib` and `libfoo.1.dylib`.
+    MacOs,
+    /// iOS, tvOS, watchOS and visionOS, e.g. `libfoo.dylib`. Libraries are usually shipped as framework bundles there,
+    /// see [`LibPath::framework_path`].
+    Ios,
+    /// Windows, e.g. `foo.dll` and `foo-1.dll`.
+    Windows,
+    /// Emscripten, loading WebAssembly side modules named like ELF libraries, e.g. `libfoo.so`.
+    Emscripten,
+    /// WebAssembly without Emscripten, e.g. `wasm32-unknown-unknown` and WASI, which cannot load libraries,
+    /// so building a filename for it returns [`SharedLibError::Unsupported`].
+    Wasm,
+}
+impl TargetOs {
+    /// Get the operating system the crate is compiled for.
+    pub fn host() -> TargetOs {
+        if cfg!(windows) {
+            TargetOs::Windows
+        } else if cfg!(target_os = "macos") {
+            TargetOs::MacOs
+        } else if cfg!(target_vendor = "apple") {
+            TargetOs::Ios
+        } else if cfg!(target_os = "android") {
+            TargetOs::Android
+        } else if cfg!(target_os = "emscripten") {
+            TargetOs::Emscripten
+        } else if cfg!(target_family = "wasm") {
+            TargetOs::Wasm
+        } else {
+            TargetOs::Linux
+        }
+    }
+    /// Get the filename convention of the operating system, e.g. `libfoo.so` on Linux.
+    pub fn convention(self) -> FilenameConvention {
+        match self {
+            TargetOs::Linux | TargetOs::Android | TargetOs::Emscripten => FilenameConvention::new("lib", ".so"),
+            TargetOs::MacOs | TargetOs::Ios => FilenameConvention::new("lib", ".dylib"),
+            TargetOs::Windows => FilenameConvention::new("", ".dll"),
+            TargetOs::Wasm => FilenameConvention::new("", ".wasm"),
+        }
+    }
+    /// Get the separator put between the library name and the version, where ELF platforms put the version after the suffix.
+    pub(crate) fn version_separator(self, suffix: &str) -> String {
+        match self {
+            TargetOs::MacOs | TargetOs::Ios => ".".to_owned(),
+            TargetOs::Windows => "-".to_owned(),
+            _ => format!("{}.", suffix),
+        }
+    }
+    /// Build the filename of a library with the given convention and version, the way the operating system versions it.
+    pub(crate) fn filename(self, convention: &FilenameConvention, lib_name: &str, version: Option<&str>) -> Result<String, SharedLibError> {
+        let (prefix, suffix) = (convention.prefix(), convention.suffix());
+        match (self, version) {
+            (TargetOs::Wasm, _) => Err(SharedLibError::Unsupported("Loading libraries on WebAssembly without Emscripten".into())),
+            (_, None | Some("")) => Ok(format!("{}{}{}", prefix, lib_name, suffix)),
+            (TargetOs::MacOs | TargetOs::Ios | TargetOs::Windows, Some(version)) => {
+                Ok(format!("{}{}{}{}{}", prefix, lib_name, self.version_separator(suffix), version, suffix))
+            }
+            (_, Some(version)) => Ok(format!("{}{}{}{}", prefix, lib_name, self.version_separator(suffix), version)),
+        }
+    }
+}
+
+impl SharedLib {
+    /// Create a new shared library from the given path, trying the given filename conventions in order.
+    ///
+    /// The library is loaded with the first convention it can be loaded with, which is kept in the library path of
+    /// the returned library, see [`LibPath::with_convention`]. If none of them works,
+    /// [`SharedLibError::NoMatchingFilename`] is returned with the error of every attempt, naming the filename tried.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("plugins"), "plugin".into());
+    ///     let lib = SharedLib::new_with_conventions(lib_path, &FilenameConvention::fallbacks()).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_with_conventions(lib_path: LibPath, conventions: &[FilenameConvention]) -> Result<SharedLib, SharedLibError> {
+        if conventions.is_empty() {
+            return Err(SharedLibError::PathEmpty);
+        }
+        let mut attempts = Vec::new();
+        for convention in conventions {
+            match SharedLib::new(lib_path.clone().with_convention(convention.clone())) {
+                Ok(lib) => return Ok(lib),
+                // The library name is invalid, which does not depend on the convention.
+                Err(e) if e.is_path_error() => return Err(e),
+                Err(e) => attempts.push(e),
+            }
+        }
+        Err(SharedLibError::NoMatchingFilename { lib_name: lib_path.lib_name, attempts })
+    }
+}
@@ -0,0 +1,171 @@
+//! Loading of plugins, shared libraries which export a constructor for a [`Plugin`] trait object.
+//!
+//! Plugins are compiled as Rust `dylib`s or `cdylib`s and declare their plugin type with [`declare_plugin!`](crate::declare_plugin).
+//! Since the plugin is passed as a Rust trait object, the plugin and the host have to be built
+//! with the same compiler and the same version of this crate.
+//!
+//! # Example
+//! In the plugin:
+//! ```no_run
+//! use shared_lib::*;
+//!
+//! #[derive(Default)]
+//! struct Greeter;
+//! impl Plugin for Greeter {
+//!     fn name(&self) -> &str {
+//!         "greeter"
+//!     }
+//!     fn version(&self) -> &str {
+//!         env!("CARGO_PKG_VERSION")
+//!     }
+//! }
+//! declare_plugin!(Greeter, Greeter::default);
+//! ```
+//! In the host:
+//! ```no_run
+//! use std::path::PathBuf;
+//! use shared_lib::*;
+//! let mut registry = PluginRegistry::new();
+//! unsafe {
+//!     let lib_path = LibPath::new(PathBuf::from("path/to/plugins"), "greeter".into());
+//!     registry.load(lib_path).expect("Failed to load plugin");
+//! }
+//! for plugin in registry.plugins() {
+//!     println!("{} {}", plugin.name(), plugin.version());
+//! }
+//! ```
+
+use crate::host::SharedHostApi;
+use crate::{LibPath, SharedLib, SharedLibError};
+
+/// Name of the symbol exported by [`declare_plugin!`](crate::declare_plugin) that creates the plugin.
+pub const PLUGIN_CREATE_SYMBOL: &str = "_plugin_create";
+
+/// Trait implemented by the plugin type of a plugin library.
+pub trait Plugin: Send {
+    /// Get the name of the plugin, which is unique in a [`PluginRegistry`].
+    fn name(&self) -> &str;
+    /// Get the version of the plugin.
+    fn version(&self) -> &str;
+    /// Called after the plugin is loaded into a [`PluginRegistry`].
+    fn on_load(&mut self) {}
+    /// Called before the plugin is unloaded from a [`PluginRegistry`].
+    fn on_unload(&mut self) {}
+}
+
+/// Declare the plugin type of a plugin library, exporting a constructor for it as [`PLUGIN_CREATE_SYMBOL`].
+///
+/// Takes the plugin type and a path to a function creating it, e.g. `declare_plugin!(Greeter, Greeter::default)`.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($plugin_type:ty, $constructor:path) => {
+        #[no_mangle]
+        pub fn _plugin_create() -> ::std::boxed::Box<dyn $crate::Plugin> {
+            let constructor: fn() -> $plugin_type = $constructor;
+            ::std::boxed::Box::new(constructor())
+        }
+    };
+}
+
+pub(crate) struct LoadedPlugin {
+    // Declared before `lib`, so the plugin is dropped before the library containing its code is unloaded.
+    pub(crate) plugin: Box<dyn Plugin>,
+    #[allow(dead_code)]
+    lib: SharedLib,
+    // Declared after `lib`, so the host API outlives the library it was bound to.
+    #[allow(dead_code)]
+    host_api: Option<SharedHostApi>,
+}
+
+/// Structure representing a collection of loaded plugins.
+///
+/// Plugins are kept in the order they were loaded, and are unloaded in reverse order when the registry is dropped.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<LoadedPlugin>,
+    // Host functions passed to the plugins loaded after it.
+    pub(crate) host_api: Option<SharedHostApi>,
+    // API version of the host the discovered plugins have to be compatible with.
+    #[cfg(feature = "manifest")]
+    pub(crate) host_api_version: Option<semver::Version>,
+    #[cfg(feature = "manifest")]
+    pub(crate) skip_incompatible: bool,
+}
+impl PluginRegistry {
+    /// Create a new empty plugin registry.
+    pub fn new() -> PluginRegistry {
+        PluginRegistry::default()
+    }
+    /// Load the plugin library from the given path, bind the host API set with [`PluginRegistry::with_host_api`], create its plugin and call [`Plugin::on_load`].
+    ///
+    /// Returns [`SharedLibError::PluginAlreadyLoaded`] if a plugin with the same name is already loaded,
+    /// in which case the new plugin is dropped without calling [`Plugin::on_load`] and its library is unloaded.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and calls its plugin constructor, which is generally unsafe as it is a foregin code.
+    /// The plugin constructor must have been declared with [`declare_plugin!`](crate::declare_plugin) by a library built
+    /// with the same compiler and version of this crate.
+    pub unsafe fn load(&mut self, lib_path: LibPath) -> Result<&mut dyn Plugin, SharedLibError> {
+        self.load_with_entry(lib_path, PLUGIN_CREATE_SYMBOL)
+    }
+    /// Load the plugin library from the given path like [`PluginRegistry::load`], creating its plugin with the
+    /// constructor exported as `entry_symbol` instead of [`PLUGIN_CREATE_SYMBOL`].
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and calls its plugin constructor, which is generally unsafe as it is a foregin code.
+    /// The constructor must have the signature `fn() -> Box<dyn Plugin>` and come from a library built
+    /// with the same compiler and version of this crate.
+    pub unsafe fn load_with_entry(&mut self, lib_path: LibPath, entry_symbol: &str) -> Result<&mut dyn Plugin, SharedLibError> {
+        let loaded = self.create(lib_path, entry_symbol)?;
+        self.insert(loaded)
+    }
+    /// Load the plugin library, bind the host API and create its plugin, without adding it to the registry.
+    pub(crate) unsafe fn create(&self, lib_path: LibPath, entry_symbol: &str) -> Result<LoadedPlugin, SharedLibError> {
+        let lib = SharedLib::new(lib_path)?;
+        let host_api = self.host_api.clone();
+        if let Some(api) = &host_api {
+            lib.bind_host(api)?;
+        }
+        let plugin = lib.get_fn::<fn() -> Box<dyn Plugin>>(entry_symbol)?.run();
+        Ok(LoadedPlugin { plugin, lib, host_api })
+    }
+    /// Add a created plugin to the registry and call [`Plugin::on_load`].
+    pub(crate) fn insert(&mut self, loaded: LoadedPlugin) -> Result<&mut dyn Plugin, SharedLibError> {
+        if self.get(loaded.plugin.name()).is_some() {
+            return Err(SharedLibError::PluginAlreadyLoaded(loaded.plugin.name().to_owned()));
+        }
+        self.plugins.push(loaded);
+        let loaded = self.plugins.last_mut().expect("the plugin was just pushed");
+        loaded.plugin.on_load();
+        Ok(loaded.plugin.as_mut())
+    }
+    /// Get the loaded plugin with the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn Plugin> {
+        self.plugins().find(|plugin| plugin.name() == name)
+    }
+    /// Get the loaded plugin with the given name mutably.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut dyn Plugin> {
+        let loaded = self.plugins.iter_mut().find(|loaded| loaded.plugin.name() == name)?;
+        Some(loaded.plugin.as_mut())
+    }
+    /// Iterate over the loaded plugins in the order they were loaded.
+    pub fn plugins(&self) -> impl Iterator<Item = &dyn Plugin> {
+        self.plugins.iter().map(|loaded| loaded.plugin.as_ref())
+    }
+    /// Call [`Plugin::on_unload`] on the plugin with the given name, drop it and unload its library.
+    ///
+    /// Returns `false` if no plugin with the given name is loaded.
+    pub fn unload(&mut self, name: &str) -> bool {
+        let Some(index) = self.plugins.iter().position(|loaded| loaded.plugin.name() == name) else {
+            return false;
+        };
+        let mut loaded = self.plugins.remove(index);
+        loaded.plugin.on_unload();
+        true
+    }
+}
+impl Drop for PluginRegistry {
+    fn drop(&mut self) {
+        while let Some(mut loaded) = self.plugins.pop() {
+            loaded.plugin.on_unload();
+        }
+    }
+}
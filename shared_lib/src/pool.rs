@@ -0,0 +1,111 @@
+//! Loading several copies of a library which is not thread-safe, see [`LibPool`].
+
+use crate::{LibPath, LoadFlags, SharedLib, SharedLibError};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::ThreadId;
+
+/// Structure representing several separate copies of the same library, e.g. of a C library keeping global state
+/// which cannot be used from two threads at once.
+///
+/// Every instance is loaded from its own copy of the library file, see [`LoadFlags::with_shadow_copy`], so the dynamic loader maps it
+/// again with its own global variables. With [`LoadFlags::with_isolated_namespace`], the instances are instead loaded into separate
+/// linker namespaces, which also gives each of them its own copy of the libraries it depends on.
+/// An instance is used by one thread at a time, either the next free one with [`LibPool::get`], or the one assigned to the thread with
+/// [`LibPool::get_for_thread`].
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use std::sync::Arc;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "legacy_parser".into());
+///     let pool = Arc::new(LibPool::new(lib_path, 4).expect("Failed to load shared library"));
+///     let workers: Vec<_> = (0..4).map(|i| {
+///         let pool = Arc::clone(&pool);
+///         std::thread::spawn(move || {
+///             let lib = pool.get_for_thread();
+///             let parse_fn = lib.get_fn::<extern "C" fn(i32) -> i32>("parse").expect("Failed to get 'parse' function");
+///             parse_fn.run(i)
+///         })
+///     }).collect();
+///     for worker in workers {
+///         worker.join().unwrap();
+///     }
+/// }
+/// ```
+pub struct LibPool {
+    instances: Vec<Mutex<SharedLib>>,
+    next: AtomicUsize,
+    // Instance assigned to every thread which called `get_for_thread`, with the index of the next instance to assign.
+    assigned: Mutex<(HashMap<ThreadId, usize>, usize)>,
+}
+impl LibPool {
+    /// Load `size` separate instances of the library.
+    /// # Panics
+    /// Panics if `size` is zero.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    pub unsafe fn new(lib_path: LibPath, size: usize) -> Result<LibPool, SharedLibError> {
+        LibPool::new_with_flags(lib_path, size, LoadFlags::default())
+    }
+    /// Load `size` separate instances of the library with the given flags, making a copy of the library file for every instance
+    /// unless the flags load it into an isolated namespace.
+    /// # Panics
+    /// Panics if `size` is zero.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    pub unsafe fn new_with_flags(lib_path: LibPath, size: usize, flags: LoadFlags) -> Result<LibPool, SharedLibError> {
+        assert!(size > 0, "a library pool needs at least one instance");
+        let flags = match flags.is_isolated_namespace() {
+            true => flags,
+            false => flags.with_shadow_copy(true),
+        };
+        let instances = (0..size).map(|_| SharedLib::new_with_flags(lib_path.clone(), flags).map(Mutex::new)).collect::<Result<_, _>>()?;
+        Ok(LibPool { instances, next: AtomicUsize::new(0), assigned: Mutex::default() })
+    }
+    /// Get an instance which is not used by another thread, waiting for one in turn if all of them are.
+    ///
+    /// The instances are handed out round-robin, so the calls are spread over all of them.
+    pub fn get(&self) -> MutexGuard<'_, SharedLib> {
+        let start = self.next_index();
+        for offset in 0..self.instances.len() {
+            if let Ok(instance) = self.instances[(start + offset) % self.instances.len()].try_lock() {
+                return instance;
+            }
+        }
+        self.lock(start)
+    }
+    /// Get the instance assigned to the current thread, waiting for it if another thread uses it.
+    ///
+    /// Instances are assigned round-robin when a thread first calls this, so with at most as many threads as instances,
+    /// every thread has an instance of its own, e.g. for libraries keeping state per thread.
+    /// The assignments are independent of the instances handed out by [`LibPool::get`], and are kept by the pool until it is dropped.
+    pub fn get_for_thread(&self) -> MutexGuard<'_, SharedLib> {
+        let index = {
+            let mut assigned = self.assigned.lock().unwrap_or_else(|e| e.into_inner());
+            let (threads, next) = &mut *assigned;
+            *threads.entry(std::thread::current().id()).or_insert_with(|| {
+                let index = *next;
+                *next = (index + 1) % self.instances.len();
+                index
+            })
+        };
+        self.lock(index)
+    }
+    /// Get the number of instances.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+    /// Check if the pool has no instances, which is never the case.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+    fn next_index(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % self.instances.len()
+    }
+    fn lock(&self, index: usize) -> MutexGuard<'_, SharedLib> {
+        self.instances[index].lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
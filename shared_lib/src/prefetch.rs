@@ -0,0 +1,62 @@
+//! Resolving the symbols a program requires when the library is loaded, see [`SharedLib::new_with_symbols`].
+
+use crate::loader::Loader;
+use crate::{LibPath, SharedLib, SharedLibError};
+
+impl SharedLib {
+    /// Create a new shared library from the given path, resolving the given symbols right away.
+    ///
+    /// Fails with [`SharedLibError::SymbolsNotFound`] listing every missing symbol if any of them is not exported,
+    /// instead of failing when the first missing function is looked up. The addresses of the symbols are put in the symbol cache,
+    /// so later lookups with [`SharedLib::get_fn`] do not go through the dynamic loader. The symbols are checked again
+    /// whenever the library is reloaded, and a reload fails the same way if the new library misses any of them.
+    /// See [`SharedLibBuilder::require_symbols`](crate::SharedLibBuilder::require_symbols) to combine this with other options.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     match SharedLib::new_with_symbols(lib_path, &["init", "update", "shutdown"]) {
+    ///         Ok(lib) => println!("Loaded {}", lib.lib_path()),
+    ///         Err(SharedLibError::SymbolsNotFound { symbol_names, .. }) => eprintln!("Missing {}", symbol_names.join(", ")),
+    ///         Err(e) => eprintln!("{}", e),
+    ///     }
+    /// }
+    /// ```
+    pub unsafe fn new_with_symbols(lib_path: LibPath, symbol_names: &[&str]) -> Result<SharedLib, SharedLibError> {
+        let mut lib = SharedLib::new(lib_path)?;
+        let symbol_names: Vec<String> = symbol_names.iter().map(|name| (*name).to_owned()).collect();
+        lib.prefetch(&symbol_names)?;
+        Ok(lib)
+    }
+    /// Check that the library exports all of `symbol_names` and put them in the symbol cache, requiring them again when it is reloaded.
+    pub(crate) unsafe fn prefetch(&mut self, symbol_names: &[String]) -> Result<(), SharedLibError> {
+        if symbol_names.is_empty() {
+            return Ok(());
+        }
+        let lib = self.library()?;
+        let (mut addresses, mut missing) = (Vec::with_capacity(symbol_names.len()), Vec::new());
+        // Every name is terminated in the same buffer, so libloading does not copy it for the lookup.
+        let mut symbol = Vec::new();
+        for name in symbol_names {
+            symbol.clear();
+            symbol.extend_from_slice(name.as_bytes());
+            symbol.push(0);
+            match lib.symbol(&symbol) {
+                Ok(address) if !address.is_null() => addresses.push((name, address)),
+                _ => missing.push(name.clone()),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(SharedLibError::SymbolsNotFound { symbol_names: missing, lib_name: self.lib_path.lossy_path() });
+        }
+        for (name, address) in addresses {
+            self.cache_fn(name, &address);
+        }
+        self.required_symbols = symbol_names.to_vec();
+        Ok(())
+    }
+}
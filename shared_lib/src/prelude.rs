@@ -0,0 +1,17 @@
+//! Commonly used items, re-exported for convenient glob importing.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use shared_lib::prelude::*;
+//! use std::path::PathBuf;
+//!
+//! let lib_path = LibPath::new(PathBuf::from("path/to/dir"), "library_name_no_ext".into());
+//! unsafe {
+//!    let lib: SharedLib = SharedLib::new(lib_path).unwrap();
+//!    let func: SharedLibFn<fn(usize, usize) -> usize> = lib.get_fn("foo").unwrap();
+//!    let result = func.run(1, 2);
+//! }
+//! ```
+
+pub use crate::{ErrorContext, LibPath, LibSearchPath, OwnedSharedLibFn, SharedLib, SharedLibError, SharedLibFn};
@@ -0,0 +1,107 @@
+//! Access to the underlying libloading library and the handle of the dynamic loader, for APIs this crate does not wrap.
+
+use crate::{trace, SharedLib, SharedLibError};
+use crate::loader::Library;
+use std::ffi::c_void;
+use std::path::Path;
+use std::sync::Arc;
+
+impl SharedLib {
+    /// Get the libloading library the shared library is loaded with, e.g. to use an API of libloading this crate does not wrap.
+    ///
+    /// Fails if the library is not loaded because reloading it failed.
+    pub fn as_libloading(&self) -> Result<&Library, SharedLibError> {
+        self.library().map(|lib| &**lib)
+    }
+    /// Take the libloading library out of the shared library, leaving it loaded until the returned library is dropped.
+    ///
+    /// Neither the callbacks registered with [`SharedLib::before_unload`] nor the shutdown function of [`LoadFlags::with_lifecycle`](crate::LoadFlags::with_lifecycle)
+    /// are run, since the library is not unloaded, and the gates returned by [`SharedLib::call_gate`] become stale once the calls in flight finish.
+    /// Fails with [`SharedLibError::UnloadFailure`] if functions obtained with [`SharedLib::get_fn_owned`] still use the library.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let lib: libloading::Library = lib.into_inner().expect("Failed to take the library");
+    ///     let add_fn = lib.get::<fn(usize, usize) -> usize>(b"add").expect("Failed to get 'add' function from library");
+    /// }
+    /// ```
+    pub fn into_inner(mut self) -> Result<Library, SharedLibError> {
+        self.library()?;
+        self.calls.stop(true);
+        match self.lib.take().map(Arc::try_unwrap) {
+            Some(Ok(lib)) => {
+                self.calls.retire();
+                trace::event!(debug, path = %self.lib_path, "Took the library out");
+                Ok(lib)
+            }
+            Some(Err(lib)) => {
+                self.lib = Some(lib);
+                self.calls.resume();
+                let msg = "The library is still used by functions obtained with `get_fn_owned`.".into();
+                Err(SharedLibError::UnloadFailure { path: self.lib_path.lossy_path(), msg })
+            }
+            None => unreachable!("the library was checked to be loaded"),
+        }
+    }
+    /// Get the handle of the library from the dynamic loader, i.e. the `HMODULE` on Windows and the handle returned by `dlopen` on Unix,
+    /// e.g. to pass it to an API of the operating system.
+    ///
+    /// The handle is only valid while the library is loaded, and must not be closed. It is supported on Linux with glibc, MacOS and Windows,
+    /// while other platforms return [`SharedLibError::Unsupported`]. For [`SharedLib::this`], the handle is the one of the main program.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    /// let lib = unsafe { SharedLib::new(lib_path).expect("Failed to load shared library") };
+    /// let handle = lib.raw_os_handle().expect("Failed to get the handle of the library");
+    /// println!("Handle of the library: {:?}", handle);
+    /// ```
+    pub fn raw_os_handle(&self) -> Result<*mut c_void, SharedLibError> {
+        self.library()?;
+        if cfg!(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows))) {
+            return Err(SharedLibError::Unsupported("Getting the handle of a library".into()));
+        }
+        let path = match self.lib_path.lib_name.is_empty() {
+            true => None,
+            false => Some(self.lib_path.path()?),
+        };
+        // Safety: the library stays loaded for the duration of the query, since `self` is borrowed.
+        unsafe { query_handle(path.as_deref()) }.map_err(|msg| SharedLibError::InspectFailure { path: self.lib_path.lossy_path(), msg })
+    }
+}
+
+#[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos"))]
+unsafe fn query_handle(path: Option<&Path>) -> Result<*mut c_void, String> {
+    // The dynamic loader returns the handle the library was loaded with, and closing it only decrements the reference count.
+    Ok(crate::dl::NoLoadHandle::open(path)?.0)
+}
+
+#[cfg(windows)]
+unsafe fn query_handle(path: Option<&Path>) -> Result<*mut c_void, String> {
+    use std::os::windows::ffi::OsStrExt;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetModuleHandleExW(flags: u32, module_name: *const u16, module: *mut *mut c_void) -> i32;
+    }
+    const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT: u32 = 0x2;
+
+    let name: Option<Vec<u16>> = path.map(|path| path.as_os_str().encode_wide().chain(Some(0)).collect());
+    let mut module = std::ptr::null_mut();
+    // The reference count is left unchanged, since the library stays loaded through the handle it was loaded with.
+    let name_ptr = name.as_ref().map_or(std::ptr::null(), |name| name.as_ptr());
+    if GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT, name_ptr, &mut module) == 0 {
+        return Err(format!("Failed to get the handle of the library. {}", std::io::Error::last_os_error()));
+    }
+    Ok(module)
+}
+
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows)))]
+unsafe fn query_handle(_path: Option<&Path>) -> Result<*mut c_void, String> {
+    unreachable!("getting the handle of a library is not supported on this platform")
+}
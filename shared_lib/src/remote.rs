@@ -0,0 +1,201 @@
+//! Downloading libraries into a content-addressed cache before loading them, see [`RemoteLib`].
+
+use crate::embed::{user_cache_dir, write_replace};
+use crate::{verify, LibPath, SharedLib, SharedLibError};
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Trait implemented by the ways of downloading a library for a [`RemoteLib`].
+///
+/// Implemented for closures taking the URL, e.g. to download with an HTTP client supporting HTTPS.
+pub trait Fetcher {
+    /// Download the contents of the file at `url`, returning the reason if it cannot be downloaded.
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String>;
+}
+impl<F: Fn(&str) -> Result<Vec<u8>, String>> Fetcher for F {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+        self(url)
+    }
+}
+
+/// Fetcher reading `file://` URLs from the file system and downloading `http://` URLs with a plain HTTP/1.0 request.
+///
+/// HTTPS is not supported, so libraries served over it need a [`Fetcher`] backed by an HTTP client.
+/// Redirects are not followed. Connecting and every read time out after [`DefaultFetcher::TIMEOUT`], and downloads
+/// larger than [`DefaultFetcher::MAX_SIZE`] fail, so a stalled or hostile server cannot hang the download or exhaust memory.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultFetcher;
+impl DefaultFetcher {
+    /// Time connecting to the server and every read of the response may take.
+    pub const TIMEOUT: Duration = Duration::from_secs(30);
+    /// Maximum size of a downloaded library in bytes.
+    pub const MAX_SIZE: u64 = 512 * 1024 * 1024;
+    /// Maximum size of the status line and headers of a response in bytes.
+    const MAX_HEADER_SIZE: u64 = 64 * 1024;
+}
+impl Fetcher for DefaultFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, String> {
+        if let Some(path) = url.strip_prefix("file://") {
+            return std::fs::read(path).map_err(|e| e.to_string());
+        }
+        let Some(rest) = url.strip_prefix("http://") else {
+            return Err("Only file:// and http:// URLs are supported, use another fetcher for other schemes.".into());
+        };
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let address = match host.contains(':') {
+            true => host.to_owned(),
+            false => format!("{}:80", host),
+        };
+        let mut stream = connect(&address).map_err(|e| format!("Failed to connect to '{}'. {}", address, e))?;
+        stream.set_read_timeout(Some(DefaultFetcher::TIMEOUT)).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(DefaultFetcher::TIMEOUT)).map_err(|e| e.to_string())?;
+        let path = if path.is_empty() { "/" } else { path };
+        let request = format!("GET {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host);
+        stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+
+        let read_failure = |e: std::io::Error| format!("Failed to read the response. {}", e);
+        let mut reader = BufReader::new(stream).take(DefaultFetcher::MAX_HEADER_SIZE);
+        let mut header = Vec::new();
+        while !header.ends_with(b"\r\n\r\n") {
+            if reader.read_until(b'\n', &mut header).map_err(read_failure)? == 0 {
+                return Err(match reader.limit() {
+                    0 => format!("The response headers exceed {} bytes.", DefaultFetcher::MAX_HEADER_SIZE),
+                    _ => "Invalid HTTP response.".into(),
+                });
+            }
+        }
+        let header = String::from_utf8_lossy(&header);
+        let status_line = header.lines().next().unwrap_or_default();
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            return Err(format!("Server responded with '{}'.", status_line));
+        }
+        let content_length = header
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .map(|(_, value)| value.trim().parse::<u64>().map_err(|_| format!("Invalid Content-Length '{}'.", value.trim())))
+            .transpose()?;
+        if let Some(len) = content_length.filter(|&len| len > DefaultFetcher::MAX_SIZE) {
+            return Err(format!("The library is {} bytes, more than the maximum of {} bytes.", len, DefaultFetcher::MAX_SIZE));
+        }
+        reader.set_limit(DefaultFetcher::MAX_SIZE + 1);
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).map_err(read_failure)?;
+        if body.len() as u64 > DefaultFetcher::MAX_SIZE {
+            return Err(format!("The library is more than the maximum of {} bytes.", DefaultFetcher::MAX_SIZE));
+        }
+        match content_length {
+            Some(len) if body.len() as u64 != len => Err(format!("The response ended after {} of {} bytes.", body.len(), len)),
+            _ => Ok(body),
+        }
+    }
+}
+
+/// Connect to the first reachable address `address` resolves to, waiting at most [`DefaultFetcher::TIMEOUT`] for each.
+fn connect(address: &str) -> std::io::Result<TcpStream> {
+    let mut last_error = None;
+    for addr in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, DefaultFetcher::TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "The host has no addresses.")))
+}
+
+/// Structure representing a library downloaded from a URL, e.g. an optional codec served by a CDN.
+///
+/// The library is stored in a cache directory under its SHA-256 hash, so it is only downloaded once
+/// and every version of the library has its own file. The hash is checked after downloading and again when loading,
+/// so a library is never loaded if it does not match. A download which does not match returns [`SharedLibError::IntegrityMismatch`],
+/// while a cached file which was changed before loading it returns [`SharedLibError::ChecksumMismatch`].
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// let sha256 = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+/// let codec = RemoteLib::new("http://cdn.example.com/codecs/libopus.so", sha256)
+///     .expect("Invalid SHA-256 hash")
+///     .cache_dir("cache/codecs");
+/// unsafe {
+///     let lib = codec.load().expect("Failed to load the codec");
+/// }
+/// ```
+pub struct RemoteLib {
+    url: String,
+    sha256: String,
+    cache_dir: PathBuf,
+    fetcher: Box<dyn Fetcher>,
+}
+impl RemoteLib {
+    /// Create a new remote library downloaded from `url`, whose contents have the SHA-256 hash `sha256` (a hex string).
+    ///
+    /// The last segment of the URL is the filename of the library, so it has to follow the naming convention of the platform.
+    /// It is downloaded with the [`DefaultFetcher`] into the per-user cache directory, like an [`EmbeddedLib`](crate::EmbeddedLib).
+    ///
+    /// Returns [`SharedLibError::InvalidSha256`] if `sha256` is not 64 hex digits, since it names the cache directory.
+    pub fn new(url: impl Into<String>, sha256: impl Into<String>) -> Result<RemoteLib, SharedLibError> {
+        let sha256 = sha256.into();
+        let hash = sha256.trim().to_ascii_lowercase();
+        if hash.len() != 64 || !hash.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(SharedLibError::InvalidSha256(sha256));
+        }
+        Ok(RemoteLib {
+            url: url.into(),
+            sha256: hash,
+            cache_dir: user_cache_dir().join("shared_lib").join("remote"),
+            fetcher: Box::new(DefaultFetcher),
+        })
+    }
+    /// Set the directory the library is cached in, e.g. the cache directory of the application.
+    pub fn cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> RemoteLib {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+    /// Set how the library is downloaded.
+    pub fn fetcher(mut self, fetcher: impl Fetcher + 'static) -> RemoteLib {
+        self.fetcher = Box::new(fetcher);
+        self
+    }
+    /// Get the library path of the library in the cache, which only exists once it is downloaded.
+    pub fn cached_path(&self) -> Result<LibPath, SharedLibError> {
+        let filename = self.url.split(['?', '#']).next().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+        LibPath::from_filename(self.cache_dir.join(&self.sha256), OsStr::new(filename))
+    }
+    /// Download the library into the cache, unless it is already cached with the right hash, and get its library path.
+    ///
+    /// The file is written under a temporary name and renamed once complete, so an interrupted download is never used.
+    pub fn fetch(&self) -> Result<LibPath, SharedLibError> {
+        let lib_path = self.cached_path()?;
+        let path = lib_path.path()?;
+        if self.is_cached(&path) {
+            return Ok(lib_path);
+        }
+        let fetch_failure = |msg: String| SharedLibError::FetchFailure { url: self.url.clone(), msg };
+        let contents = self.fetcher.fetch(&self.url).map_err(fetch_failure)?;
+        let actual = verify::sha256_hex(&mut contents.as_slice()).map_err(|e| fetch_failure(e.to_string()))?;
+        if actual != self.sha256 {
+            return Err(SharedLibError::IntegrityMismatch { path: self.url.clone(), expected: self.sha256.clone(), actual });
+        }
+        let write_failure = |e: std::io::Error| fetch_failure(format!("Failed to write '{}' to the cache. {}", path.display(), e));
+        std::fs::create_dir_all(&lib_path.dir_path).map_err(write_failure)?;
+        write_replace(&path, &contents).map_err(write_failure)?;
+        Ok(lib_path)
+    }
+    /// Download the library like [`RemoteLib::fetch`] and load it, checking its hash like [`SharedLib::new_verified`].
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn load(&self) -> Result<SharedLib, SharedLibError> {
+        let lib_path = self.fetch()?;
+        SharedLib::new_verified(lib_path, &self.sha256)
+    }
+    /// Check if the file at `path` has the expected hash, so it does not have to be downloaded again.
+    fn is_cached(&self, path: &Path) -> bool {
+        let Ok(mut file) = std::fs::File::open(path) else {
+            return false;
+        };
+        verify::sha256_hex(&mut file).is_ok_and(|actual| actual == self.sha256)
+    }
+}
@@ -0,0 +1,152 @@
+//! Detailed reports of why a library could not be loaded, see [`SharedLibBuilder::load_report`](crate::SharedLibBuilder::load_report).
+
+use crate::{inspect, LibPath, LibSearchPath, SharedLibError};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Directories the dynamic loader searches for libraries by default, after the ones of the environment variables.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn system_dirs() -> Vec<PathBuf> {
+    let multiarch = format!("{}-linux-gnu", std::env::consts::ARCH);
+    let dirs = ["/lib", "/usr/lib", "/lib64", "/usr/lib64", "/usr/local/lib"].map(PathBuf::from);
+    dirs.into_iter().chain([Path::new("/lib").join(&multiarch), Path::new("/usr/lib").join(&multiarch)]).collect()
+}
+#[cfg(target_os = "macos")]
+fn system_dirs() -> Vec<PathBuf> {
+    vec![PathBuf::from("/usr/local/lib"), PathBuf::from("/usr/lib")]
+}
+#[cfg(windows)]
+fn system_dirs() -> Vec<PathBuf> {
+    let system_root = std::env::var_os("SystemRoot").map_or_else(|| PathBuf::from("C:\\Windows"), PathBuf::from);
+    vec![system_root.join("System32"), system_root]
+}
+
+/// Check if a dependency is provided by the operating system without a file of its own,
+/// e.g. the libraries of the dyld shared cache on MacOS or the API sets on Windows.
+fn is_system_provided(name: &str) -> bool {
+    if cfg!(target_os = "macos") {
+        name.starts_with("/usr/lib/") || name.starts_with("/System/")
+    } else if cfg!(windows) {
+        let name = name.to_ascii_lowercase();
+        name.starts_with("api-ms-") || name.starts_with("ext-ms-")
+    } else {
+        false
+    }
+}
+
+/// Report of a failed attempt to load a library, listing everything that was tried, created by a [`SharedLibBuilder`](crate::SharedLibBuilder)
+/// with [`SharedLibBuilder::load_report`](crate::SharedLibBuilder::load_report) set.
+#[derive(Clone, Debug)]
+pub struct LoadReport {
+    /// Name of the library that failed to load.
+    pub lib_name: String,
+    /// Directories searched for the library, which are the search directories of the builder, or the ones of the
+    /// library search environment variables followed by the default system directories if the dynamic loader searched.
+    pub searched_dirs: Vec<PathBuf>,
+    /// Every library path tried, in order, with the error it failed with.
+    pub attempts: Vec<LoadAttempt>,
+    /// Dependencies declared by the library files which were found, but which could not be located themselves.
+    pub missing_dependencies: Vec<MissingDependency>,
+    /// Error the library failed to load with, as returned without the report.
+    pub error: SharedLibError,
+}
+
+/// Library path tried to load a library, see [`LoadReport::attempts`].
+#[derive(Clone, Debug)]
+pub struct LoadAttempt {
+    /// Path of the library file, which is only a filename if the dynamic loader searched for it.
+    pub path: PathBuf,
+    /// Error loading the library file failed with.
+    pub error: SharedLibError,
+}
+
+/// Dependency of a library file which could not be located, see [`LoadReport::missing_dependencies`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingDependency {
+    /// Name of the dependency as declared in the library file, e.g. `libfoo.so.1` or `@rpath/libfoo.dylib`.
+    pub name: String,
+    /// Path of the library file declaring the dependency.
+    pub required_by: PathBuf,
+}
+
+impl LoadReport {
+    /// Create the report of the failed attempts to load the library named `lib_name` from the given library paths.
+    ///
+    /// The dependencies of every library file that exists are checked, looking for them in the directory of the library,
+    /// the searched directories and the default system directories. Dependencies of the dependencies are not checked.
+    pub(crate) fn new(lib_name: String, search_dirs: &[PathBuf], attempts: Vec<(LibPath, SharedLibError)>, error: SharedLibError) -> LoadReport {
+        let searched_dirs = match search_dirs.is_empty() {
+            true => {
+                let mut dirs = LibSearchPath::from_env(lib_name.clone()).dir_paths;
+                dirs.extend(system_dirs());
+                dirs
+            }
+            false => search_dirs.to_vec(),
+        };
+        let attempts: Vec<_> = attempts.into_iter().map(|(lib_path, error)| LoadAttempt { path: lib_path.error_path(), error }).collect();
+        let mut missing_dependencies = Vec::new();
+        for attempt in &attempts {
+            // Without a directory the file is only known to the dynamic loader, so it is looked up like the library.
+            let path = match attempt.path.parent().is_some_and(|dir| !dir.as_os_str().is_empty()) {
+                true => Some(attempt.path.clone()),
+                false => searched_dirs.iter().map(|dir| dir.join(&attempt.path)).find(|path| path.is_file()),
+            };
+            let Some(path) = path.filter(|path| path.is_file()) else {
+                continue;
+            };
+            let Ok(dependencies) = inspect::dependencies(&path) else {
+                continue;
+            };
+            for name in dependencies {
+                let missing = MissingDependency { name, required_by: path.clone() };
+                if !locate_dependency(&missing.name, &path, &searched_dirs) && !missing_dependencies.contains(&missing) {
+                    missing_dependencies.push(missing);
+                }
+            }
+        }
+        LoadReport { lib_name, searched_dirs, attempts, missing_dependencies, error }
+    }
+}
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Failed to load library '{}'. {}", self.lib_name, self.error)?;
+        writeln!(f, "Searched directories:")?;
+        for dir in &self.searched_dirs {
+            writeln!(f, "  {}", dir.display())?;
+        }
+        writeln!(f, "Tried files:")?;
+        for attempt in &self.attempts {
+            writeln!(f, "  {}: {}", attempt.path.display(), attempt.error)?;
+        }
+        if !self.missing_dependencies.is_empty() {
+            writeln!(f, "Missing dependencies:")?;
+            for dependency in &self.missing_dependencies {
+                writeln!(f, "  {} (required by {})", dependency.name, dependency.required_by.display())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check if the dependency `name` of the library file at `lib_path` can be found.
+fn locate_dependency(name: &str, lib_path: &Path, searched_dirs: &[PathBuf]) -> bool {
+    if is_system_provided(name) {
+        return true;
+    }
+    let lib_dir = lib_path.parent().unwrap_or(Path::new(""));
+    // Mach-O install names relative to the library are resolved against its directory.
+    if let Some(relative) = name.strip_prefix("@loader_path/") {
+        return lib_dir.join(relative).is_file();
+    }
+    if let Some(relative) = name.strip_prefix("@executable_path/") {
+        return std::env::current_exe().is_ok_and(|exe| exe.parent().is_some_and(|dir| dir.join(relative).is_file()));
+    }
+    let name = name.strip_prefix("@rpath/").unwrap_or(name);
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return path.is_file();
+    }
+    let system_dirs = system_dirs();
+    let mut dirs = std::iter::once(lib_dir).chain(searched_dirs.iter().chain(&system_dirs).map(PathBuf::as_path));
+    dirs.any(|dir| dir.join(path).is_file())
+}
@@ -0,0 +1,98 @@
+//! Retrying loads of library files which are still being written, see [`RetryPolicy`].
+
+use crate::{ErrorKind, LibPath, SharedLibError};
+use std::time::{Duration, SystemTime};
+
+/// Policy retrying the load of a library which fails because its file is missing or still being written,
+/// e.g. when a watcher reloads a library the compiler has not finished writing, see [`LoadFlags::with_retry`](crate::LoadFlags::with_retry).
+///
+/// The library is loaded at most `attempts` times, waiting `delay` after every failed attempt. Only failures caused by the file
+/// are retried, i.e. errors of the kinds [`ErrorKind::NotFound`], [`ErrorKind::LoadFailed`] and [`ErrorKind::BadFormat`].
+/// With the stability check, the size and modification time of the library file are compared before and after waiting `delay`,
+/// and the attempt fails if they changed, so a file which is still being written is not loaded truncated.
+/// The default policy loads the library once without checking its file.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use std::time::Duration;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("target/debug"), "plugin".into());
+///     let retry = RetryPolicy::new(10, Duration::from_millis(100)).with_stability_check(true);
+///     let mut lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_retry(retry)).expect("Failed to load shared library");
+///     // The compiler starts writing the library...
+///     lib.reload().expect("Failed to reload shared library");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct RetryPolicy {
+    attempts: u32,
+    delay: Duration,
+    stability_check: bool,
+}
+impl RetryPolicy {
+    /// Create a new policy loading the library at most `attempts` times, waiting `delay` after every failed attempt.
+    pub fn new(attempts: u32, delay: Duration) -> RetryPolicy {
+        RetryPolicy { attempts, delay, stability_check: false }
+    }
+    /// Check that the library file does not change for `delay` before every attempt.
+    pub fn with_stability_check(mut self, stability_check: bool) -> RetryPolicy {
+        self.stability_check = stability_check;
+        self
+    }
+    /// Get the maximum number of attempts, which is at least one.
+    pub fn attempts(&self) -> u32 {
+        self.attempts.max(1)
+    }
+    /// Get the delay after every failed attempt.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+    /// Check if the library file must not change for the delay before every attempt.
+    pub fn is_stability_check(&self) -> bool {
+        self.stability_check
+    }
+    /// Run `load` for the library at `lib_path` until it succeeds, fails with an error which is not retried, or runs out of attempts.
+    pub(crate) fn run<T>(&self, lib_path: &LibPath, mut load: impl FnMut() -> Result<T, SharedLibError>) -> Result<T, SharedLibError> {
+        let mut attempt = 1;
+        loop {
+            let result = self.check_stable(lib_path).and_then(|_| load());
+            match result {
+                Err(e) if attempt < self.attempts() && is_transient(&e) => {
+                    crate::trace::event!(debug, path = %lib_path, attempt, error = %e, "Retrying to load library");
+                    std::thread::sleep(self.delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+    /// Fail if the library file changes within the delay, when the stability check is enabled.
+    fn check_stable(&self, lib_path: &LibPath) -> Result<(), SharedLibError> {
+        if !self.stability_check || lib_path.dir_path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        let path = lib_path.path()?;
+        let Some(before) = file_state(&path) else {
+            // A missing file fails the load, which is retried.
+            return Ok(());
+        };
+        std::thread::sleep(self.delay);
+        match file_state(&path) == Some(before) {
+            true => Ok(()),
+            false => Err(SharedLibError::load_failure(path, String::from("Library file changed while waiting for it to be written."))),
+        }
+    }
+}
+
+/// Get the size and modification time of the file, if it exists.
+fn file_state(path: &std::path::Path) -> Option<(u64, Option<SystemTime>)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// Check if the load failure may be caused by a file which is missing or still being written.
+fn is_transient(e: &SharedLibError) -> bool {
+    matches!(e.kind(), ErrorKind::NotFound | ErrorKind::LoadFailed | ErrorKind::BadFormat)
+}
@@ -0,0 +1,473 @@
+//! Loading of untrusted libraries in a child process, which calls their functions on behalf of the host, see [`SandboxedLib`].
+
+use crate::{LibPath, SharedLib, SharedLibError};
+use std::ffi::c_void;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+const ADDR_VAR: &str = "SHARED_LIB_SANDBOX_ADDR";
+const TOKEN_VAR: &str = "SHARED_LIB_SANDBOX_TOKEN";
+const PATH_VAR: &str = "SHARED_LIB_SANDBOX_PATH";
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum length of a message, so a sandbox cannot make the host allocate an arbitrary amount of memory.
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+// Classes of values, which decide the registers they are passed in.
+const UNIT: u8 = 0;
+const INT: u8 = 1;
+const F32: u8 = 2;
+const F64: u8 = 3;
+
+const LOOKUP: u8 = 1;
+const CALL: u8 = 2;
+
+/// Trait implemented by the plain-old-data types which can be passed to and returned from the functions of a [`SandboxedLib`].
+///
+/// Implemented for integers, `bool`, `f32` and `f64`, and for `()` as a return type.
+pub trait SandboxValue: Copy {
+    #[doc(hidden)]
+    const CLASS: u8;
+    #[doc(hidden)]
+    fn to_bits(self) -> u64;
+    #[doc(hidden)]
+    fn from_bits(bits: u64) -> Self;
+}
+macro_rules! impl_sandbox_int {
+    ($($T:ty),*) => {
+        $(impl SandboxValue for $T {
+            const CLASS: u8 = INT;
+            fn to_bits(self) -> u64 {
+                // Sign or zero extended, so the value is the same whichever part of the register the callee reads.
+                self as i64 as u64
+            }
+            fn from_bits(bits: u64) -> $T {
+                bits as $T
+            }
+        })*
+    };
+}
+impl_sandbox_int!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl SandboxValue for bool {
+    const CLASS: u8 = INT;
+    fn to_bits(self) -> u64 {
+        self as u64
+    }
+    fn from_bits(bits: u64) -> bool {
+        bits as u8 != 0
+    }
+}
+impl SandboxValue for f32 {
+    const CLASS: u8 = F32;
+    fn to_bits(self) -> u64 {
+        u64::from(f32::to_bits(self))
+    }
+    fn from_bits(bits: u64) -> f32 {
+        f32::from_bits(bits as u32)
+    }
+}
+impl SandboxValue for f64 {
+    const CLASS: u8 = F64;
+    fn to_bits(self) -> u64 {
+        f64::to_bits(self)
+    }
+    fn from_bits(bits: u64) -> f64 {
+        f64::from_bits(bits)
+    }
+}
+impl SandboxValue for () {
+    const CLASS: u8 = UNIT;
+    fn to_bits(self) -> u64 {
+        0
+    }
+    fn from_bits(_: u64) {}
+}
+
+/// Write a message prefixed with its length.
+fn write_message(stream: &mut impl Write, message: &[u8]) -> std::io::Result<()> {
+    stream.write_all(&(message.len() as u32).to_le_bytes())?;
+    stream.write_all(message)
+}
+/// Read a message written with `write_message`, failing if it is longer than [`MAX_MESSAGE_LEN`].
+fn read_message(stream: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        let msg = format!("Received a message of {} bytes, more than the maximum of {} bytes.", len, MAX_MESSAGE_LEN);
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, msg));
+    }
+    let mut message = vec![0; len];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+/// Generate the token the sandbox proves it is the child with, from the random source of the operating system.
+fn random_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+    #[cfg(unix)]
+    std::fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    #[cfg(windows)]
+    {
+        #[link(name = "bcrypt")]
+        extern "system" {
+            fn BCryptGenRandom(algorithm: *mut c_void, buffer: *mut u8, len: u32, flags: u32) -> i32;
+        }
+        const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 2;
+        let status = unsafe { BCryptGenRandom(std::ptr::null_mut(), bytes.as_mut_ptr(), bytes.len() as u32, BCRYPT_USE_SYSTEM_PREFERRED_RNG) };
+        if status != 0 {
+            return Err(std::io::Error::other(format!("BCryptGenRandom failed with status {:#x}.", status)));
+        }
+    }
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+/// Encode the reply to a request, either its result or the reason it failed.
+fn reply(result: Result<u64, String>) -> Vec<u8> {
+    match result {
+        Ok(bits) => [&[0][..], &bits.to_le_bytes()].concat(),
+        Err(msg) => [&[1][..], msg.as_bytes()].concat(),
+    }
+}
+
+/// Structure representing a library loaded in a child process, so a crash of the library only kills the child.
+///
+/// The child process runs the given program, by default the current executable, which has to call
+/// [`SandboxedLib::serve_if_child`] at the start of `main`. The child loads the library and connects back to the host
+/// over a loopback TCP connection, through which its functions are looked up and called. The standard streams
+/// of the child are inherited unless set otherwise on the command.
+///
+/// Only `extern "C"` functions with up to 4 arguments can be called, whose arguments and return values implement
+/// [`SandboxValue`], e.g. integers and floats. Pointers cannot be passed, since they would refer to the memory of
+/// the other process. If the child crashes or exits, every call returns [`SharedLibError::SandboxFailure`]
+/// with its exit status until it is started again with [`SandboxedLib::restart`]. A child which does not answer a request within
+/// the call timeout, see [`SandboxedLib::with_call_timeout`], is killed the same way. The child is killed when the library is dropped.
+///
+/// Only available on 64-bit targets, where every argument is passed in a 64-bit register.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+///
+/// fn main() {
+///     // Runs the sandbox and exits if this process is the child of a `SandboxedLib`.
+///     unsafe { SandboxedLib::serve_if_child() };
+///
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let lib = SandboxedLib::new(lib_path).expect("Failed to start the sandbox");
+///     let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("add").expect("Failed to get 'add' function from shared library");
+///     match add_fn.run(1, 2) {
+///         Ok(result) => println!("1 + 2 = {}", result),
+///         Err(e) => println!("The library crashed. {}", e),
+///     }
+/// }
+/// ```
+pub struct SandboxedLib {
+    lib_path: LibPath,
+    sandbox: Mutex<Sandbox>,
+}
+/// Child process running a sandbox, the connection to it and the command it was started with.
+struct Sandbox {
+    child: Child,
+    stream: TcpStream,
+    command: Command,
+    call_timeout: Option<Duration>,
+}
+impl SandboxedLib {
+    /// Default time the sandbox has to answer a request, see [`SandboxedLib::with_call_timeout`].
+    pub const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Start a child process running the current executable, which loads the library at `lib_path`.
+    ///
+    /// The library path is passed to the child as the full path of the library file, which is parsed with [`LibPath::from_full_path`].
+    /// Returns [`SharedLibError::SandboxFailure`] if the child cannot be started, and the error of the child if it cannot load the library.
+    pub fn new(lib_path: LibPath) -> Result<SandboxedLib, SharedLibError> {
+        let program = std::env::current_exe().map_err(|e| SharedLibError::SandboxFailure {
+            path: lib_path.lossy_path(),
+            msg: format!("Failed to get the current executable. {}", e),
+        })?;
+        SandboxedLib::spawn(lib_path, Command::new(program))
+    }
+    /// Start a child process running `command`, which loads the library at `lib_path` like [`SandboxedLib::new`].
+    ///
+    /// The program of the command has to call [`SandboxedLib::serve_if_child`] before doing anything else, e.g. a helper executable
+    /// shipped with the application, or the current executable with arguments selecting the sandbox.
+    pub fn spawn(lib_path: LibPath, mut command: Command) -> Result<SandboxedLib, SharedLibError> {
+        let call_timeout = Some(SandboxedLib::CALL_TIMEOUT);
+        let (child, stream) = Sandbox::start(&lib_path, &mut command, call_timeout)?;
+        Ok(SandboxedLib { lib_path, sandbox: Mutex::new(Sandbox { child, stream, command, call_timeout }) })
+    }
+    /// Set the time the sandbox has to answer a request, [`SandboxedLib::CALL_TIMEOUT`] by default, or `None` to wait for it forever.
+    ///
+    /// A sandbox which does not answer in time, e.g. because the function hangs, is killed and the call returns
+    /// [`SharedLibError::SandboxFailure`], so it has to be started again with [`SandboxedLib::restart`].
+    pub fn with_call_timeout(self, timeout: Option<Duration>) -> Result<SandboxedLib, SharedLibError> {
+        let mut sandbox = self.sandbox();
+        sandbox.stream.set_read_timeout(timeout).map_err(|e| SharedLibError::SandboxFailure { path: self.lib_path.lossy_path(), msg: e.to_string() })?;
+        sandbox.call_timeout = timeout;
+        drop(sandbox);
+        Ok(self)
+    }
+    /// Run the sandbox if this process was started by a [`SandboxedLib`], exiting once the host disconnects,
+    /// and return right away otherwise.
+    ///
+    /// Has to be called at the start of `main` of the program of the sandbox, before anything the sandbox should not do.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn serve_if_child() {
+        let (Some(addr), Some(token), Some(path)) = (std::env::var_os(ADDR_VAR), std::env::var_os(TOKEN_VAR), std::env::var_os(PATH_VAR)) else {
+            return;
+        };
+        let Ok(mut stream) = TcpStream::connect(addr.to_string_lossy().as_ref()) else {
+            std::process::exit(1);
+        };
+        let _ = stream.set_nodelay(true);
+        let lib = LibPath::from_full_path(&path).and_then(|lib_path| SharedLib::new(lib_path));
+        let loaded = match &lib {
+            Ok(_) => Ok(0),
+            Err(e) => Err(e.to_string()),
+        };
+        if write_message(&mut stream, token.to_string_lossy().as_bytes()).and_then(|_| write_message(&mut stream, &reply(loaded))).is_err() {
+            std::process::exit(1);
+        }
+        let Ok(lib) = lib else {
+            std::process::exit(1);
+        };
+        while let Ok(request) = read_message(&mut stream) {
+            if write_message(&mut stream, &reply(serve(&lib, &request))).is_err() {
+                break;
+            }
+        }
+        std::process::exit(0);
+    }
+    /// Get a function by name from the library in the sandbox, which is only called through the sandbox.
+    ///
+    /// `T` is an `extern "C"` function type with up to 4 arguments implementing [`SandboxValue`], e.g. `extern "C" fn(i32, f64) -> f64`.
+    /// The type is not checked against the actual type of the function, but calling it with the wrong type only affects the sandbox.
+    /// Errors in the sandbox, e.g. a missing function, are returned as [`SharedLibError::SandboxFailure`] with their message.
+    pub fn get_fn<T>(&self, fn_name: &str) -> Result<SandboxedFn<'_, T>, SharedLibError> {
+        self.request(&[&[LOOKUP][..], fn_name.as_bytes()].concat())?;
+        Ok(SandboxedFn { lib: self, fn_name: fn_name.to_owned(), func: PhantomData })
+    }
+    /// Get the library path of the library in the sandbox.
+    pub fn lib_path(&self) -> &LibPath {
+        &self.lib_path
+    }
+    /// Check if the child process is still running, i.e. the library did not crash.
+    pub fn is_alive(&self) -> bool {
+        self.sandbox().child.try_wait().is_ok_and(|status| status.is_none())
+    }
+    /// Kill the child process and start a new one running the same command, e.g. after the library crashed.
+    ///
+    /// Functions looked up before are called in the new child.
+    pub fn restart(&self) -> Result<(), SharedLibError> {
+        let mut sandbox = self.sandbox();
+        sandbox.kill();
+        let call_timeout = sandbox.call_timeout;
+        (sandbox.child, sandbox.stream) = Sandbox::start(&self.lib_path, &mut sandbox.command, call_timeout)?;
+        Ok(())
+    }
+    fn sandbox(&self) -> MutexGuard<'_, Sandbox> {
+        self.sandbox.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    /// Send a request to the sandbox and wait for its result.
+    fn request(&self, request: &[u8]) -> Result<u64, SharedLibError> {
+        let failure = |msg: String| SharedLibError::SandboxFailure { path: self.lib_path.lossy_path(), msg };
+        let mut sandbox = self.sandbox();
+        if let Ok(Some(status)) = sandbox.child.try_wait() {
+            return Err(failure(format!("The sandbox exited with {}.", status)));
+        }
+        let response = match write_message(&mut sandbox.stream, request).and_then(|_| read_message(&mut sandbox.stream)) {
+            Ok(response) => response,
+            Err(e) => return Err(failure(exit_reason(&mut sandbox.child, e))),
+        };
+        match response.split_first() {
+            Some((0, bits)) if bits.len() == 8 => Ok(u64::from_le_bytes(bits.try_into().unwrap_or_default())),
+            Some((1, msg)) => Err(failure(String::from_utf8_lossy(msg).into_owned())),
+            _ => Err(failure("Invalid response from the sandbox.".into())),
+        }
+    }
+}
+impl Drop for SandboxedLib {
+    fn drop(&mut self) {
+        self.sandbox.get_mut().unwrap_or_else(|e| e.into_inner()).kill();
+    }
+}
+impl Sandbox {
+    /// Start the child process and wait for it to connect and load the library, after which it has `call_timeout`
+    /// to answer every request.
+    fn start(lib_path: &LibPath, command: &mut Command, call_timeout: Option<Duration>) -> Result<(Child, TcpStream), SharedLibError> {
+        let failure = |msg: String| SharedLibError::SandboxFailure { path: lib_path.lossy_path(), msg };
+        if std::env::var_os(ADDR_VAR).is_some() {
+            return Err(failure("A sandbox cannot start another sandbox, call `SandboxedLib::serve_if_child` at the start of `main`.".into()));
+        }
+        let path = lib_path.path()?;
+        let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| failure(format!("Failed to listen for the sandbox. {}", e)))?;
+        let addr = listener.local_addr().map_err(|e| failure(e.to_string()))?;
+        // Other processes can connect to the listener as well, so the sandbox proves it is the child with a random token.
+        let token = random_token().map_err(|e| failure(format!("Failed to generate the sandbox token. {}", e)))?;
+        let mut child = command
+            .env(ADDR_VAR, addr.to_string())
+            .env(TOKEN_VAR, &token)
+            .env(PATH_VAR, &path)
+            .spawn()
+            .map_err(|e| failure(format!("Failed to start the sandbox. {}", e)))?;
+        let connected = Sandbox::accept(&listener, &token, &mut child).and_then(|mut stream| match read_message(&mut stream) {
+            Ok(loaded) if loaded.first() == Some(&0) => stream.set_read_timeout(call_timeout).map(|_| stream).map_err(|e| e.to_string()),
+            Ok(loaded) => Err(String::from_utf8_lossy(&loaded[1.min(loaded.len())..]).into_owned()),
+            Err(e) => Err(exit_reason(&mut child, e)),
+        });
+        match connected {
+            Ok(stream) => Ok((child, stream)),
+            Err(msg) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(failure(msg))
+            }
+        }
+    }
+    /// Wait for the child to connect, polling the listener, so a child that exits or hangs is not waited for forever.
+    fn accept(listener: &TcpListener, token: &str, child: &mut Child) -> Result<TcpStream, String> {
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let start = Instant::now();
+        while start.elapsed() < STARTUP_TIMEOUT {
+            match listener.accept() {
+                Ok((mut stream, _)) => {
+                    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+                    stream.set_read_timeout(Some(STARTUP_TIMEOUT)).map_err(|e| e.to_string())?;
+                    if read_message(&mut stream).is_ok_and(|received| received == token.as_bytes()) {
+                        let _ = stream.set_nodelay(true);
+                        return Ok(stream);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if let Ok(Some(status)) = child.try_wait() {
+                        return Err(format!("The sandbox exited with {} before connecting.", status));
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => return Err(format!("Failed to accept the sandbox. {}", e)),
+            }
+        }
+        Err(format!("The sandbox did not connect within {:?}, check that it calls `SandboxedLib::serve_if_child`.", STARTUP_TIMEOUT))
+    }
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+/// Get why the connection to `child` failed, killing it if it is still running, e.g. because it did not respond in time.
+fn exit_reason(child: &mut Child, e: std::io::Error) -> String {
+    let running = child.try_wait().is_ok_and(|status| status.is_none());
+    let _ = child.kill();
+    let status = child.wait();
+    match e.kind() {
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut if running => "The sandbox did not respond in time and was killed.".into(),
+        std::io::ErrorKind::InvalidData => format!("{} The sandbox was killed.", e),
+        _ => match status {
+            Ok(status) => format!("The sandbox exited with {}.", status),
+            Err(_) => format!("Lost the connection to the sandbox. {}", e),
+        },
+    }
+}
+/// Handle a request of the host in the sandbox.
+unsafe fn serve(lib: &SharedLib, request: &[u8]) -> Result<u64, String> {
+    match request.split_first() {
+        Some((&LOOKUP, name)) => lib.get_symbol_address(&String::from_utf8_lossy(name)).map(|_| 0).map_err(|e| e.to_string()),
+        Some((&CALL, [ret, argc, rest @ ..])) => {
+            let argc = usize::from(*argc);
+            let (args, name) = rest.split_at_checked(argc * 9).ok_or("Invalid request.")?;
+            let args: Vec<(u8, u64)> = args.chunks(9).map(|arg| (arg[0], u64::from_le_bytes(arg[1..].try_into().unwrap_or_default()))).collect();
+            let func = lib.get_symbol_address(&String::from_utf8_lossy(name)).map_err(|e| e.to_string())?;
+            call(func, *ret, &args)
+        }
+        _ => Err("Invalid request.".into()),
+    }
+}
+
+// Calls `$func` with the types of the classes of the remaining arguments, adding one argument per expansion.
+macro_rules! call_with {
+    ($func:ident, $ret:ident, [$($T:ty = $v:expr),*], []) => {
+        match $ret {
+            UNIT => {
+                std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($T),*)>($func)($($v),*);
+                0
+            }
+            INT => std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($T),*) -> i64>($func)($($v),*) as u64,
+            F32 => u64::from(std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($T),*) -> f32>($func)($($v),*).to_bits()),
+            _ => std::mem::transmute::<*mut c_void, unsafe extern "C" fn($($T),*) -> f64>($func)($($v),*).to_bits(),
+        }
+    };
+    ($func:ident, $ret:ident, [$($T:ty = $v:expr),*], [$arg:expr $(, $rest:expr)*]) => {
+        match $arg {
+            (F32, bits) => {
+                let arg = f32::from_bits(bits as u32);
+                call_with!($func, $ret, [$($T = $v,)* f32 = arg], [$($rest),*])
+            }
+            (F64, bits) => {
+                let arg = f64::from_bits(bits);
+                call_with!($func, $ret, [$($T = $v,)* f64 = arg], [$($rest),*])
+            }
+            (_, bits) => {
+                let arg = bits as i64;
+                call_with!($func, $ret, [$($T = $v,)* i64 = arg], [$($rest),*])
+            }
+        }
+    };
+}
+
+/// Call `func` with the given arguments, returning the bits of its return value.
+///
+/// Integers are passed as 64-bit integers, which the callee reads the lower bits of, so only the class of every
+/// argument decides the type the function is called with. This only holds because the module is limited to 64-bit targets,
+/// whose calling conventions pass the first 4 integer arguments in 64-bit registers, while 32-bit targets pass them
+/// on the stack, where a wider argument shifts the ones after it.
+unsafe fn call(func: *mut c_void, ret: u8, args: &[(u8, u64)]) -> Result<u64, String> {
+    if args.iter().any(|(class, _)| *class == UNIT) {
+        return Err("Arguments cannot be of the unit type.".into());
+    }
+    Ok(match *args {
+        [] => call_with!(func, ret, [], []),
+        [a1] => call_with!(func, ret, [], [a1]),
+        [a1, a2] => call_with!(func, ret, [], [a1, a2]),
+        [a1, a2, a3] => call_with!(func, ret, [], [a1, a2, a3]),
+        [a1, a2, a3, a4] => call_with!(func, ret, [], [a1, a2, a3, a4]),
+        _ => return Err(format!("Functions with {} arguments cannot be called in the sandbox.", args.len())),
+    })
+}
+
+/// Structure representing a function of a [`SandboxedLib`], which is called in the sandbox, see [`SandboxedLib::get_fn`].
+pub struct SandboxedFn<'a, Fn> {
+    lib: &'a SandboxedLib,
+    fn_name: String,
+    func: PhantomData<Fn>,
+}
+macro_rules! impl_sandboxed_run {
+    ([$($fn_ty:tt)+] $($a:ident: $A:ident),*) => {
+        impl<Ret: SandboxValue, $($A: SandboxValue),*> SandboxedFn<'_, $($fn_ty)+ ($($A),*) -> Ret> {
+            /// Call the function in the sandbox, returning [`SharedLibError::SandboxFailure`] if the sandbox crashed.
+            pub fn run(&self, $($a: $A),*) -> Result<Ret, SharedLibError> {
+                let args: &[(u8, u64)] = &[$(($A::CLASS, $a.to_bits())),*];
+                let mut request = vec![CALL, Ret::CLASS, args.len() as u8];
+                for (class, bits) in args {
+                    request.push(*class);
+                    request.extend_from_slice(&bits.to_le_bytes());
+                }
+                request.extend_from_slice(self.fn_name.as_bytes());
+                self.lib.request(&request).map(Ret::from_bits)
+            }
+        }
+    };
+}
+macro_rules! impl_sandboxed_run_all {
+    ($($a:ident: $A:ident),*) => {
+        impl_sandboxed_run!([extern "C" fn] $($a: $A),*);
+        impl_sandboxed_run!([unsafe extern "C" fn] $($a: $A),*);
+    };
+}
+impl_sandboxed_run_all!();
+impl_sandboxed_run_all!(a1: A1);
+impl_sandboxed_run_all!(a1: A1, a2: A2);
+impl_sandboxed_run_all!(a1: A1, a2: A2, a3: A3);
+impl_sandboxed_run_all!(a1: A1, a2: A2, a3: A3, a4: A4);
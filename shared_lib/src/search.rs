@@ -0,0 +1,250 @@
+//! Loading of libraries which may be located in one of several directories.
+
+use crate::{LibPath, SharedLib, SharedLibError};
+use std::path::PathBuf;
+
+/// Environment variables listing the directories the dynamic loader searches for libraries, in the order they are consulted.
+#[cfg(windows)]
+const SEARCH_PATH_VARS: &[&str] = &["PATH"];
+#[cfg(target_os = "macos")]
+const SEARCH_PATH_VARS: &[&str] = &["DYLD_LIBRARY_PATH", "DYLD_FALLBACK_LIBRARY_PATH"];
+#[cfg(not(any(windows, target_os = "macos")))]
+const SEARCH_PATH_VARS: &[&str] = &["LD_LIBRARY_PATH"];
+
+#[cfg(windows)]
+fn system_dir_candidates() -> Vec<PathBuf> {
+    let windows_dir = std::env::var_os("SystemRoot").map_or_else(|| PathBuf::from(r"C:\Windows"), PathBuf::from);
+    vec![windows_dir.join("System32"), windows_dir]
+}
+
+#[cfg(target_os = "macos")]
+fn system_dir_candidates() -> Vec<PathBuf> {
+    ["/usr/local/lib", "/opt/homebrew/lib", "/usr/lib"].iter().map(PathBuf::from).collect()
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn system_dir_candidates() -> Vec<PathBuf> {
+    let mut dir_paths = vec![PathBuf::from("/usr/local/lib")];
+    if let Some(multiarch) = multiarch() {
+        dir_paths.extend(["/usr/local/lib", "/lib", "/usr/lib"].iter().map(|root| PathBuf::from(root).join(multiarch)));
+    }
+    if cfg!(target_pointer_width = "64") {
+        dir_paths.extend([PathBuf::from("/lib64"), PathBuf::from("/usr/lib64")]);
+    }
+    dir_paths.extend([PathBuf::from("/lib"), PathBuf::from("/usr/lib")]);
+    dir_paths
+}
+
+/// Get the multiarch directory name of the target in Debian based distributions, e.g. `/usr/lib/x86_64-linux-gnu`.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn multiarch() -> Option<&'static str> {
+    if !cfg!(all(target_os = "linux", target_env = "gnu")) {
+        return None;
+    }
+    if cfg!(target_arch = "x86_64") {
+        Some("x86_64-linux-gnu")
+    } else if cfg!(target_arch = "aarch64") {
+        Some("aarch64-linux-gnu")
+    } else if cfg!(target_arch = "x86") {
+        Some("i386-linux-gnu")
+    } else if cfg!(target_arch = "arm") {
+        Some("arm-linux-gnueabihf")
+    } else if cfg!(target_arch = "riscv64") {
+        Some("riscv64-linux-gnu")
+    } else if cfg!(all(target_arch = "powerpc64", target_endian = "little")) {
+        Some("powerpc64le-linux-gnu")
+    } else if cfg!(target_arch = "s390x") {
+        Some("s390x-linux-gnu")
+    } else {
+        None
+    }
+}
+
+/// Structure representing a shared library that may be located in any of several directories.
+///
+/// `dir_paths` are the directories searched for the library, in order.
+///
+/// `lib_name` is the library name without the platform specific extension and prefix.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LibSearchPath {
+    pub dir_paths: Vec<PathBuf>,
+    pub lib_name: String,
+}
+impl LibSearchPath {
+    /// Create a new library search path from the given directories and library name.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// let search_path = LibSearchPath::new(vec![PathBuf::from("plugins"), PathBuf::from("/usr/lib/app")], "shared_library".into());
+    /// ```
+    pub fn new(dir_paths: Vec<PathBuf>, lib_name: String) -> LibSearchPath {
+        LibSearchPath { dir_paths, lib_name }
+    }
+    /// Create a new library search path from the library search environment variables of the platform, followed by the current directory.
+    ///
+    /// The variables are `LD_LIBRARY_PATH` on Linux and other Unix platforms,
+    /// `DYLD_LIBRARY_PATH` and `DYLD_FALLBACK_LIBRARY_PATH` on macOS and `PATH` on Windows.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// let search_path = LibSearchPath::from_env("shared_library".into());
+    /// let path = search_path.resolve().expect("Failed to find shared library");
+    /// ```
+    pub fn from_env(lib_name: String) -> LibSearchPath {
+        let mut dir_paths: Vec<PathBuf> = SEARCH_PATH_VARS
+            .iter()
+            .filter_map(std::env::var_os)
+            .flat_map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+            .filter(|dir_path| !dir_path.as_os_str().is_empty())
+            .collect();
+        dir_paths.extend(std::env::current_dir());
+        LibSearchPath { dir_paths, lib_name }
+    }
+    /// Create a new library search path from the conventional system library directories of the platform, see [`LibSearchPath::system_dirs`].
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let search_path = LibSearchPath::system("z".into());
+    ///     let lib = SharedLib::new_search(&search_path).expect("Failed to load the system copy of the library");
+    /// }
+    /// ```
+    pub fn system(lib_name: String) -> LibSearchPath {
+        LibSearchPath { dir_paths: LibSearchPath::system_dirs(), lib_name }
+    }
+    /// Get the conventional system library directories of the platform which exist, in the order the dynamic loader usually searches them.
+    ///
+    /// These are `/usr/local/lib`, the multiarch directories such as `/usr/lib/x86_64-linux-gnu`, `/lib64`, `/usr/lib64`, `/lib` and `/usr/lib`
+    /// on Linux, `/usr/local/lib`, `/opt/homebrew/lib` and `/usr/lib` on macOS, and `System32` and the Windows directory on Windows.
+    /// Libraries in the shared cache of macOS are not files in these directories, but can still be loaded from them.
+    pub fn system_dirs() -> Vec<PathBuf> {
+        system_dir_candidates().into_iter().filter(|dir_path| dir_path.is_dir()).collect()
+    }
+    /// Get the library path in every directory, in search order.
+    pub fn lib_paths(&self) -> impl Iterator<Item = LibPath> + '_ {
+        self.dir_paths.iter().map(|dir_path| LibPath::new(dir_path.clone(), self.lib_name.clone()))
+    }
+    /// Get the path of the library file in the first directory that contains it.
+    ///
+    /// Returns [`SharedLibError::LibNotFound`] with every searched path if none of the directories contains the library file.
+    pub fn resolve(&self) -> Result<PathBuf, SharedLibError> {
+        let mut searched = Vec::new();
+        for lib_path in self.lib_paths() {
+            let path = lib_path.path()?;
+            if path.is_file() {
+                return Ok(path);
+            }
+            searched.push(path);
+        }
+        Err(SharedLibError::LibNotFound { lib_name: self.lib_name.clone(), searched })
+    }
+}
+
+impl LibPath {
+    /// Get the path of the library file, searching the directories listed by the platform environment variables
+    /// and the current directory if the library path has no directory, see [`LibSearchPath::from_env`].
+    ///
+    /// This is where a library created with [`LibPath::new_no_path`] is usually found. Returns [`SharedLibError::LibNotFound`]
+    /// if the library file does not exist.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// let lib_path = LibPath::new_no_path("shared_library".into());
+    /// let path = lib_path.resolve().expect("Failed to find shared library");
+    /// ```
+    pub fn resolve(&self) -> Result<PathBuf, SharedLibError> {
+        if self.dir_path.as_os_str().is_empty() {
+            LibSearchPath::from_env(self.lib_name.clone()).resolve()
+        } else {
+            LibSearchPath::new(vec![self.dir_path.clone()], self.lib_name.clone()).resolve()
+        }
+    }
+}
+
+impl SharedLib {
+    /// Create a new shared library from the first directory of the search path it can be loaded from.
+    ///
+    /// Directories are tried in order, skipping the ones the library cannot be loaded from for any reason,
+    /// e.g. a missing file or an [`SharedLibError::ArchMismatch`]. If none of them works,
+    /// [`SharedLibError::NotInSearchPath`] is returned with the error of every attempt.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let search_path = LibSearchPath::new(vec![PathBuf::from("plugins"), PathBuf::from("/usr/lib/app")], "shared_library".into());
+    ///     let lib = SharedLib::new_search(&search_path).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_search(search_path: &LibSearchPath) -> Result<SharedLib, SharedLibError> {
+        if search_path.dir_paths.is_empty() {
+            return Err(SharedLibError::PathEmpty);
+        }
+        let mut attempts = Vec::new();
+        for lib_path in search_path.lib_paths() {
+            match SharedLib::new(lib_path) {
+                Ok(lib) => return Ok(lib),
+                // The library name is invalid, which does not depend on the directory.
+                Err(e) if e.is_path_error() => return Err(e),
+                Err(e) => attempts.push(e),
+            }
+        }
+        Err(SharedLibError::NotInSearchPath { lib_name: search_path.lib_name.clone(), attempts })
+    }
+    /// Create a new shared library from the first of the given library paths it can be loaded from,
+    /// e.g. a bundled copy of the library followed by the system one.
+    ///
+    /// Candidates are tried in order, skipping the ones that cannot be loaded for any reason, including invalid paths.
+    /// If none of them works, [`SharedLibError::NoCandidateLoaded`] is returned with every candidate and its error.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let bundled = LibPath::new(PathBuf::from("vendor"), "shared_library".into());
+    ///     let system = LibPath::new_no_path("shared_library".into()).with_version("1".into());
+    ///     let lib = SharedLib::new_first_of(&[bundled, system]).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_first_of(lib_paths: &[LibPath]) -> Result<SharedLib, SharedLibError> {
+        if lib_paths.is_empty() {
+            return Err(SharedLibError::PathEmpty);
+        }
+        let mut attempts = Vec::new();
+        for lib_path in lib_paths {
+            match SharedLib::new(lib_path.clone()) {
+                Ok(lib) => return Ok(lib),
+                Err(e) => attempts.push((lib_path.clone(), e)),
+            }
+        }
+        Err(SharedLibError::NoCandidateLoaded { attempts })
+    }
+    /// Create a new shared library from the first directory of the search path it can be loaded from, like
+    /// [`SharedLib::new_search`], without blocking the async runtime.
+    ///
+    /// The directories are searched and the library is loaded on the blocking thread pool of the tokio runtime.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// # async fn load() {
+    /// unsafe {
+    ///     let search_path = LibSearchPath::new(vec![PathBuf::from("//server/share/plugins")], "shared_library".into());
+    ///     let lib = SharedLib::new_search_async(search_path).await.expect("Failed to load shared library");
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async unsafe fn new_search_async(search_path: LibSearchPath) -> Result<SharedLib, SharedLibError> {
+        let path = PathBuf::from(&search_path.lib_name);
+        SharedLib::spawn_blocking(path, move || SharedLib::new_search(&search_path)).await
+    }
+}
@@ -0,0 +1,120 @@
+//! Loading of libraries which depend on each other.
+
+use crate::{FnSignature, LibPath, LoadFlags, SharedLib, SharedLibError, SharedLibFn};
+
+/// Order in which the libraries of a [`SharedLibSet`] are searched when resolving a symbol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ResolutionOrder {
+    /// The first loaded library exporting the symbol provides it.
+    ///
+    /// This matches how the dynamic loader resolves symbols of globally loaded libraries,
+    /// so a library loaded earlier interposes the symbols of the libraries loaded after it.
+    #[default]
+    LoadOrder,
+    /// The last loaded library exporting the symbol provides it, so later libraries override earlier ones.
+    ReverseLoadOrder,
+}
+
+/// Structure representing an ordered set of shared libraries that are loaded and kept loaded together.
+///
+/// Libraries are loaded front to back, so each library can depend on the ones before it,
+/// and are unloaded in reverse order when the set is dropped.
+pub struct SharedLibSet {
+    libs: Vec<SharedLib>,
+    order: ResolutionOrder,
+}
+impl SharedLibSet {
+    /// Load the given libraries in order.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    pub unsafe fn new(lib_paths: impl IntoIterator<Item = LibPath>) -> Result<SharedLibSet, SharedLibError> {
+        SharedLibSet::open(lib_paths, LoadFlags::default())
+    }
+    /// Load the given libraries in order, making the symbols of each library globally available
+    /// to the libraries loaded after it (`RTLD_GLOBAL` on Unix).
+    ///
+    /// This allows a library to use symbols of a previously loaded library it is not explicitly linked against.
+    /// Symbols of loaded DLLs are always globally available on Windows.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let runtime_path = LibPath::new(PathBuf::from("path/to/shared/library"), "runtime".into());
+    ///     let plugin_path = LibPath::new(PathBuf::from("path/to/shared/library"), "plugin".into());
+    ///     let libs = SharedLibSet::new_global([runtime_path, plugin_path]).expect("Failed to load shared libraries");
+    ///     let init_fn = libs.get_fn::<fn()>("plugin_init").expect("Failed to get 'plugin_init' function from shared libraries");
+    ///     init_fn.run();
+    /// }
+    /// ```
+    pub unsafe fn new_global(lib_paths: impl IntoIterator<Item = LibPath>) -> Result<SharedLibSet, SharedLibError> {
+        SharedLibSet::open(lib_paths, LoadFlags::new().with_global(true))
+    }
+    pub(crate) unsafe fn open(lib_paths: impl IntoIterator<Item = LibPath>, flags: LoadFlags) -> Result<SharedLibSet, SharedLibError> {
+        let mut set = SharedLibSet { libs: Vec::new(), order: ResolutionOrder::default() };
+        for lib_path in lib_paths {
+            // On failure, the libraries loaded so far are unloaded in reverse order by `Drop`.
+            set.libs.push(SharedLib::open(lib_path, flags)?);
+        }
+        Ok(set)
+    }
+    /// Set the order in which the libraries are searched when resolving a symbol.
+    pub fn with_resolution_order(mut self, order: ResolutionOrder) -> SharedLibSet {
+        self.order = order;
+        self
+    }
+    /// Get the order in which the libraries are searched when resolving a symbol.
+    pub fn resolution_order(&self) -> ResolutionOrder {
+        self.order
+    }
+    /// Get the loaded libraries, in the order they were loaded.
+    pub fn libs(&self) -> &[SharedLib] {
+        &self.libs
+    }
+    /// Get the library which provides the symbol with the given name according to the resolution order,
+    /// or `None` if none of the libraries export it.
+    /// # Safety
+    /// This function is unsafe because it looks up a symbol in the shared libraries, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let host_path = LibPath::new(PathBuf::from("path/to/shared/library"), "host_overrides".into());
+    ///     let plugin_path = LibPath::new(PathBuf::from("path/to/shared/library"), "plugin".into());
+    ///     let libs = SharedLibSet::new_global([host_path, plugin_path]).expect("Failed to load shared libraries");
+    ///     let provider = libs.provider_of("malloc_hook").expect("No library exports 'malloc_hook'");
+    ///     println!("'malloc_hook' is provided by {}", provider.lib_path().lib_name());
+    /// }
+    /// ```
+    pub unsafe fn provider_of(&self, symbol_name: &str) -> Option<&SharedLib> {
+        let has_symbol = |lib: &&SharedLib| lib.has_symbol(symbol_name);
+        match self.order {
+            ResolutionOrder::LoadOrder => self.libs.iter().find(has_symbol),
+            ResolutionOrder::ReverseLoadOrder => self.libs.iter().rev().find(has_symbol),
+        }
+    }
+    /// Get a function by name from the library in the set that provides it according to the resolution order.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn get_fn<T: FnSignature>(&self, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        match self.provider_of(fn_name) {
+            Some(lib) => lib.get_fn(fn_name),
+            None => Err(SharedLibError::symbol_not_found(
+                fn_name,
+                self.libs.iter().map(|lib| lib.lib_path.to_string()).collect::<Vec<_>>().join(", "),
+                String::from("None of the libraries in the set export the symbol."),
+            )),
+        }
+    }
+}
+impl Drop for SharedLibSet {
+    fn drop(&mut self) {
+        // Unload in reverse order of loading, so each library is unloaded before the libraries it depends on.
+        while let Some(lib) = self.libs.pop() {
+            drop(lib);
+        }
+    }
+}
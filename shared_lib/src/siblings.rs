@@ -0,0 +1,66 @@
+//! Loading of the dependencies located next to a library, e.g. `plugins/bar.dll` used by `plugins/foo.dll`.
+//!
+//! The dynamic loader does not search the directory of the library that is being loaded for its dependencies
+//! (unless told to with `$ORIGIN` or `LOAD_WITH_ALTERED_SEARCH_PATH`). However, a dependency that is already loaded
+//! into the process is matched by its name, so pre-loading the dependencies found next to the library lets it load.
+
+use crate::{inspect, LibPath, LoadFlags, SharedLib, SharedLibError};
+use crate::loader::{Library, Loader};
+use std::path::{Path, PathBuf};
+
+impl SharedLib {
+    /// Create a new shared library from the given path, loading its dependencies found in the same directory first.
+    ///
+    /// Every dependency of the library (and of those dependencies) whose file is located in the directory of the library
+    /// is loaded before the library itself and kept loaded for as long as the library is alive. Dependencies that are not
+    /// found there are left for the dynamic loader to resolve. On Linux, dependencies are matched by their `SONAME`,
+    /// so a dependency without one is not matched to the file loaded here.
+    /// # Safety
+    /// This function is unsafe because it loads shared libraries, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("plugins"), "plugin".into());
+    ///     let lib = SharedLib::new_with_sibling_dependencies(lib_path).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_with_sibling_dependencies(lib_path: LibPath) -> Result<SharedLib, SharedLibError> {
+        let path = lib_path.path()?;
+        let mut loaded = vec![(path.clone(), None)];
+        load_sibling_dependencies(&path, lib_path.dir(), &mut loaded)?;
+        let mut lib = SharedLib::new(lib_path)?;
+        // Unload in reverse order of loading, so each library is unloaded before its dependencies.
+        lib.deps = loaded.into_iter().rev().filter_map(|(_, dep)| dep).collect();
+        Ok(lib)
+    }
+}
+
+/// Load every dependency of the library located at `path` that is found in `dir`.
+///
+/// Dependencies are loaded depth first, so every library in `loaded` comes after the libraries it depends on.
+/// Libraries already in `loaded` are skipped, including those without a [`Library`], which are not loaded here.
+unsafe fn load_sibling_dependencies(
+    path: &Path,
+    dir: &Path,
+    loaded: &mut Vec<(PathBuf, Option<Library>)>,
+) -> Result<(), SharedLibError> {
+    for name in inspect::dependencies(path)? {
+        // Mach-O install names are paths, e.g. `@rpath/libfoo.dylib`, of which only the file name is looked up.
+        let Some(filename) = Path::new(&name).file_name() else {
+            continue;
+        };
+        let dep_path = dir.join(filename);
+        if !dep_path.is_file() || loaded.iter().any(|(loaded_path, _)| *loaded_path == dep_path) {
+            continue;
+        }
+        loaded.push((dep_path.clone(), None));
+        load_sibling_dependencies(&dep_path, dir, loaded)?;
+        let lib = Library::open(dep_path.as_os_str(), LoadFlags::default()).map_err(|e| SharedLibError::load_failure(&dep_path, e))?;
+        // The dependency is moved after its own dependencies, which were pushed after its placeholder.
+        loaded.retain(|(loaded_path, _)| *loaded_path != dep_path);
+        loaded.push((dep_path, Some(lib)));
+    }
+    Ok(())
+}
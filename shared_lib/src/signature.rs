@@ -0,0 +1,82 @@
+//! Verification of library files with a pluggable [`Verifier`], e.g. an ed25519 signature, before loading them.
+
+use crate::{LibPath, LoadFlags, SharedLib, SharedLibError};
+use ed25519_dalek::{Signature, VerifyingKey};
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Extension appended to the library filename to get the path of its detached signature, e.g. `libplugin.so.sig`.
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Trait implemented by checks which have to pass before a library is loaded with [`SharedLib::new_with_verifier`].
+pub trait Verifier {
+    /// Check the contents of the library file at `path`, returning the reason if the library is not trusted.
+    fn verify(&self, path: &Path, contents: &[u8]) -> Result<(), String>;
+}
+
+/// Verifier checking the ed25519 signature of a library, stored as 64 raw bytes next to it
+/// in a file with [`SIGNATURE_EXTENSION`] appended to the library filename.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ed25519Verifier {
+    public_key: [u8; 32],
+}
+impl Ed25519Verifier {
+    /// Create a new verifier accepting libraries signed by the key with the given public key.
+    pub fn new(public_key: [u8; 32]) -> Ed25519Verifier {
+        Ed25519Verifier { public_key }
+    }
+    /// Get the path of the detached signature of the library at `path`.
+    pub fn signature_path(path: &Path) -> PathBuf {
+        let mut signature_path: OsString = path.as_os_str().to_owned();
+        signature_path.push(".");
+        signature_path.push(SIGNATURE_EXTENSION);
+        signature_path.into()
+    }
+}
+impl Verifier for Ed25519Verifier {
+    fn verify(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        let public_key = VerifyingKey::from_bytes(&self.public_key).map_err(|e| format!("Invalid public key. {}", e))?;
+        let signature_path = Ed25519Verifier::signature_path(path);
+        let signature = std::fs::read(&signature_path)
+            .map_err(|e| format!("Failed to read the signature '{}'. {}", signature_path.display(), e))?;
+        let signature: [u8; 64] = signature
+            .try_into()
+            .map_err(|_| format!("The signature '{}' is not 64 bytes long.", signature_path.display()))?;
+        public_key
+            .verify_strict(contents, &Signature::from_bytes(&signature))
+            .map_err(|e| format!("The signature does not match. {}", e))
+    }
+}
+
+impl SharedLib {
+    /// Create a new shared library from the given path, after checking the library file with `verifier`.
+    ///
+    /// The whole file is read and checked before it is loaded, so none of its code (including initializers) runs
+    /// if the check fails, in which case [`SharedLibError::VerificationFailure`] is returned.
+    ///
+    /// On Linux, the library is loaded through the file descriptor that was checked, so the file cannot be replaced in between.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let verifier = Ed25519Verifier::new([0; 32]);
+    ///     let lib = SharedLib::new_with_verifier(lib_path, &verifier).expect("Failed to load shared library");
+    /// }
+    /// ```
+    pub unsafe fn new_with_verifier(lib_path: LibPath, verifier: &dyn Verifier) -> Result<SharedLib, SharedLibError> {
+        SharedLib::open_checked(lib_path, LoadFlags::default(), |path, file| {
+            let verification_failure = |msg: String| SharedLibError::VerificationFailure {
+                path: path.to_string_lossy().to_string(),
+                msg,
+            };
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).map_err(|e| verification_failure(e.to_string()))?;
+            verifier.verify(path, &contents).map_err(verification_failure)
+        })
+    }
+}
@@ -0,0 +1,179 @@
+//! Generation of typed bindings from a symbol specification file at build time, see [`shared_lib_codegen`].
+//!
+//! The specification lists the functions of a library with their Rust signatures, for libraries where only a few
+//! functions are needed and translating their headers with [`DynamicBindings`](crate::DynamicBindings) is not worth it:
+//! ```toml
+//! # Name of the generated struct.
+//! struct_name = "CalculatorApi"
+//! # ABI of the functions, "C" by default.
+//! abi = "C"
+//!
+//! [[functions]]
+//! name = "c_add"
+//! # Signature of the function as a Rust `fn` type, with or without argument names.
+//! signature = "fn(left: i32, right: i32) -> i32"
+//! # Documentation of the field holding the function.
+//! doc = "Add two numbers."
+//! # Name the function is exported as, if it differs from `name`.
+//! link_name = "calculator_add"
+//! # Whether the function is safe to call with any arguments, which generates a safe method calling it.
+//! safe = true
+//! ```
+
+use crate::SharedLibError;
+use quote::{format_ident, quote};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Structure representing a symbol specification file, from which [`shared_lib_codegen`] generates typed bindings.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolSpec {
+    /// Name of the generated struct.
+    pub struct_name: String,
+    /// ABI of the functions, e.g. `"C"` or `"system"`.
+    #[serde(default = "default_abi")]
+    pub abi: String,
+    /// Functions of the library, in the order of the fields of the generated struct.
+    #[serde(default)]
+    pub functions: Vec<SymbolSpecFn>,
+}
+
+/// Structure representing a function listed in a [`SymbolSpec`].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolSpecFn {
+    /// Name of the field holding the function.
+    pub name: String,
+    /// Signature of the function as a Rust `fn` type, e.g. `"fn(left: i32, right: i32) -> i32"`.
+    pub signature: String,
+    /// Documentation of the field holding the function.
+    #[serde(default)]
+    pub doc: Option<String>,
+    /// Name the function is exported as, if it differs from [`SymbolSpecFn::name`].
+    #[serde(default)]
+    pub link_name: Option<String>,
+    /// Whether the function is safe to call with any arguments, which generates a safe method of the same name calling it.
+    #[serde(default)]
+    pub safe: bool,
+}
+
+fn default_abi() -> String {
+    "C".to_owned()
+}
+
+impl SymbolSpec {
+    /// Read the TOML symbol specification file at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<SymbolSpec, SharedLibError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| SharedLibError::BindingsFailure(format!("Failed to read symbol spec '{}'. {}", path.display(), e)))?;
+        SymbolSpec::from_toml(&contents).map_err(|e| match e {
+            SharedLibError::BindingsFailure(msg) => SharedLibError::BindingsFailure(format!("Invalid symbol spec '{}'. {}", path.display(), msg)),
+            e => e,
+        })
+    }
+    /// Parse a TOML symbol specification.
+    pub fn from_toml(contents: &str) -> Result<SymbolSpec, SharedLibError> {
+        toml::from_str(contents).map_err(|e| SharedLibError::BindingsFailure(e.to_string()))
+    }
+    /// Generate the Rust source of the bindings, declaring the functions with [`shared_lib_extern!`](crate::shared_lib_extern)
+    /// and adding a safe method for every function marked as safe.
+    /// # Errors
+    /// Returns [`SharedLibError::BindingsFailure`] if a name is not a valid identifier or listed more than once,
+    /// or a signature is not a plain, non-variadic `fn` type.
+    pub fn generate(&self) -> Result<String, SharedLibError> {
+        let struct_name = ident(&self.struct_name, "struct name")?;
+        let abi = &self.abi;
+        let (mut fns, mut safe_fns) = (Vec::new(), Vec::new());
+        for (index, func) in self.functions.iter().enumerate() {
+            if self.functions[..index].iter().any(|other| other.name == func.name) {
+                return Err(SharedLibError::BindingsFailure(format!("Function '{}' is listed more than once.", func.name)));
+            }
+            let name = ident(&func.name, "function name")?;
+            let invalid = |msg: String| SharedLibError::BindingsFailure(format!("Invalid signature of function '{}'. {}", func.name, msg));
+            let sig = syn::parse_str::<syn::TypeBareFn>(&func.signature).map_err(|e| invalid(e.to_string()))?;
+            if sig.abi.is_some() || sig.unsafety.is_some() || sig.lifetimes.is_some() {
+                return Err(invalid("The signature must be a plain `fn` type, the ABI is set for all functions by `abi`.".into()));
+            }
+            if sig.variadic.is_some() {
+                return Err(invalid("Variadic functions are not supported.".into()));
+            }
+            let (arg_names, arg_types): (Vec<_>, Vec<_>) = sig
+                .inputs
+                .iter()
+                .enumerate()
+                .map(|(i, arg)| match &arg.name {
+                    Some((arg_name, _)) if arg_name != "_" => (arg_name.clone(), &arg.ty),
+                    _ => (format_ident!("arg{}", i + 1), &arg.ty),
+                })
+                .unzip();
+            let output = &sig.output;
+            let doc = func.doc.iter().map(|doc| format!(" {}", doc));
+            let link_name = func.link_name.iter();
+            fns.push(quote! {
+                #(#[doc = #doc])*
+                #(#[link_name = #link_name])*
+                fn #name(#(#arg_names: #arg_types),*) #output;
+            });
+            if func.safe {
+                let doc = func.doc.iter().map(|doc| format!(" {}", doc));
+                safe_fns.push(quote! {
+                    #(#[doc = #doc])*
+                    pub fn #name(&self, #(#arg_names: #arg_types),*) #output {
+                        unsafe { self.#name.run(#(#arg_names),*) }
+                    }
+                });
+            }
+        }
+        let safe_impl = (!safe_fns.is_empty()).then(|| {
+            quote! {
+                impl<'lib> #struct_name<'lib> {
+                    #(#safe_fns)*
+                }
+            }
+        });
+        let tokens = quote! {
+            ::shared_lib::shared_lib_extern! {
+                pub struct #struct_name;
+                extern #abi {
+                    #(#fns)*
+                }
+            }
+            #safe_impl
+        };
+        Ok(tokens.to_string())
+    }
+}
+
+/// Parse `name` as an identifier, describing it as `what` in the error.
+fn ident(name: &str, what: &str) -> Result<syn::Ident, SharedLibError> {
+    syn::parse_str(name).map_err(|_| SharedLibError::BindingsFailure(format!("'{}' is not a valid {}.", name, what)))
+}
+
+/// Generate typed bindings from the TOML symbol specification file at `spec_path` and write them to the file at `out_path`,
+/// telling cargo to rerun the build script when the specification changes. To be called from a build script.
+///
+/// The bindings are a struct of the functions declared with [`shared_lib_extern!`](crate::shared_lib_extern), loaded with
+/// its `load` function, and a safe method for every function marked as safe. See the [module documentation](self) for the format.
+/// # Errors
+/// Returns [`SharedLibError::BindingsFailure`] if the specification cannot be read or is invalid, see [`SymbolSpec::generate`],
+/// or the bindings cannot be written.
+/// # Example
+/// ```no_run
+/// use shared_lib::*;
+/// // In build.rs
+/// let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+/// shared_lib_codegen("calculator.toml", out_dir.join("calculator.rs")).expect("Failed to generate calculator bindings");
+/// // In the crate, which uses the generated struct like `CalculatorApi::load(&lib)?.c_add(1, 2)`
+/// // include!(concat!(env!("OUT_DIR"), "/calculator.rs"));
+/// ```
+pub fn shared_lib_codegen(spec_path: impl AsRef<Path>, out_path: impl AsRef<Path>) -> Result<(), SharedLibError> {
+    let spec_path = spec_path.as_ref();
+    let bindings = SymbolSpec::from_file(spec_path)?.generate()?;
+    let out_path = out_path.as_ref();
+    std::fs::write(out_path, bindings)
+        .map_err(|e| SharedLibError::BindingsFailure(format!("Failed to write bindings to '{}'. {}", out_path.display(), e)))?;
+    println!("cargo:rerun-if-changed={}", spec_path.display());
+    Ok(())
+}
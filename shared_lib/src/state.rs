@@ -0,0 +1,69 @@
+//! Handing the state of a library over to its new version when it is reloaded.
+//!
+//! A library takes part by exporting both of the following functions:
+//! ```c
+//! // Return the state of the library, which stays valid until the library is unloaded, and set `len` to its size.
+//! const uint8_t *plugin_save_state(size_t *len);
+//! // Restore the state saved by the previous version of the library, which is only valid during the call.
+//! void plugin_restore_state(const uint8_t *data, size_t len);
+//! ```
+//! The state is an opaque buffer, so its format is up to the library, e.g. a serialized struct with a version field.
+
+use crate::{SharedLib, SharedLibError};
+
+/// Name of the function returning the state of a library before it is unloaded, see [`SharedLib::reload_with_state`].
+pub const SAVE_STATE_SYMBOL: &str = "plugin_save_state";
+/// Name of the function restoring the state of a library after it is reloaded, see [`SharedLib::reload_with_state`].
+pub const RESTORE_STATE_SYMBOL: &str = "plugin_restore_state";
+
+type SaveStateFn = unsafe extern "C" fn(*mut usize) -> *const u8;
+type RestoreStateFn = unsafe extern "C" fn(*const u8, usize);
+
+impl SharedLib {
+    /// Reload the shared library like [`SharedLib::reload`], handing its state over from the old to the new version.
+    ///
+    /// If the old library exports [`SAVE_STATE_SYMBOL`], its state is copied before it is unloaded,
+    /// and passed to [`RESTORE_STATE_SYMBOL`] of the new library, if it exports it. Libraries exporting neither
+    /// are reloaded as usual. If reloading fails, the saved state is dropped together with the old library.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and calls its functions, which is generally unsafe as it is a foregin code.
+    /// The state functions must have the signatures described in the [`state`](crate::state) module.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let mut lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     // Rebuild the library...
+    ///     lib.reload_with_state().expect("Failed to reload shared library");
+    /// }
+    /// ```
+    pub unsafe fn reload_with_state(&mut self) -> Result<(), SharedLibError> {
+        let state = self.save_state()?;
+        self.reload()?;
+        if let Some(state) = state {
+            self.restore_state(&state)?;
+        }
+        Ok(())
+    }
+    /// Copy the state returned by [`SAVE_STATE_SYMBOL`], or return `None` if the library does not export it.
+    pub(crate) unsafe fn save_state(&self) -> Result<Option<Vec<u8>>, SharedLibError> {
+        if !self.has_symbol(SAVE_STATE_SYMBOL) {
+            return Ok(None);
+        }
+        let mut len = 0;
+        let data = self.get_fn::<SaveStateFn>(SAVE_STATE_SYMBOL)?.run(&mut len);
+        match data.is_null() {
+            true => Ok(Some(Vec::new())),
+            false => Ok(Some(std::slice::from_raw_parts(data, len).to_vec())),
+        }
+    }
+    /// Pass the state to [`RESTORE_STATE_SYMBOL`], unless the library does not export it.
+    pub(crate) unsafe fn restore_state(&self, state: &[u8]) -> Result<(), SharedLibError> {
+        if self.has_symbol(RESTORE_STATE_SYMBOL) {
+            self.get_fn::<RestoreStateFn>(RESTORE_STATE_SYMBOL)?.run(state.as_ptr(), state.len());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+//! Stubbing libraries without loading them, e.g. in CI or under Miri, see [`SharedLib::set_dry_run`].
+
+use crate::{LibPath, LoadFlags, MockLib, SharedLib};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+/// Stubs registered with `SharedLib::register_stub`, with unique library names.
+static STUBS: Mutex<Vec<Arc<MockLib>>> = Mutex::new(Vec::new());
+
+impl SharedLib {
+    /// Enable or disable the dry-run mode of the process, in which libraries are stubbed instead of loaded,
+    /// e.g. to run code which normally needs native libraries in CI, when cross-compiling or under Miri.
+    ///
+    /// In dry-run mode, [`SharedLib::new`] and the other functions loading a library from its path succeed without accessing
+    /// the file system or the dynamic loader, and [`SharedLib::get_fn`] returns the functions of the stub registered with
+    /// [`SharedLib::register_stub`] for the library name, failing with [`SharedLibError::SymbolNotFound`](crate::SharedLibError::SymbolNotFound)
+    /// for the others. Reloading a stubbed library picks up the stub registered at that time. Functions which need the library
+    /// to be loaded, e.g. [`SharedLib::get_fn_owned`] or [`SharedLib::module_info`], fail for stubbed libraries.
+    /// Libraries loaded before the dry-run mode is enabled are not affected.
+    /// # Example
+    /// ```no_run
+    /// use shared_lib::*;
+    /// SharedLib::set_dry_run(std::env::var_os("CI").is_some());
+    /// SharedLib::register_stub(MockLib::new("calculator").with_fn::<fn(usize, usize) -> usize>("add", |a, b| a + b));
+    /// unsafe {
+    ///     let lib = SharedLib::new(LibPath::new_no_path("calculator".into())).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+    ///     assert_eq!(add_fn.run(1, 2), 3);
+    /// }
+    /// ```
+    pub fn set_dry_run(enabled: bool) {
+        DRY_RUN.store(enabled, Ordering::Relaxed);
+    }
+    /// Check if the dry-run mode is enabled, see [`SharedLib::set_dry_run`].
+    pub fn is_dry_run() -> bool {
+        DRY_RUN.load(Ordering::Relaxed)
+    }
+    /// Register `stub` as the stub of the libraries named like it in dry-run mode, replacing the stub registered with the same name before.
+    pub fn register_stub(stub: MockLib) {
+        let mut stubs = STUBS.lock().unwrap_or_else(|e| e.into_inner());
+        stubs.retain(|registered| registered.lib_name() != stub.lib_name());
+        stubs.push(Arc::new(stub));
+    }
+    /// Check if the library is stubbed, because it was loaded in dry-run mode.
+    pub fn is_stubbed(&self) -> bool {
+        self.stub.is_some()
+    }
+    /// Create the stub of the library if the dry-run mode is enabled.
+    pub(crate) fn open_stub(lib_path: &LibPath, flags: LoadFlags) -> Option<SharedLib> {
+        if !SharedLib::is_dry_run() {
+            return None;
+        }
+        let stubs = STUBS.lock().unwrap_or_else(|e| e.into_inner());
+        let stub = stubs.iter().find(|stub| stub.lib_name() == lib_path.lib_name).cloned();
+        let stub = stub.unwrap_or_else(|| Arc::new(MockLib::new(lib_path.lib_name.clone())));
+        let mut lib = SharedLib::from_library(None, lib_path.clone(), flags);
+        lib.stub = Some(stub);
+        Some(lib)
+    }
+}
@@ -0,0 +1,75 @@
+//! Suggestions of exported names for symbols that are not found, see [`LoadFlags::with_symbol_suggestions`](crate::LoadFlags::with_symbol_suggestions).
+
+use crate::{inspect, LoaderError, SharedLib, SharedLibError};
+
+/// Maximum number of names suggested for a missing symbol.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Get the exported names closest to `symbol_name`, closest first.
+///
+/// Names within an edit distance of a third of the length of `symbol_name` (at least 1) are suggested,
+/// as well as names mangled from a path ending with `symbol_name`, e.g. `_ZN4math3add17h0123456789abcdefE` for `add`,
+/// which are exported by Rust functions missing `#[no_mangle]`.
+pub(crate) fn suggestions(symbol_name: &str, exported: &[String]) -> Vec<String> {
+    let max_distance = (symbol_name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &String)> = exported
+        .iter()
+        .filter_map(|name| match mangled_name(name) == Some(symbol_name) {
+            true => Some((0, name)),
+            false => Some((edit_distance(symbol_name, name), name)).filter(|(distance, _)| (1..=max_distance).contains(distance)),
+        })
+        .collect();
+    matches.sort();
+    matches.dedup_by(|(_, a), (_, b)| a == b);
+    matches.into_iter().take(MAX_SUGGESTIONS).map(|(_, name)| name.clone()).collect()
+}
+
+/// Get the last identifier of an Itanium mangled nested name, e.g. `add` of `_ZN4math3addEii`,
+/// skipping the hash Rust appends to its legacy mangled names.
+fn mangled_name(symbol: &str) -> Option<&str> {
+    let mut rest = symbol.strip_prefix("_ZN")?;
+    let mut last = None;
+    while let Some(len_digits) = rest.find(|c: char| !c.is_ascii_digit()).filter(|&i| i > 0) {
+        let len: usize = rest[..len_digits].parse().ok()?;
+        let ident = rest.get(len_digits..len_digits + len)?;
+        rest = &rest[len_digits + len..];
+        let is_hash = ident.len() == 17 && ident.starts_with('h') && ident[1..].bytes().all(|b| b.is_ascii_hexdigit());
+        if !is_hash {
+            last = Some(ident);
+        }
+    }
+    rest.starts_with('E').then_some(last?)
+}
+
+/// Get the Levenshtein distance between `a` and `b`, i.e. the number of characters to insert, remove or replace.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+impl SharedLib {
+    /// Create a [`SharedLibError::SymbolNotFound`] of the symbol, suggesting the closest exported names
+    /// if the library was loaded with [`LoadFlags::with_symbol_suggestions`](crate::LoadFlags::with_symbol_suggestions).
+    pub(crate) fn symbol_not_found(&self, symbol_name: &str, e: impl Into<LoaderError>) -> SharedLibError {
+        let mut e = e.into();
+        if self.flags.is_symbol_suggestions() {
+            let exported = self.lib_path.resolve().and_then(inspect::exported_symbols).unwrap_or_default();
+            let suggestions = suggestions(symbol_name, &exported);
+            if !suggestions.is_empty() {
+                let names = suggestions.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", ");
+                e.msg = format!("{} Did you mean {}?", e.msg, names);
+            }
+        }
+        SharedLibError::symbol_not_found(symbol_name, self.lib_path.error_path(), e)
+    }
+}
@@ -0,0 +1,112 @@
+//! Compiling libraries from source for tests, see [`TestLib`].
+//!
+//! # Example
+//! ```no_run
+//! use shared_lib::testing::TestLib;
+//! unsafe {
+//!     let lib = TestLib::rust("#[no_mangle] pub extern \"C\" fn add(a: i32, b: i32) -> i32 { a + b }")
+//!         .compile()
+//!         .expect("Failed to compile test library");
+//!     let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("add").expect("Failed to get 'add' function from library");
+//!     assert_eq!(add_fn.run(1, 2), 3);
+//! }
+//! ```
+
+use crate::{ExtractionGuard, LibPath, SharedLib, SharedLibError};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Language of the source of a [`TestLib`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Language {
+    Rust,
+    C,
+}
+
+/// Structure representing a library compiled from source into a temporary directory and loaded, e.g. as a fixture of a test.
+///
+/// Rust sources are compiled with `rustc --crate-type cdylib`, and C sources with `cc -shared`. The compiler is taken from the
+/// `RUSTC` and `CC` environment variables if they are set, and must accept the arguments of `rustc` or of gcc and clang,
+/// e.g. MinGW gcc or clang on Windows rather than MSVC. The temporary directory is removed when the library is dropped,
+/// see [`SharedLib::extraction`].
+pub struct TestLib {
+    source: String,
+    language: Language,
+    lib_name: String,
+    compiler: Option<PathBuf>,
+    args: Vec<OsString>,
+}
+impl TestLib {
+    /// Create a new library compiled from the Rust source of a crate, whose functions are exported with `#[no_mangle]`.
+    pub fn rust(source: impl Into<String>) -> TestLib {
+        TestLib::new(source.into(), Language::Rust)
+    }
+    /// Create a new library compiled from C source.
+    pub fn c(source: impl Into<String>) -> TestLib {
+        TestLib::new(source.into(), Language::C)
+    }
+    fn new(source: String, language: Language) -> TestLib {
+        TestLib { source, language, lib_name: "test_lib".into(), compiler: None, args: Vec::new() }
+    }
+    /// Set the name of the library, without the platform specific extension and prefix, which is `test_lib` by default.
+    pub fn lib_name(mut self, lib_name: impl Into<String>) -> TestLib {
+        self.lib_name = lib_name.into();
+        self
+    }
+    /// Set the path of the compiler, instead of the one of the `RUSTC` or `CC` environment variable, or `rustc` and `cc` in `PATH`.
+    pub fn compiler_path(mut self, path: impl Into<PathBuf>) -> TestLib {
+        self.compiler = Some(path.into());
+        self
+    }
+    /// Pass an argument to the compiler, e.g. `-O` or `-DVERSION=2`.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> TestLib {
+        self.args.push(arg.into());
+        self
+    }
+    /// Compile the source and load the library.
+    /// # Errors
+    /// Returns [`SharedLibError::CompileFailure`] with the output of the compiler if it cannot be run or fails to compile the source.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn compile(&self) -> Result<SharedLib, SharedLibError> {
+        let compile_failure = |msg: String| SharedLibError::CompileFailure { lib_name: self.lib_name.clone(), msg };
+        let extraction = ExtractionGuard::create_dir(&self.lib_name).map_err(|e| compile_failure(format!("Failed to create the build directory. {}", e)))?;
+        let lib_path = LibPath::new(extraction.path().to_path_buf(), self.lib_name.clone());
+        let source_path = extraction.path().join(match self.language {
+            Language::Rust => "lib.rs",
+            Language::C => "lib.c",
+        });
+        std::fs::write(&source_path, &self.source).map_err(|e| compile_failure(format!("Failed to write the source. {}", e)))?;
+        let (env_var, default) = match self.language {
+            Language::Rust => ("RUSTC", "rustc"),
+            Language::C => ("CC", "cc"),
+        };
+        let compiler = self.compiler.clone().or_else(|| std::env::var_os(env_var).map(PathBuf::from)).unwrap_or_else(|| PathBuf::from(default));
+        let mut command = Command::new(&compiler);
+        match self.language {
+            Language::Rust => {
+                command.args(["--crate-type", "cdylib", "--edition", "2021", "--crate-name"]).arg(self.lib_name.replace('-', "_"));
+            }
+            Language::C if cfg!(windows) => {
+                command.arg("-shared");
+            }
+            Language::C => {
+                command.args(["-shared", "-fPIC"]);
+            }
+        }
+        let output = command
+            .arg("-o")
+            .arg(lib_path.path()?)
+            .arg(&source_path)
+            .args(&self.args)
+            .output()
+            .map_err(|e| compile_failure(format!("Failed to run '{}'. {}", compiler.display(), e)))?;
+        if !output.status.success() {
+            return Err(compile_failure(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+        }
+        let mut lib = SharedLib::new(lib_path)?;
+        lib.extraction = Some(extraction);
+        Ok(lib)
+    }
+}
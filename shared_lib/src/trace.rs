@@ -0,0 +1,11 @@
+//! `tracing` events of loading libraries, resolving symbols, reloading and unloading, enabled by the `tracing` feature.
+
+/// Emit a `tracing` event of the given level with the `shared_lib` target if the `tracing` feature is enabled,
+/// e.g. `event!(debug, path = %lib_path, "Loaded library")`.
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!(target: "shared_lib", $($arg)+)
+    };
+}
+pub(crate) use event;
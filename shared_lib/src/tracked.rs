@@ -0,0 +1,101 @@
+//! Functions tracking the generation of their library to detect that it was reloaded or unloaded, see [`TrackedSharedLibFn`].
+
+use crate::{CallGate, FnSignature, SharedLib, SharedLibError, SharedLibFn};
+
+/// Structure representing a shared library function which fails with [`SharedLibError::StaleSymbol`] instead of being called
+/// once its library was reloaded or unloaded, e.g. a handle forgotten by code which does not know of a hot reload.
+///
+/// Like [`OwnedSharedLibFn`](crate::OwnedSharedLibFn), it does not borrow the [`SharedLib`] it was resolved from, but unlike it,
+/// it does not keep the library loaded either. The function belongs to the generation of the library it was looked up from,
+/// see [`SharedLib::generation`]. Every call is marked as in flight with the [`CallGate`] of the library,
+/// so the library is not unloaded or reloaded while the function runs.
+/// # Example
+/// ```no_run
+/// use std::path::PathBuf;
+/// use shared_lib::*;
+/// unsafe {
+///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+///     let mut lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+///     let add_fn = lib.get_fn_tracked::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+///     assert_eq!(add_fn.run(1, 2).unwrap(), 3);
+///     lib.reload().expect("Failed to reload shared library");
+///     assert!(matches!(add_fn.run(1, 2), Err(SharedLibError::StaleSymbol { .. })));
+/// }
+/// ```
+#[derive(Clone)]
+pub struct TrackedSharedLibFn<Fn: 'static> {
+    func: SharedLibFn<'static, Fn>,
+    gate: CallGate,
+    fn_name: String,
+    path: String,
+    generation: u64,
+}
+impl<Fn: 'static> TrackedSharedLibFn<Fn> {
+    /// Get the generation of the library the function was looked up from, see [`SharedLib::generation`].
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+    /// Check if the library the function was looked up from was reloaded or unloaded, so calls of the function fail.
+    pub fn is_stale(&self) -> bool {
+        !self.gate.is_valid()
+    }
+    /// Get the error returned by calls of the function after its library was reloaded or unloaded.
+    fn stale(&self) -> SharedLibError {
+        SharedLibError::StaleSymbol { symbol_name: self.fn_name.clone(), path: self.path.clone(), generation: self.generation }
+    }
+}
+
+macro_rules! impl_tracked_run {
+    ([$($fn_ty:tt)+] $($a:ident: $A:ident),*) => {
+        #[allow(clippy::too_many_arguments)]
+        impl<Ret: 'static, $($A: 'static),*> TrackedSharedLibFn<$($fn_ty)+ ($($A),*) -> Ret> {
+            /// Call the function, returning [`SharedLibError::StaleSymbol`] if its library was reloaded or unloaded.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            pub unsafe fn run(&self, $($a: $A),*) -> Result<Ret, SharedLibError> {
+                let Some(_call) = self.gate.enter() else {
+                    return Err(self.stale());
+                };
+                Ok(self.func.run($($a),*))
+            }
+        }
+    };
+}
+macro_rules! impl_tracked_run_all {
+    ($($a:ident: $A:ident),*) => {
+        impl_tracked_run!([fn] $($a: $A),*);
+        impl_tracked_run!([unsafe fn] $($a: $A),*);
+        impl_tracked_run!([extern "C" fn] $($a: $A),*);
+        impl_tracked_run!([unsafe extern "C" fn] $($a: $A),*);
+    };
+}
+impl_tracked_run_all!();
+impl_tracked_run_all!(a1: A1);
+impl_tracked_run_all!(a1: A1, a2: A2);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15);
+impl_tracked_run_all!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15, a16: A16);
+
+impl SharedLib {
+    /// Get a function by name from the shared library, which fails instead of being called once the library is reloaded
+    /// or unloaded, see [`TrackedSharedLibFn`].
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    pub unsafe fn get_fn_tracked<T: FnSignature>(&self, fn_name: &str) -> Result<TrackedSharedLibFn<T>, SharedLibError> {
+        let func = self.get_fn::<T>(fn_name)?;
+        // The symbol is only called through the gate, which fails once the library it was looked up from is unloaded.
+        let func = std::mem::transmute::<SharedLibFn<'_, T>, SharedLibFn<'static, T>>(func);
+        Ok(TrackedSharedLibFn { func, gate: self.call_gate(), fn_name: fn_name.to_owned(), path: self.lib_path.lossy_path(), generation: self.generation })
+    }
+}
@@ -0,0 +1,156 @@
+//! Verification of the signatures of the functions exported by a library, see [`SharedLib::get_fn_checked`].
+//!
+//! A library takes part by exporting a signature descriptor next to each function, named like the function with the
+//! [`SIGNATURE_SUFFIX`], which holds the [`TypeSignature::SIGNATURE`] hash of the function type as a `u64`.
+//! Rust libraries export it with [`export_signature!`](crate::export_signature):
+//! ```
+//! use shared_lib::*;
+//!
+//! #[no_mangle]
+//! pub extern "C" fn add(left: u32, right: u32) -> u32 {
+//!     left + right
+//! }
+//! export_signature!(add: extern "C" fn(u32, u32) -> u32);
+//! ```
+
+use crate::{FnSignature, SharedLib, SharedLibError, SharedLibFn};
+use std::ffi::c_void;
+
+/// Suffix of the name of the signature descriptor exported for a function, e.g. `add__sig` for `add`.
+pub const SIGNATURE_SUFFIX: &str = "__sig";
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+const fn hash_bytes(mut hash: u64, bytes: &[u8]) -> u64 {
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// Hash the name of a type for its [`TypeSignature::SIGNATURE`], e.g. of a `#[repr(C)]` struct passed to an exported function.
+pub const fn signature_of(name: &str) -> u64 {
+    hash_bytes(FNV_OFFSET, name.as_bytes())
+}
+
+/// Combine the signature of a type with the signature of a type it is composed of, e.g. a pointer with its pointee.
+pub const fn combine_signatures(signature: u64, part: u64) -> u64 {
+    hash_bytes(signature, &part.to_le_bytes())
+}
+
+/// Types whose structure is described by a hash, which is the same in every build of the library and of the application.
+///
+/// Implemented for the primitive types, raw pointers, `Option` and function pointers of up to 8 arguments with the Rust
+/// or C ABI. Other types passed to exported functions, e.g. `#[repr(C)]` structs, can implement it with [`signature_of`].
+/// The hash only depends on the names of the types, so two types with the same name but another layout have the same hash.
+pub trait TypeSignature {
+    /// Hash describing the type.
+    const SIGNATURE: u64;
+}
+
+macro_rules! impl_named_signature {
+    ($($ty:ty),*) => {
+        $(
+            impl TypeSignature for $ty {
+                const SIGNATURE: u64 = signature_of(stringify!($ty));
+            }
+        )*
+    };
+}
+impl_named_signature!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, bool, char, ());
+impl TypeSignature for c_void {
+    const SIGNATURE: u64 = signature_of("c_void");
+}
+impl<T: TypeSignature> TypeSignature for *const T {
+    const SIGNATURE: u64 = combine_signatures(signature_of("*const"), T::SIGNATURE);
+}
+impl<T: TypeSignature> TypeSignature for *mut T {
+    const SIGNATURE: u64 = combine_signatures(signature_of("*mut"), T::SIGNATURE);
+}
+impl<T: TypeSignature> TypeSignature for Option<T> {
+    const SIGNATURE: u64 = combine_signatures(signature_of("Option"), T::SIGNATURE);
+}
+
+macro_rules! impl_fn_signature {
+    ([$($fn_ty:tt)+] $($A:ident),*) => {
+        impl<Ret: TypeSignature, $($A: TypeSignature),*> TypeSignature for $($fn_ty)+ ($($A),*) -> Ret {
+            const SIGNATURE: u64 = {
+                let arg_count = <[&str]>::len(&[$(stringify!($A)),*]);
+                let signature = combine_signatures(signature_of(stringify!($($fn_ty)+)), arg_count as u64);
+                $(let signature = combine_signatures(signature, $A::SIGNATURE);)*
+                combine_signatures(signature, Ret::SIGNATURE)
+            };
+        }
+    };
+}
+macro_rules! impl_fn_signature_all {
+    ($($A:ident),*) => {
+        impl_fn_signature!([fn] $($A),*);
+        impl_fn_signature!([unsafe fn] $($A),*);
+        impl_fn_signature!([extern "C" fn] $($A),*);
+        impl_fn_signature!([unsafe extern "C" fn] $($A),*);
+    };
+}
+impl_fn_signature_all!();
+impl_fn_signature_all!(A1);
+impl_fn_signature_all!(A1, A2);
+impl_fn_signature_all!(A1, A2, A3);
+impl_fn_signature_all!(A1, A2, A3, A4);
+impl_fn_signature_all!(A1, A2, A3, A4, A5);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7);
+impl_fn_signature_all!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+/// Export the signature descriptor of a function of the library, see [`SharedLib::get_fn_checked`].
+///
+/// Takes the name of the function, which must be in scope, and its function type, e.g.
+/// `export_signature!(add: extern "C" fn(u32, u32) -> u32)`. It does not compile if the function has another type.
+#[macro_export]
+macro_rules! export_signature {
+    ($fn_name:ident: $fn_type:ty) => {
+        const _: () = {
+            const _: $fn_type = $fn_name;
+            #[export_name = concat!(stringify!($fn_name), "__sig")]
+            pub static SIGNATURE: u64 = <$fn_type as $crate::TypeSignature>::SIGNATURE;
+        };
+    };
+}
+
+impl SharedLib {
+    /// Get a function by name from the shared library like [`SharedLib::get_fn`], after checking that it was exported
+    /// with the requested type.
+    ///
+    /// The library must export the signature descriptor of the function, see the [`export_signature!`](crate::export_signature) macro.
+    /// Returns [`SharedLibError::SymbolNotFound`] if the function or its descriptor is not exported, and
+    /// [`SharedLibError::SignatureMismatch`] if the descriptor describes another function type.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// The descriptor only describes the names of the types, so it cannot rule out every mismatch, e.g. of a struct layout.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+    ///     let add_fn = lib.get_fn_checked::<extern "C" fn(u32, u32) -> u32>("add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn get_fn_checked<T: TypeSignature + FnSignature>(&self, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        let func = self.get_fn::<T>(fn_name)?;
+        let found = *self.get_var::<u64>(&format!("{}{}", fn_name, SIGNATURE_SUFFIX))?;
+        if found != T::SIGNATURE {
+            return Err(SharedLibError::SignatureMismatch {
+                symbol_name: fn_name.to_owned(),
+                path: self.lib_path.lossy_path(),
+                expected: std::any::type_name::<T>().to_owned(),
+            });
+        }
+        Ok(func)
+    }
+}
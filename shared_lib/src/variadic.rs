@@ -0,0 +1,106 @@
+//! Support for calling C-variadic functions, e.g. `extern "C" fn(*const c_char, ...)`.
+//!
+//! The variadic arguments are passed to [`SharedLibFn::run`] as a tuple after the fixed arguments.
+//!
+//! # Example
+//! ```no_run
+//! use std::ffi::c_char;
+//! use std::path::PathBuf;
+//! use shared_lib::*;
+//! unsafe {
+//!     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+//!     let lib = SharedLib::new(lib_path).expect("Failed to load shared library");
+//!     let log_fn = lib.get_fn::<unsafe extern "C" fn(*const c_char, ...)>("log").expect("Failed to get 'log' function from shared library");
+//!     log_fn.run(c"%d + %d".as_ptr(), (1, 2));
+//! }
+//! ```
+
+use crate::SharedLibFn;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Types which can be passed as a variadic argument of a C function.
+///
+/// C promotes smaller integer types to `int` and `float` to `double` when passing them as variadic arguments,
+/// so only the promoted types are allowed. Cast the value before passing it, e.g. `x as i32` or `y as f64`.
+pub trait VarArg: sealed::Sealed {}
+
+macro_rules! impl_var_arg {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl VarArg for $ty {}
+        )*
+    };
+}
+impl_var_arg!(i32, u32, i64, u64, isize, usize, f64);
+impl<T> sealed::Sealed for *const T {}
+impl<T> VarArg for *const T {}
+impl<T> sealed::Sealed for *mut T {}
+impl<T> VarArg for *mut T {}
+
+/// Tuples of [`VarArg`]s, passed as the variadic arguments of a C function.
+pub trait VarArgs: sealed::Sealed {
+    #[doc(hidden)]
+    unsafe fn call1<Ret, A1>(self, f: unsafe extern "C" fn(A1, ...) -> Ret, a1: A1) -> Ret;
+    #[doc(hidden)]
+    unsafe fn call2<Ret, A1, A2>(self, f: unsafe extern "C" fn(A1, A2, ...) -> Ret, a1: A1, a2: A2) -> Ret;
+    #[doc(hidden)]
+    unsafe fn call3<Ret, A1, A2, A3>(self, f: unsafe extern "C" fn(A1, A2, A3, ...) -> Ret, a1: A1, a2: A2, a3: A3) -> Ret;
+}
+
+macro_rules! impl_var_args {
+    ($($v:ident),*) => {
+        impl<$($v: VarArg),*> sealed::Sealed for ($($v,)*) {}
+        #[allow(non_snake_case)]
+        impl<$($v: VarArg),*> VarArgs for ($($v,)*) {
+            unsafe fn call1<Ret, A1>(self, f: unsafe extern "C" fn(A1, ...) -> Ret, a1: A1) -> Ret {
+                let ($($v,)*) = self;
+                f(a1, $($v),*)
+            }
+            unsafe fn call2<Ret, A1, A2>(self, f: unsafe extern "C" fn(A1, A2, ...) -> Ret, a1: A1, a2: A2) -> Ret {
+                let ($($v,)*) = self;
+                f(a1, a2, $($v),*)
+            }
+            unsafe fn call3<Ret, A1, A2, A3>(self, f: unsafe extern "C" fn(A1, A2, A3, ...) -> Ret, a1: A1, a2: A2, a3: A3) -> Ret {
+                let ($($v,)*) = self;
+                f(a1, a2, a3, $($v),*)
+            }
+        }
+    };
+}
+impl_var_args!();
+impl_var_args!(V1);
+impl_var_args!(V1, V2);
+impl_var_args!(V1, V2, V3);
+impl_var_args!(V1, V2, V3, V4);
+impl_var_args!(V1, V2, V3, V4, V5);
+impl_var_args!(V1, V2, V3, V4, V5, V6);
+impl_var_args!(V1, V2, V3, V4, V5, V6, V7);
+impl_var_args!(V1, V2, V3, V4, V5, V6, V7, V8);
+
+macro_rules! impl_variadic_run {
+    ($call:ident, $($a:ident: $A:ident),*) => {
+        impl<'a, Ret, $($A),*> SharedLibFn<'a, unsafe extern "C" fn($($A),*, ...) -> Ret> {
+            /// Call the function with the given fixed arguments, followed by the variadic arguments from the `args` tuple.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            pub unsafe fn run<V: VarArgs>(&self, $($a: $A),*, args: V) -> Ret {
+                self.measured(|| args.$call(*self.symbol, $($a),*))
+            }
+        }
+        impl<'a, Ret, $($A),*> SharedLibFn<'a, extern "C" fn($($A),*, ...) -> Ret> {
+            /// Call the function with the given fixed arguments, followed by the variadic arguments from the `args` tuple.
+            /// # Safety
+            /// This function is unsafe because it calls a foreign function, which may violate Rust's safety guarantees.
+            pub unsafe fn run<V: VarArgs>(&self, $($a: $A),*, args: V) -> Ret {
+                self.measured(|| args.$call(*self.symbol, $($a),*))
+            }
+        }
+    };
+}
+impl_variadic_run!(call1, a1: A1);
+impl_variadic_run!(call2, a1: A1, a2: A2);
+impl_variadic_run!(call3, a1: A1, a2: A2, a3: A3);
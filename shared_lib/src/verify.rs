@@ -0,0 +1,34 @@
+//! Verification of library files before loading them.
+
+use crate::SharedLibError;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Check that the SHA-256 hash of the library file at `path`, opened as `file`, is `expected_sha256` (a hex string).
+pub(crate) fn check_sha256(path: &Path, file: &mut File, expected_sha256: &str) -> Result<(), SharedLibError> {
+    let actual = sha256_hex(file).map_err(|e| SharedLibError::load_failure(path, e))?;
+    if !actual.eq_ignore_ascii_case(expected_sha256.trim()) {
+        return Err(SharedLibError::ChecksumMismatch {
+            path: path.to_string_lossy().to_string(),
+            expected: expected_sha256.to_owned(),
+            actual,
+        });
+    }
+    Ok(())
+}
+
+/// Compute the SHA-256 hash of everything read from `reader`, as a lowercase hex string.
+pub(crate) fn sha256_hex(reader: &mut impl Read) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
@@ -0,0 +1,58 @@
+//! Lookup of versioned symbols, e.g. `memcpy@GLIBC_2.14`, which is only supported by glibc.
+
+use crate::{FnSignature, SharedLib, SharedLibError, SharedLibFn};
+
+impl SharedLib {
+    /// Get a function by name and symbol version from the shared library, e.g. `memcpy` of version `GLIBC_2.14`.
+    ///
+    /// The function is looked up with `dlvsym`, so this is only supported on Linux with glibc,
+    /// and returns [`SharedLibError::Unsupported`] on other platforms.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::ffi::c_void;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib = SharedLib::new(LibPath::new_no_path("c".into()).with_version("6".into())).expect("Failed to load libc");
+    ///     let memcpy_fn = lib
+    ///         .get_fn_versioned::<unsafe extern "C" fn(*mut c_void, *const c_void, usize) -> *mut c_void>("memcpy", "GLIBC_2.14")
+    ///         .expect("Failed to get 'memcpy' function from libc");
+    /// }
+    /// ```
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    pub unsafe fn get_fn_versioned<T: FnSignature>(&self, fn_name: &str, version: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        use crate::dl::{last_error, NoLoadHandle};
+        use crate::FnSymbol;
+        use std::ffi::{c_char, c_void, CString};
+
+        extern "C" {
+            fn dlvsym(handle: *mut c_void, symbol: *const c_char, version: *const c_char) -> *mut c_void;
+        }
+
+        assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<*mut c_void>(), "the function type must be a function pointer");
+        self.library()?;
+        let path = self.lib_path.path()?;
+        let symbol_not_found = |msg: String| SharedLibError::symbol_not_found(format!("{}@{}", fn_name, version), &path, msg);
+        let nul_error = |e: std::ffi::NulError| symbol_not_found(e.to_string());
+        let (fn_name, version) = (CString::new(fn_name).map_err(nul_error)?, CString::new(version).map_err(nul_error)?);
+
+        let handle = NoLoadHandle::open(Some(&path)).map_err(symbol_not_found)?;
+        let pointer = dlvsym(handle.0, fn_name.as_ptr(), version.as_ptr());
+        if pointer.is_null() {
+            return Err(symbol_not_found(last_error()));
+        }
+        let func = std::mem::transmute_copy::<*mut c_void, T>(&pointer);
+        Ok(SharedLibFn { symbol: FnSymbol::Resolved(func, std::marker::PhantomData), metrics: None })
+    }
+    /// Get a function by name and symbol version from the shared library, e.g. `memcpy` of version `GLIBC_2.14`.
+    ///
+    /// The function is looked up with `dlvsym`, so this is only supported on Linux with glibc,
+    /// and returns [`SharedLibError::Unsupported`] on other platforms.
+    /// # Safety
+    /// This function is unsafe because it loads a function from the shared library, which is generally unsafe as it is a foregin code.
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    pub unsafe fn get_fn_versioned<T: FnSignature>(&self, _fn_name: &str, _version: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        Err(SharedLibError::Unsupported("Looking up versioned symbols".into()))
+    }
+}
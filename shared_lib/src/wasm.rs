@@ -0,0 +1,210 @@
+//! Loading of WebAssembly modules through the same API as native libraries, see [`SharedLib::new`].
+//!
+//! The functions of a module are looked up as function pointer types, which are not bound by any trait, so the types of their
+//! arguments and result are matched with the Rust types of WebAssembly values by their `TypeId`, and calls are dispatched to
+//! the interpreter through a function monomorphized for the function pointer type when it was looked up.
+
+use crate::wasm_vm::{self, FuncType, Instance, ValType};
+use crate::{trace, FilenameConvention, FnSignature, FnSymbol, LibEventKind, LibPath, LoadFlags, SharedLib, SharedLibError, SharedLibFn};
+use std::any::TypeId;
+use std::io::Read;
+use std::mem::MaybeUninit;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// WebAssembly module a [`SharedLib`] was loaded from instead of a native library.
+pub(crate) struct WasmModule {
+    // Locked for every call, since the functions of the module share its memory and globals.
+    instance: Mutex<Instance>,
+}
+
+/// Check if the file at `path` is a WebAssembly module.
+pub(crate) fn is_module(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == b"\0asm"
+}
+
+/// Get the library path of the `{lib_name}.wasm` module in the directory of `lib_path`, if the module exists while the native
+/// library does not, and `lib_path` otherwise.
+pub(crate) fn module_path(lib_path: LibPath) -> LibPath {
+    if lib_path.dir_path.as_os_str().is_empty() || lib_path.path().is_ok_and(|path| path.exists()) {
+        return lib_path;
+    }
+    let mut module_path = lib_path.clone().with_convention(FilenameConvention::new("", ".wasm"));
+    module_path.version = None;
+    match module_path.path().is_ok_and(|path| path.is_file()) {
+        true => module_path,
+        false => lib_path,
+    }
+}
+
+impl SharedLib {
+    /// Load the WebAssembly module from `file`, which refers to the same file as `lib_path`.
+    pub(crate) fn open_wasm(lib_path: LibPath, file: &Path, flags: LoadFlags) -> Result<SharedLib, SharedLibError> {
+        let load_failure = |msg: String| SharedLibError::load_failure(lib_path.error_path(), msg);
+        let bytes = std::fs::read(file).map_err(|e| load_failure(format!("Failed to read the WebAssembly module. {}", e)))?;
+        let instance = wasm_vm::decode(&bytes)
+            .and_then(Instance::new)
+            .map_err(|e| load_failure(format!("Failed to instantiate the WebAssembly module. {}", e)))?;
+        trace::event!(debug, path = %lib_path, ?flags, "Loaded WebAssembly module");
+        let mut lib = SharedLib::from_library(None, lib_path, flags);
+        lib.wasm = Some(Arc::new(WasmModule { instance: Mutex::new(instance) }));
+        Ok(lib)
+    }
+    /// Get the function `fn_name` exported by the WebAssembly module, checking that its type is `T`.
+    pub(crate) fn wasm_fn<T: FnSignature>(&self, module: &Arc<WasmModule>, fn_name: &str) -> Result<SharedLibFn<'_, T>, SharedLibError> {
+        let instance = module.instance.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((func, ty)) = instance.export(fn_name) else {
+            return Err(self.symbol_not_found(fn_name, String::from("The WebAssembly module does not export the function.")));
+        };
+        let signature = T::wasm_signature().filter(|signature| signature.ty == *ty).ok_or_else(|| SharedLibError::SignatureMismatch {
+            symbol_name: fn_name.to_owned(),
+            path: self.lib_path.lossy_path(),
+            expected: std::any::type_name::<T>().to_owned(),
+        })?;
+        drop(instance);
+        trace::event!(trace, path = %self.lib_path, symbol = fn_name, "Resolved symbol");
+        self.hooks.emit(&self.lib_path, || LibEventKind::SymbolResolved { symbol_name: fn_name.to_owned() });
+        let func = WasmFn { module: Arc::clone(module), func, symbol_name: fn_name.to_owned(), path: self.lib_path.lossy_path(), call: signature.call };
+        Ok(self.with_metrics(fn_name, SharedLibFn { symbol: FnSymbol::Wasm(func), metrics: None }))
+    }
+}
+
+/// Call of a WebAssembly function with the arguments and the result of the function type it was looked up as.
+type ErasedCall = unsafe fn(&WasmFn, *const (), *mut ()) -> Result<(), SharedLibError>;
+
+/// Function exported by a WebAssembly module, see [`SharedLib::wasm_fn`].
+#[derive(Clone)]
+pub(crate) struct WasmFn {
+    module: Arc<WasmModule>,
+    func: u32,
+    symbol_name: String,
+    path: String,
+    call: ErasedCall,
+}
+impl WasmFn {
+    /// Call the function with `args`, returning its result or [`SharedLibError::WasmTrap`] if it trapped.
+    /// # Safety
+    /// `Args` must be the tuple of the arguments and `Ret` the result of the function type the function was looked up as.
+    pub(crate) unsafe fn call<Args, Ret>(&self, args: Args) -> Result<Ret, SharedLibError> {
+        let mut ret = MaybeUninit::<Ret>::uninit();
+        (self.call)(self, (&args as *const Args).cast(), ret.as_mut_ptr().cast())?;
+        Ok(ret.assume_init())
+    }
+    fn invoke(&self, args: &[u64]) -> Result<Vec<u64>, SharedLibError> {
+        let mut instance = self.module.instance.lock().unwrap_or_else(|e| e.into_inner());
+        instance.call(self.func, args).map_err(|msg| SharedLibError::WasmTrap { symbol_name: self.symbol_name.clone(), path: self.path.clone(), msg })
+    }
+}
+
+/// Type of a function pointer type as a WebAssembly function, see [`FnSignature::wasm_signature`].
+///
+/// It is public so it can be named by the sealed trait, but not reachable from outside of the crate.
+pub struct WasmSignature {
+    ty: FuncType,
+    call: ErasedCall,
+}
+impl WasmSignature {
+    /// Get the signature of functions taking the tuple of arguments `Args` and returning `Ret`,
+    /// if all of them are WebAssembly values.
+    pub(crate) fn of<Args: WasmArgs, Ret: 'static>() -> Option<WasmSignature> {
+        let results = match TypeId::of::<Ret>() == TypeId::of::<()>() {
+            true => Vec::new(),
+            false => vec![val_type::<Ret>()?],
+        };
+        Some(WasmSignature { ty: FuncType { params: Args::types()?, results }, call: call_erased::<Args, Ret> })
+    }
+}
+
+unsafe fn call_erased<Args: WasmArgs, Ret: 'static>(func: &WasmFn, args: *const (), ret: *mut ()) -> Result<(), SharedLibError> {
+    let results = func.invoke(&(*args.cast::<Args>()).bits())?;
+    if let Some(&result) = results.first() {
+        ret.cast::<Ret>().write(from_bits::<Ret>(result));
+    }
+    Ok(())
+}
+
+/// Tuple of the arguments of a function pointer type, see [`WasmSignature::of`].
+pub(crate) trait WasmArgs: 'static {
+    /// Get the types of the arguments, if all of them are WebAssembly values.
+    fn types() -> Option<Vec<ValType>>;
+    /// Get the bits of the arguments as WebAssembly values.
+    /// # Safety
+    /// The arguments must be WebAssembly values, see [`WasmArgs::types`].
+    unsafe fn bits(&self) -> Vec<u64>;
+}
+macro_rules! impl_wasm_args {
+    ($($a:ident: $A:ident),*) => {
+        impl<$($A: 'static),*> WasmArgs for ($($A,)*) {
+            fn types() -> Option<Vec<ValType>> {
+                Some(vec![$(val_type::<$A>()?),*])
+            }
+            unsafe fn bits(&self) -> Vec<u64> {
+                let ($($a,)*) = self;
+                vec![$(to_bits($a)),*]
+            }
+        }
+    };
+}
+impl_wasm_args!();
+impl_wasm_args!(a1: A1);
+impl_wasm_args!(a1: A1, a2: A2);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15);
+impl_wasm_args!(a1: A1, a2: A2, a3: A3, a4: A4, a5: A5, a6: A6, a7: A7, a8: A8, a9: A9, a10: A10, a11: A11, a12: A12, a13: A13, a14: A14, a15: A15, a16: A16);
+
+macro_rules! wasm_values {
+    ($value:ident, $bits:ident; $($ty:ty => $val_type:ident, $to_bits:expr, $from_bits:expr;)*) => {
+        /// Get the type of the WebAssembly value of the Rust type `T`, if it is one.
+        fn val_type<T: 'static>() -> Option<ValType> {
+            $(if TypeId::of::<T>() == TypeId::of::<$ty>() {
+                return Some(ValType::$val_type);
+            })*
+            None
+        }
+        /// Get the bits of `value` as a WebAssembly value.
+        /// # Safety
+        /// `T` must be the type of a WebAssembly value, see [`val_type`].
+        unsafe fn to_bits<T: 'static>(value: &T) -> u64 {
+            $(if TypeId::of::<T>() == TypeId::of::<$ty>() {
+                let $value = *(value as *const T).cast::<$ty>();
+                return $to_bits;
+            })*
+            unreachable!("the type is checked to be a WebAssembly value")
+        }
+        /// Get the value of type `T` from its bits as a WebAssembly value.
+        /// # Safety
+        /// `T` must be the type of a WebAssembly value, see [`val_type`].
+        unsafe fn from_bits<T: 'static>($bits: u64) -> T {
+            $(if TypeId::of::<T>() == TypeId::of::<$ty>() {
+                return std::mem::transmute_copy::<$ty, T>(&$from_bits);
+            })*
+            unreachable!("the type is checked to be a WebAssembly value")
+        }
+    };
+}
+wasm_values! {
+    value, bits;
+    i32 => I32, value as u32 as u64, bits as u32 as i32;
+    u32 => I32, value as u64, bits as u32;
+    i64 => I64, value as u64, bits as i64;
+    u64 => I64, value, bits;
+    f32 => F32, value.to_bits() as u64, f32::from_bits(bits as u32);
+    f64 => F64, value.to_bits(), f64::from_bits(bits);
+    bool => I32, value as u64, bits as u32 != 0;
+    i8 => I32, value as i32 as u32 as u64, bits as i8;
+    u8 => I32, value as u64, bits as u8;
+    i16 => I32, value as i32 as u32 as u64, bits as i16;
+    u16 => I32, value as u64, bits as u16;
+}
@@ -0,0 +1,1122 @@
+//! Decoding and interpretation of WebAssembly modules, for the modules loaded with the `wasm` feature, see [`wasm`](crate::wasm).
+//!
+//! The instructions of the WebAssembly 2.0 core specification are supported, except for SIMD, reference types and tables
+//! other than the function table used by `call_indirect`. Modules are checked while decoding for the invariants the interpreter
+//! relies on, e.g. valid indices and branch depths, but the types of the values are not, so an invalid module may compute
+//! wrong values. Every memory and table access is still checked, so it traps instead.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Size of a page of the linear memory.
+const PAGE_SIZE: usize = 65536;
+/// Maximum number of pages of the linear memory, 1 GiB.
+const MAX_PAGES: u32 = 16384;
+/// Maximum number of elements of the function table.
+const MAX_TABLE_LEN: u32 = 1 << 20;
+/// Maximum number of nested calls, reached e.g. by infinite recursion.
+const MAX_CALL_DEPTH: usize = 16384;
+/// Maximum number of values on the value stack and in the locals of all calls.
+const MAX_STACK_LEN: usize = 1 << 22;
+/// Maximum number of locals of a function.
+const MAX_LOCALS: u64 = 50_000;
+
+const OUT_OF_BOUNDS_MEMORY: &str = "Out of bounds memory access.";
+const OUT_OF_BOUNDS_TABLE: &str = "Out of bounds table access.";
+const STACK_EXHAUSTED: &str = "The call stack is exhausted.";
+// Only reached by invalid modules, since the operands of valid ones are checked while decoding.
+const STACK_UNDERFLOW: &str = "The value stack underflowed, the module is invalid.";
+
+/// Type of a WebAssembly value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+impl ValType {
+    fn decode(byte: u8) -> Result<ValType, String> {
+        match byte {
+            0x7f => Ok(ValType::I32),
+            0x7e => Ok(ValType::I64),
+            0x7d => Ok(ValType::F32),
+            0x7c => Ok(ValType::F64),
+            0x7b => Err("SIMD values are not supported.".into()),
+            0x70 | 0x6f => Err("Reference values are not supported.".into()),
+            _ => Err(format!("Invalid value type 0x{:02x}.", byte)),
+        }
+    }
+}
+impl fmt::Display for ValType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ValType::I32 => "i32",
+            ValType::I64 => "i64",
+            ValType::F32 => "f32",
+            ValType::F64 => "f64",
+        })
+    }
+}
+
+/// Types of the parameters and results of a function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct FuncType {
+    pub(crate) params: Vec<ValType>,
+    pub(crate) results: Vec<ValType>,
+}
+impl fmt::Display for FuncType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |types: &[ValType]| types.iter().map(ValType::to_string).collect::<Vec<_>>().join(", ");
+        write!(f, "({}) -> ({})", join(&self.params), join(&self.results))
+    }
+}
+
+/// Instruction of a function, decoded with the positions of the ends of its blocks.
+///
+/// Values are kept as their bits, with 32-bit values zero-extended, so the constants of all types are [`Instr::Const`],
+/// and the numeric, load and store instructions are kept as their opcodes.
+#[derive(Clone, Copy, Debug)]
+enum Instr {
+    Unreachable,
+    Nop,
+    Block { params: u32, results: u32, end: u32 },
+    Loop { params: u32, results: u32 },
+    // `else_` is the position of the `Else` instruction, or of the `End` instruction without it.
+    If { params: u32, results: u32, else_: u32, end: u32 },
+    Else { end: u32 },
+    End,
+    Br(u32),
+    BrIf(u32),
+    // The depths of the targets are `br_targets[start..=start + len]` of the function, the last one being the default.
+    BrTable { start: u32, len: u32 },
+    Return,
+    Call(u32),
+    CallIndirect(u32),
+    Drop,
+    Select,
+    LocalGet(u32),
+    LocalSet(u32),
+    LocalTee(u32),
+    GlobalGet(u32),
+    GlobalSet(u32),
+    Load(u8, u32),
+    Store(u8, u32),
+    MemorySize,
+    MemoryGrow,
+    MemoryInit(u32),
+    DataDrop(u32),
+    MemoryCopy,
+    MemoryFill,
+    Const(u64),
+    Numeric(u8),
+    TruncSat(u8),
+}
+
+struct Func {
+    type_index: u32,
+    local_count: u32,
+    code: Vec<Instr>,
+    br_targets: Vec<u32>,
+}
+struct Global {
+    mutable: bool,
+    init: u64,
+}
+/// Element segment of the function table, of which only the active ones are used.
+struct Element {
+    offset: Option<u32>,
+    funcs: Vec<Option<u32>>,
+}
+struct Data {
+    offset: Option<u32>,
+    bytes: Vec<u8>,
+}
+
+/// Decoded WebAssembly module, which is instantiated into an [`Instance`].
+#[derive(Default)]
+pub(crate) struct Module {
+    types: Vec<FuncType>,
+    // Indices of the types of the functions, decoded before their bodies.
+    func_types: Vec<u32>,
+    funcs: Vec<Func>,
+    table: Option<(u32, Option<u32>)>,
+    memory: Option<(u32, Option<u32>)>,
+    globals: Vec<Global>,
+    exports: HashMap<String, u32>,
+    start: Option<u32>,
+    elements: Vec<Element>,
+    data_count: Option<u32>,
+    datas: Vec<Data>,
+}
+
+/// Bytes of a module, read as the types of the binary format.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, pos: 0 }
+    }
+    fn is_empty(&self) -> bool {
+        self.pos == self.data.len()
+    }
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = self.peek().ok_or("Unexpected end of the module.")?;
+        self.pos += 1;
+        Ok(byte)
+    }
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let bytes = self.pos.checked_add(len).and_then(|end| self.data.get(self.pos..end)).ok_or("Unexpected end of the module.")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+    fn zero(&mut self) -> Result<(), String> {
+        match self.byte()? {
+            0 => Ok(()),
+            _ => Err("Multiple memories and tables are not supported.".into()),
+        }
+    }
+    fn u32(&mut self) -> Result<u32, String> {
+        let (mut value, mut shift) = (0u64, 0);
+        loop {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return u32::try_from(value).map_err(|_| "Invalid integer in the module.".into());
+            }
+            if shift >= 35 {
+                return Err("Invalid integer in the module.".into());
+            }
+        }
+    }
+    /// Read a signed integer of `bits` bits.
+    fn signed(&mut self, bits: u32) -> Result<i64, String> {
+        let (mut value, mut shift) = (0i64, 0);
+        loop {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as i64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1i64 << shift;
+                }
+                return Ok(value);
+            }
+            if shift >= bits {
+                return Err("Invalid integer in the module.".into());
+            }
+        }
+    }
+    fn u32_le(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    fn u64_le(&mut self) -> Result<u64, String> {
+        Ok(self.u32_le()? as u64 | (self.u32_le()? as u64) << 32)
+    }
+    fn len(&mut self) -> Result<usize, String> {
+        Ok(self.u32()? as usize)
+    }
+    fn name(&mut self) -> Result<String, String> {
+        let len = self.len()?;
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "Invalid UTF-8 in a name of the module.".into())
+    }
+    fn limits(&mut self, max_min: u32) -> Result<(u32, Option<u32>), String> {
+        let limits = match self.byte()? {
+            0 => (self.u32()?, None),
+            1 => (self.u32()?, Some(self.u32()?)),
+            _ => return Err("Shared and 64-bit memories are not supported.".into()),
+        };
+        if limits.0 > max_min || limits.1.is_some_and(|max| max < limits.0) {
+            return Err(format!("Invalid limits {}..{:?}, the maximum size is {}.", limits.0, limits.1, max_min));
+        }
+        Ok(limits)
+    }
+}
+
+/// Decode the module from its binary format.
+pub(crate) fn decode(bytes: &[u8]) -> Result<Module, String> {
+    if bytes.get(..4) != Some(b"\0asm") {
+        return Err("Not a WebAssembly module.".into());
+    }
+    let mut reader = Reader::new(bytes);
+    reader.pos = 4;
+    let version = reader.u32_le()?;
+    if version != 1 {
+        return Err(format!("Unsupported version {} of the binary format.", version));
+    }
+    let mut module = Module::default();
+    while !reader.is_empty() {
+        let id = reader.byte()?;
+        let len = reader.len()?;
+        let mut section = Reader::new(reader.bytes(len)?);
+        match id {
+            0 => continue,
+            1 => module.types = section_vec(&mut section, decode_func_type)?,
+            2 => {
+                if section.len()? > 0 {
+                    let (import_module, name) = (section.name()?, section.name()?);
+                    return Err(format!("The module imports '{}.{}', while imports are not supported.", import_module, name));
+                }
+            }
+            3 => {
+                module.func_types = section_vec(&mut section, |r| r.u32())?;
+                if let Some(&index) = module.func_types.iter().find(|&&index| index as usize >= module.types.len()) {
+                    return Err(format!("Invalid type index {} of a function.", index));
+                }
+            }
+            4 => {
+                let count = section.len()?;
+                if count > 1 {
+                    return Err("Multiple memories and tables are not supported.".into());
+                }
+                if count == 1 {
+                    if section.byte()? != 0x70 {
+                        return Err("Reference values are not supported.".into());
+                    }
+                    module.table = Some(section.limits(MAX_TABLE_LEN)?);
+                }
+            }
+            5 => {
+                let count = section.len()?;
+                if count > 1 {
+                    return Err("Multiple memories and tables are not supported.".into());
+                }
+                if count == 1 {
+                    module.memory = Some(section.limits(MAX_PAGES)?);
+                }
+            }
+            6 => {
+                for _ in 0..section.len()? {
+                    ValType::decode(section.byte()?)?;
+                    let mutable = section.byte()? == 1;
+                    let init = const_expr(&mut section, &module.globals)?;
+                    module.globals.push(Global { mutable, init });
+                }
+            }
+            7 => {
+                for _ in 0..section.len()? {
+                    let name = section.name()?;
+                    let (kind, index) = (section.byte()?, section.u32()?);
+                    // Only functions can be looked up, the other exports are only used by the module itself.
+                    if kind == 0 {
+                        check_index(index, module.func_types.len(), "function")?;
+                        module.exports.insert(name, index);
+                    }
+                }
+            }
+            8 => {
+                let index = section.u32()?;
+                check_index(index, module.func_types.len(), "function")?;
+                module.start = Some(index);
+            }
+            9 => {
+                for _ in 0..section.len()? {
+                    let element = decode_element(&mut section, &module)?;
+                    module.elements.push(element);
+                }
+            }
+            10 => {
+                if section.len()? != module.func_types.len() {
+                    return Err("The number of function bodies does not match the number of functions.".into());
+                }
+                for i in 0..module.func_types.len() {
+                    let len = section.len()?;
+                    let func = decode_func(&mut Reader::new(section.bytes(len)?), module.func_types[i], &module)?;
+                    module.funcs.push(func);
+                }
+            }
+            11 => {
+                for _ in 0..section.len()? {
+                    let offset = match section.u32()? {
+                        0 => Some(const_expr(&mut section, &module.globals)? as u32),
+                        1 => None,
+                        2 => {
+                            section.zero()?;
+                            Some(const_expr(&mut section, &module.globals)? as u32)
+                        }
+                        _ => return Err("Invalid data segment.".into()),
+                    };
+                    let len = section.len()?;
+                    module.datas.push(Data { offset, bytes: section.bytes(len)?.to_vec() });
+                }
+                if module.data_count.is_some_and(|count| count as usize != module.datas.len()) {
+                    return Err("The number of data segments does not match the data count.".into());
+                }
+            }
+            12 => module.data_count = Some(section.u32()?),
+            _ => return Err(format!("Invalid section id {}.", id)),
+        }
+        if !section.is_empty() {
+            return Err(format!("Section {} is longer than its contents.", id));
+        }
+    }
+    if module.funcs.len() != module.func_types.len() {
+        return Err("The number of function bodies does not match the number of functions.".into());
+    }
+    if module.elements.iter().any(|element| element.offset.is_some()) && module.table.is_none() {
+        return Err("The module initializes a table it does not define.".into());
+    }
+    if module.datas.iter().any(|data| data.offset.is_some()) && module.memory.is_none() {
+        return Err("The module initializes a memory it does not define.".into());
+    }
+    Ok(module)
+}
+
+fn section_vec<T>(section: &mut Reader, mut decode: impl FnMut(&mut Reader) -> Result<T, String>) -> Result<Vec<T>, String> {
+    (0..section.len()?).map(|_| decode(section)).collect()
+}
+
+fn check_index(index: u32, len: usize, what: &str) -> Result<(), String> {
+    match (index as usize) < len {
+        true => Ok(()),
+        false => Err(format!("Invalid {} index {}.", what, index)),
+    }
+}
+
+fn decode_func_type(reader: &mut Reader) -> Result<FuncType, String> {
+    if reader.byte()? != 0x60 {
+        return Err("Invalid function type.".into());
+    }
+    let params = section_vec(reader, |r| ValType::decode(r.byte()?))?;
+    let results = section_vec(reader, |r| ValType::decode(r.byte()?))?;
+    Ok(FuncType { params, results })
+}
+
+/// Evaluate a constant expression, e.g. the initial value of a global or the offset of a data segment.
+fn const_expr(reader: &mut Reader, globals: &[Global]) -> Result<u64, String> {
+    let value = match reader.byte()? {
+        0x41 => reader.signed(32)? as u32 as u64,
+        0x42 => reader.signed(64)? as u64,
+        0x43 => reader.u32_le()? as u64,
+        0x44 => reader.u64_le()?,
+        0x23 => {
+            let index = reader.u32()?;
+            check_index(index, globals.len(), "global")?;
+            globals[index as usize].init
+        }
+        op => return Err(format!("Unsupported instruction 0x{:02x} in a constant expression.", op)),
+    };
+    match reader.byte()? {
+        0x0b => Ok(value),
+        _ => Err("Unsupported constant expression.".into()),
+    }
+}
+
+fn decode_element(reader: &mut Reader, module: &Module) -> Result<Element, String> {
+    let flags = reader.u32()?;
+    if flags > 7 {
+        return Err("Invalid element segment.".into());
+    }
+    let is_active = flags & 1 == 0;
+    if flags & 2 != 0 && is_active {
+        reader.zero()?;
+    }
+    let offset = match is_active {
+        true => Some(const_expr(reader, &module.globals)? as u32),
+        false => None,
+    };
+    // The kind of the elements, which is always `funcref`, is only encoded if the table index is or the segment is not active.
+    let kind = match flags & 4 {
+        0 => 0x00,
+        _ => 0x70,
+    };
+    if flags & 3 != 0 && reader.byte()? != kind {
+        return Err("Reference values are not supported.".into());
+    }
+    let funcs = section_vec(reader, |r| {
+        let func = match flags & 4 {
+            0 => Some(r.u32()?),
+            _ => match (r.byte()?, r.byte()?) {
+                (0xd2, _) => {
+                    r.pos -= 1;
+                    Some(r.u32()?)
+                }
+                (0xd0, 0x70) => None,
+                _ => return Err("Unsupported element expression.".into()),
+            },
+        };
+        if flags & 4 != 0 && r.byte()? != 0x0b {
+            return Err("Unsupported element expression.".into());
+        }
+        if let Some(func) = func {
+            check_index(func, module.func_types.len(), "function")?;
+        }
+        Ok(func)
+    })?;
+    Ok(Element { offset, funcs })
+}
+
+/// Read the type of a block as the number of its parameters and results.
+fn block_type(reader: &mut Reader, types: &[FuncType]) -> Result<(u32, u32), String> {
+    match reader.peek() {
+        Some(0x40) => {
+            reader.pos += 1;
+            Ok((0, 0))
+        }
+        Some(byte) if byte & 0xc0 == 0x40 => {
+            reader.pos += 1;
+            ValType::decode(byte).map(|_| (0, 1))
+        }
+        _ => {
+            let index = reader.signed(33)?;
+            let ty = usize::try_from(index).ok().and_then(|index| types.get(index)).ok_or_else(|| format!("Invalid type index {} of a block.", index))?;
+            Ok((ty.params.len() as u32, ty.results.len() as u32))
+        }
+    }
+}
+
+fn decode_func(reader: &mut Reader, type_index: u32, module: &Module) -> Result<Func, String> {
+    let mut local_count = module.types[type_index as usize].params.len() as u64;
+    for _ in 0..reader.len()? {
+        local_count += reader.u32()? as u64;
+        ValType::decode(reader.byte()?)?;
+        if local_count > MAX_LOCALS {
+            return Err(format!("A function has more than {} locals.", MAX_LOCALS));
+        }
+    }
+    let (mut code, mut br_targets) = (Vec::new(), Vec::new());
+    // Positions of the blocks which are not ended yet.
+    let mut open: Vec<usize> = Vec::new();
+    let has_memory = || match module.memory {
+        Some(_) => Ok(()),
+        None => Err(String::from("The module accesses a memory it does not define.")),
+    };
+    let branch = |depth: u32, open: &[usize]| match depth as usize <= open.len() {
+        true => Ok(depth),
+        false => Err(format!("Invalid branch depth {}.", depth)),
+    };
+    let local = |index: u32| check_index(index, local_count as usize, "local").map(|_| index);
+    let global = |index: u32| check_index(index, module.globals.len(), "global").map(|_| index);
+    loop {
+        let op = reader.byte()?;
+        let instr = match op {
+            0x00 => Instr::Unreachable,
+            0x01 => Instr::Nop,
+            0x02..=0x04 => {
+                let (params, results) = block_type(reader, &module.types)?;
+                open.push(code.len());
+                match op {
+                    0x02 => Instr::Block { params, results, end: 0 },
+                    0x03 => Instr::Loop { params, results },
+                    _ => Instr::If { params, results, else_: 0, end: 0 },
+                }
+            }
+            0x05 => {
+                let pos = code.len() as u32;
+                match open.last().map(|&start| &mut code[start]) {
+                    Some(Instr::If { else_, .. }) if *else_ == 0 => *else_ = pos,
+                    _ => return Err("Unexpected else instruction.".into()),
+                }
+                Instr::Else { end: 0 }
+            }
+            0x0b => {
+                let Some(start) = open.pop() else {
+                    code.push(Instr::End);
+                    break;
+                };
+                let end = code.len() as u32;
+                let else_pos = match &mut code[start] {
+                    Instr::Block { end: block_end, .. } => {
+                        *block_end = end;
+                        None
+                    }
+                    Instr::If { else_, end: if_end, .. } => {
+                        *if_end = end;
+                        match *else_ {
+                            0 => {
+                                *else_ = end;
+                                None
+                            }
+                            else_ => Some(else_),
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(else_pos) = else_pos {
+                    code[else_pos as usize] = Instr::Else { end };
+                }
+                Instr::End
+            }
+            0x0c => Instr::Br(branch(reader.u32()?, &open)?),
+            0x0d => Instr::BrIf(branch(reader.u32()?, &open)?),
+            0x0e => {
+                let (start, len) = (br_targets.len() as u32, reader.u32()?);
+                for _ in 0..=len {
+                    br_targets.push(branch(reader.u32()?, &open)?);
+                }
+                Instr::BrTable { start, len }
+            }
+            0x0f => Instr::Return,
+            0x10 => {
+                let index = reader.u32()?;
+                check_index(index, module.func_types.len(), "function")?;
+                Instr::Call(index)
+            }
+            0x11 => {
+                let index = reader.u32()?;
+                check_index(index, module.types.len(), "type")?;
+                reader.zero()?;
+                if module.table.is_none() {
+                    return Err("The module calls through a table it does not define.".into());
+                }
+                Instr::CallIndirect(index)
+            }
+            0x1a => Instr::Drop,
+            0x1b => Instr::Select,
+            0x1c => {
+                if reader.len()? != 1 {
+                    return Err("Invalid select instruction.".into());
+                }
+                ValType::decode(reader.byte()?)?;
+                Instr::Select
+            }
+            0x20 => Instr::LocalGet(local(reader.u32()?)?),
+            0x21 => Instr::LocalSet(local(reader.u32()?)?),
+            0x22 => Instr::LocalTee(local(reader.u32()?)?),
+            0x23 => Instr::GlobalGet(global(reader.u32()?)?),
+            0x24 => {
+                let index = global(reader.u32()?)?;
+                if !module.globals[index as usize].mutable {
+                    return Err(format!("The immutable global {} is set.", index));
+                }
+                Instr::GlobalSet(index)
+            }
+            0x28..=0x3e => {
+                has_memory()?;
+                // Alignment hints with bit 6 set are followed by a memory index.
+                if reader.u32()? >= 64 {
+                    return Err("Multiple memories and tables are not supported.".into());
+                }
+                let offset = reader.u32()?;
+                match op {
+                    0x28..=0x35 => Instr::Load(op, offset),
+                    _ => Instr::Store(op, offset),
+                }
+            }
+            0x3f | 0x40 => {
+                has_memory()?;
+                reader.zero()?;
+                match op {
+                    0x3f => Instr::MemorySize,
+                    _ => Instr::MemoryGrow,
+                }
+            }
+            0x41 => Instr::Const(reader.signed(32)? as u32 as u64),
+            0x42 => Instr::Const(reader.signed(64)? as u64),
+            0x43 => Instr::Const(reader.u32_le()? as u64),
+            0x44 => Instr::Const(reader.u64_le()?),
+            0x45..=0xc4 => Instr::Numeric(op),
+            0xfc => match reader.u32()? {
+                op @ 0..=7 => Instr::TruncSat(op as u8),
+                8 => {
+                    has_memory()?;
+                    let index = reader.u32()?;
+                    check_index(index, module.data_count.unwrap_or(0) as usize, "data")?;
+                    reader.zero()?;
+                    Instr::MemoryInit(index)
+                }
+                9 => {
+                    let index = reader.u32()?;
+                    check_index(index, module.data_count.unwrap_or(0) as usize, "data")?;
+                    Instr::DataDrop(index)
+                }
+                10 => {
+                    has_memory()?;
+                    reader.zero()?;
+                    reader.zero()?;
+                    Instr::MemoryCopy
+                }
+                11 => {
+                    has_memory()?;
+                    reader.zero()?;
+                    Instr::MemoryFill
+                }
+                op => return Err(format!("Unsupported table instruction 0xfc {}.", op)),
+            },
+            0x25 | 0x26 | 0xd0..=0xd2 => return Err("Reference values are not supported.".into()),
+            0xfd => return Err("SIMD instructions are not supported.".into()),
+            op => return Err(format!("Invalid instruction 0x{:02x}.", op)),
+        };
+        code.push(instr);
+    }
+    if !reader.is_empty() {
+        return Err("A function body is longer than its code.".into());
+    }
+    Ok(Func { type_index, local_count: local_count as u32, code, br_targets })
+}
+
+/// Block being executed, which a branch to it continues after, or at the start of for loops.
+#[derive(Clone, Copy)]
+struct Label {
+    // Height of the value stack below the parameters of the block.
+    height: usize,
+    // Number of values passed by a branch to the label, which are the parameters of loops and the results of other blocks.
+    arity: usize,
+    results: usize,
+    target: usize,
+    is_loop: bool,
+}
+struct Frame {
+    func: usize,
+    pc: usize,
+    locals: usize,
+    labels: usize,
+}
+/// Stacks of a call of an exported function.
+#[derive(Default)]
+struct Stacks {
+    values: Vec<u64>,
+    locals: Vec<u64>,
+    labels: Vec<Label>,
+    frames: Vec<Frame>,
+}
+impl Stacks {
+    fn pop(&mut self) -> Result<u64, String> {
+        self.values.pop().ok_or_else(|| STACK_UNDERFLOW.into())
+    }
+    /// Push a value without popping one before it.
+    fn push(&mut self, value: u64) -> Result<(), String> {
+        if self.values.len() + self.locals.len() >= MAX_STACK_LEN {
+            return Err(STACK_EXHAUSTED.into());
+        }
+        self.values.push(value);
+        Ok(())
+    }
+    /// Get the height of the value stack below its top `count` values.
+    fn height(&self, count: u32) -> Result<usize, String> {
+        self.values.len().checked_sub(count as usize).ok_or_else(|| STACK_UNDERFLOW.into())
+    }
+    /// Drop the values between `height` and the top `arity` values.
+    fn unwind(&mut self, height: usize, arity: usize) -> Result<(), String> {
+        let len = self.values.len();
+        if len < height + arity {
+            return Err(STACK_UNDERFLOW.into());
+        }
+        self.values.drain(height..len - arity);
+        Ok(())
+    }
+    /// Branch to the label at `depth`, returning the position to continue at.
+    fn branch(&mut self, depth: u32) -> Result<usize, String> {
+        let index = self.labels.len() - 1 - depth as usize;
+        let label = self.labels[index];
+        self.unwind(label.height, label.arity)?;
+        self.labels.truncate(if label.is_loop { index + 1 } else { index });
+        Ok(label.target)
+    }
+    fn enter(&mut self, module: &Module, func: usize) -> Result<(), String> {
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(STACK_EXHAUSTED.into());
+        }
+        let f = &module.funcs[func];
+        let ty = &module.types[f.type_index as usize];
+        let height = self.height(ty.params.len() as u32)?;
+        let locals = self.locals.len();
+        if self.values.len() + locals + f.local_count as usize >= MAX_STACK_LEN {
+            return Err(STACK_EXHAUSTED.into());
+        }
+        self.locals.extend(self.values.drain(height..));
+        self.locals.resize(locals + f.local_count as usize, 0);
+        self.frames.push(Frame { func, pc: 0, locals, labels: self.labels.len() });
+        let results = ty.results.len();
+        self.labels.push(Label { height, arity: results, results, target: f.code.len(), is_loop: false });
+        Ok(())
+    }
+}
+
+/// Instance of a module, with its own memory, globals and table.
+pub(crate) struct Instance {
+    module: Module,
+    memory: Vec<u8>,
+    max_pages: u32,
+    globals: Vec<u64>,
+    table: Vec<Option<u32>>,
+    // Data segments dropped with `data.drop`, or initialized when the module was instantiated.
+    dropped: Vec<bool>,
+}
+impl Instance {
+    /// Instantiate the module, initializing its memory and table and running its start function.
+    pub(crate) fn new(mut module: Module) -> Result<Instance, String> {
+        let (min_pages, max_pages) = module.memory.unwrap_or((0, Some(0)));
+        let table_len = module.table.map_or(0, |(min, _)| min);
+        let mut instance = Instance {
+            memory: vec![0; min_pages as usize * PAGE_SIZE],
+            max_pages: max_pages.unwrap_or(MAX_PAGES).min(MAX_PAGES),
+            globals: module.globals.iter().map(|global| global.init).collect(),
+            table: vec![None; table_len as usize],
+            dropped: module.datas.iter().map(|data| data.offset.is_some()).collect(),
+            module: Module::default(),
+        };
+        for element in module.elements.iter().filter(|element| element.offset.is_some()) {
+            let offset = element.offset.unwrap_or_default() as usize;
+            let slots = instance.table.get_mut(offset..offset + element.funcs.len()).ok_or(OUT_OF_BOUNDS_TABLE)?;
+            slots.copy_from_slice(&element.funcs);
+        }
+        for data in module.datas.iter_mut().filter(|data| data.offset.is_some()) {
+            let offset = data.offset.unwrap_or_default() as usize;
+            let dest = instance.memory.get_mut(offset..offset + data.bytes.len()).ok_or(OUT_OF_BOUNDS_MEMORY)?;
+            dest.copy_from_slice(&std::mem::take(&mut data.bytes));
+        }
+        let start = module.start;
+        instance.module = module;
+        if let Some(start) = start {
+            instance.call(start, &[]).map_err(|e| format!("The start function trapped. {}", e))?;
+        }
+        Ok(instance)
+    }
+    /// Get the index and the type of the function exported as `name`.
+    pub(crate) fn export(&self, name: &str) -> Option<(u32, &FuncType)> {
+        let &func = self.module.exports.get(name)?;
+        Some((func, &self.module.types[self.module.func_types[func as usize] as usize]))
+    }
+    /// Call the function `func` with `args`, the bits of its parameters, returning the bits of its results or the trap message.
+    pub(crate) fn call(&mut self, func: u32, args: &[u64]) -> Result<Vec<u64>, String> {
+        let mut stacks = Stacks { values: args.to_vec(), ..Stacks::default() };
+        stacks.enter(&self.module, func as usize)?;
+        self.run(&mut stacks)?;
+        Ok(stacks.values)
+    }
+    fn run(&mut self, stacks: &mut Stacks) -> Result<(), String> {
+        let Instance { module, memory, max_pages, globals, table, dropped } = self;
+        while let Some(frame) = stacks.frames.last() {
+            let (func, locals) = (&module.funcs[frame.func], frame.locals);
+            let mut pc = frame.pc;
+            loop {
+                let Some(&instr) = func.code.get(pc) else {
+                    let frame = stacks.frames.pop().expect("the frame is on the stack");
+                    stacks.labels.truncate(frame.labels);
+                    stacks.locals.truncate(frame.locals);
+                    break;
+                };
+                pc += 1;
+                match instr {
+                    Instr::Unreachable => return Err("Unreachable code was executed.".into()),
+                    Instr::Nop => {}
+                    Instr::Block { params, results, end } => {
+                        let height = stacks.height(params)?;
+                        stacks.labels.push(Label { height, arity: results as usize, results: results as usize, target: end as usize + 1, is_loop: false });
+                    }
+                    Instr::Loop { params, results } => {
+                        let height = stacks.height(params)?;
+                        stacks.labels.push(Label { height, arity: params as usize, results: results as usize, target: pc, is_loop: true });
+                    }
+                    Instr::If { params, results, else_, end } => {
+                        let condition = stacks.pop()? as u32;
+                        let height = stacks.height(params)?;
+                        stacks.labels.push(Label { height, arity: results as usize, results: results as usize, target: end as usize + 1, is_loop: false });
+                        if condition == 0 {
+                            pc = else_ as usize + (else_ != end) as usize;
+                        }
+                    }
+                    Instr::Else { end } => pc = end as usize,
+                    Instr::End => {
+                        let label = stacks.labels.pop().ok_or(STACK_UNDERFLOW)?;
+                        stacks.unwind(label.height, label.results)?;
+                    }
+                    Instr::Br(depth) => pc = stacks.branch(depth)?,
+                    Instr::BrIf(depth) => {
+                        if stacks.pop()? as u32 != 0 {
+                            pc = stacks.branch(depth)?;
+                        }
+                    }
+                    Instr::BrTable { start, len } => {
+                        let index = (stacks.pop()? as u32).min(len);
+                        pc = stacks.branch(func.br_targets[(start + index) as usize])?;
+                    }
+                    Instr::Return => {
+                        let labels = stacks.frames.last().expect("the frame is on the stack").labels;
+                        pc = stacks.branch((stacks.labels.len() - labels - 1) as u32)?;
+                    }
+                    Instr::Call(callee) => {
+                        stacks.frames.last_mut().expect("the frame is on the stack").pc = pc;
+                        stacks.enter(module, callee as usize)?;
+                        break;
+                    }
+                    Instr::CallIndirect(type_index) => {
+                        let index = stacks.pop()? as u32 as usize;
+                        let callee = table.get(index).ok_or(OUT_OF_BOUNDS_TABLE)?.ok_or("Call of an uninitialized table element.")?;
+                        if module.types[type_index as usize] != module.types[module.funcs[callee as usize].type_index as usize] {
+                            return Err("Indirect call with a mismatching signature.".into());
+                        }
+                        stacks.frames.last_mut().expect("the frame is on the stack").pc = pc;
+                        stacks.enter(module, callee as usize)?;
+                        break;
+                    }
+                    Instr::Drop => {
+                        stacks.pop()?;
+                    }
+                    Instr::Select => {
+                        let (condition, second, first) = (stacks.pop()? as u32, stacks.pop()?, stacks.pop()?);
+                        stacks.values.push(if condition != 0 { first } else { second });
+                    }
+                    Instr::LocalGet(index) => stacks.push(stacks.locals[locals + index as usize])?,
+                    Instr::LocalSet(index) => stacks.locals[locals + index as usize] = stacks.pop()?,
+                    Instr::LocalTee(index) => stacks.locals[locals + index as usize] = *stacks.values.last().ok_or(STACK_UNDERFLOW)?,
+                    Instr::GlobalGet(index) => stacks.push(globals[index as usize])?,
+                    Instr::GlobalSet(index) => globals[index as usize] = stacks.pop()?,
+                    Instr::Load(op, offset) => {
+                        let (len, signed, is_32) = match op {
+                            0x28 | 0x2a => (4, false, true),
+                            0x29 | 0x2b => (8, false, false),
+                            0x2c..=0x2f => (1 << ((op - 0x2c) / 2), op % 2 == 0, true),
+                            _ => (1 << ((op - 0x30) / 2), op % 2 == 0, false),
+                        };
+                        let start = address(memory, stacks.pop()?, offset, len)?;
+                        let mut bytes = [0u8; 8];
+                        bytes[..len].copy_from_slice(&memory[start..start + len]);
+                        let mut value = u64::from_le_bytes(bytes);
+                        if signed {
+                            let shift = 64 - 8 * len as u32;
+                            value = ((value << shift) as i64 >> shift) as u64;
+                        }
+                        stacks.values.push(if is_32 { value as u32 as u64 } else { value });
+                    }
+                    Instr::Store(op, offset) => {
+                        let len = match op {
+                            0x36 | 0x38 | 0x3e => 4,
+                            0x37 | 0x39 => 8,
+                            0x3a | 0x3c => 1,
+                            _ => 2,
+                        };
+                        let value = stacks.pop()?;
+                        let start = address(memory, stacks.pop()?, offset, len)?;
+                        memory[start..start + len].copy_from_slice(&value.to_le_bytes()[..len]);
+                    }
+                    Instr::MemorySize => stacks.push((memory.len() / PAGE_SIZE) as u64)?,
+                    Instr::MemoryGrow => {
+                        let (pages, delta) = ((memory.len() / PAGE_SIZE) as u64, stacks.pop()? as u32 as u64);
+                        match pages + delta <= *max_pages as u64 {
+                            true => {
+                                memory.resize((pages + delta) as usize * PAGE_SIZE, 0);
+                                stacks.values.push(pages);
+                            }
+                            false => stacks.values.push(u32::MAX as u64),
+                        }
+                    }
+                    Instr::MemoryInit(index) => {
+                        let (len, src, dest) = (stacks.pop()? as u32 as usize, stacks.pop()?, stacks.pop()?);
+                        let data: &[u8] = match dropped[index as usize] {
+                            true => &[],
+                            false => &module.datas[index as usize].bytes,
+                        };
+                        let src = address(data, src, 0, len)?;
+                        let dest = address(memory, dest, 0, len)?;
+                        memory[dest..dest + len].copy_from_slice(&data[src..src + len]);
+                    }
+                    Instr::DataDrop(index) => dropped[index as usize] = true,
+                    Instr::MemoryCopy => {
+                        let (len, src, dest) = (stacks.pop()? as u32 as usize, stacks.pop()?, stacks.pop()?);
+                        let src = address(memory, src, 0, len)?;
+                        let dest = address(memory, dest, 0, len)?;
+                        memory.copy_within(src..src + len, dest);
+                    }
+                    Instr::MemoryFill => {
+                        let (len, value, dest) = (stacks.pop()? as u32 as usize, stacks.pop()? as u8, stacks.pop()?);
+                        let dest = address(memory, dest, 0, len)?;
+                        memory[dest..dest + len].fill(value);
+                    }
+                    Instr::Const(value) => stacks.push(value)?,
+                    Instr::Numeric(op) => numeric(stacks, op)?,
+                    Instr::TruncSat(op) => {
+                        let value = stacks.pop()?;
+                        let (x32, x64) = (f32::from_bits(value as u32), f64::from_bits(value));
+                        stacks.values.push(match op {
+                            0 => x32 as i32 as u32 as u64,
+                            1 => x32 as u32 as u64,
+                            2 => x64 as i32 as u32 as u64,
+                            3 => x64 as u32 as u64,
+                            4 => x32 as i64 as u64,
+                            5 => x32 as u64,
+                            6 => x64 as i64 as u64,
+                            _ => x64 as u64,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get the position of the `len` bytes at `addr + offset` of `memory`, checking that they are in bounds.
+fn address(memory: &[u8], addr: u64, offset: u32, len: usize) -> Result<usize, String> {
+    let start = addr as u32 as u64 + offset as u64;
+    match start + len as u64 <= memory.len() as u64 {
+        true => Ok(start as usize),
+        false => Err(OUT_OF_BOUNDS_MEMORY.into()),
+    }
+}
+
+macro_rules! int_ops {
+    ($compare:ident, $unary:ident, $binary:ident, $u:ty, $s:ty) => {
+        /// Compare `a` and `b` with the comparison `op` of eq, ne, lt_s, lt_u, gt_s, gt_u, le_s, le_u, ge_s and ge_u.
+        fn $compare(op: u8, a: $u, b: $u) -> bool {
+            let (sa, sb) = (a as $s, b as $s);
+            match op {
+                0 => a == b,
+                1 => a != b,
+                2 => sa < sb,
+                3 => a < b,
+                4 => sa > sb,
+                5 => a > b,
+                6 => sa <= sb,
+                7 => a <= b,
+                8 => sa >= sb,
+                _ => a >= b,
+            }
+        }
+        /// Apply the operation `op` of clz, ctz and popcnt to `a`.
+        fn $unary(op: u8, a: $u) -> $u {
+            match op {
+                0 => a.leading_zeros() as $u,
+                1 => a.trailing_zeros() as $u,
+                _ => a.count_ones() as $u,
+            }
+        }
+        /// Apply the operation `op` of add, sub, mul, div_s, div_u, rem_s, rem_u, and, or, xor, shl, shr_s, shr_u, rotl and rotr.
+        fn $binary(op: u8, a: $u, b: $u) -> Result<$u, String> {
+            let (sa, sb) = (a as $s, b as $s);
+            if (3..=6).contains(&op) && b == 0 {
+                return Err("Integer division by zero.".into());
+            }
+            Ok(match op {
+                0 => a.wrapping_add(b),
+                1 => a.wrapping_sub(b),
+                2 => a.wrapping_mul(b),
+                3 => sa.checked_div(sb).ok_or("Integer overflow.")? as $u,
+                4 => a / b,
+                5 => sa.wrapping_rem(sb) as $u,
+                6 => a % b,
+                7 => a & b,
+                8 => a | b,
+                9 => a ^ b,
+                10 => a.wrapping_shl(b as u32),
+                11 => sa.wrapping_shr(b as u32) as $u,
+                12 => a.wrapping_shr(b as u32),
+                13 => a.rotate_left((b % <$u>::BITS as $u) as u32),
+                _ => a.rotate_right((b % <$u>::BITS as $u) as u32),
+            })
+        }
+    };
+}
+int_ops!(i32_compare, i32_unary, i32_binary, u32, i32);
+int_ops!(i64_compare, i64_unary, i64_binary, u64, i64);
+
+macro_rules! float_ops {
+    ($compare:ident, $unary:ident, $binary:ident, $f:ty) => {
+        /// Compare `a` and `b` with the comparison `op` of eq, ne, lt, gt, le and ge.
+        fn $compare(op: u8, a: $f, b: $f) -> bool {
+            match op {
+                0 => a == b,
+                1 => a != b,
+                2 => a < b,
+                3 => a > b,
+                4 => a <= b,
+                _ => a >= b,
+            }
+        }
+        /// Apply the operation `op` of abs, neg, ceil, floor, trunc, nearest and sqrt to `a`.
+        fn $unary(op: u8, a: $f) -> $f {
+            match op {
+                0 => a.abs(),
+                1 => -a,
+                2 => a.ceil(),
+                3 => a.floor(),
+                4 => a.trunc(),
+                5 => a.round_ties_even(),
+                _ => a.sqrt(),
+            }
+        }
+        /// Apply the operation `op` of add, sub, mul, div, min, max and copysign.
+        fn $binary(op: u8, a: $f, b: $f) -> $f {
+            // Unlike `min` and `max` of Rust, the ones of WebAssembly return NaN for NaN and order -0 before +0.
+            match op {
+                0 => a + b,
+                1 => a - b,
+                2 => a * b,
+                3 => a / b,
+                4 | 5 if a.is_nan() || b.is_nan() => <$f>::NAN,
+                4 if a == b => <$f>::from_bits(a.to_bits() | b.to_bits()),
+                5 if a == b => <$f>::from_bits(a.to_bits() & b.to_bits()),
+                4 => a.min(b),
+                5 => a.max(b),
+                _ => a.copysign(b),
+            }
+        }
+    };
+}
+float_ops!(f32_compare, f32_unary, f32_binary, f32);
+float_ops!(f64_compare, f64_unary, f64_binary, f64);
+
+/// Truncate `x` to an integer of `bits` bits, failing if it is NaN or out of its range.
+fn trunc(x: f64, signed: bool, bits: i32) -> Result<f64, String> {
+    if x.is_nan() {
+        return Err("Invalid conversion of NaN to an integer.".into());
+    }
+    let (t, limit) = (x.trunc(), 2f64.powi(bits - signed as i32));
+    match if signed { t >= -limit && t < limit } else { t > -1.0 && t < limit } {
+        true => Ok(t),
+        false => Err("Integer overflow.".into()),
+    }
+}
+
+/// Execute the numeric instruction `op`, i.e. a comparison, an arithmetic operation or a conversion.
+fn numeric(stacks: &mut Stacks, op: u8) -> Result<(), String> {
+    let b = stacks.pop()?;
+    // The first operand of binary operations, which are the ones with the opcodes after their unary ones.
+    let mut a = || stacks.pop();
+    let (b32, b64) = (f32::from_bits(b as u32), f64::from_bits(b));
+    let value = match op {
+        0x45 => (b as u32 == 0) as u64,
+        0x46..=0x4f => i32_compare(op - 0x46, a()? as u32, b as u32) as u64,
+        0x50 => (b == 0) as u64,
+        0x51..=0x5a => i64_compare(op - 0x51, a()?, b) as u64,
+        0x5b..=0x60 => f32_compare(op - 0x5b, f32::from_bits(a()? as u32), b32) as u64,
+        0x61..=0x66 => f64_compare(op - 0x61, f64::from_bits(a()?), b64) as u64,
+        0x67..=0x69 => i32_unary(op - 0x67, b as u32) as u64,
+        0x6a..=0x78 => i32_binary(op - 0x6a, a()? as u32, b as u32)? as u64,
+        0x79..=0x7b => i64_unary(op - 0x79, b),
+        0x7c..=0x8a => i64_binary(op - 0x7c, a()?, b)?,
+        0x8b..=0x91 => f32_unary(op - 0x8b, b32).to_bits() as u64,
+        0x92..=0x98 => f32_binary(op - 0x92, f32::from_bits(a()? as u32), b32).to_bits() as u64,
+        0x99..=0x9f => f64_unary(op - 0x99, b64).to_bits(),
+        0xa0..=0xa6 => f64_binary(op - 0xa0, f64::from_bits(a()?), b64).to_bits(),
+        0xa7 => b as u32 as u64,
+        0xa8 => trunc(b32 as f64, true, 32)? as i32 as u32 as u64,
+        0xa9 => trunc(b32 as f64, false, 32)? as u32 as u64,
+        0xaa => trunc(b64, true, 32)? as i32 as u32 as u64,
+        0xab => trunc(b64, false, 32)? as u32 as u64,
+        0xac => b as u32 as i32 as i64 as u64,
+        0xad => b as u32 as u64,
+        0xae => trunc(b32 as f64, true, 64)? as i64 as u64,
+        0xaf => trunc(b32 as f64, false, 64)? as u64,
+        0xb0 => trunc(b64, true, 64)? as i64 as u64,
+        0xb1 => trunc(b64, false, 64)? as u64,
+        0xb2 => (b as u32 as i32 as f32).to_bits() as u64,
+        0xb3 => (b as u32 as f32).to_bits() as u64,
+        0xb4 => (b as i64 as f32).to_bits() as u64,
+        0xb5 => (b as f32).to_bits() as u64,
+        0xb6 => (b64 as f32).to_bits() as u64,
+        0xb7 => (b as u32 as i32 as f64).to_bits(),
+        0xb8 => (b as u32 as f64).to_bits(),
+        0xb9 => (b as i64 as f64).to_bits(),
+        0xba => (b as f64).to_bits(),
+        0xbb => (b32 as f64).to_bits(),
+        // Values are kept as their bits, so reinterpreting them does nothing.
+        0xbc..=0xbf => b,
+        0xc0 => b as u8 as i8 as i32 as u32 as u64,
+        0xc1 => b as u16 as i16 as i32 as u32 as u64,
+        0xc2 => b as u8 as i8 as i64 as u64,
+        0xc3 => b as u16 as i16 as i64 as u64,
+        _ => b as u32 as i32 as i64 as u64,
+    };
+    stacks.values.push(value);
+    Ok(())
+}
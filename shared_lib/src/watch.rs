@@ -0,0 +1,128 @@
+//! Reloading a shared library whenever its file changes on disk.
+
+use crate::{LibPath, SharedLib, SharedLibError};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// How long the library file has to stay unchanged before it is reloaded, so a file that is still being written is not loaded.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Structure representing a shared library that is reloaded whenever its file changes on disk.
+///
+/// The directory of the library is watched rather than the file itself, since build tools usually replace
+/// the file instead of writing to it. Each change is handled with [`SharedLib::reload`] on a background thread,
+/// which swaps the library while no [`WatchedSharedLib::lib`] guard is held, and the result is passed to the callback.
+pub struct WatchedSharedLib {
+    lib: Arc<RwLock<SharedLib>>,
+    // Dropping the watcher disconnects the channel, which stops the reload thread.
+    watcher: Option<RecommendedWatcher>,
+    thread: Option<JoinHandle<()>>,
+}
+impl WatchedSharedLib {
+    /// Create a new shared library from the given path and start watching its file.
+    ///
+    /// `on_reload` is called on the background thread after every reload attempt, with the reloaded library or the error.
+    /// The library path needs a directory, since the file the dynamic loader would find is not known otherwise.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and reloads it whenever its file changes, which is generally unsafe as it is a foregin code.
+    /// # Example
+    /// ```no_run
+    /// use std::path::PathBuf;
+    /// use shared_lib::*;
+    /// unsafe {
+    ///     let lib_path = LibPath::new(PathBuf::from("path/to/shared/library"), "shared_library".into());
+    ///     let watched = WatchedSharedLib::new(lib_path, |result| match result {
+    ///         Ok(lib) => println!("Reloaded '{}'", lib.lib_path().lib_name()),
+    ///         Err(e) => eprintln!("{}", e),
+    ///     })
+    ///     .expect("Failed to load shared library");
+    ///     let lib = watched.lib();
+    ///     let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").expect("Failed to get 'add' function from shared library");
+    ///     let result = add_fn.run(1, 2);
+    /// }
+    /// ```
+    pub unsafe fn new<F>(lib_path: LibPath, on_reload: F) -> Result<WatchedSharedLib, SharedLibError>
+    where
+        F: FnMut(Result<&SharedLib, SharedLibError>) + Send + 'static,
+    {
+        WatchedSharedLib::watch(lib_path, false, on_reload)
+    }
+    /// Create a new shared library from the given path and start watching its file like [`WatchedSharedLib::new`],
+    /// reloading it with [`SharedLib::reload_with_state`], so the state of the library is handed over to the new version.
+    /// # Safety
+    /// This function is unsafe because it loads a shared library and reloads it whenever its file changes, which is generally unsafe as it is a foregin code.
+    /// The state functions must have the signatures described in the [`state`](crate::state) module.
+    pub unsafe fn new_with_state<F>(lib_path: LibPath, on_reload: F) -> Result<WatchedSharedLib, SharedLibError>
+    where
+        F: FnMut(Result<&SharedLib, SharedLibError>) + Send + 'static,
+    {
+        WatchedSharedLib::watch(lib_path, true, on_reload)
+    }
+    unsafe fn watch<F>(lib_path: LibPath, with_state: bool, mut on_reload: F) -> Result<WatchedSharedLib, SharedLibError>
+    where
+        F: FnMut(Result<&SharedLib, SharedLibError>) + Send + 'static,
+    {
+        let watch_failure = |msg: String| SharedLibError::load_failure(lib_path.error_path(), format!("Failed to watch the library file. {}", msg));
+        if lib_path.dir_path.as_os_str().is_empty() {
+            return Err(watch_failure("The library path has no directory.".into()));
+        }
+        let filename: OsString = lib_path.filename()?;
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(|e| watch_failure(e.to_string()))?;
+        watcher
+            .watch(&lib_path.dir_path, RecursiveMode::NonRecursive)
+            .map_err(|e| watch_failure(e.to_string()))?;
+
+        let lib = Arc::new(RwLock::new(SharedLib::new(lib_path)?));
+        let thread_lib = Arc::clone(&lib);
+        let changed = move |event: notify::Result<notify::Event>| {
+            event.is_ok_and(|event| {
+                matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                    && event.paths.iter().any(|path| path.file_name() == Some(filename.as_os_str()))
+            })
+        };
+        let thread = std::thread::spawn(move || {
+            while let Ok(event) = receiver.recv() {
+                if !changed(event) {
+                    continue;
+                }
+                // Wait until the file stops changing.
+                loop {
+                    match receiver.recv_timeout(DEBOUNCE) {
+                        Ok(_) => {}
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                let mut lib = thread_lib.write().unwrap_or_else(|e| e.into_inner());
+                let result = match with_state {
+                    true => lib.reload_with_state(),
+                    false => lib.reload(),
+                };
+                match result {
+                    Ok(()) => on_reload(Ok(&lib)),
+                    Err(e) => on_reload(Err(e)),
+                }
+            }
+        });
+        Ok(WatchedSharedLib { lib, watcher: Some(watcher), thread: Some(thread) })
+    }
+    /// Get the current library, blocking while it is being reloaded.
+    ///
+    /// The library is not reloaded while the returned guard is held, so functions obtained from it stay valid.
+    pub fn lib(&self) -> RwLockReadGuard<'_, SharedLib> {
+        self.lib.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+impl Drop for WatchedSharedLib {
+    fn drop(&mut self) {
+        self.watcher.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
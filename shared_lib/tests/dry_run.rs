@@ -0,0 +1,29 @@
+//! The dry-run mode applies to the whole process, so it is tested apart from the other integration tests.
+use shared_lib::*;
+use std::path::PathBuf;
+
+#[test]
+fn load_stubbed_shared_lib() {
+    SharedLib::register_stub(MockLib::new("stubbed").with_fn::<fn(usize, usize) -> usize>("add", |a, b| a + b));
+    SharedLib::set_dry_run(true);
+    assert!(SharedLib::is_dry_run());
+    unsafe {
+        let mut lib = SharedLib::new(LibPath::new(PathBuf::from("missing_dir"), "stubbed".into())).unwrap();
+        assert!(lib.is_stubbed());
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+        assert!(matches!(lib.get_fn::<fn(usize) -> usize>("add"), Err(SharedLibError::SignatureMismatch { .. })));
+        assert!(lib.try_get_fn::<fn()>("missing").unwrap().is_none());
+        assert!(lib.get_fn_owned::<fn(usize, usize) -> usize>("add").is_err());
+
+        SharedLib::register_stub(MockLib::new("stubbed").with_fn::<fn(usize, usize) -> usize>("add", |a, b| a * b));
+        lib.reload().unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(2, 3), 6);
+
+        let unregistered = SharedLib::new(LibPath::new_no_path("unregistered".into())).unwrap();
+        assert!(matches!(unregistered.get_fn::<fn()>("init"), Err(SharedLibError::SymbolNotFound { .. })));
+
+        SharedLib::set_dry_run(false);
+        assert!(!SharedLib::is_dry_run());
+        assert!(SharedLib::new(LibPath::new(PathBuf::from("missing_dir"), "stubbed".into())).is_err());
+    }
+}
@@ -0,0 +1,14 @@
+typedef int (*callback_t)(int value, void *user_data);
+typedef int (*callback_data_first_t)(void *user_data, int value);
+
+int call_each(callback_t callback, void *user_data, int count) {
+    int sum = 0;
+    for (int i = 1; i <= count; i++) {
+        sum += callback(i, user_data);
+    }
+    return sum;
+}
+
+int call_data_first(callback_data_first_t callback, void *user_data, int value) {
+    return callback(user_data, value);
+}
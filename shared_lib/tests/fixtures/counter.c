@@ -0,0 +1,5 @@
+static int counter = 0;
+
+int next_count(void) {
+    return ++counter;
+}
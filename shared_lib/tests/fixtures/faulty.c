@@ -0,0 +1,9 @@
+int faulty_read(const int *ptr) {
+    return *(const volatile int *)ptr;
+}
+
+int faulty_recurse(int depth) {
+    volatile char frame[1024];
+    frame[0] = (char)depth;
+    return faulty_recurse(depth + 1) + frame[0];
+}
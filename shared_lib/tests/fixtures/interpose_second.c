@@ -0,0 +1,7 @@
+int interposed(void) {
+    return 0;
+}
+
+int only_in_second(void) {
+    return 0;
+}
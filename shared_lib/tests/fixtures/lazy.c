@@ -0,0 +1,5 @@
+int missing_function(void);
+
+int call_missing_function(void) {
+    return missing_function();
+}
@@ -0,0 +1,24 @@
+#include <stdio.h>
+
+#ifndef INIT_RESULT
+#define INIT_RESULT 0
+#endif
+
+static int initialized = 0;
+
+int lib_init(void) {
+    initialized = 1;
+    return INIT_RESULT;
+}
+
+int is_initialized(void) {
+    return initialized;
+}
+
+void lib_shutdown(void) {
+    FILE *file = fopen(SHUTDOWN_FILE, "a");
+    if (file) {
+        fputs("shutdown\n", file);
+        fclose(file);
+    }
+}
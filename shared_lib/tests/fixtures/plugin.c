@@ -0,0 +1,7 @@
+// Provided by the runtime library, which is loaded before the plugin.
+// Data symbols are bound when the plugin is loaded, so loading fails if the runtime is not globally visible.
+extern int runtime_value;
+
+int plugin_value(void) {
+    return runtime_value * 6;
+}
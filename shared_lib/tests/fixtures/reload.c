@@ -0,0 +1,3 @@
+int reload_value(void) {
+    return RELOAD_VALUE;
+}
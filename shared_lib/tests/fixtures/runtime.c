@@ -0,0 +1 @@
+int runtime_value = 7;
@@ -0,0 +1,16 @@
+#include <stdlib.h>
+#include <unistd.h>
+
+double mix(int a, float b, double c, long long d) {
+    return a + b + c + d;
+}
+
+int crash(void) {
+    abort();
+}
+
+int hang(void) {
+    for (;;) {
+        sleep(1);
+    }
+}
@@ -0,0 +1,5 @@
+int sibling_value(void);
+
+int call_sibling(void) {
+    return sibling_value() + 1;
+}
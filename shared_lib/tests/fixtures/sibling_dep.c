@@ -0,0 +1,3 @@
+int sibling_value(void) {
+    return 41;
+}
@@ -0,0 +1,6 @@
+#include <unistd.h>
+
+int sleep_ms(int ms) {
+    usleep(ms * 1000);
+    return ms;
+}
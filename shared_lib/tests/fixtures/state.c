@@ -0,0 +1,20 @@
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+static int count = 0;
+
+int next_count(void) {
+    return count += STATE_STEP;
+}
+
+const uint8_t *plugin_save_state(size_t *len) {
+    *len = sizeof(count);
+    return (const uint8_t *)&count;
+}
+
+void plugin_restore_state(const uint8_t *data, size_t len) {
+    if (len == sizeof(count)) {
+        memcpy(&count, data, len);
+    }
+}
@@ -0,0 +1,21 @@
+#include <stdarg.h>
+#include <stdio.h>
+
+int sum_ints(int count, ...) {
+    va_list args;
+    va_start(args, count);
+    int sum = 0;
+    for (int i = 0; i < count; i++) {
+        sum += va_arg(args, int);
+    }
+    va_end(args);
+    return sum;
+}
+
+int format_message(char *buf, size_t len, const char *fmt, ...) {
+    va_list args;
+    va_start(args, fmt);
+    int written = vsnprintf(buf, len, fmt, args);
+    va_end(args);
+    return written;
+}
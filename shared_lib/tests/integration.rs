@@ -1,4 +1,37 @@
 use shared_lib::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Directory containing the test executable, next to which cargo places the `calculator` library.
+fn deps_dir() -> PathBuf {
+    std::env::current_exe().unwrap().parent().unwrap().to_path_buf()
+}
+/// Compile a C source file from `tests/fixtures` into a shared library in the cargo test temp directory.
+#[cfg(unix)]
+fn compile_c_fixture(name: &str) -> LibPath {
+    compile_c_fixture_as(name, name, &[])
+}
+/// Compile a C source file from `tests/fixtures` like [`compile_c_fixture`] into a library named `lib_name`,
+/// passing extra arguments to the compiler.
+#[cfg(unix)]
+fn compile_c_fixture_as(name: &str, lib_name: &str, args: &[&str]) -> LibPath {
+    let lib_path = LibPath::new(PathBuf::from(env!("CARGO_TARGET_TMPDIR")), lib_name.into());
+    let source = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(format!("{}.c", name));
+    let mut cc = std::process::Command::new("cc");
+    if cfg!(target_os = "macos") {
+        // Allow fixtures to use symbols of libraries they are not linked against.
+        cc.args(["-undefined", "dynamic_lookup"]);
+    }
+    let status = cc
+        .args(["-shared", "-fPIC", "-o"])
+        .arg(lib_path.path().unwrap())
+        .arg(source)
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to compile fixture '{}'", name);
+    lib_path
+}
 
 #[test]
 fn create_shared_lib() {
@@ -8,6 +41,13 @@ fn create_shared_lib() {
     }
 }
 #[test]
+fn create_shared_lib_from_dir() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        SharedLib::new(lib_path).unwrap();
+    }
+}
+#[test]
 #[should_panic]
 fn create_shared_lib_fail() {
     let lib_path = LibPath::new_no_path("non_existent".into());
@@ -42,4 +82,2033 @@ fn call_fn_from_shared_lib() {
         let result = add_fn.run(1, 2);
         assert_eq!(result, 3);
     }
-}
\ No newline at end of file
+}
+#[test]
+fn call_fn_in_closure() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let result = lib.get_fn_with::<fn(usize, usize) -> usize, _>("add", |add_fn| add_fn.run(1, 2)).unwrap();
+        assert_eq!(result, 3);
+        let err = lib.get_fn_with::<fn(usize, usize) -> usize, _>("non_existent", |add_fn| add_fn.run(1, 2)).err().unwrap();
+        assert!(err.is_symbol_error());
+    }
+}
+#[test]
+#[cfg(target_os = "macos")]
+fn call_fn_from_shared_lib_with_rpaths() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new_with_rpaths(lib_path, &[deps_dir()]).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+}
+#[test]
+#[cfg(windows)]
+fn call_fn_from_shared_lib_with_dll_dirs() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new_with_dll_dirs(lib_path, &[deps_dir()]).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+    let dll_dir = DllDirectory::add(deps_dir()).unwrap();
+    assert_eq!(dll_dir.path(), deps_dir());
+    dll_dir.remove().unwrap();
+    let err = DllDirectory::add(deps_dir().join("missing_dir")).err().unwrap();
+    assert!(matches!(err, SharedLibError::DllDirectoryFailure { .. }), "{}", err);
+}
+#[test]
+fn create_shared_lib_from_search_path() {
+    let search_path = LibSearchPath::new(vec![deps_dir().join("missing_dir"), deps_dir()], "calculator".into());
+    unsafe {
+        let lib = SharedLib::new_search(&search_path).unwrap();
+        assert_eq!(lib.lib_path().dir(), deps_dir());
+    }
+}
+#[test]
+fn create_shared_lib_from_search_path_fail() {
+    let search_path = LibSearchPath::new(vec![deps_dir().join("first_dir"), deps_dir().join("second_dir")], "calculator".into());
+    unsafe {
+        let err = SharedLib::new_search(&search_path).err().unwrap();
+        assert!(matches!(&err, SharedLibError::NotInSearchPath { attempts, .. } if attempts.len() == 2));
+        assert!(err.to_string().contains("first_dir") && err.to_string().contains("second_dir"));
+    }
+}
+#[test]
+fn create_shared_lib_from_first_candidate() {
+    let bundled = LibPath::new(deps_dir().join("vendor"), "calculator".into());
+    let system = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new_first_of(&[bundled.clone(), system.clone()]).unwrap();
+        assert_eq!(lib.lib_path(), &system);
+        let missing = LibPath::new(deps_dir(), "missing_lib".into());
+        let err = SharedLib::new_first_of(&[bundled.clone(), missing.clone(), LibPath::new_no_path("".into())]).err().unwrap();
+        let SharedLibError::NoCandidateLoaded { attempts } = &err else { panic!("unexpected error: {}", err) };
+        assert_eq!(attempts.iter().map(|(lib_path, _)| lib_path).collect::<Vec<_>>(), [&bundled, &missing, &LibPath::new_no_path("".into())]);
+        assert!(attempts[2].1.is_path_error());
+        assert!(err.to_string().contains("vendor") && err.to_string().contains("missing_lib"));
+        assert!(matches!(SharedLib::new_first_of(&[]).err().unwrap(), SharedLibError::PathEmpty));
+    }
+}
+#[test]
+fn discover_libs_in_dir() {
+    let dir = std::env::temp_dir().join(format!("shared_lib_discover_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join(LibPath::new_no_path("nested".into()).filename().unwrap())).unwrap();
+    for lib_path in [LibPath::new_no_path("plugin_b".into()), LibPath::new_no_path("plugin_a".into()), LibPath::new_no_path("other".into())] {
+        std::fs::write(dir.join(lib_path.filename().unwrap()), []).unwrap();
+    }
+    std::fs::write(dir.join("readme.txt"), []).unwrap();
+    let lib_names = |lib_paths: Vec<LibPath>| lib_paths.iter().map(|lib_path| lib_path.lib_name().to_owned()).collect::<Vec<_>>();
+    assert_eq!(lib_names(discover_libs(&dir).unwrap()), ["other", "plugin_a", "plugin_b"]);
+    assert_eq!(lib_names(discover_libs_matching(&dir, "plugin_*").unwrap()), ["plugin_a", "plugin_b"]);
+    assert!(discover_libs(&dir).unwrap().iter().all(|lib_path| lib_path.dir() == dir));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(matches!(discover_libs(&dir).err().unwrap(), SharedLibError::LoadFailure { .. }));
+}
+#[test]
+fn walk_libs_in_dir_tree() {
+    let root = std::env::temp_dir().join(format!("shared_lib_walk_{}", std::process::id()));
+    let filename = |lib_name: &str| LibPath::new_no_path(lib_name.into()).filename().unwrap();
+    for (dir, lib_name) in [("", "root"), ("vendor_a", "plugin_a"), ("vendor_a/nested", "deep"), ("vendor_b", "plugin_b"), ("target", "built")] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+        std::fs::write(root.join(dir).join(filename(lib_name)), []).unwrap();
+    }
+    std::fs::write(root.join("vendor_b").join(filename("skipped")), []).unwrap();
+    std::fs::write(root.join("vendor_b").join("readme.txt"), []).unwrap();
+    let lib_names = |walker: LibWalker| walker.walk().map(|lib_path| lib_path.unwrap().lib_name().to_owned()).collect::<Vec<_>>();
+    assert_eq!(lib_names(LibWalker::new(&root)), ["root", "built", "plugin_a", "deep", "plugin_b", "skipped"]);
+    assert_eq!(lib_names(LibWalker::new(&root).max_depth(1).ignore("target")), ["root", "plugin_a", "plugin_b", "skipped"]);
+    let walker = LibWalker::new(&root).matching("plugin_*").filter(|lib_path| !lib_path.dir().ends_with("vendor_b"));
+    assert_eq!(lib_names(walker), ["plugin_a"]);
+    assert_eq!(lib_names(LibWalker::new(&root).ignore(filename("skipped").to_str().unwrap())).len(), 5);
+    std::fs::remove_dir_all(&root).unwrap();
+    let mut walk = LibWalker::new(&root).walk();
+    assert!(matches!(walk.next(), Some(Err(SharedLibError::LoadFailure { .. }))));
+    assert!(walk.next().is_none());
+}
+#[test]
+fn resolve_lib_path() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    let path = lib_path.resolve().unwrap();
+    assert!(path.is_file());
+    assert_eq!(path.file_name(), Some(lib_path.filename().unwrap().as_os_str()));
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    assert_eq!(lib_path.resolve().unwrap(), lib_path.path().unwrap());
+}
+#[test]
+fn resolve_lib_path_fail() {
+    let lib_path = LibPath::new(deps_dir(), "missing_lib".into());
+    let err = lib_path.resolve().unwrap_err();
+    assert!(matches!(err, SharedLibError::LibNotFound { searched, .. } if searched == [lib_path.path().unwrap()]));
+}
+#[test]
+fn list_exported_symbols() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let symbols = lib.exported_symbols().unwrap();
+        for name in ["add", "c_add", "_ZN4math3addEii", "answer_ptr"] {
+            assert!(symbols.iter().any(|symbol| symbol == name), "'{}' is not exported", name);
+        }
+    }
+}
+#[test]
+fn list_exported_symbols_fail() {
+    let lib_path = LibPath::new(deps_dir(), "missing_lib".into());
+    let err = inspect::exported_symbols(lib_path.path().unwrap()).unwrap_err();
+    assert!(matches!(err, SharedLibError::InspectFailure { .. }));
+}
+#[test]
+fn diagnose_lib() {
+    let diagnosis = LibPath::new_no_path("calculator".into()).diagnose().unwrap();
+    assert!(diagnosis.is_ok(), "{}", diagnosis);
+    assert_eq!(diagnosis.architectures.len(), 1);
+    let diagnosis = inspect::diagnose(LibPath::new(deps_dir(), "missing_lib".into()).path().unwrap());
+    assert_eq!(diagnosis.problems, [inspect::LibProblem::NotFound]);
+}
+#[test]
+#[cfg(target_os = "linux")]
+fn list_dependencies() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    let dependencies = lib_path.dependencies().unwrap();
+    assert!(dependencies.iter().any(|name| name.starts_with("libc.so")), "{:?}", dependencies);
+}
+#[test]
+fn list_dependencies_fail() {
+    let lib_path = LibPath::new(deps_dir(), "missing_lib".into());
+    let err = lib_path.dependencies().unwrap_err();
+    assert!(matches!(err, SharedLibError::LibNotFound { .. }));
+    let err = inspect::dependencies(lib_path.path().unwrap()).unwrap_err();
+    assert!(matches!(err, SharedLibError::InspectFailure { .. }));
+}
+#[test]
+#[cfg(target_os = "linux")]
+fn read_lib_metadata() {
+    let lib_path = compile_c_fixture_as("counter", "metadata", &["-Wl,-soname,libmetadata.so.2.1", "-Wl,--build-id"]);
+    let metadata = lib_path.metadata().unwrap();
+    assert_eq!(metadata.name.as_deref(), Some("libmetadata.so.2.1"));
+    assert_eq!(metadata.version.as_deref(), Some("2.1"));
+    assert_eq!(metadata.compatibility_version, None);
+    let build_id = metadata.build_id.unwrap();
+    assert!(!build_id.is_empty() && build_id.chars().all(|c| c.is_ascii_hexdigit()), "{}", build_id);
+    let err = inspect::metadata(LibPath::new(deps_dir(), "missing_lib".into()).path().unwrap()).unwrap_err();
+    assert!(matches!(err, SharedLibError::InspectFailure { .. }));
+}
+#[test]
+fn load_fn_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let (_lib, add_fn) = SharedLib::load_fn::<fn(usize, usize) -> usize>(lib_path, "add").unwrap();
+        let result = add_fn.run(1, 2);
+        assert_eq!(result, 3);
+    }
+}
+#[test]
+fn create_shared_lib_not_found() {
+    let lib_path = LibPath::new(deps_dir(), "missing_lib".into());
+    unsafe {
+        let err = SharedLib::new(lib_path.clone()).err().unwrap();
+        assert!(matches!(&err, SharedLibError::NotFound { path } if *path == lib_path.path().unwrap()));
+        assert!(err.is_not_found() && err.is_load_error());
+        // A file which exists but cannot be loaded is not reported as missing.
+        let corrupt = LibPath::new(PathBuf::from(env!("CARGO_TARGET_TMPDIR")), "corrupt_lib".into());
+        std::fs::write(corrupt.path().unwrap(), b"not a library").unwrap();
+        let err = SharedLib::new(corrupt).err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { .. }));
+    }
+}
+#[test]
+fn load_fn_from_shared_lib_fail() {
+    unsafe {
+        let lib_path = LibPath::new_no_path("non_existent".into());
+        let err = SharedLib::load_fn::<fn(usize, usize) -> usize>(lib_path, "add").err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { .. }));
+        let lib_path = LibPath::new_no_path("calculator".into());
+        let err = SharedLib::load_fn::<fn(usize, usize) -> usize>(lib_path, "non_existent").err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { .. }));
+    }
+}
+#[test]
+#[cfg(feature = "demangle")]
+fn get_demangled_fn_from_shared_lib() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn_demangled::<extern "C" fn(i32, i32) -> i32>("math::add(int, int)").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        let err = lib.get_fn_demangled::<extern "C" fn(i32, i32) -> i32>("math::sub(int, int)").err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { .. }));
+        let err = lib.get_fn_demangled::<extern "C" fn(i64, i64) -> i64>("math::add(long, long)").err().unwrap();
+        assert!(matches!(&err, SharedLibError::SymbolNotFound { msg, .. } if msg.ends_with("Candidates: math::add(int, int).")));
+    }
+}
+#[test]
+#[cfg(feature = "demangle")]
+fn get_mangled_fn_from_shared_lib_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("_ZN4math3subEii").err().unwrap();
+        assert!(err.to_string().contains("'_ZN4math3subEii' (math::sub(int, int))"));
+    }
+}
+#[test]
+#[cfg(unix)]
+fn call_variadic_fn_from_shared_lib() {
+    use std::ffi::{c_char, c_int, CStr};
+    let lib_path = compile_c_fixture("variadic");
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let sum_fn = lib.get_fn::<unsafe extern "C" fn(c_int, ...) -> c_int>("sum_ints").unwrap();
+        assert_eq!(sum_fn.run(0, ()), 0);
+        assert_eq!(sum_fn.run(3, (1, 2, 3)), 6);
+        let format_fn = lib.get_fn::<unsafe extern "C" fn(*mut c_char, usize, *const c_char, ...) -> c_int>("format_message").unwrap();
+        let mut buf = [0 as c_char; 64];
+        format_fn.run(buf.as_mut_ptr(), buf.len(), c"%s: %d %.1f".as_ptr(), (c"plugin".as_ptr(), 42, 1.5));
+        assert_eq!(CStr::from_ptr(buf.as_ptr()).to_str().unwrap(), "plugin: 42 1.5");
+    }
+}
+#[test]
+#[cfg(unix)]
+fn call_closure_from_shared_lib() {
+    use std::cell::Cell;
+    use std::ffi::{c_int, c_void};
+    use std::rc::Rc;
+    let lib_path = compile_c_fixture("callback");
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let call_each_fn = lib.get_fn::<unsafe extern "C" fn(unsafe extern "C" fn(c_int, *mut c_void) -> c_int, *mut c_void, c_int) -> c_int>("call_each").unwrap();
+        let calls = Rc::new(Cell::new(0));
+        let callback = Callback::<(c_int,), c_int>::new({
+            let calls = Rc::clone(&calls);
+            move |value| {
+                calls.set(calls.get() + 1);
+                value * 10
+            }
+        });
+        assert_eq!(call_each_fn.run(callback.trampoline(), callback.user_data(), 3), 60);
+        assert_eq!(calls.get(), 3);
+        let call_data_first_fn = lib.get_fn::<unsafe extern "C" fn(unsafe extern "C" fn(*mut c_void, c_int) -> c_int, *mut c_void, c_int) -> c_int>("call_data_first").unwrap();
+        assert_eq!(call_data_first_fn.run(callback.trampoline_data_first(), callback.user_data(), 4), 40);
+        assert_eq!(calls.get(), 4);
+        drop(callback);
+        assert_eq!(Rc::strong_count(&calls), 1);
+    }
+}
+#[test]
+fn call_c_fn_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("c_add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        let add_fn = lib.get_fn::<unsafe extern "C" fn(i32, i32) -> i32>("c_add").unwrap();
+        assert_eq!(add_fn.run(-1, 2), 1);
+    }
+}
+#[test]
+fn call_fn_catching_panics_from_shared_lib() {
+    // The test binary links the standard library statically, so a panic in the library could not be caught here.
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run_catching(1, 2).unwrap(), 3);
+    }
+}
+#[test]
+fn get_fns_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let [add_fn, math_add_fn] = lib.get_fns::<extern "C" fn(i32, i32) -> i32, 2>(["c_add", "_ZN4math3addEii"]).unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        assert_eq!(math_add_fn.run(1, 2), 3);
+    }
+}
+#[test]
+fn get_fns_from_shared_lib_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_fns::<fn(), 3>(["missing_fn", "add", "other_missing_fn"]).err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolsNotFound { symbol_names, .. } if symbol_names == ["missing_fn", "other_missing_fn"]));
+    }
+}
+#[test]
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))]
+fn get_versioned_fn_from_shared_lib() {
+    type Memcpy = unsafe extern "C" fn(*mut std::ffi::c_void, *const std::ffi::c_void, usize) -> *mut std::ffi::c_void;
+    let lib_path = LibPath::new_no_path("c".into()).with_version("6".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let memcpy_fn = lib.get_fn_versioned::<Memcpy>("memcpy", "GLIBC_2.14").unwrap();
+        let (src, mut dst) = ([1u8, 2, 3], [0u8; 3]);
+        memcpy_fn.run(dst.as_mut_ptr().cast(), src.as_ptr().cast(), src.len());
+        assert_eq!(dst, src);
+    }
+}
+#[test]
+fn get_versioned_fn_from_shared_lib_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_fn_versioned::<fn(usize, usize) -> usize>("add", "MISSING_1.0").err().unwrap();
+        if cfg!(all(target_os = "linux", target_env = "gnu")) {
+            assert!(matches!(err, SharedLibError::SymbolNotFound { symbol_name, .. } if symbol_name == "add@MISSING_1.0"));
+        } else {
+            assert!(matches!(err, SharedLibError::Unsupported(_)));
+        }
+    }
+}
+#[test]
+fn get_fn_with_decorated_names() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_decorated_names(true)).unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+        let err = lib.get_fn::<fn(usize, usize) -> usize>("sub").err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { symbol_name, .. } if symbol_name == "sub"));
+    }
+}
+#[test]
+fn get_fn_with_symbol_suggestions() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new_with_flags(lib_path.clone(), LoadFlags::new().with_symbol_suggestions(true)).unwrap();
+        let err = lib.get_fn::<fn(usize, usize) -> usize>("ad").err().unwrap();
+        assert!(matches!(&err, SharedLibError::SymbolNotFound { msg, .. } if msg.ends_with("Did you mean 'add'?")), "{}", err);
+        let err = lib.get_fn::<fn(usize, usize) -> usize>("multiply").err().unwrap();
+        assert!(!err.to_string().contains("Did you mean"), "{}", err);
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_fn::<fn(usize, usize) -> usize>("ad").err().unwrap();
+        assert!(!err.to_string().contains("Did you mean"), "{}", err);
+    }
+}
+#[test]
+fn get_fn_with_call_metrics() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new_with_flags(lib_path.clone(), LoadFlags::new().with_call_metrics(true)).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        assert_eq!(add_fn.clone().run(2, 3), 5);
+        let c_add_fn = lib.get_fn_cached::<extern "C" fn(i32, i32) -> i32>("c_add").unwrap();
+        assert_eq!(c_add_fn.run(1, 2), 3);
+        let stats = lib.stats();
+        assert_eq!(stats["add"].calls, 2);
+        assert!(stats["add"].total >= stats["add"].last);
+        assert_eq!(stats["c_add"].calls, 1);
+        lib.reset_stats();
+        assert_eq!(lib.stats()["add"], CallStats::default());
+        add_fn.run(1, 2);
+        assert_eq!(lib.stats()["add"].calls, 1);
+        let lib = SharedLib::new(lib_path).unwrap();
+        lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2);
+        assert!(lib.stats().is_empty());
+    }
+}
+#[test]
+fn get_fn_checked_signature() {
+    let lib_path = LibPath::new_no_path("greeter".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let greeting_len = lib.get_fn_checked::<extern "C" fn(u32) -> u32>("greeting_len").unwrap();
+        assert_eq!(greeting_len.run(2), 12);
+        let err = lib.get_fn_checked::<extern "C" fn(u64) -> u64>("greeting_len").err().unwrap();
+        assert!(matches!(&err, SharedLibError::SignatureMismatch { symbol_name, .. } if symbol_name == "greeting_len"), "{}", err);
+        assert!(err.is_symbol_error());
+        let err = lib.get_fn_checked::<fn() -> *mut u8>("greeter_create_null").err().unwrap();
+        assert!(matches!(&err, SharedLibError::SymbolNotFound { symbol_name, .. } if symbol_name == "greeter_create_null__sig"), "{}", err);
+    }
+}
+shared_lib_extern! {
+    struct CalculatorApi;
+    extern "C" {
+        fn c_add(left: i32, right: i32) -> i32;
+        /// ABI version of the calculator.
+        #[link_name = "abi_version"]
+        fn version() -> u32;
+    }
+}
+shared_lib_extern! {
+    struct MissingApi;
+    extern "C" {
+        fn c_add(left: i32, right: i32) -> i32;
+        fn c_sub(left: i32, right: i32) -> i32;
+        #[link_name = "c_mul"]
+        fn mul(left: i32, right: i32) -> i32;
+    }
+}
+#[test]
+fn load_extern_block_api() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let api = CalculatorApi::load(&lib).unwrap();
+        assert_eq!(api.c_add.run(1, 2), 3);
+        assert_eq!(api.version.run(), 1);
+        let err = MissingApi::load(&lib).err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolsNotFound { symbol_names, .. } if symbol_names == ["c_sub", "c_mul"]));
+    }
+}
+#[test]
+fn get_fn_by_ordinal_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_fn_by_ordinal::<fn(usize, usize) -> usize>(u16::MAX).err().unwrap();
+        if cfg!(windows) {
+            assert!(matches!(err, SharedLibError::SymbolNotFound { symbol_name, .. } if symbol_name == "#65535"));
+        } else {
+            assert!(matches!(err, SharedLibError::Unsupported(_)));
+        }
+    }
+}
+#[test]
+#[cfg(unix)]
+fn get_fn_from_this() {
+    unsafe {
+        let mut this = SharedLib::this().unwrap();
+        let getpid_fn = this.get_fn::<extern "C" fn() -> i32>("getpid").unwrap();
+        assert_eq!(getpid_fn.run() as u32, std::process::id());
+        let err = this.get_fn::<fn()>("missing_fn").err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { .. }));
+        assert!(matches!(this.reload().unwrap_err(), SharedLibError::PathEmpty));
+        assert!(this.get_fn::<extern "C" fn() -> i32>("getpid").is_ok());
+    }
+}
+#[test]
+fn get_module_info_of_shared_lib() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path.clone()).unwrap();
+        let info = lib.module_info().unwrap();
+        // The library may already be loaded from a hard link with another path, e.g. by other tests.
+        assert_eq!(info.path.file_name().unwrap(), lib_path.filename().unwrap());
+        assert!(info.path.is_file());
+        let address = lib.get_symbol_address("add").unwrap() as usize;
+        assert!(info.base_address <= address);
+        if let Some(size) = info.size {
+            assert!(address < info.base_address + size);
+        }
+    }
+}
+#[test]
+fn get_loaded_libraries() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path.clone()).unwrap();
+        let info = lib.module_info().unwrap();
+        let modules = loaded_libraries().unwrap();
+        assert!(modules.contains(&info), "{:?}", modules);
+        let exe = std::env::current_exe().unwrap().canonicalize().unwrap();
+        assert!(modules.iter().any(|module| module.path.canonicalize().is_ok_and(|path| path == exe)));
+    }
+}
+#[test]
+#[cfg(unix)]
+fn get_module_info_of_this() {
+    let info = SharedLib::this().unwrap().module_info().unwrap();
+    assert_eq!(info.path.canonicalize().unwrap(), std::env::current_exe().unwrap().canonicalize().unwrap());
+    assert!(info.base_address <= get_module_info_of_this as *const () as usize);
+}
+#[test]
+fn try_get_fn_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.try_get_fn::<fn(usize, usize) -> usize>("add").unwrap().unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        assert!(lib.try_get_fn::<fn(usize, usize) -> usize>("missing_fn").unwrap().is_none());
+    }
+}
+#[test]
+fn get_var_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        assert_eq!(*lib.get_var::<usize>("ANSWER").unwrap(), 42);
+        let answer_ptr = lib.get_var_ptr::<usize>("ANSWER").unwrap();
+        let answer_fn = lib.get_fn::<fn(bool) -> *const usize>("answer_ptr").unwrap();
+        assert_eq!(answer_ptr.cast_const(), answer_fn.run(true));
+    }
+}
+#[test]
+fn get_symbol_address_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("c_add").unwrap();
+        let address = lib.get_symbol_address("c_add").unwrap();
+        assert_eq!(add_fn.as_raw_ptr(), address);
+        let add_fn: extern "C" fn(i32, i32) -> i32 = std::mem::transmute(address);
+        assert_eq!(add_fn(1, 2), 3);
+        assert!(matches!(lib.get_symbol_address("missing_fn").unwrap_err(), SharedLibError::SymbolNotFound { .. }));
+    }
+}
+#[test]
+fn get_var_from_shared_lib_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_var::<usize>("MISSING_VAR").unwrap_err();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { symbol_name, .. } if symbol_name == "MISSING_VAR"));
+    }
+}
+#[test]
+fn check_abi_of_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        lib.check_abi("abi_version", 1).unwrap();
+    }
+}
+#[test]
+fn check_abi_of_shared_lib_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.check_abi("abi_version", 2).unwrap_err();
+        assert!(matches!(err, SharedLibError::AbiMismatch { expected: 2, found: 1, .. }));
+        let err = lib.check_abi("missing_abi_version", 1).unwrap_err();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { .. }));
+    }
+}
+#[test]
+fn call_fn_with_many_args_from_shared_lib() {
+    type Sum16 = extern "C" fn(i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32, i32) -> i32;
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let sum_fn = lib.get_fn::<Sum16>("c_sum16").unwrap();
+        assert_eq!(sum_fn.run(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16), 136);
+    }
+}
+#[test]
+fn call_ptr_fn_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let answer_fn = lib.get_fn::<fn(bool) -> *const usize>("answer_ptr").unwrap();
+        assert_eq!(*answer_fn.run_checked_ptr(true).unwrap().as_ref(), 42);
+        assert!(answer_fn.run_checked_ptr(false).is_none());
+    }
+}
+#[test]
+fn call_cstr_fn_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let name_fn = lib.get_fn::<extern "C" fn(i32) -> *const std::ffi::c_char>("c_name").unwrap();
+        assert_eq!(name_fn.run_cstr(0).unwrap().to_str(), Ok("calculator"));
+        assert_eq!(name_fn.run_string(0).as_deref(), Some("calculator"));
+        assert_eq!(name_fn.run_string(1).as_deref(), Some("calc\u{FFFD}ulator"));
+        assert!(name_fn.run_cstr(2).is_none());
+        assert!(name_fn.run_string(2).is_none());
+    }
+}
+#[test]
+#[cfg(unix)]
+fn load_lib_set_in_order() {
+    let runtime_path = compile_c_fixture("runtime");
+    let plugin_path = compile_c_fixture("plugin");
+    unsafe {
+        let libs = SharedLibSet::new_global([runtime_path, plugin_path]).unwrap();
+        assert_eq!(libs.libs().len(), 2);
+        let plugin_fn = libs.get_fn::<extern "C" fn() -> i32>("plugin_value").unwrap();
+        assert_eq!(plugin_fn.run(), 42);
+        let err = libs.get_fn::<extern "C" fn() -> i32>("non_existent").err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { .. }));
+    }
+}
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn create_shared_lib_async() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new_async(lib_path).await.unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+}
+#[tokio::test]
+#[cfg(feature = "tokio")]
+async fn reload_shared_lib_async() {
+    let search_path = LibSearchPath::new(vec![deps_dir().join("missing_dir"), deps_dir()], "calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new_search_async(search_path).await.unwrap();
+        lib.reload_async().await.unwrap();
+        assert_eq!(lib.generation(), 1);
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn resolve_interposed_symbol_from_lib_set() {
+    let first_path = compile_c_fixture("interpose_first");
+    let second_path = compile_c_fixture("interpose_second");
+    unsafe {
+        let libs = SharedLibSet::new_global([first_path, second_path]).unwrap();
+        assert_eq!(libs.resolution_order(), ResolutionOrder::LoadOrder);
+        assert_eq!(libs.provider_of("interposed").unwrap().lib_path().lib_name(), "interpose_first");
+        assert_eq!(libs.provider_of("only_in_second").unwrap().lib_path().lib_name(), "interpose_second");
+        assert!(libs.provider_of("non_existent").is_none());
+
+        let libs = libs.with_resolution_order(ResolutionOrder::ReverseLoadOrder);
+        assert_eq!(libs.provider_of("interposed").unwrap().lib_path().lib_name(), "interpose_second");
+        assert_eq!(libs.provider_of("only_in_first").unwrap().lib_path().lib_name(), "interpose_first");
+    }
+}
+#[test]
+#[cfg(feature = "verify")]
+fn create_verified_shared_lib() {
+    use sha2::{Digest, Sha256};
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    let data = std::fs::read(lib_path.path().unwrap()).unwrap();
+    let sha256: String = Sha256::digest(&data).iter().map(|byte| format!("{:02X}", byte)).collect();
+    unsafe {
+        let lib = SharedLib::new_verified(lib_path, &sha256).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+}
+#[test]
+#[cfg(feature = "verify")]
+fn create_verified_shared_lib_mismatch() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    let sha256 = "0".repeat(64);
+    unsafe {
+        let err = SharedLib::new_verified(lib_path, &sha256).err().unwrap();
+        assert!(matches!(err, SharedLibError::ChecksumMismatch { expected, .. } if expected == sha256));
+    }
+}
+#[test]
+fn create_shared_lib_with_builder() {
+    unsafe {
+        let lib = SharedLib::builder("calculator")
+            .search_dir(deps_dir().join("missing_dir"))
+            .search_dir(deps_dir())
+            .flags(LoadFlags::new().with_now(true))
+            .load()
+            .unwrap();
+        assert_eq!(lib.lib_path(), &LibPath::new(deps_dir(), "calculator".into()));
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        let err = SharedLib::builder("missing_lib").search_dir(deps_dir()).load().err().unwrap();
+        assert!(matches!(err, SharedLibError::NotFound { .. }));
+        let err = SharedLib::builder("missing_lib").search_dirs([deps_dir(), deps_dir()]).load().err().unwrap();
+        assert!(matches!(err, SharedLibError::NotInSearchPath { attempts, .. } if attempts.len() == 2));
+    }
+}
+#[test]
+#[cfg(all(unix, feature = "archive"))]
+fn create_shared_lib_from_archive() {
+    let lib_path = compile_c_fixture_as("counter", "archive_counter", &[]);
+    let src_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("archive_src");
+    let _ = std::fs::remove_dir_all(&src_dir);
+    std::fs::create_dir_all(src_dir.join("plugin")).unwrap();
+    std::fs::create_dir_all(src_dir.join("assets")).unwrap();
+    let filename = lib_path.filename().unwrap();
+    std::fs::copy(lib_path.path().unwrap(), src_dir.join("plugin").join(&filename)).unwrap();
+    std::fs::write(src_dir.join("assets/readme.txt"), "Counter plugin").unwrap();
+    std::os::unix::fs::symlink(&filename, src_dir.join("plugin").join(LibPath::new_no_path("archive_link".into()).filename().unwrap())).unwrap();
+    let archive = |name: &str, program: &str, args: &[&str]| {
+        let path = src_dir.with_file_name(name);
+        let _ = std::fs::remove_file(&path);
+        let status = std::process::Command::new(program).current_dir(&src_dir).args(args).arg(&path).args(["plugin", "assets"]).status().unwrap();
+        assert!(status.success(), "Failed to create archive '{}'", name);
+        path
+    };
+    let zip = archive("plugin.zip", "zip", &["-q", "-r", "-y"]);
+    let tar_gz = archive("plugin.tar.gz", "tar", &["-czf"]);
+    let tar = archive("plugin.tar", "tar", &["-cf"]);
+    unsafe {
+        for (path, lib_name) in [(&zip, "archive_counter"), (&tar_gz, "archive_counter"), (&tar, "archive_link")] {
+            let lib = SharedLib::from_archive(path, lib_name).unwrap();
+            let next_count = lib.get_fn::<extern "C" fn() -> i32>("next_count").unwrap();
+            assert_eq!(next_count.run(), 1);
+            let dir = lib.lib_path().dir_path.clone();
+            assert_eq!(std::fs::read_to_string(dir.parent().unwrap().join("assets/readme.txt")).unwrap(), "Counter plugin");
+            drop(lib);
+            assert!(!dir.exists(), "Extraction of '{}' was not removed", path.display());
+        }
+        let err = SharedLib::from_archive(&zip, "missing_lib").err().unwrap();
+        assert!(matches!(err, SharedLibError::LibNotFound { .. }), "{}", err);
+        let err = SharedLib::from_archive(src_dir.join("assets/readme.txt"), "archive_counter").err().unwrap();
+        assert!(matches!(err, SharedLibError::InvalidArchive { .. }), "{}", err);
+        let err = SharedLib::from_archive(src_dir.join("missing.zip"), "archive_counter").err().unwrap();
+        assert!(matches!(err, SharedLibError::NotFound { .. }), "{}", err);
+        // Change the CRC-32 of the readme in the central directory, which is listed after the local headers.
+        let mut data = std::fs::read(&zip).unwrap();
+        let name_pos = data.windows(17).rposition(|name| name == b"assets/readme.txt").unwrap();
+        data[name_pos - 46 + 16] ^= 0xff;
+        let corrupted = src_dir.with_file_name("corrupted.zip");
+        std::fs::write(&corrupted, data).unwrap();
+        let err = SharedLib::from_archive(&corrupted, "archive_counter").err().unwrap();
+        assert!(matches!(&err, SharedLibError::InvalidArchive { msg, .. } if msg.contains("CRC-32")), "{}", err);
+    }
+}
+#[test]
+#[cfg(feature = "verify")]
+fn create_verified_shared_lib_with_builder() {
+    use sha2::{Digest, Sha256};
+    let data = std::fs::read(LibPath::new(deps_dir(), "calculator".into()).path().unwrap()).unwrap();
+    let sha256: String = Sha256::digest(&data).iter().map(|byte| format!("{:02x}", byte)).collect();
+    unsafe {
+        let lib = SharedLib::builder("calculator").search_dir(deps_dir()).verify_sha256(sha256).load().unwrap();
+        assert!(lib.get_fn::<fn(usize, usize) -> usize>("add").is_ok());
+        let err = SharedLib::builder("calculator").search_dir(deps_dir()).verify_sha256("0".repeat(64)).load().err().unwrap();
+        assert!(matches!(err, SharedLibError::ChecksumMismatch { .. }));
+        let err = SharedLib::builder("calculator").verify_sha256("0".repeat(64)).load().err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { .. }));
+    }
+}
+#[test]
+#[cfg(feature = "remote")]
+fn load_remote_lib() {
+    use sha2::{Digest, Sha256};
+    use std::io::{Read, Write};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    let path = LibPath::new(deps_dir(), "calculator".into()).path().unwrap();
+    let data = std::fs::read(&path).unwrap();
+    let sha256: String = Sha256::digest(&data).iter().map(|byte| format!("{:02x}", byte)).collect();
+    let filename = path.file_name().unwrap().to_str().unwrap().to_owned();
+    let cache_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("remote_cache");
+    let _ = std::fs::remove_dir_all(&cache_dir);
+
+    // Serve the library once over HTTP, so loading it again has to use the cache.
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/libs/{}", listener.local_addr().unwrap(), filename);
+    let server_data = data.clone();
+    let server = std::thread::spawn(move || {
+        let respond = |header: String, body: &[u8]| {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut byte = [0];
+                stream.read_exact(&mut byte).unwrap();
+                request.push(byte[0]);
+            }
+            assert!(request.starts_with(b"GET /libs/"));
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(body).unwrap();
+        };
+        respond(format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", server_data.len()), &server_data);
+        respond(format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", DefaultFetcher::MAX_SIZE + 1), &[]);
+        respond(format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", server_data.len()), &server_data[..16]);
+    });
+    unsafe {
+        let remote = RemoteLib::new(&url, &sha256).unwrap().cache_dir(&cache_dir);
+        let lib = remote.load().unwrap();
+        assert!(lib.get_fn::<fn(usize, usize) -> usize>("add").is_ok());
+        let err = DefaultFetcher.fetch(&url).err().unwrap();
+        assert!(err.contains("more than the maximum"), "{}", err);
+        let err = DefaultFetcher.fetch(&url).err().unwrap();
+        assert!(err.contains(&format!("ended after 16 of {} bytes", data.len())), "{}", err);
+        server.join().unwrap();
+        assert_eq!(remote.cached_path().unwrap().path().unwrap(), cache_dir.join(&sha256).join(&filename));
+        remote.load().unwrap();
+    }
+
+    let fetches = std::sync::Arc::new(AtomicUsize::new(0));
+    let counter = fetches.clone();
+    let remote = RemoteLib::new(format!("file://{}", path.display()), &sha256).unwrap().cache_dir(&cache_dir).fetcher(move |url: &str| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        DefaultFetcher.fetch(url)
+    });
+    remote.fetch().unwrap();
+    assert_eq!(fetches.load(Ordering::SeqCst), 0);
+    std::fs::write(remote.cached_path().unwrap().path().unwrap(), b"corrupted").unwrap();
+    remote.fetch().unwrap();
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+    let err = RemoteLib::new(format!("file://{}", path.display()), "0".repeat(64)).unwrap().cache_dir(&cache_dir).fetch().err().unwrap();
+    assert!(matches!(err, SharedLibError::IntegrityMismatch { .. }), "{}", err);
+    let err = RemoteLib::new(format!("https://example.com/{}", filename), &sha256).unwrap().cache_dir(cache_dir.join("empty")).fetch().err().unwrap();
+    assert!(matches!(err, SharedLibError::FetchFailure { .. }), "{}", err);
+
+    assert!(RemoteLib::new(format!("file://{}", path.display()), format!(" {} ", sha256.to_uppercase())).is_ok());
+    for sha256 in ["../../escape".to_owned(), "0".repeat(63), "0".repeat(65), "g".repeat(64)] {
+        let err = RemoteLib::new(format!("file://{}", path.display()), &sha256).err().unwrap();
+        assert!(matches!(&err, SharedLibError::InvalidSha256(hash) if *hash == sha256), "{}", err);
+    }
+}
+#[test]
+#[cfg(feature = "signature")]
+fn create_signed_shared_lib() {
+    use ed25519_dalek::{Signer, SigningKey};
+    let dir_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("signed");
+    std::fs::create_dir_all(&dir_path).unwrap();
+    let lib_path = LibPath::new(dir_path, "calculator".into());
+    let path = lib_path.path().unwrap();
+    std::fs::copy(deps_dir().join(lib_path.filename().unwrap()), &path).unwrap();
+    let signing_key = SigningKey::from_bytes(&[7; 32]);
+    let signature = signing_key.sign(&std::fs::read(&path).unwrap());
+    std::fs::write(Ed25519Verifier::signature_path(&path), signature.to_bytes()).unwrap();
+    unsafe {
+        let verifier = Ed25519Verifier::new(signing_key.verifying_key().to_bytes());
+        let lib = SharedLib::new_with_verifier(lib_path.clone(), &verifier).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+
+        let verifier = Ed25519Verifier::new(SigningKey::from_bytes(&[8; 32]).verifying_key().to_bytes());
+        let err = SharedLib::new_with_verifier(lib_path, &verifier).err().unwrap();
+        assert!(matches!(err, SharedLibError::VerificationFailure { .. }));
+    }
+}
+#[test]
+#[cfg(feature = "signature")]
+fn create_signed_shared_lib_missing_signature() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let err = SharedLib::new_with_verifier(lib_path, &Ed25519Verifier::new([0; 32])).err().unwrap();
+        assert!(matches!(err, SharedLibError::VerificationFailure { .. }));
+    }
+}
+#[test]
+fn open_cached_shared_lib() {
+    let manager = LibManager::new();
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = manager.open(lib_path.clone()).unwrap();
+        let same_lib = manager.open(LibPath::new(deps_dir().join("."), "calculator".into())).unwrap();
+        assert!(Arc::ptr_eq(&lib, &same_lib));
+        assert_eq!(manager.len(), 1);
+        let add_fn = same_lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+    assert!(manager.get(&lib_path).is_some());
+    assert!(manager.evict(&lib_path).is_some());
+    assert!(manager.get(&lib_path).is_none());
+    assert!(manager.is_empty());
+}
+#[test]
+fn open_cached_shared_lib_fail() {
+    let lib_path = LibPath::new(deps_dir(), "missing_lib".into());
+    unsafe {
+        let err = SharedLib::open_cached(lib_path.clone()).err().unwrap();
+        assert!(matches!(err, SharedLibError::NotFound { .. }));
+    }
+    assert!(LibManager::global().get(&lib_path).is_none());
+}
+#[test]
+fn run_shared_lib_hooks() {
+    let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let log = |event: &'static str| {
+        let events = Arc::clone(&events);
+        move |lib: &SharedLib| events.lock().unwrap().push((event, lib.generation()))
+    };
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new(lib_path.clone()).unwrap();
+        lib.on_load(log("load"));
+        lib.before_unload(log("unload"));
+        lib.after_reload(log("reload"));
+        lib.reload().unwrap();
+        let _add_fn = lib.get_fn_owned::<fn(usize, usize) -> usize>("add").unwrap();
+        assert!(lib.reload().is_err());
+        drop(_add_fn);
+        lib.close().unwrap();
+        assert_eq!(*events.lock().unwrap(), [("unload", 0), ("load", 1), ("reload", 1), ("unload", 1)]);
+        events.lock().unwrap().clear();
+
+        let manager = LibManager::new();
+        manager.on_load(log("load"));
+        manager.before_unload(log("unload"));
+        let lib = manager.open(lib_path.clone()).unwrap();
+        let same_lib = manager.open(lib_path.clone()).unwrap();
+        assert_eq!(*events.lock().unwrap(), [("load", 0)]);
+        manager.evict(&lib_path);
+        drop((lib, same_lib));
+        assert_eq!(*events.lock().unwrap(), [("load", 0), ("unload", 0)]);
+    }
+}
+#[test]
+fn get_fn_from_symbol_source() {
+    unsafe fn add_twice(lib: &impl SymbolSource, a: usize, b: usize) -> Result<usize, SharedLibError> {
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add")?;
+        Ok(add_fn.run(add_fn.run(a, b), b))
+    }
+    let mock = MockLib::new("calculator").with_fn::<fn(usize, usize) -> usize>("add", |a, b| a * 10 + b);
+    unsafe {
+        let lib = SharedLib::new(LibPath::new(deps_dir(), "calculator".into())).unwrap();
+        assert_eq!(add_twice(&lib, 1, 2).unwrap(), 5);
+        assert_eq!(add_twice(&mock, 1, 2).unwrap(), 122);
+        assert_eq!(mock.calls("add"), 2);
+        let err = mock.get_fn::<fn() -> usize>("add").err().unwrap();
+        assert!(matches!(err, SharedLibError::SignatureMismatch { .. }), "{}", err);
+        let err = mock.get_fn::<fn() -> usize>("missing_fn").err().unwrap();
+        assert!(err.is_symbol_error());
+    }
+}
+#[test]
+fn get_raw_shared_lib() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path.clone()).unwrap();
+        assert_eq!(lib.as_libloading().unwrap().get::<fn(usize, usize) -> usize>(b"add").unwrap()(1, 2), 3);
+        #[cfg(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos", windows))]
+        assert!(!lib.raw_os_handle().unwrap().is_null());
+        let add_fn = lib.get_fn_owned::<fn(usize, usize) -> usize>("add").unwrap();
+        let err = lib.into_inner().err().unwrap();
+        assert!(matches!(err, SharedLibError::UnloadFailure { .. }), "{}", err);
+        drop(add_fn);
+        let lib = SharedLib::new(lib_path).unwrap().into_inner().unwrap();
+        assert_eq!(lib.get::<fn(usize, usize) -> usize>(b"add").unwrap()(2, 3), 5);
+    }
+}
+#[test]
+fn open_existing_shared_lib() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path.clone()).unwrap();
+        let existing = SharedLib::open_existing(lib_path).unwrap();
+        drop(lib);
+        let add_fn = existing.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        let err = SharedLib::open_existing(LibPath::new(deps_dir(), "missing_lib".into())).err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { .. }), "{}", err);
+    }
+}
+lazy_lib!(static CALCULATOR = "calculator");
+lazy_fn!(static LAZY_ADD: fn(usize, usize) -> usize = (CALCULATOR, "add"));
+lazy_fn!(static LAZY_C_ADD: extern "C" fn(i32, i32) -> i32 = ("calculator", "c_add"));
+lazy_fn!(static LAZY_MISSING: fn() = (CALCULATOR, "missing_fn"));
+lazy_fn!(static LAZY_MISSING_LIB: fn() = ("missing_lib", "missing_fn"));
+#[test]
+fn call_lazy_fn() {
+    unsafe {
+        assert_eq!(LAZY_ADD.get().unwrap().run(1, 2), 3);
+        assert_eq!(LAZY_C_ADD.get().unwrap().run(1, 2), 3);
+        assert!(CALCULATOR.get().is_ok());
+    }
+}
+#[test]
+fn call_lazy_fn_fail() {
+    unsafe {
+        assert!(matches!(LAZY_MISSING.get().err().unwrap(), SharedLibError::SymbolNotFound { .. }));
+        assert!(matches!(LAZY_MISSING_LIB.get().err().unwrap(), SharedLibError::LoadFailure { .. }));
+        assert!(matches!(LAZY_MISSING_LIB.get().err().unwrap(), SharedLibError::LoadFailure { .. }));
+    }
+}
+#[test]
+fn call_owned_fn_after_lib_dropped() {
+    struct Calculator {
+        add_fn: OwnedSharedLibFn<fn(usize, usize) -> usize>,
+    }
+    fn load_calculator() -> Calculator {
+        let lib_path = LibPath::new_no_path("calculator".into());
+        unsafe {
+            let lib = SharedLib::new(lib_path).unwrap();
+            Calculator { add_fn: lib.get_fn_owned("add").unwrap() }
+        }
+    }
+    let calculator = load_calculator();
+    let add_fn = calculator.add_fn.clone();
+    drop(calculator);
+    unsafe {
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+}
+#[test]
+fn get_cached_fn_from_threads() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    let lib = Arc::new(unsafe { SharedLib::new(lib_path).unwrap() });
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            let lib = Arc::clone(&lib);
+            std::thread::spawn(move || unsafe { lib.get_fn_cached::<fn(usize, usize) -> usize>("add").unwrap().run(i, 1) })
+        })
+        .collect();
+    let results: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+    assert_eq!(results, [1, 2, 3, 4]);
+    unsafe {
+        assert!(matches!(lib.get_fn_cached::<fn()>("missing").err().unwrap(), SharedLibError::SymbolNotFound { .. }));
+    }
+}
+#[test]
+fn get_fn_from_symbol_cache() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        for _ in 0..2 {
+            assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+        }
+        lib.clear_symbol_cache();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(2, 2), 4);
+        lib.reload().unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(3, 2), 5);
+        assert!(lib.get_fn::<fn()>("missing_fn").is_err());
+    }
+}
+#[test]
+fn create_shared_lib_with_symbols() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new_with_symbols(lib_path.clone(), &["add", "c_add"]).unwrap();
+        lib.reload().unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+        let err = SharedLib::new_with_symbols(lib_path, &["add", "missing_a", "missing_b"]).err().unwrap();
+        assert!(matches!(&err, SharedLibError::SymbolsNotFound { symbol_names, .. } if symbol_names == &["missing_a", "missing_b"]), "{}", err);
+
+        let lib = SharedLib::builder("calculator").search_dir(deps_dir()).require_symbols(["c_add"]).load().unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn(i32, i32) -> i32>("c_add").unwrap().run(1, 2), 3);
+        let err = SharedLib::builder("calculator").search_dir(deps_dir()).require_symbols(["missing_fn"]).load().err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolsNotFound { .. }), "{}", err);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn load_shared_lib_from_canonical_path() {
+    let lib_path = compile_c_fixture_as("counter", "canonical_counter", &[]);
+    let link_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("canonical_link");
+    let _ = std::fs::remove_file(&link_dir);
+    std::os::unix::fs::symlink(lib_path.dir(), &link_dir).unwrap();
+    let linked_path = LibPath::new(link_dir.join("."), "canonical_counter".into());
+    assert_eq!(linked_path.canonical_path().unwrap(), lib_path.path().unwrap().canonicalize().unwrap());
+    unsafe {
+        let mut lib = SharedLib::new_with_flags(linked_path.clone(), LoadFlags::new().with_canonical_path(true)).unwrap();
+        assert_eq!(lib.resolved_path().unwrap(), lib_path.path().unwrap().canonicalize().unwrap());
+        lib.reload().unwrap();
+        assert_eq!(lib.lib_path(), &linked_path);
+    }
+    let err = LibPath::new(link_dir, "missing_lib".into()).canonical_path().err().unwrap();
+    assert!(matches!(err, SharedLibError::NotFound { .. }), "{}", err);
+}
+#[test]
+fn load_shared_lib_relative_to_exe() {
+    let lib_path = LibPath::relative_to_exe("", "calculator").unwrap();
+    assert_eq!(lib_path.dir().canonicalize().unwrap(), deps_dir().canonicalize().unwrap());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+    }
+    let lib_path = LibPath::relative_to_exe("plugins", "foo").unwrap();
+    assert_eq!(lib_path.dir().file_name().unwrap(), "plugins");
+    assert!(lib_path.dir().is_absolute());
+}
+#[test]
+fn find_lib_in_system_dirs() {
+    let search_path = LibSearchPath::system("calculator".into());
+    assert_eq!(search_path.dir_paths, LibSearchPath::system_dirs());
+    assert!(search_path.dir_paths.iter().all(|dir_path| dir_path.is_dir()));
+    #[cfg(target_os = "linux")]
+    assert!(search_path.dir_paths.contains(&PathBuf::from("/usr/lib")));
+    assert!(matches!(search_path.resolve(), Err(SharedLibError::LibNotFound { .. })));
+}
+#[test]
+fn load_shared_lib_from_cargo_target() {
+    let lib_path = LibPath::from_cargo_target("calculator").unwrap();
+    assert_eq!(lib_path.dir(), deps_dir().canonicalize().unwrap().parent().unwrap());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+    }
+    assert_eq!(LibPath::from_cargo_target("my-plugin").unwrap().lib_name, "my_plugin");
+}
+#[test]
+#[cfg(feature = "testing")]
+fn compile_test_lib() {
+    use shared_lib::testing::TestLib;
+    unsafe {
+        let lib = TestLib::rust("#[no_mangle] pub extern \"C\" fn triple(x: i32) -> i32 { x * 3 }").lib_name("rust_test_lib").compile().unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn(i32) -> i32>("triple").unwrap().run(2), 6);
+        let build_dir = lib.extraction().unwrap().path().to_path_buf();
+        drop(lib);
+        assert!(!build_dir.exists());
+        #[cfg(unix)]
+        {
+            let lib = TestLib::c("int scaled(int x) { return x * SCALE; }").lib_name("c_test_lib").arg("-DSCALE=4").compile().unwrap();
+            assert_eq!(lib.get_fn::<extern "C" fn(i32) -> i32>("scaled").unwrap().run(2), 8);
+        }
+        let result = TestLib::rust("not rust").compile();
+        assert!(matches!(result, Err(SharedLibError::CompileFailure { lib_name, .. }) if lib_name == "test_lib"));
+    }
+}
+#[test]
+fn get_raw_fn_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new_with_flags(lib_path, LoadFlags::default().with_call_metrics(true)).unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().into_raw_fn();
+        assert_eq!((0..100).fold(0, |sum, _| add_fn(sum, 2)), 200);
+        assert_eq!(lib.stats().get("add").map(|stats| stats.calls), Some(0));
+    }
+}
+#[test]
+fn get_fn_generic_over_fn_signature() {
+    unsafe fn lookup<T: FnSignature>(lib: &SharedLib, fn_name: &str) -> bool {
+        lib.get_fn::<T>(fn_name).is_ok()
+    }
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        assert!(lookup::<fn(usize, usize) -> usize>(&lib, "add"));
+        assert!(lookup::<unsafe extern "C" fn(i32, i32) -> i32>(&lib, "c_add"));
+        assert!(lookup::<extern "system" fn(i32, i32) -> i32>(&lib, "c_add"));
+        assert!(!lookup::<extern "C" fn(i32, ...) -> i32>(&lib, "non_existent"));
+    }
+}
+#[test]
+#[cfg(all(feature = "guarded", any(all(target_os = "linux", target_env = "gnu"), target_os = "macos")))]
+fn run_guarded_fn_from_shared_lib() {
+    let lib_path = compile_c_fixture("faulty");
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let read_fn = lib.get_fn::<extern "C" fn(*const i32) -> i32>("faulty_read").unwrap();
+        let value = 7;
+        assert_eq!(read_fn.run_guarded(&value).unwrap(), 7);
+        assert!(matches!(read_fn.run_guarded(std::ptr::null()), Err(SharedLibError::Fault { address: 0 })));
+        assert!(matches!(read_fn.run_guarded(0x10 as *const i32), Err(SharedLibError::Fault { address: 0x10 })));
+        assert_eq!(read_fn.run_guarded(&value).unwrap(), 7);
+        let recurse_fn = lib.get_fn::<extern "C" fn(i32) -> i32>("faulty_recurse").unwrap();
+        assert!(matches!(recurse_fn.run_guarded(0), Err(SharedLibError::Fault { .. })));
+        assert_eq!(read_fn.run_guarded(&value).unwrap(), 7);
+    }
+}
+#[test]
+fn get_fn_by_alias_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let (add_fn, name) = lib.get_fn_any::<fn(usize, usize) -> usize>(&["_add", "add_v2", "add"]).unwrap();
+        assert_eq!(name, "add");
+        assert_eq!(add_fn.run(1, 2), 3);
+        let result = lib.get_fn_any::<fn(usize, usize) -> usize>(&["_mul", "mul_v2"]);
+        assert!(matches!(result, Err(SharedLibError::SymbolsNotFound { symbol_names, .. }) if symbol_names == ["_mul", "mul_v2"]));
+    }
+}
+#[test]
+fn get_fn_by_cstr_from_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        assert_eq!(lib.get_fn_cstr::<fn(usize, usize) -> usize>(c"add").unwrap().run(1, 2), 3);
+        let bytes = [b"c_add".as_slice(), &[0]].concat();
+        let name = std::ffi::CStr::from_bytes_with_nul(&bytes).unwrap();
+        assert_eq!(lib.get_fn_cstr::<extern "C" fn(i32, i32) -> i32>(name).unwrap().run(1, 2), 3);
+        assert!(matches!(lib.get_fn_cstr::<fn()>(c"missing"), Err(SharedLibError::SymbolNotFound { symbol_name, .. }) if symbol_name == "missing"));
+    }
+}
+#[test]
+fn load_shared_lib_with_retry() {
+    use std::time::Duration;
+    let dir_path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("retry");
+    std::fs::create_dir_all(&dir_path).unwrap();
+    let lib_path = LibPath::new(dir_path, "retry_calculator".into());
+    let calculator = std::fs::read(LibPath::new(deps_dir(), "calculator".into()).path().unwrap()).unwrap();
+    // A truncated file, as left by a compiler which has not finished writing the library.
+    std::fs::write(lib_path.path().unwrap(), &calculator[..64]).unwrap();
+    unsafe {
+        let err = SharedLib::new(lib_path.clone()).err().unwrap();
+        assert!(matches!(err.kind(), ErrorKind::LoadFailed | ErrorKind::BadFormat), "{}", err);
+        let path = lib_path.path().unwrap();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            std::fs::write(path, calculator).unwrap();
+        });
+        let retry = RetryPolicy::new(40, Duration::from_millis(50)).with_stability_check(true);
+        let lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_retry(retry)).unwrap();
+        writer.join().unwrap();
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+        let missing = LibPath::new(deps_dir(), "non_existent".into());
+        let err = SharedLib::new_with_flags(missing, LoadFlags::new().with_retry(RetryPolicy::new(3, Duration::from_millis(1)))).err().unwrap();
+        assert!(matches!(err, SharedLibError::NotFound { .. }));
+    }
+}
+#[test]
+fn detect_stale_tracked_fn() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn_tracked::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2).unwrap(), 3);
+        assert_eq!(add_fn.generation(), 0);
+        assert!(!add_fn.is_stale());
+        lib.reload().unwrap();
+        assert!(add_fn.is_stale());
+        let err = add_fn.run(1, 2).unwrap_err();
+        assert!(matches!(&err, SharedLibError::StaleSymbol { symbol_name, generation: 0, .. } if symbol_name == "add"));
+        assert_eq!(err.kind(), ErrorKind::StaleSymbol);
+        let c_add_fn = lib.get_fn_tracked::<extern "C" fn(i32, i32) -> i32>("c_add").unwrap();
+        assert_eq!(c_add_fn.generation(), 1);
+        assert_eq!(c_add_fn.run(1, 2).unwrap(), 3);
+        drop(lib);
+        assert!(matches!(c_add_fn.run(1, 2), Err(SharedLibError::StaleSymbol { generation: 1, .. })));
+    }
+}
+#[test]
+fn subscribe_to_lib_events() {
+    let manager = LibManager::new();
+    let events = manager.events();
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let lib = manager.open(lib_path.clone()).unwrap();
+        lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        manager.evict(&lib_path);
+        drop(lib);
+        assert!(manager.open(LibPath::new(deps_dir(), "non_existent".into())).is_err());
+        let events: Vec<_> = events.try_iter().collect();
+        assert_eq!(events[0].path, lib_path.path().unwrap());
+        assert!(matches!(&events[..], [
+            LibEvent { kind: LibEventKind::Loaded, .. },
+            LibEvent { kind: LibEventKind::SymbolResolved { symbol_name }, .. },
+            LibEvent { kind: LibEventKind::Unloaded, .. },
+            LibEvent { kind: LibEventKind::LoadFailed(SharedLibError::NotFound { .. }), .. },
+        ] if symbol_name == "add"));
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        let (sender, events) = std::sync::mpsc::channel();
+        lib.subscribe(sender);
+        lib.reload().unwrap();
+        lib.close().unwrap();
+        let kinds: Vec<_> = events.try_iter().map(|event| event.kind).collect();
+        assert!(matches!(kinds[..], [LibEventKind::Unloaded, LibEventKind::Reloaded { generation: 1 }, LibEventKind::Unloaded]));
+    }
+}
+#[test]
+fn load_lib_graph_in_dependency_order() {
+    let calculator_path = LibPath::new(deps_dir(), "calculator".into());
+    let greeter_path = LibPath::new(deps_dir(), "greeter".into());
+    let graph = LibGraph::new().add(greeter_path.clone(), &["calculator"]).add(calculator_path.clone(), &[]);
+    assert_eq!(graph.load_order().unwrap(), [&calculator_path, &greeter_path]);
+    unsafe {
+        let libs = graph.load().unwrap();
+        let names: Vec<_> = libs.libs().iter().map(|lib| lib.lib_path().lib_name()).collect();
+        assert_eq!(names, ["calculator", "greeter"]);
+        let cyclic = graph.clone().add(calculator_path.clone(), &["greeter"]);
+        assert!(matches!(cyclic.load(), Err(SharedLibError::DependencyCycle(cycle)) if cycle == ["greeter", "calculator", "greeter"]));
+        let unknown = graph.add(calculator_path, &["core"]);
+        let err = unknown.load().err().unwrap();
+        assert!(matches!(&err, SharedLibError::UnknownDependency { lib_name, dependency } if lib_name == "calculator" && dependency == "core"));
+        assert_eq!(err.kind(), ErrorKind::InvalidDependencies);
+    }
+}
+#[test]
+fn attach_context_to_errors() {
+    use std::error::Error;
+    unsafe {
+        let err = SharedLib::builder("non_existent").search_dir(deps_dir()).context("loading audio backend").load().err().unwrap();
+        assert!(err.to_string().starts_with("loading audio backend: "), "{}", err);
+        assert!(err.is_load_error() && err.is_not_found());
+        assert!(matches!(err.without_context(), SharedLibError::NotFound { .. }));
+        assert!(err.source().is_some_and(|source| source.downcast_ref::<Box<SharedLibError>>().is_some()));
+        let lib = SharedLib::new(LibPath::new_no_path("calculator".into())).context("loading calculator").unwrap();
+        let err = lib.get_fn::<fn()>("missing").with_context(|| format!("starting {}", "calculator")).err().unwrap();
+        assert!(matches!(&err, SharedLibError::Context { context, .. } if context == "starting calculator"));
+        assert!(err.is_symbol_error());
+    }
+}
+#[test]
+fn use_shared_lib_in_scope() {
+    let lib_path = LibPath::new(deps_dir(), "calculator".into());
+    unsafe {
+        let sum = SharedLib::with(lib_path.clone(), |lib| lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2)).unwrap();
+        assert_eq!(sum, 3);
+        let err = SharedLib::with(lib_path.clone(), |lib| lib.get_fn_owned::<fn(usize, usize) -> usize>("add").unwrap()).err().unwrap();
+        assert!(matches!(err, SharedLibError::UnloadFailure { .. }), "{}", err);
+        assert!(SharedLib::with(LibPath::new(deps_dir(), "missing_lib".into()), |_| ()).is_err());
+    }
+}
+#[test]
+fn close_shared_lib() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        lib.close().unwrap();
+    }
+}
+#[test]
+fn leak_shared_lib() {
+    type AddFn = fn(usize, usize) -> usize;
+    static ADD_FN: std::sync::OnceLock<SharedLibFn<'static, AddFn>> = std::sync::OnceLock::new();
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap().leak();
+        ADD_FN.set(lib.get_fn("add").unwrap()).ok().unwrap();
+        assert_eq!(ADD_FN.get().unwrap().run(1, 2), 3);
+        assert_eq!(*lib.get_var::<usize>("ANSWER").unwrap(), 42);
+    }
+}
+#[test]
+fn close_shared_lib_with_owned_fn_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn_owned::<fn(usize, usize) -> usize>("add").unwrap();
+        let err = lib.close().err().unwrap();
+        assert!(matches!(err, SharedLibError::UnloadFailure { .. }));
+        assert_eq!(add_fn.run(1, 2), 3);
+    }
+}
+#[test]
+fn reload_shared_lib_with_calls_in_flight() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        let add: fn(usize, usize) -> usize = std::mem::transmute(lib.get_symbol_address("add").unwrap());
+        let gate = lib.call_gate();
+        let finished = Arc::new(AtomicBool::new(false));
+        let (entered_tx, entered_rx) = std::sync::mpsc::channel();
+        let worker = std::thread::spawn({
+            let (gate, finished) = (gate.clone(), finished.clone());
+            move || {
+                let _call = gate.enter().unwrap();
+                entered_tx.send(()).unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                finished.store(true, Ordering::SeqCst);
+                add(1, 2)
+            }
+        });
+        entered_rx.recv().unwrap();
+        assert_eq!(lib.calls_in_flight(), 1);
+        let err = lib.try_reload().err().unwrap();
+        assert!(matches!(err, SharedLibError::UnloadFailure { .. }));
+        assert!(gate.is_valid());
+        lib.reload().unwrap();
+        assert!(finished.load(Ordering::SeqCst));
+        assert_eq!(worker.join().unwrap(), 3);
+        assert_eq!(lib.calls_in_flight(), 0);
+        assert!(gate.enter().is_none());
+        let gate = lib.call_gate();
+        assert!(gate.enter().is_some());
+        lib.try_reload().unwrap();
+        lib.close().unwrap();
+        assert!(!gate.is_valid());
+    }
+}
+#[test]
+#[cfg(unix)]
+fn run_fn_with_timeout() {
+    use std::time::Duration;
+    let lib_path = compile_c_fixture("sleep");
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let sleep_fn = lib.get_fn_owned::<extern "C" fn(i32) -> i32>("sleep_ms").unwrap();
+        assert_eq!(sleep_fn.run_with_timeout(Duration::from_secs(10), 1).unwrap(), 1);
+        let err = sleep_fn.run_with_timeout(Duration::from_millis(10), 500).err().unwrap();
+        assert!(matches!(err, SharedLibError::Timeout(timeout) if timeout == Duration::from_millis(10)));
+        drop(sleep_fn);
+        // The timed out call keeps the library loaded until it returns.
+        let err = lib.close().err().unwrap();
+        assert!(matches!(err, SharedLibError::UnloadFailure { .. }));
+    }
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn_owned::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run_with_timeout(Duration::from_secs(10), 1, 2).unwrap(), 3);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn load_shared_lib_with_lifecycle() {
+    let shutdown_file = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("lifecycle_shutdown.txt");
+    let _ = std::fs::remove_file(&shutdown_file);
+    let shutdown_file_arg = format!("-DSHUTDOWN_FILE=\"{}\"", shutdown_file.display());
+    let shutdowns = || std::fs::read_to_string(&shutdown_file).unwrap_or_default().lines().count();
+    let lib_path = compile_c_fixture_as("lifecycle", "lifecycle", &[&shutdown_file_arg]);
+    let flags = LoadFlags::new().with_lifecycle(true);
+    unsafe {
+        let mut lib = SharedLib::new_with_flags(lib_path.clone(), flags).unwrap();
+        let is_initialized = |lib: &SharedLib| lib.get_fn::<extern "C" fn() -> i32>("is_initialized").unwrap().run();
+        assert_eq!(is_initialized(&lib), 1);
+        lib.reload().unwrap();
+        assert_eq!(shutdowns(), 1);
+        assert_eq!(is_initialized(&lib), 1);
+        lib.close().unwrap();
+        assert_eq!(shutdowns(), 2);
+        drop(SharedLib::new_with_flags(lib_path.clone(), flags).unwrap());
+        assert_eq!(shutdowns(), 3);
+        let lib = SharedLib::new(lib_path).unwrap();
+        assert_eq!(is_initialized(&lib), 0);
+        lib.close().unwrap();
+        assert_eq!(shutdowns(), 3);
+
+        let lib_path = compile_c_fixture_as("lifecycle", "lifecycle_fail", &[&shutdown_file_arg, "-DINIT_RESULT=3"]);
+        let err = SharedLib::new_with_flags(lib_path, flags).err().unwrap();
+        assert!(matches!(err, SharedLibError::InitFailure { code: 3, .. }), "{}", err);
+        assert_eq!(shutdowns(), 3);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn reload_shared_lib() {
+    let lib_path = compile_c_fixture_as("reload", "reload", &["-DRELOAD_VALUE=1"]);
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 1);
+        compile_c_fixture_as("reload", "reload", &["-DRELOAD_VALUE=2"]);
+        lib.reload().unwrap();
+        assert_eq!(lib.generation(), 1);
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 2);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn reload_shadow_copied_shared_lib() {
+    let copies = || {
+        let prefix = format!("{}shadow_reload-{}-", std::env::consts::DLL_PREFIX, std::process::id());
+        let entries = std::fs::read_dir(std::env::temp_dir()).unwrap();
+        let names = entries.map(|entry| entry.unwrap().file_name().to_string_lossy().to_string());
+        names.filter(|name| name.starts_with(&prefix)).collect::<Vec<_>>()
+    };
+    let lib_path = compile_c_fixture_as("reload", "shadow_reload", &["-DRELOAD_VALUE=1"]);
+    unsafe {
+        let mut lib = SharedLib::new_with_flags(lib_path.clone(), LoadFlags::new().with_shadow_copy(true)).unwrap();
+        assert_eq!(lib.lib_path(), &lib_path);
+        let first_copies = copies();
+        assert_eq!(first_copies.len(), 1);
+        std::fs::remove_file(lib_path.path().unwrap()).unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 1);
+        compile_c_fixture_as("reload", "shadow_reload", &["-DRELOAD_VALUE=2"]);
+        lib.reload().unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 2);
+        // Replace the file right away, which is reloaded from a copy with a new name.
+        compile_c_fixture_as("reload", "shadow_reload", &["-DRELOAD_VALUE=3"]);
+        lib.reload().unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 3);
+        let last_copies = copies();
+        assert_eq!(last_copies.len(), 1);
+        assert_ne!(first_copies, last_copies);
+        drop(lib);
+    }
+    assert!(copies().is_empty());
+}
+#[test]
+#[cfg(unix)]
+fn reload_shared_lib_with_state() {
+    let lib_path = compile_c_fixture_as("state", "state", &["-DSTATE_STEP=1"]);
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        let next_count = |lib: &SharedLib| lib.get_fn::<extern "C" fn() -> i32>("next_count").unwrap().run();
+        assert_eq!((next_count(&lib), next_count(&lib)), (1, 2));
+        compile_c_fixture_as("state", "state", &["-DSTATE_STEP=10"]);
+        lib.reload_with_state().unwrap();
+        assert_eq!(next_count(&lib), 12);
+    }
+    let lib_path = compile_c_fixture_as("reload", "stateless_reload", &["-DRELOAD_VALUE=1"]);
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        lib.reload_with_state().unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 1);
+    }
+}
+#[test]
+fn reload_shared_lib_with_owned_fn_fail() {
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let mut lib = SharedLib::new(lib_path).unwrap();
+        let add_fn = lib.get_fn_owned::<fn(usize, usize) -> usize>("add").unwrap();
+        let err = lib.reload().err().unwrap();
+        assert!(matches!(err, SharedLibError::UnloadFailure { .. }));
+        assert_eq!(lib.generation(), 0);
+        assert_eq!(add_fn.run(1, 2), 3);
+        assert_eq!(lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+    }
+}
+#[test]
+#[cfg(all(unix, feature = "watch"))]
+fn reload_watched_shared_lib() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+    let lib_path = compile_c_fixture_as("reload", "watched_reload", &["-DRELOAD_VALUE=1"]);
+    let (sender, receiver) = mpsc::channel();
+    unsafe {
+        let watched = WatchedSharedLib::new(lib_path, move |result| {
+            let value = result.map(|lib| lib.get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run());
+            sender.send(value).unwrap();
+        })
+        .unwrap();
+        assert_eq!(watched.lib().get_fn::<extern "C" fn() -> i32>("reload_value").unwrap().run(), 1);
+        compile_c_fixture_as("reload", "watched_reload", &["-DRELOAD_VALUE=2"]);
+        let value = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(value.unwrap(), 2);
+        assert_eq!(watched.lib().generation(), 1);
+    }
+}
+#[test]
+#[cfg(target_os = "linux")]
+fn create_shared_lib_with_flags() {
+    let lib_path = compile_c_fixture("lazy");
+    unsafe {
+        SharedLib::new_with_flags(lib_path.clone(), LoadFlags::new()).unwrap();
+        let err = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_now(true)).err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { msg, .. } if msg.contains("missing_function")));
+    }
+}
+#[test]
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn create_shared_lib_in_isolated_namespace() {
+    let lib_path = compile_c_fixture("counter");
+    unsafe {
+        let lib = SharedLib::new(lib_path.clone()).unwrap();
+        let same_lib = SharedLib::new(lib_path.clone()).unwrap();
+        let isolated_lib = SharedLib::new_with_flags(lib_path, LoadFlags::new().with_isolated_namespace(true)).unwrap();
+        let next_count = |lib: &SharedLib| lib.get_fn::<extern "C" fn() -> i32>("next_count").unwrap().run();
+        assert_eq!(next_count(&lib), 1);
+        assert_eq!(next_count(&same_lib), 2);
+        assert_eq!(next_count(&isolated_lib), 1);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn create_lib_pool() {
+    let lib_path = compile_c_fixture_as("counter", "pool_counter", &[]);
+    let next_count = |lib: &SharedLib| unsafe { lib.get_fn::<extern "C" fn() -> i32>("next_count").unwrap().run() };
+    let pool = unsafe { LibPool::new(lib_path, 2).unwrap() };
+    assert_eq!(pool.len(), 2);
+    {
+        let (first, second) = (pool.get(), pool.get());
+        assert_eq!((next_count(&first), next_count(&first), next_count(&second)), (1, 2, 1));
+    }
+    assert_eq!(next_count(&pool.get_for_thread()), 3);
+    assert_eq!(next_count(&pool.get_for_thread()), 4);
+    // Handing out an instance does not shift the assignments.
+    drop(pool.get());
+    std::thread::scope(|scope| {
+        assert_eq!(scope.spawn(|| next_count(&pool.get_for_thread())).join().unwrap(), 2);
+    });
+}
+#[test]
+#[cfg(target_os = "linux")]
+fn create_shared_lib_with_sibling_dependencies() {
+    let dir = env!("CARGO_TARGET_TMPDIR");
+    compile_c_fixture_as("sibling_dep", "sibling_dep", &["-Wl,-soname,libsibling_dep.so"]);
+    let lib_path = compile_c_fixture_as("sibling", "sibling", &["-L", dir, "-lsibling_dep"]);
+    unsafe {
+        let err = SharedLib::new(lib_path.clone()).err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { msg, .. } if msg.contains("libsibling_dep.so")));
+        let lib = SharedLib::new_with_sibling_dependencies(lib_path).unwrap();
+        let call_sibling = lib.get_fn::<extern "C" fn() -> i32>("call_sibling").unwrap();
+        assert_eq!(call_sibling.run(), 42);
+    }
+}
+#[test]
+#[cfg(target_os = "linux")]
+fn load_report_with_missing_dependency() {
+    let dir = env!("CARGO_TARGET_TMPDIR");
+    let dep_path = compile_c_fixture_as("sibling_dep", "report_dep", &["-Wl,-soname,libreport_dep.so"]);
+    let lib_path = compile_c_fixture_as("sibling", "report_lib", &["-L", dir, "-lreport_dep"]);
+    std::fs::remove_file(dep_path.path().unwrap()).unwrap();
+    unsafe {
+        let builder = SharedLib::builder("report_lib").search_dir(deps_dir()).search_dir(PathBuf::from(dir));
+        let err = builder.clone().load().err().unwrap();
+        assert!(matches!(err, SharedLibError::NotInSearchPath { .. }));
+        let err = builder.load_report(true).load().err().unwrap();
+        assert!(err.is_load_error());
+        let SharedLibError::LoadReport(report) = &err else {
+            panic!("Expected a load report, got {}", err);
+        };
+        assert_eq!(report.searched_dirs, [deps_dir(), PathBuf::from(dir)]);
+        let tried: Vec<_> = report.attempts.iter().map(|attempt| attempt.path.clone()).collect();
+        assert_eq!(tried, [LibPath::new(deps_dir(), "report_lib".into()).path().unwrap(), lib_path.path().unwrap()]);
+        assert!(matches!(report.attempts[0].error, SharedLibError::NotFound { .. }));
+        assert_eq!(report.missing_dependencies, [MissingDependency { name: "libreport_dep.so".into(), required_by: lib_path.path().unwrap() }]);
+        assert!(err.to_string().contains("Missing dependencies:\n  libreport_dep.so"), "{}", err);
+    }
+}
+#[test]
+#[cfg(unix)]
+fn create_shared_lib_with_conventions() {
+    let lib_path = compile_c_fixture_as("counter", "unprefixed_counter", &[]);
+    let unprefixed = lib_path.clone().with_convention(FilenameConvention::no_prefix());
+    std::fs::rename(lib_path.path().unwrap(), unprefixed.path().unwrap()).unwrap();
+    unsafe {
+        let lib = SharedLib::new_with_conventions(lib_path.clone(), &FilenameConvention::fallbacks()).unwrap();
+        assert_eq!(lib.lib_path().convention(), &FilenameConvention::no_prefix());
+        assert_eq!(lib.get_fn::<extern "C" fn() -> i32>("next_count").unwrap().run(), 1);
+        let err = SharedLib::new_with_conventions(lib_path, &[FilenameConvention::platform()]).err().unwrap();
+        assert!(matches!(err, SharedLibError::NoMatchingFilename { attempts, .. } if attempts.len() == 1));
+    }
+}
+#[test]
+fn create_shared_lib_from_bytes() {
+    let bytes = std::fs::read(LibPath::new(deps_dir(), "calculator".into()).path().unwrap()).unwrap();
+    unsafe {
+        let lib = SharedLib::from_bytes("calculator/copy", &bytes).unwrap();
+        let path = lib.lib_path().path().unwrap();
+        assert_eq!(path.parent(), Some(std::env::temp_dir().as_path()));
+        assert!(path.is_file());
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        assert_eq!(lib.extraction().unwrap().path(), path);
+        drop(lib);
+        assert!(!path.exists());
+        let mut lib = SharedLib::from_bytes("calculator_persisted", &bytes).unwrap();
+        let extraction = lib.extraction_mut().unwrap();
+        assert!(!extraction.is_persisted());
+        extraction.set_persist(true);
+        let path = extraction.path().to_path_buf();
+        drop(lib);
+        assert!(path.is_file());
+        std::fs::remove_file(path).unwrap();
+    }
+}
+#[test]
+fn create_shared_lib_from_bytes_fail() {
+    unsafe {
+        let err = SharedLib::from_bytes("not_a_lib", b"not a library").err().unwrap();
+        assert!(matches!(err, SharedLibError::LoadFailure { .. }));
+    }
+}
+#[test]
+fn load_embedded_lib() {
+    let bytes = std::fs::read(LibPath::new(deps_dir(), "calculator".into()).path().unwrap()).unwrap();
+    let cache_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("embedded");
+    let embedded = EmbeddedLib::new("calculator", "1.0.0", bytes.leak()).with_cache_dir(cache_dir.clone());
+    assert_eq!(embedded.extract_dir(), cache_dir.join("calculator").join("1.0.0"));
+    let lib_path = embedded.extract().unwrap();
+    // A modified file is extracted again.
+    std::fs::write(lib_path.path().unwrap(), b"modified").unwrap();
+    unsafe {
+        let lib = embedded.load().unwrap();
+        let add_fn = lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert_eq!(add_fn.run(1, 2), 3);
+        assert!(lib.extraction().unwrap().is_persisted());
+        drop(lib);
+        assert!(lib_path.path().unwrap().is_file());
+        drop(embedded.with_persist(false).load().unwrap());
+        assert!(!lib_path.path().unwrap().exists());
+    }
+}
+#[test]
+#[cfg(feature = "derive")]
+fn load_api_from_shared_lib() {
+    #[derive(SharedLibApi)]
+    struct CalculatorApi {
+        add: fn(usize, usize) -> usize,
+        #[symbol = "c_add"]
+        add_c: extern "C" fn(i32, i32) -> i32,
+    }
+    #[derive(SharedLibApi)]
+    #[allow(dead_code)]
+    struct MissingApi {
+        add: fn(usize, usize) -> usize,
+        missing_fn: fn(),
+        #[symbol = "other_missing_fn"]
+        other_fn: fn(),
+    }
+    let lib_path = LibPath::new_no_path("calculator".into());
+    unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let api = CalculatorApi::load(&lib).unwrap();
+        assert_eq!((api.add)(1, 2), 3);
+        assert_eq!((api.add_c)(1, 2), 3);
+        let err = MissingApi::load(&lib).err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolsNotFound { symbol_names, .. } if symbol_names == ["missing_fn", "other_missing_fn"]));
+    }
+}
+#[test]
+fn load_plugin_into_registry() {
+    let mut registry = PluginRegistry::new();
+    unsafe {
+        let plugin = registry.load(LibPath::new_no_path("greeter".into())).unwrap();
+        assert_eq!(plugin.name(), "greeter");
+        let err = registry.load(LibPath::new_no_path("greeter".into())).err().unwrap();
+        assert!(matches!(err, SharedLibError::PluginAlreadyLoaded(name) if name == "greeter"));
+    }
+    let plugins: Vec<_> = registry.plugins().map(|plugin| (plugin.name(), plugin.version())).collect();
+    assert_eq!(plugins, [("greeter", "0.1.0")]);
+    assert!(registry.unload("greeter"));
+    assert!(!registry.unload("greeter"));
+    assert!(registry.get("greeter").is_none());
+}
+#[test]
+fn bind_host_api_to_plugins() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    #[repr(C)]
+    struct HostApi {
+        greeted: extern "C" fn(u32),
+    }
+    static GREETED: AtomicU32 = AtomicU32::new(0);
+    extern "C" fn greeted(times: u32) {
+        GREETED.fetch_add(times, Ordering::SeqCst);
+    }
+
+    let mut registry = PluginRegistry::new().with_host_api(HostApi { greeted });
+    assert!(registry.host_api::<HostApi>().is_some());
+    assert!(registry.host_api::<u32>().is_none());
+    unsafe {
+        registry.load(LibPath::new_no_path("greeter".into())).unwrap();
+        let lib = SharedLib::new(LibPath::new_no_path("greeter".into())).unwrap();
+        let greet_host = lib.get_fn::<unsafe extern "C" fn(u32) -> bool>("greet_host").unwrap();
+        assert!(greet_host.run(3));
+    }
+    assert_eq!(GREETED.load(Ordering::SeqCst), 3);
+}
+#[test]
+fn create_plugin_instance() {
+    let lib_path = LibPath::new_no_path("greeter".into());
+    let instance = unsafe {
+        let lib = SharedLib::new(lib_path).unwrap();
+        let err = lib.get_instance::<dyn Plugin>("greeter_create", "missing_destroy").err().unwrap();
+        assert!(matches!(err, SharedLibError::SymbolNotFound { symbol_name, .. } if symbol_name == "missing_destroy"));
+        let err = lib.get_instance::<dyn Plugin>("greeter_create_null", "greeter_destroy").err().unwrap();
+        assert!(matches!(&err, SharedLibError::NullInstance { symbol_name, .. } if symbol_name == "greeter_create_null"), "{}", err);
+        assert!(!err.is_symbol_error() && !err.is_not_found());
+        lib.get_instance::<dyn Plugin>("greeter_create", "greeter_destroy").unwrap()
+    };
+    assert_eq!(instance.name(), "greeter");
+    drop(instance);
+}
+#[test]
+#[cfg(feature = "manifest")]
+fn discover_plugins_from_manifests() {
+    let plugins_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("discover_plugins");
+    let plugin_dir = plugins_dir.join("greeter");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    let filename = LibPath::new_no_path("greeter".into()).filename().unwrap();
+    std::fs::copy(deps_dir().join(&filename), plugin_dir.join(&filename)).unwrap();
+    std::fs::write(plugin_dir.join(MANIFEST_FILE_NAME), "name = \"greeter\"\nversion = \"0.1.0\"\n").unwrap();
+    let mut registry = PluginRegistry::new();
+    unsafe {
+        assert_eq!(registry.discover(&plugins_dir).unwrap(), ["greeter"]);
+    }
+    assert_eq!(registry.get("greeter").unwrap().version(), "0.1.0");
+}
+#[test]
+#[cfg(feature = "manifest")]
+fn discover_plugins_with_missing_dependency_fail() {
+    let plugins_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("discover_missing_dependency");
+    std::fs::create_dir_all(plugins_dir.join("greeter")).unwrap();
+    let manifest = "name = \"greeter\"\nversion = \"0.1.0\"\ndependencies = [\"logger\"]\n";
+    std::fs::write(plugins_dir.join("greeter").join(MANIFEST_FILE_NAME), manifest).unwrap();
+    let mut registry = PluginRegistry::new();
+    unsafe {
+        let err = registry.discover(&plugins_dir).unwrap_err();
+        assert!(matches!(err, SharedLibError::InvalidManifest { msg, .. } if msg.contains("'logger'")));
+    }
+}
+#[test]
+#[cfg(feature = "manifest")]
+fn discover_plugins_compatible_with_host() {
+    let plugins_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("discover_compatible");
+    let plugin_dir = plugins_dir.join("greeter");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    let filename = LibPath::new_no_path("greeter".into()).filename().unwrap();
+    std::fs::copy(deps_dir().join(&filename), plugin_dir.join(&filename)).unwrap();
+    std::fs::write(plugin_dir.join(MANIFEST_FILE_NAME), "name = \"greeter\"\nversion = \"0.1.0\"\nhost_api = \"1.2\"\n").unwrap();
+    std::fs::create_dir_all(plugins_dir.join("extension")).unwrap();
+    let manifest = "name = \"extension\"\nversion = \"0.1.0\"\ndependencies = [\"greeter\"]\n";
+    std::fs::write(plugins_dir.join("extension").join(MANIFEST_FILE_NAME), manifest).unwrap();
+    unsafe {
+        let mut registry = PluginRegistry::new().with_host_api_version("2.0.0");
+        let err = registry.discover(&plugins_dir).unwrap_err();
+        assert!(matches!(&err, SharedLibError::IncompatibleVersion { plugin, required, host } if plugin == "greeter" && required == "1.2" && host == "2.0.0"));
+        assert_eq!(err.kind(), ErrorKind::AbiMismatch);
+        let mut registry = PluginRegistry::new().with_host_api_version("2.0.0").skip_incompatible(true);
+        assert!(registry.discover(&plugins_dir).unwrap().is_empty());
+        std::fs::remove_dir_all(plugins_dir.join("extension")).unwrap();
+        let mut registry = PluginRegistry::new().with_host_api_version("1.5.0");
+        assert_eq!(registry.discover(&plugins_dir).unwrap(), ["greeter"]);
+    }
+}
+#[test]
+#[cfg(feature = "config")]
+fn load_libs_from_config() {
+    let config_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    let config_path = config_dir.join("libraries.toml");
+    let config = format!(
+        "[[libraries]]\nname = \"calculator\"\nsearch_dirs = [{:?}]\nrequired_symbols = [\"add\"]\n\n\
+         [[libraries]]\nname = \"audio\"\nsearch_dirs = [\"plugins\"]\noptional = true\nflags = {{ now = true }}\n",
+        deps_dir()
+    );
+    std::fs::write(&config_path, config).unwrap();
+    let entries = LibConfig::from_file(&config_path).unwrap().libraries;
+    assert_eq!(entries[1].search_dirs, [config_dir.join("plugins")]);
+    assert_eq!(entries[1].flags, LoadFlags::new().with_now(true));
+    unsafe {
+        let loaded = load_from_config(&config_path).unwrap();
+        assert_eq!(loaded.libs.len(), 1);
+        assert_eq!(loaded.libs["calculator"].get_fn::<fn(usize, usize) -> usize>("add").unwrap().run(1, 2), 3);
+        assert_eq!(loaded.report.iter().map(|outcome| outcome.name.as_str()).collect::<Vec<_>>(), ["calculator", "audio"]);
+        assert!(loaded.report[0].error.is_none());
+        assert!(loaded.report[1].error.as_ref().is_some_and(SharedLibError::is_not_found));
+        std::fs::write(&config_path, "[[libraries]]\nname = \"audio\"\nsearch_dirs = [\"plugins\"]\n").unwrap();
+        let err = load_from_config(&config_path).err().unwrap();
+        assert!(err.to_string().starts_with("Loading required library 'audio': "), "{}", err);
+        std::fs::write(&config_path, "[[libraries]]\nname = \"audio\"\n[[libraries]]\nname = \"audio\"\n").unwrap();
+        assert!(matches!(load_from_config(&config_path), Err(SharedLibError::InvalidConfig { msg, .. }) if msg.contains("more than once")));
+    }
+}
+#[test]
+#[cfg(feature = "serde")]
+fn deserialize_lib_paths() {
+    use std::time::Duration;
+    let config = r#"
+        flags = { now = true, retry = { attempts = 3 } }
+        [[plugins]]
+        dir_path = "plugins"
+        lib_name = "calculator"
+        [[plugins]]
+        dir_path = "mods"
+        lib_name = "greeter"
+        version = "1"
+        convention = { prefix = "", suffix = ".mod" }
+    "#;
+    let config: std::collections::HashMap<String, toml::Value> = toml::from_str(config).unwrap();
+    let plugins: Vec<LibPath> = config["plugins"].clone().try_into().unwrap();
+    assert_eq!(plugins[0], LibPath::new(PathBuf::from("plugins"), "calculator".into()));
+    let greeter = LibPath::new(PathBuf::from("mods"), "greeter".into()).with_version("1".into()).with_prefix("").with_extension("mod");
+    assert_eq!(plugins[1], greeter);
+    let flags: LoadFlags = config["flags"].clone().try_into().unwrap();
+    assert_eq!(flags, LoadFlags::new().with_now(true).with_retry(RetryPolicy::new(3, Duration::ZERO)));
+    assert_eq!(toml::from_str::<LibPath>(&toml::to_string(&greeter).unwrap()).unwrap(), greeter);
+}
+#[test]
+#[cfg(feature = "tracing")]
+fn trace_shared_lib_events() {
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Subscriber recording the message of every event of the `shared_lib` target.
+    #[derive(Default)]
+    struct Messages(Mutex<Vec<String>>);
+    impl Visit for &Messages {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                self.0.lock().unwrap().push(format!("{:?}", value));
+            }
+        }
+    }
+    impl Subscriber for &'static Messages {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "shared_lib"
+        }
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            event.record(&mut &**self);
+        }
+        fn enter(&self, _span: &Id) {}
+        fn exit(&self, _span: &Id) {}
+    }
+
+    let messages: &'static Messages = Box::leak(Box::default());
+    tracing::subscriber::with_default(messages, || unsafe {
+        let lib_path = LibPath::new(deps_dir(), "calculator".into());
+        let lib = SharedLib::new(lib_path).unwrap();
+        lib.get_fn::<fn(usize, usize) -> usize>("add").unwrap();
+        assert!(lib.get_fn::<fn(usize, usize) -> usize>("missing_fn").is_err());
+        lib.close().unwrap();
+    });
+    let messages = messages.0.lock().unwrap();
+    assert_eq!(*messages, ["Loaded library", "Resolved symbol", "Failed to resolve symbol", "Unloaded library"]);
+}
+#[test]
+#[cfg(all(feature = "sandbox", target_pointer_width = "64"))]
+fn sandbox_child() {
+    // Runs the sandbox when this test is selected by the child of `SandboxedLib`, and does nothing otherwise.
+    unsafe { SandboxedLib::serve_if_child() };
+}
+#[test]
+#[cfg(all(unix, feature = "sandbox", target_pointer_width = "64"))]
+fn call_fn_in_sandbox() {
+    let sandbox_command = || {
+        let mut command = std::process::Command::new(std::env::current_exe().unwrap());
+        command.args(["sandbox_child", "--exact", "--quiet"]);
+        command
+    };
+    let lib = SandboxedLib::spawn(compile_c_fixture("sandbox"), sandbox_command()).unwrap();
+    let mix_fn = lib.get_fn::<extern "C" fn(i32, f32, f64, i64) -> f64>("mix").unwrap();
+    assert_eq!(mix_fn.run(-1, 0.5, 0.25, 1 << 40).unwrap(), -1.0 + 0.5 + 0.25 + (1u64 << 40) as f64);
+    assert!(lib.get_fn::<extern "C" fn() -> i32>("missing").is_err());
+    let crash_fn = lib.get_fn::<extern "C" fn() -> i32>("crash").unwrap();
+    let err = crash_fn.run().err().unwrap();
+    assert!(matches!(&err, SharedLibError::SandboxFailure { msg, .. } if msg.contains("exited")), "{}", err);
+    assert!(!lib.is_alive());
+    assert!(lib.get_fn::<extern "C" fn() -> i32>("crash").is_err());
+    lib.restart().unwrap();
+    assert!(lib.is_alive());
+    assert_eq!(mix_fn.run(1, 1.0, 1.0, 1).unwrap(), 4.0);
+
+    let lib = SandboxedLib::spawn(LibPath::new(deps_dir(), "calculator".into()), sandbox_command()).unwrap();
+    let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("c_add").unwrap();
+    assert_eq!(add_fn.run(-2, 5).unwrap(), 3);
+    let err = SandboxedLib::spawn(LibPath::new(deps_dir(), "missing_lib".into()), sandbox_command()).err().unwrap();
+    assert!(matches!(err, SharedLibError::SandboxFailure { .. }), "{}", err);
+
+    let lib = SandboxedLib::spawn(compile_c_fixture("sandbox"), sandbox_command()).unwrap();
+    let lib = lib.with_call_timeout(Some(std::time::Duration::from_millis(200))).unwrap();
+    let hang_fn = lib.get_fn::<extern "C" fn() -> i32>("hang").unwrap();
+    let err = hang_fn.run().err().unwrap();
+    assert!(matches!(&err, SharedLibError::SandboxFailure { msg, .. } if msg.contains("did not respond")), "{}", err);
+    assert!(!lib.is_alive());
+    lib.restart().unwrap();
+    let mix_fn = lib.get_fn::<extern "C" fn(i32, f32, f64, i64) -> f64>("mix").unwrap();
+    assert_eq!(mix_fn.run(1, 1.0, 1.0, 1).unwrap(), 4.0);
+}
+#[test]
+#[cfg(feature = "wasm")]
+fn call_fn_from_wasm_module() {
+    fn leb(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            match value {
+                0 => return [bytes, vec![byte]].concat(),
+                _ => bytes.push(byte | 0x80),
+            }
+        }
+    }
+    let items = |items: Vec<Vec<u8>>| [leb(items.len()), items.concat()].concat();
+    let section = |id: u8, contents: Vec<u8>| [vec![id], leb(contents.len()), contents].concat();
+    let name = |name: &str| [leb(name.len()), name.as_bytes().to_vec()].concat();
+    let module = |sections: Vec<Vec<u8>>| [b"\0asm\x01\0\0\0".to_vec(), sections.concat()].concat();
+    // Types: (i32, i32) -> i32, (i64) -> i64, (i32) -> i32, () -> i32, (f64, f64) -> f64.
+    let types = items(vec![vec![0x60, 2, 0x7f, 0x7f, 1, 0x7f], vec![0x60, 1, 0x7e, 1, 0x7e], vec![0x60, 1, 0x7f, 1, 0x7f], vec![0x60, 0, 1, 0x7f], vec![0x60, 2, 0x7c, 0x7c, 1, 0x7c]]);
+    let bodies: Vec<Vec<u8>> = vec![
+        // add: a + b
+        vec![0, 0x20, 0, 0x20, 1, 0x6a, 0x0b],
+        // factorial: a loop multiplying the accumulator by n until n is 0
+        vec![1, 1, 0x7e, 0x42, 1, 0x21, 1, 0x02, 0x40, 0x03, 0x40, 0x20, 0, 0x50, 0x0d, 1, 0x20, 1, 0x20, 0, 0x7e, 0x21, 1, 0x20, 0, 0x42, 1, 0x7d, 0x21, 0, 0x0c, 0, 0x0b, 0x0b, 0x20, 1, 0x0b],
+        // fib: n if n < 2, else fib(n - 1) + fib(n - 2)
+        vec![0, 0x20, 0, 0x41, 2, 0x48, 0x04, 0x7f, 0x20, 0, 0x05, 0x20, 0, 0x41, 1, 0x6b, 0x10, 2, 0x20, 0, 0x41, 2, 0x6b, 0x10, 2, 0x6a, 0x0b, 0x0b],
+        // load: the i32 at the address
+        vec![0, 0x20, 0, 0x28, 2, 0, 0x0b],
+        // div: a / b
+        vec![0, 0x20, 0, 0x20, 1, 0x6d, 0x0b],
+        // next: increment the global and return it
+        vec![0, 0x23, 0, 0x41, 1, 0x6a, 0x24, 0, 0x23, 0, 0x0b],
+        // hypot: sqrt(a * a + b * b)
+        vec![0, 0x20, 0, 0x20, 0, 0xa2, 0x20, 1, 0x20, 1, 0xa2, 0xa0, 0x9f, 0x0b],
+        // dispatch: call the function at the index of the table with x
+        vec![0, 0x20, 1, 0x20, 0, 0x11, 2, 0, 0x0b],
+        // double: x * 2
+        vec![0, 0x20, 0, 0x41, 2, 0x6c, 0x0b],
+    ];
+    let exports = ["add", "factorial", "fib", "load", "div", "next", "hypot", "dispatch"];
+    let plugin = module(vec![
+        section(1, types),
+        section(3, items(vec![vec![0], vec![1], vec![2], vec![2], vec![0], vec![3], vec![4], vec![0], vec![2]])),
+        section(4, vec![1, 0x70, 0, 2]),
+        section(5, vec![1, 0, 1]),
+        section(6, vec![1, 0x7f, 1, 0x41, 0, 0x0b]),
+        section(7, items(exports.iter().enumerate().map(|(i, export)| [name(export), vec![0, i as u8]].concat()).collect())),
+        section(9, vec![1, 0, 0x41, 0, 0x0b, 2, 8, 2]),
+        section(10, items(bodies.into_iter().map(|body| [leb(body.len()), body].concat()).collect())),
+        section(11, vec![1, 0, 0x41, 16, 0x0b, 4, 42, 0, 0, 0]),
+    ]);
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("wasm");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("wasm_plugin.wasm"), &plugin).unwrap();
+    unsafe {
+        // The module is loaded instead of the native library, which does not exist.
+        let mut lib = SharedLib::new(LibPath::new(dir.clone(), "wasm_plugin".into())).unwrap();
+        assert_eq!(lib.lib_path().filename().unwrap(), "wasm_plugin.wasm");
+        let add_fn = lib.get_fn::<extern "C" fn(i32, i32) -> i32>("add").unwrap();
+        assert_eq!(add_fn.run(2, -5), -3);
+        assert_eq!(add_fn.run(i32::MAX, 1), i32::MIN);
+        assert_eq!(lib.get_fn::<fn(u64) -> u64>("factorial").unwrap().run(20), 2432902008176640000);
+        assert_eq!(lib.get_fn::<fn(i32) -> i32>("fib").unwrap().run(20), 6765);
+        assert_eq!(lib.get_fn::<fn(f64, f64) -> f64>("hypot").unwrap().run(3.0, 4.0), 5.0);
+        let load_fn = lib.get_fn::<fn(u32) -> i32>("load").unwrap();
+        assert_eq!(load_fn.run(16), 42);
+        let err = load_fn.run_catching(65536).unwrap_err();
+        assert!(matches!(&err, SharedLibError::WasmTrap { symbol_name, msg, .. } if symbol_name == "load" && msg.contains("Out of bounds memory")), "{}", err);
+        assert_eq!(err.kind(), ErrorKind::CallFailed);
+        let div_fn = lib.get_fn::<fn(i32, i32) -> i32>("div").unwrap();
+        assert_eq!(div_fn.run(-7, 2), -3);
+        assert!(matches!(div_fn.run_catching(1, 0), Err(SharedLibError::WasmTrap { msg, .. }) if msg.contains("division by zero")));
+        assert!(matches!(div_fn.run_catching(i32::MIN, -1), Err(SharedLibError::WasmTrap { msg, .. }) if msg.contains("overflow")));
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| div_fn.run(1, 0))).is_err());
+        let dispatch_fn = lib.get_fn::<fn(i32, i32) -> i32>("dispatch").unwrap();
+        assert_eq!(dispatch_fn.run(0, 21), 42);
+        assert_eq!(dispatch_fn.run(1, 10), 55);
+        assert!(matches!(dispatch_fn.run_catching(2, 0), Err(SharedLibError::WasmTrap { msg, .. }) if msg.contains("Out of bounds table")));
+        let next_fn = lib.get_fn::<fn() -> i32>("next").unwrap();
+        assert_eq!((next_fn.run(), next_fn.run()), (1, 2));
+        assert!(matches!(lib.get_fn::<fn(i64, i64) -> i64>("add"), Err(SharedLibError::SignatureMismatch { symbol_name, .. }) if symbol_name == "add"));
+        assert!(matches!(lib.get_fn::<fn(*const u8) -> i32>("load"), Err(SharedLibError::SignatureMismatch { .. })));
+        assert!(matches!(lib.get_fn::<fn()>("missing"), Err(SharedLibError::SymbolNotFound { .. })));
+        assert!(matches!(lib.get_var::<i32>("next"), Err(SharedLibError::LoadFailure { msg, .. }) if msg.contains("WebAssembly")));
+        // Reloading instantiates the module again, with the initial value of the global.
+        lib.reload().unwrap();
+        assert_eq!(lib.get_fn::<fn() -> i32>("next").unwrap().run(), 1);
+        lib.close().unwrap();
+
+        // A module is also loaded from the filename of a native library.
+        let native_path = LibPath::new(dir.clone(), "wasm_native".into());
+        std::fs::write(native_path.path().unwrap(), &plugin).unwrap();
+        let lib = SharedLib::new(native_path).unwrap();
+        assert_eq!(lib.get_fn::<extern "C" fn(i32, i32) -> i32>("add").unwrap().run(1, 2), 3);
+
+        let import = module(vec![section(1, items(vec![vec![0x60, 0, 0]])), section(2, items(vec![[name("env"), name("log"), vec![0, 0]].concat()]))]);
+        std::fs::write(dir.join("wasm_import.wasm"), import).unwrap();
+        let result = SharedLib::new(LibPath::new(dir, "wasm_import".into()));
+        assert!(matches!(result, Err(SharedLibError::LoadFailure { msg, .. }) if msg.contains("'env.log'")));
+    }
+}
@@ -0,0 +1,61 @@
+//! Derive macros for the [shared_lib](https://docs.rs/shared_lib) crate, re-exported by it behind the `derive` feature.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr};
+
+/// Derive a `load` function that resolves every field of a struct of function pointers from a shared library.
+///
+/// Each field is resolved by its name, or by the name given with the `#[symbol = "name"]` attribute.
+/// If any of the symbols is missing, the returned `SharedLibError::SymbolsNotFound` lists all of them.
+/// See `shared_lib::SharedLibApi` for an example.
+#[proc_macro_derive(SharedLibApi, attributes(symbol))]
+pub fn derive_shared_lib_api(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "SharedLibApi can only be derived for structs with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "SharedLibApi can only be derived for structs")),
+    };
+
+    let mut symbols = Vec::new();
+    let mut inits = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named fields have an identifier");
+        let ty = &field.ty;
+        let mut symbol = LitStr::new(&ident.to_string(), ident.span());
+        for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("symbol")) {
+            match &attr.meta.require_name_value()?.value {
+                Expr::Lit(ExprLit { lit: Lit::Str(name), .. }) => symbol = name.clone(),
+                value => return Err(syn::Error::new_spanned(value, "expected a string literal, e.g. `#[symbol = \"name\"]`")),
+            }
+        }
+        symbols.push(symbol.clone());
+        inits.push(quote! {
+            #ident: ::shared_lib::__private::raw_symbol::<#ty>(lib, #symbol)?
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Resolve every function of the API from the shared library.
+            /// # Safety
+            /// This function is unsafe because the function types are not checked against the actual types of the functions,
+            /// and the functions must not be called after the shared library is unloaded.
+            pub unsafe fn load(lib: &::shared_lib::SharedLib) -> ::core::result::Result<Self, ::shared_lib::SharedLibError> {
+                lib.check_symbols(&[#(#symbols),*])?;
+                ::core::result::Result::Ok(Self {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}